@@ -6,10 +6,17 @@ pragma solidity ^0.8.24;
  * @dev Decentralized, tamper-proof registry for ephemeral secret sharing on EVM blockchains.
  * Encrypted secret payloads are stored on IPFS, while access controls, expiration timestamps,
  * read limits, and sender identities (msg.sender) are immutably verified on-chain.
+ *
+ * Each write also has a `...BySig` form taking an EIP-712 signature, so a relayer can pay the
+ * gas for a user (e.g. a recipient with no native tokens). The signer takes the place of
+ * msg.sender; per-signer nonces and a deadline stop a signed intent from being replayed.
+ *
+ * `multicall` runs several writes from one sender in a single transaction (batch revoke,
+ * sharing to many recipients).
  */
 contract BsecSecretRegistry {
     struct SecretRecord {
-        address sender;          // Verified sender address (msg.sender)
+        address sender;          // Verified sender address (msg.sender or EIP-712 signer)
         address recipient;       // Recipient address (0x0 for public secrets)
         string ipfsCid;          // IPFS CID containing AES-256-GCM + ECDH payload
         uint64 createdAt;        // Timestamp of creation
@@ -20,9 +27,32 @@ contract BsecSecretRegistry {
         bool isPublic;           // Public secret indicator
     }
 
+    // Arguments of shareSecret, as taken by shareSecretBySig
+    struct ShareRequest {
+        bytes32 secretId;
+        address recipient;
+        string ipfsCid;
+        uint64 expiresAt;
+        uint32 maxReads;
+        bool isPublic;
+    }
+
     // Mapping from unique secret ID (bytes32) to SecretRecord
     mapping(bytes32 => SecretRecord) private _secrets;
 
+    // Next EIP-712 nonce of each signer
+    mapping(address => uint256) public nonces;
+
+    bytes32 private constant DOMAIN_TYPEHASH =
+        keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)");
+    bytes32 private constant SHARE_TYPEHASH = keccak256(
+        "ShareSecret(bytes32 secretId,address recipient,string ipfsCid,uint64 expiresAt,uint32 maxReads,bool isPublic,uint256 nonce,uint256 deadline)"
+    );
+    bytes32 private constant READ_TYPEHASH = keccak256("RecordRead(bytes32 secretId,uint256 nonce,uint256 deadline)");
+    bytes32 private constant REVOKE_TYPEHASH = keccak256("RevokeSecret(bytes32 secretId,uint256 nonce,uint256 deadline)");
+    // secp256k1n / 2: higher s values are the malleable twin of a valid signature.
+    uint256 private constant MAX_S = 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0;
+
     // Events
     event SecretShared(
         bytes32 indexed secretId,
@@ -55,6 +85,8 @@ contract BsecSecretRegistry {
     error UnauthorizedViewer(bytes32 secretId, address viewer);
     error UnauthorizedRevoker(bytes32 secretId, address caller);
     error InvalidParameters();
+    error InvalidSignature();
+    error SignatureExpired(uint256 deadline);
 
     /**
      * @dev Shares a new encrypted secret by registering its IPFS CID and access rules on-chain.
@@ -67,12 +99,54 @@ contract BsecSecretRegistry {
         uint32 maxReads,
         bool isPublic
     ) external {
+        _share(msg.sender, secretId, recipient, ipfsCid, expiresAt, maxReads, isPublic);
+    }
+
+    /**
+     * @dev shareSecret on behalf of `signer`, authorized by its EIP-712 ShareSecret signature.
+     * The share arguments travel as one struct to keep the stack shallow.
+     */
+    function shareSecretBySig(
+        ShareRequest calldata req,
+        address signer,
+        uint256 deadline,
+        bytes calldata signature
+    ) external {
+        _useSignature(signer, _shareStructHash(req, nonces[signer], deadline), deadline, signature);
+        _share(signer, req.secretId, req.recipient, req.ipfsCid, req.expiresAt, req.maxReads, req.isPublic);
+    }
+
+    function _shareStructHash(ShareRequest calldata req, uint256 nonce, uint256 deadline) private pure returns (bytes32) {
+        return keccak256(
+            abi.encode(
+                SHARE_TYPEHASH,
+                req.secretId,
+                req.recipient,
+                keccak256(bytes(req.ipfsCid)),
+                req.expiresAt,
+                req.maxReads,
+                req.isPublic,
+                nonce,
+                deadline
+            )
+        );
+    }
+
+    function _share(
+        address sender,
+        bytes32 secretId,
+        address recipient,
+        string calldata ipfsCid,
+        uint64 expiresAt,
+        uint32 maxReads,
+        bool isPublic
+    ) private {
         if (secretId == bytes32(0) || bytes(ipfsCid).length == 0) revert InvalidParameters();
         if (_secrets[secretId].sender != address(0)) revert SecretAlreadyExists(secretId);
         if (expiresAt <= block.timestamp) revert InvalidParameters();
 
         _secrets[secretId] = SecretRecord({
-            sender: msg.sender,
+            sender: sender,
             recipient: recipient,
             ipfsCid: ipfsCid,
             createdAt: uint64(block.timestamp),
@@ -85,7 +159,7 @@ contract BsecSecretRegistry {
 
         emit SecretShared(
             secretId,
-            msg.sender,
+            sender,
             recipient,
             ipfsCid,
             expiresAt,
@@ -98,6 +172,19 @@ contract BsecSecretRegistry {
      * @dev Increments the read count when an authorized user accesses a secret.
      */
     function recordRead(bytes32 secretId) external {
+        _recordRead(msg.sender, secretId);
+    }
+
+    /**
+     * @dev recordRead on behalf of `signer`, authorized by its EIP-712 RecordRead signature.
+     */
+    function recordReadBySig(bytes32 secretId, address signer, uint256 deadline, bytes calldata signature) external {
+        bytes32 structHash = keccak256(abi.encode(READ_TYPEHASH, secretId, nonces[signer], deadline));
+        _useSignature(signer, structHash, deadline, signature);
+        _recordRead(signer, secretId);
+    }
+
+    function _recordRead(address viewer, bytes32 secretId) private {
         SecretRecord storage record = _secrets[secretId];
         if (record.sender == address(0)) revert SecretNotFound(secretId);
         if (record.revoked) revert SecretIsRevoked(secretId);
@@ -110,29 +197,91 @@ contract BsecSecretRegistry {
             if (record.readCount >= record.maxReads) {
                 revert ReadLimitExceeded(secretId, record.readCount, record.maxReads);
             }
-            if (msg.sender != record.recipient && msg.sender != record.sender) {
-                revert UnauthorizedViewer(secretId, msg.sender);
+            if (viewer != record.recipient && viewer != record.sender) {
+                revert UnauthorizedViewer(secretId, viewer);
             }
             // Only non-public secrets track reads. Incrementing for public secrets would
             // waste storage-write gas without enforcing any limit.
             record.readCount += 1;
         }
 
-        emit SecretViewed(secretId, msg.sender, record.readCount, record.maxReads);
+        emit SecretViewed(secretId, viewer, record.readCount, record.maxReads);
     }
 
     /**
      * @dev Revokes a shared secret immediately. Only the original sender (msg.sender) can revoke.
      */
     function revokeSecret(bytes32 secretId) external {
+        _revoke(msg.sender, secretId);
+    }
+
+    /**
+     * @dev revokeSecret on behalf of `signer`, authorized by its EIP-712 RevokeSecret signature.
+     */
+    function revokeSecretBySig(bytes32 secretId, address signer, uint256 deadline, bytes calldata signature) external {
+        bytes32 structHash = keccak256(abi.encode(REVOKE_TYPEHASH, secretId, nonces[signer], deadline));
+        _useSignature(signer, structHash, deadline, signature);
+        _revoke(signer, secretId);
+    }
+
+    function _revoke(address caller, bytes32 secretId) private {
         SecretRecord storage record = _secrets[secretId];
         if (record.sender == address(0)) revert SecretNotFound(secretId);
-        if (record.sender != msg.sender) revert UnauthorizedRevoker(secretId, msg.sender);
+        if (record.sender != caller) revert UnauthorizedRevoker(secretId, caller);
         if (record.revoked) revert SecretIsRevoked(secretId);
 
         record.revoked = true;
 
-        emit SecretRevoked(secretId, msg.sender);
+        emit SecretRevoked(secretId, caller);
+    }
+
+    /**
+     * @dev Runs each of `calls` (ABI-encoded registry writes) as if sent directly by msg.sender.
+     * All or none take effect: the first failing call's revert data is re-raised unchanged.
+     */
+    function multicall(bytes[] calldata calls) external returns (bytes[] memory results) {
+        results = new bytes[](calls.length);
+        for (uint256 i = 0; i < calls.length; i++) {
+            (bool ok, bytes memory ret) = address(this).delegatecall(calls[i]);
+            if (!ok) {
+                assembly {
+                    revert(add(ret, 32), mload(ret))
+                }
+            }
+            results[i] = ret;
+        }
+    }
+
+    /**
+     * @dev EIP-712 domain separator, recomputed per call so it follows the chain ID across forks.
+     */
+    function domainSeparator() public view returns (bytes32) {
+        return keccak256(
+            abi.encode(
+                DOMAIN_TYPEHASH,
+                keccak256("BsecSecretRegistry"),
+                keccak256("1"),
+                block.chainid,
+                address(this)
+            )
+        );
+    }
+
+    /**
+     * @dev Checks `signature` (65 bytes: r, s, v) over `structHash` by `signer` and consumes
+     * the signer's nonce.
+     */
+    function _useSignature(address signer, bytes32 structHash, uint256 deadline, bytes calldata signature) private {
+        if (block.timestamp > deadline) revert SignatureExpired(deadline);
+        if (signature.length != 65) revert InvalidSignature();
+        bytes32 r = bytes32(signature[0:32]);
+        bytes32 s = bytes32(signature[32:64]);
+        uint8 v = uint8(signature[64]);
+        if (uint256(s) > MAX_S) revert InvalidSignature();
+        bytes32 digest = keccak256(abi.encodePacked("\x19\x01", domainSeparator(), structHash));
+        address recovered = ecrecover(digest, v, r, s);
+        if (recovered == address(0) || recovered != signer) revert InvalidSignature();
+        nonces[signer] += 1;
     }
 
     /**
@@ -0,0 +1,73 @@
+#!/usr/bin/env bash
+# Compile BsecSecretRegistry.sol into the artifact embedded by `bsec registry deploy`
+# (contracts/artifacts/BsecSecretRegistry.json).
+#
+# The compiler and settings are pinned so anyone can reproduce the checked-in bytecode:
+# solc 0.8.24, optimizer on (200 runs), EVM version cancun, no metadata hash (the CBOR
+# trailer then carries only the compiler version, not source paths). The artifact records
+# the SHA-256 of the source it was built from and of the bytecode; bsec refuses to deploy
+# if either no longer matches. The keccak256 of the runtime bytecode goes into
+# SHIPPED_REGISTRY_CODE_HASHES in src/blockchain.rs, which `bsec config verify-registry`
+# compares deployed registries against; `cargo test shipped_artifact` fails, naming the
+# hash, until it is listed there.
+#
+# Uses a local `solc` if it is exactly 0.8.24, otherwise the ethereum/solc docker image.
+#
+# Usage:
+#   ./scripts/build-contract.sh           # rebuild and rewrite the artifact
+#   ./scripts/build-contract.sh --check   # rebuild and fail if it differs from the artifact
+set -euo pipefail
+
+SOLC_VERSION="0.8.24"
+SRC="contracts/BsecSecretRegistry.sol"
+OUT="contracts/artifacts/BsecSecretRegistry.json"
+SOLC_FLAGS="--optimize --optimize-runs 200 --evm-version cancun --metadata-hash none --bin --bin-runtime"
+
+cd "$(dirname "$0")/.."
+
+compile() {
+  if command -v solc >/dev/null && solc --version | grep -q "Version: ${SOLC_VERSION}+"; then
+    solc $SOLC_FLAGS "$SRC"
+  else
+    docker run --rm -v "$PWD/contracts:/contracts:ro" "ethereum/solc:${SOLC_VERSION}" \
+      $SOLC_FLAGS "/$SRC"
+  fi
+}
+
+# solc prints each "Binary..." heading followed by the hex on the next line.
+SOLC_OUT=$(compile)
+BYTECODE="0x$(echo "$SOLC_OUT" | awk '/^Binary:/ { getline; print; exit }')"
+RUNTIME="0x$(echo "$SOLC_OUT" | awk '/^Binary of the runtime part:/ { getline; print; exit }')"
+test "${#BYTECODE}" -gt 2 || { echo "solc produced no bytecode" >&2; exit 1; }
+test "${#RUNTIME}" -gt 2 || { echo "solc produced no runtime bytecode" >&2; exit 1; }
+
+sha() { sha256sum | awk '{print $1}'; }
+SOURCE_SHA=$(sha < "$SRC")
+BYTECODE_SHA=$(printf '%s' "$BYTECODE" | sha)
+
+ARTIFACT=$(cat <<JSON
+{
+  "contract": "BsecSecretRegistry",
+  "compiler": "solc ${SOLC_VERSION}",
+  "settings": "optimizer 200 runs, evm cancun, metadata hash none",
+  "source_sha256": "${SOURCE_SHA}",
+  "bytecode_sha256": "${BYTECODE_SHA}",
+  "bytecode": "${BYTECODE}",
+  "runtime_bytecode": "${RUNTIME}"
+}
+JSON
+)
+
+if [ "${1:-}" = "--check" ]; then
+  if [ "$ARTIFACT" = "$(cat "$OUT")" ]; then
+    echo "$OUT is reproducible"
+  else
+    echo "$OUT does not match a fresh build of $SRC; run ./scripts/build-contract.sh" >&2
+    exit 1
+  fi
+else
+  mkdir -p "$(dirname "$OUT")"
+  printf '%s\n' "$ARTIFACT" > "$OUT"
+  echo "wrote $OUT (bytecode sha256 ${BYTECODE_SHA})"
+  echo "run \`cargo test shipped_artifact\` for the runtime code hash to add to SHIPPED_REGISTRY_CODE_HASHES"
+fi
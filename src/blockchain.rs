@@ -5,16 +5,20 @@
 //! (`~/.bsec/secret_index.json`) only enumerates the secret IDs this wallet has created or
 //! viewed and tracks a local-only `hidden` flag — the authoritative state (reads, revocation,
 //! expiry, recipient) always comes from the chain.
+//!
+//! Writes and `getSecretInfo` go through a `RegistryBackend`: the deployed contract, the
+//! in-process `mock_chain` on network `mock`, or the file-backed `team_registry` on `team`.
 
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::eth;
-use crate::network_config::{load_network_config, NetworkConfig};
+use crate::eth::{self, PortableTx, TxEstimate, TxOptions};
+use crate::network_config::{load_network_config, NetworkConfig, RegistryCheck};
+use crate::relay;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OnChainSecretInfo {
@@ -86,7 +90,19 @@ pub fn encode_bytes32_hex(id_str: &str) -> Result<[u8; 32]> {
     Ok(bytes)
 }
 
+/// Fee estimates, unsigned transactions, relaying and deployment need a contract on a node.
+fn require_real_chain(conf: &NetworkConfig) -> Result<()> {
+    if !conf.has_registry_contract() {
+        return Err(anyhow!(
+            "network {:?} has no registry contract: fee estimates, unsigned transactions, relaying and deployment need a real chain",
+            conf.network
+        ));
+    }
+    Ok(())
+}
+
 fn registry_address(conf: &NetworkConfig) -> Result<[u8; 20]> {
+    require_real_chain(conf)?;
     eth::parse_address(&conf.registry_address).map_err(|e| {
         anyhow!(
             "Invalid registry_address '{}' in network config: {}",
@@ -96,6 +112,13 @@ fn registry_address(conf: &NetworkConfig) -> Result<[u8; 20]> {
     })
 }
 
+/// `registry_address`, after the `registry_check` policy has been applied to it.
+pub fn trusted_registry_address(conf: &NetworkConfig) -> Result<[u8; 20]> {
+    let to = registry_address(conf)?;
+    ensure_registry_trusted(conf)?;
+    Ok(to)
+}
+
 // ---------------------------------------------------------------------------
 // Local index (enumeration + hidden flag only)
 // ---------------------------------------------------------------------------
@@ -106,6 +129,9 @@ struct IndexEntry {
     hidden: bool,
     #[serde(default)]
     role: String,
+    /// Set once `bsec gc` has unpinned/evicted the payload, so later sweeps skip it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    collected: bool,
 }
 
 type Index = BTreeMap<String, IndexEntry>;
@@ -146,11 +172,131 @@ fn index_is_hidden(secret_id: &str) -> bool {
     load_index().get(secret_id).map(|e| e.hidden).unwrap_or(false)
 }
 
+/// Secret IDs (with this wallet's role) whose payloads `bsec gc` has not yet collected.
+pub fn index_uncollected() -> Vec<(String, String)> {
+    load_index()
+        .into_iter()
+        .filter(|(_, e)| !e.collected)
+        .map(|(id, e)| (id, e.role))
+        .collect()
+}
+
+pub fn index_set_collected(secret_id: &str) {
+    let mut index = load_index();
+    index.entry(secret_id.to_string()).or_default().collected = true;
+    let _ = save_index(&index);
+}
+
 // ---------------------------------------------------------------------------
 // Contract operations
 // ---------------------------------------------------------------------------
 
+/// A registry write bound to the current network config, ready to send or estimate.
+struct RegistryCall {
+    conf: NetworkConfig,
+    to: [u8; 20],
+    action: relay::Action,
+    data: Vec<u8>,
+}
+
+impl RegistryCall {
+    fn new(action: relay::Action) -> Result<Self> {
+        Self::on(load_network_config(), action)
+    }
+
+    fn on(conf: NetworkConfig, action: relay::Action) -> Result<Self> {
+        let to = trusted_registry_address(&conf)?;
+        let data = action.calldata()?;
+        Ok(Self { conf, to, action, data })
+    }
+
+    /// Send from the wallet, or with `tx.relayer` as a signed intent the relayer pays for.
+    fn send(&self, priv_bytes: &[u8], tx: &TxOptions) -> Result<String> {
+        match &tx.relayer {
+            Some(url) => relay::submit(url, &self.conf, &self.to, priv_bytes, self.action.clone()),
+            None => eth::send_contract_tx(&self.conf, priv_bytes, &self.to, &self.data, tx),
+        }
+    }
+
+    fn estimate(&self, priv_bytes: &[u8], tx: &TxOptions) -> Result<TxEstimate> {
+        eth::estimate_contract_tx(&self.conf, priv_bytes, &self.to, &self.data, tx)
+    }
+
+    fn build(&self, from: &[u8; 20], tx: &TxOptions, nonce: Option<u64>) -> Result<PortableTx> {
+        eth::build_tx(&self.conf, from, Some(&self.to), &self.data, tx, nonce)
+    }
+}
+
+/// The registry's state-changing and read entry points.
+pub trait RegistryBackend {
+    /// Apply `action` as the wallet of `priv_bytes`; returns the transaction hash.
+    fn send(&self, priv_bytes: &[u8], action: &relay::Action, tx: &TxOptions) -> Result<String>;
+    /// `getSecretInfo`; fails with the contract's error for an unknown id.
+    fn secret_info(&self, secret_id: &[u8; 32]) -> Result<eth::DecodedSecretInfo>;
+}
+
+/// The deployed BsecSecretRegistry, through the configured RPC nodes.
+struct ChainRegistry {
+    conf: NetworkConfig,
+}
+
+impl RegistryBackend for ChainRegistry {
+    fn send(&self, priv_bytes: &[u8], action: &relay::Action, tx: &TxOptions) -> Result<String> {
+        RegistryCall::on(self.conf.clone(), action.clone())?.send(priv_bytes, tx)
+    }
+
+    fn secret_info(&self, secret_id: &[u8; 32]) -> Result<eth::DecodedSecretInfo> {
+        let to = trusted_registry_address(&self.conf)?;
+        let data = eth::encode_bytes32_call("getSecretInfo(bytes32)", secret_id);
+        let shown = eth::display_secret_id(secret_id);
+        let ret = eth::eth_call(&self.conf, &to, &data).map_err(|e| {
+            if e.is::<crate::errors::BsecError>() {
+                e
+            } else {
+                anyhow!("Secret ID '{}' not found on-chain (or RPC error): {}", shown, e)
+            }
+        })?;
+        if ret.is_empty() {
+            return Err(anyhow!("Secret ID '{}' not found on blockchain registry.", shown));
+        }
+        eth::decode_secret_info(&ret)
+    }
+}
+
+/// Backend for the configured network.
+fn registry_backend() -> Result<Box<dyn RegistryBackend>> {
+    let conf = load_network_config();
+    Ok(if conf.is_mock() {
+        Box::new(crate::mock_chain::MockRegistry::open(&conf)?)
+    } else if conf.is_team() {
+        Box::new(crate::team_registry::TeamRegistry::open(&conf)?)
+    } else {
+        Box::new(ChainRegistry { conf })
+    })
+}
+
+fn share_call(
+    secret_id: &str,
+    recipient_addr: &[u8; 20],
+    ipfs_cid: &str,
+    expires_at: u64,
+    max_reads: u32,
+    is_public: bool,
+) -> Result<RegistryCall> {
+    let id32 = encode_bytes32_hex(secret_id)?;
+    RegistryCall::new(relay::Action::share(&id32, recipient_addr, ipfs_cid, expires_at, max_reads, is_public))
+}
+
+fn read_call(secret_id: &str) -> Result<RegistryCall> {
+    RegistryCall::new(relay::Action::record_read(&encode_bytes32_hex(secret_id)?))
+}
+
+fn revoke_call(secret_id: &str) -> Result<RegistryCall> {
+    RegistryCall::new(relay::Action::revoke(&encode_bytes32_hex(secret_id)?))
+}
+
 /// Register a secret on-chain via a signed shareSecret transaction. Returns the tx hash.
+#[allow(clippy::too_many_arguments)]
 pub fn register_secret_on_chain(
     priv_bytes: &[u8],
     secret_id: &str,
@@ -159,29 +305,48 @@ pub fn register_secret_on_chain(
     expires_at: u64,
     max_reads: u32,
     is_public: bool,
+    tx: &TxOptions,
 ) -> Result<String> {
-    let conf = load_network_config();
-    let to = registry_address(&conf)?;
     let id32 = encode_bytes32_hex(secret_id)?;
-    let data = eth::encode_share_secret(&id32, recipient_addr, ipfs_cid, expires_at, max_reads, is_public);
-    let tx_hash = eth::send_contract_tx(&conf, priv_bytes, &to, &data)?;
+    let action = relay::Action::share(&id32, recipient_addr, ipfs_cid, expires_at, max_reads, is_public);
+    let tx_hash = registry_backend()?.send(priv_bytes, &action, tx)?;
     index_note(secret_id, "sender");
     Ok(tx_hash)
 }
 
-pub fn get_secret_info_on_chain(secret_id: &str) -> Result<OnChainSecretInfo> {
-    let conf = load_network_config();
-    let to = registry_address(&conf)?;
-    let id32 = encode_bytes32_hex(secret_id)?;
-    let data = eth::encode_bytes32_call("getSecretInfo(bytes32)", &id32);
+/// Fee estimate for `register_secret_on_chain` with the same arguments; nothing is sent.
+#[allow(clippy::too_many_arguments)]
+pub fn estimate_register_secret(
+    priv_bytes: &[u8],
+    secret_id: &str,
+    recipient_addr: &[u8; 20],
+    ipfs_cid: &str,
+    expires_at: u64,
+    max_reads: u32,
+    is_public: bool,
+    tx: &TxOptions,
+) -> Result<TxEstimate> {
+    share_call(secret_id, recipient_addr, ipfs_cid, expires_at, max_reads, is_public)?.estimate(priv_bytes, tx)
+}
 
-    let ret = eth::eth_call(&conf, &to, &data).map_err(|e| {
-        anyhow!("Secret ID '{}' not found on-chain (or RPC error): {}", secret_id, e)
-    })?;
-    if ret.is_empty() {
-        return Err(anyhow!("Secret ID '{}' not found on blockchain registry.", secret_id));
-    }
-    let d = eth::decode_secret_info(&ret)?;
+/// Unsigned `shareSecret` transaction from `from` for offline signing.
+#[allow(clippy::too_many_arguments)]
+pub fn build_register_secret(
+    from: &[u8; 20],
+    secret_id: &str,
+    recipient_addr: &[u8; 20],
+    ipfs_cid: &str,
+    expires_at: u64,
+    max_reads: u32,
+    is_public: bool,
+    tx: &TxOptions,
+    nonce: Option<u64>,
+) -> Result<PortableTx> {
+    share_call(secret_id, recipient_addr, ipfs_cid, expires_at, max_reads, is_public)?.build(from, tx, nonce)
+}
+
+pub fn get_secret_info_on_chain(secret_id: &str) -> Result<OnChainSecretInfo> {
+    let d = registry_backend()?.secret_info(&encode_bytes32_hex(secret_id)?)?;
 
     let recipient = if d.is_public {
         "public".to_string()
@@ -205,28 +370,158 @@ pub fn get_secret_info_on_chain(secret_id: &str) -> Result<OnChainSecretInfo> {
     })
 }
 
-pub fn record_read_on_chain(priv_bytes: &[u8], secret_id: &str) -> Result<()> {
-    let conf = load_network_config();
-    let to = registry_address(&conf)?;
-    let id32 = encode_bytes32_hex(secret_id)?;
-    let data = eth::encode_bytes32_call("recordRead(bytes32)", &id32);
-    eth::send_contract_tx(&conf, priv_bytes, &to, &data)?;
+pub fn record_read_on_chain(priv_bytes: &[u8], secret_id: &str, tx: &TxOptions) -> Result<()> {
+    registry_backend()?.send(priv_bytes, &relay::Action::record_read(&encode_bytes32_hex(secret_id)?), tx)?;
     Ok(())
 }
 
-pub fn revoke_secret_on_chain(priv_bytes: &[u8], secret_id: &str) -> Result<()> {
-    let conf = load_network_config();
-    let to = registry_address(&conf)?;
-    let id32 = encode_bytes32_hex(secret_id)?;
-    let data = eth::encode_bytes32_call("revokeSecret(bytes32)", &id32);
-    eth::send_contract_tx(&conf, priv_bytes, &to, &data)?;
+pub fn estimate_record_read(priv_bytes: &[u8], secret_id: &str, tx: &TxOptions) -> Result<TxEstimate> {
+    read_call(secret_id)?.estimate(priv_bytes, tx)
+}
+
+pub fn revoke_secret_on_chain(priv_bytes: &[u8], secret_id: &str, tx: &TxOptions) -> Result<()> {
+    registry_backend()?.send(priv_bytes, &relay::Action::revoke(&encode_bytes32_hex(secret_id)?), tx)?;
     Ok(())
 }
 
+pub fn estimate_revoke(priv_bytes: &[u8], secret_id: &str, tx: &TxOptions) -> Result<TxEstimate> {
+    revoke_call(secret_id)?.estimate(priv_bytes, tx)
+}
+
+/// Unsigned `revokeSecret` transaction from `from` for offline signing.
+pub fn build_revoke(from: &[u8; 20], secret_id: &str, tx: &TxOptions, nonce: Option<u64>) -> Result<PortableTx> {
+    revoke_call(secret_id)?.build(from, tx, nonce)
+}
+
+/// Outcome of one write in a batch.
+#[derive(Serialize, Debug, Clone)]
+pub struct BatchItem {
+    pub secret_id: String,
+    /// Transaction that carried the write (shared by every item of a multicall).
+    pub tx_hash: Option<String>,
+    /// Why the write was left out of the batch or failed.
+    pub error: Option<String>,
+}
+
+/// Dry-run each call as the wallet; returns the reason for every call that would revert.
+fn simulate_batch(from: &[u8; 20], calls: &[RegistryCall]) -> Vec<Option<String>> {
+    calls
+        .iter()
+        .map(|c| eth::eth_call_from(&c.conf, from, &c.to, &c.data).err().map(|e| e.to_string()))
+        .collect()
+}
+
+/// One transaction for all of `calls`: the call itself if there is only one, else a multicall.
+fn batch_call(calls: &[&RegistryCall]) -> Option<(NetworkConfig, [u8; 20], Vec<u8>)> {
+    let first = calls.first()?;
+    let data = match calls {
+        [only] => only.data.clone(),
+        _ => eth::encode_multicall(&calls.iter().map(|c| c.data.clone()).collect::<Vec<_>>()),
+    };
+    Some((first.conf.clone(), first.to, data))
+}
+
+/// Send several registry writes from one wallet. Each is dry-run on its own first; those that
+/// would revert are reported and left out, and the rest go in one `multicall` transaction,
+/// which applies all of them or none. Through a relayer (`tx.relayer`) they are submitted as
+/// one signed intent each instead, and without a registry contract (mock, team) applied one
+/// by one. Shares that went through are noted in the local index.
+pub fn send_batch(priv_bytes: &[u8], writes: Vec<(String, relay::Action)>, tx: &TxOptions) -> Result<Vec<BatchItem>> {
+    let conf = load_network_config();
+    if !conf.has_registry_contract() {
+        let backend = registry_backend()?;
+        let items = writes
+            .iter()
+            .map(|(secret_id, action)| {
+                let sent = backend.send(priv_bytes, action, tx);
+                if sent.is_ok() && matches!(action, relay::Action::ShareSecret { .. }) {
+                    index_note(secret_id, "sender");
+                }
+                let (tx_hash, error) = match sent {
+                    Ok(hash) => (Some(hash), None),
+                    Err(e) => (None, Some(e.to_string())),
+                };
+                BatchItem { secret_id: secret_id.clone(), tx_hash, error }
+            })
+            .collect();
+        return Ok(items);
+    }
+    let (ids, calls): (Vec<String>, Vec<RegistryCall>) = writes
+        .into_iter()
+        .map(|(id, action)| Ok((id, RegistryCall::on(conf.clone(), action)?)))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let mut items: Vec<BatchItem> = ids
+        .into_iter()
+        .map(|secret_id| BatchItem { secret_id, tx_hash: None, error: None })
+        .collect();
+    if tx.relayer.is_some() {
+        for (item, call) in items.iter_mut().zip(&calls) {
+            match call.send(priv_bytes, tx) {
+                Ok(hash) => item.tx_hash = Some(hash),
+                Err(e) => item.error = Some(e.to_string()),
+            }
+        }
+    } else {
+        let from = eth::address_bytes_from_secret(priv_bytes)?;
+        for (item, error) in items.iter_mut().zip(simulate_batch(&from, &calls)) {
+            item.error = error;
+        }
+        let ready: Vec<&RegistryCall> = calls.iter().zip(&items).filter(|(_, i)| i.error.is_none()).map(|(c, _)| c).collect();
+        if let Some((conf, to, data)) = batch_call(&ready) {
+            let hash = eth::send_contract_tx(&conf, priv_bytes, &to, &data, tx)?;
+            for item in items.iter_mut().filter(|i| i.error.is_none()) {
+                item.tx_hash = Some(hash.clone());
+            }
+        }
+    }
+
+    for (item, call) in items.iter().zip(&calls) {
+        if item.tx_hash.is_some() && matches!(call.action, relay::Action::ShareSecret { .. }) {
+            index_note(&item.secret_id, "sender");
+        }
+    }
+    Ok(items)
+}
+
+/// Fee estimate for `send_batch`: the multicall of the writes that would go through. Those
+/// that would not are named in a warning.
+pub fn estimate_batch(priv_bytes: &[u8], writes: Vec<(String, relay::Action)>, tx: &TxOptions) -> Result<TxEstimate> {
+    let from = eth::address_bytes_from_secret(priv_bytes)?;
+    let calls = writes
+        .iter()
+        .map(|(_, action)| RegistryCall::new(action.clone()))
+        .collect::<Result<Vec<_>>>()?;
+    let mut ready = Vec::new();
+    for ((id, _), (call, error)) in writes.iter().zip(calls.iter().zip(simulate_batch(&from, &calls))) {
+        match error {
+            Some(e) => log::warn!("Secret '{}' would be left out of the batch: {}", id, e),
+            None => ready.push(call),
+        }
+    }
+    let (conf, to, data) = batch_call(&ready).ok_or_else(|| anyhow!("none of the {} writes would succeed", writes.len()))?;
+    eth::estimate_contract_tx(&conf, priv_bytes, &to, &data, tx)
+}
+
+/// Broadcast a transaction signed by `bsec tx sign` on the configured network and wait for
+/// it. A share is noted in the local index like one sent directly.
+pub fn broadcast_signed_tx(tx: &PortableTx) -> Result<String> {
+    let tx_hash = eth::broadcast_signed(&load_network_config(), tx)?;
+    // shareSecret's first argument (after 0x and the selector) is the secret id.
+    if let Some(id) = tx.data.get(10..74).filter(|_| tx.description == "shareSecret") {
+        index_note(&format!("0x{}", id.to_lowercase()), "sender");
+    }
+    Ok(tx_hash)
+}
+
 /// Hide a secret locally (contract has no hidden state) and best-effort revoke on-chain.
-pub fn hide_secret_on_chain(priv_bytes: &[u8], secret_id: &str) -> Result<()> {
+/// The payload stays pinned: the index entry is left uncollected, so once the revocation
+/// lands `bsec gc` unpins it like any other revoked secret this wallet sent.
+pub fn hide_secret_on_chain(priv_bytes: &[u8], secret_id: &str, tx: &TxOptions) -> Result<()> {
     index_set_hidden(secret_id, true);
-    if let Err(e) = revoke_secret_on_chain(priv_bytes, secret_id) {
+    if let Err(e) = revoke_secret_on_chain(priv_bytes, secret_id, tx) {
         log::warn!(
             "On-chain revocation not performed for secret '{}' during hide (kept local hide): {}",
             secret_id,
@@ -236,6 +531,238 @@ pub fn hide_secret_on_chain(priv_bytes: &[u8], secret_id: &str) -> Result<()> {
     Ok(())
 }
 
+// ---------------------------------------------------------------------------
+// Deployment
+// ---------------------------------------------------------------------------
+
+const REGISTRY_SOURCE: &str = include_str!("../contracts/BsecSecretRegistry.sol");
+const REGISTRY_ARTIFACT: &str = include_str!("../contracts/artifacts/BsecSecretRegistry.json");
+
+/// Compiled registry as written by `scripts/build-contract.sh`.
+#[derive(Deserialize, Debug)]
+struct RegistryArtifact {
+    compiler: String,
+    source_sha256: String,
+    bytecode_sha256: String,
+    bytecode: String,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    crate::wallet::bytes_to_hex(&crate::wallet::hash_digest(data))
+}
+
+/// Creation bytecode from an artifact, checked against the source it claims to be built
+/// from and against its own recorded hash.
+fn artifact_bytecode(artifact_json: &str, source: &str) -> Result<(Vec<u8>, String)> {
+    let artifact: RegistryArtifact =
+        serde_json::from_str(artifact_json).map_err(|e| anyhow!("invalid embedded registry artifact: {}", e))?;
+    if artifact.bytecode.is_empty() {
+        return Err(anyhow!(
+            "this build has no embedded registry bytecode; run scripts/build-contract.sh and rebuild, or pass --bytecode <file>"
+        ));
+    }
+    if artifact.source_sha256 != sha256_hex(source.as_bytes()) {
+        return Err(anyhow!(
+            "embedded registry bytecode was compiled from a different BsecSecretRegistry.sol; run scripts/build-contract.sh"
+        ));
+    }
+    if artifact.bytecode_sha256 != sha256_hex(artifact.bytecode.as_bytes()) {
+        return Err(anyhow!("embedded registry bytecode does not match its recorded hash"));
+    }
+    Ok((crate::wallet::hex_to_bytes(&artifact.bytecode)?, artifact.compiler))
+}
+
+/// Creation bytecode from a file: bare hex, or a solc/forge JSON artifact whose `bytecode` is
+/// a hex string or `{ "object": "0x..." }`.
+fn bytecode_from_file(path: &Path) -> Result<Vec<u8>> {
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("reading {}: {}", path.display(), e))?;
+    let text = text.trim();
+    let hex = if text.starts_with('{') {
+        let v: serde_json::Value = serde_json::from_str(text).map_err(|e| anyhow!("{}: invalid JSON: {}", path.display(), e))?;
+        let b = &v["bytecode"];
+        b.as_str()
+            .or_else(|| b["object"].as_str())
+            .ok_or_else(|| anyhow!("{}: no `bytecode` field", path.display()))?
+            .to_string()
+    } else {
+        text.split_whitespace().collect()
+    };
+    let bytes = crate::wallet::hex_to_bytes(&hex).map_err(|e| anyhow!("{}: invalid bytecode hex: {}", path.display(), e))?;
+    if bytes.is_empty() {
+        return Err(anyhow!("{}: bytecode is empty", path.display()));
+    }
+    Ok(bytes)
+}
+
+/// Registry creation bytecode and a description of where it came from.
+fn registry_bytecode(bytecode_file: Option<&Path>) -> Result<(Vec<u8>, String)> {
+    match bytecode_file {
+        Some(path) => Ok((bytecode_from_file(path)?, path.display().to_string())),
+        None => {
+            let (code, compiler) = artifact_bytecode(REGISTRY_ARTIFACT, REGISTRY_SOURCE)?;
+            Ok((code, format!("embedded ({})", compiler)))
+        }
+    }
+}
+
+/// Outcome of `bsec registry deploy`.
+#[derive(Serialize, Debug, Clone)]
+pub struct DeployReport {
+    pub address: String,
+    pub tx_hash: String,
+    pub chain_id: u32,
+    pub bytecode_source: String,
+    /// Whether `registry_address` in the network config now points at the new contract.
+    pub saved: bool,
+}
+
+/// Deploy BsecSecretRegistry from the wallet and, if `save`, make it the configured registry.
+pub fn deploy_registry(priv_bytes: &[u8], bytecode_file: Option<&Path>, save: bool, tx: &TxOptions) -> Result<DeployReport> {
+    let (code, bytecode_source) = registry_bytecode(bytecode_file)?;
+    let conf = load_network_config();
+    require_real_chain(&conf)?;
+    let (tx_hash, address) = eth::deploy_contract(&conf, priv_bytes, &code, tx)?;
+    let address = bytes_to_checksum_address(&address);
+    if save {
+        // The wallet deployed this code itself, so its hash is trusted even when it came from
+        // a --bytecode build rather than the embedded one.
+        let code_hash = eth::code_hash(&conf, &address)?.filter(|h| !known_registry_hashes(&conf).contains(h));
+        crate::network_config::update_network_config(crate::network_config::ConfigUpdate {
+            registry: Some(address.clone()),
+            trust_registry_code_hash: code_hash,
+            ..Default::default()
+        })?;
+    }
+    Ok(DeployReport { address, tx_hash, chain_id: conf.chain_id, bytecode_source, saved: save })
+}
+
+pub fn estimate_deploy_registry(priv_bytes: &[u8], bytecode_file: Option<&Path>, tx: &TxOptions) -> Result<TxEstimate> {
+    let (code, _) = registry_bytecode(bytecode_file)?;
+    let conf = load_network_config();
+    require_real_chain(&conf)?;
+    eth::estimate_deploy(&conf, priv_bytes, &code, tx)
+}
+
+// ---------------------------------------------------------------------------
+// Registry verification
+// ---------------------------------------------------------------------------
+
+/// keccak256 of the runtime code of the registry builds shipped with bsec, as printed by
+/// `scripts/build-contract.sh`. A test checks that the embedded artifact's is listed.
+const SHIPPED_REGISTRY_CODE_HASHES: &[&str] = &[];
+
+/// Runtime code hashes accepted for `conf`: the shipped builds, then the config's
+/// `trusted_registry_code_hashes`, which `registry deploy` adds to.
+fn known_registry_hashes(conf: &NetworkConfig) -> Vec<String> {
+    SHIPPED_REGISTRY_CODE_HASHES
+        .iter()
+        .map(|h| h.to_string())
+        .chain(conf.trusted_registry_code_hashes.iter().map(|h| h.to_lowercase()))
+        .collect()
+}
+
+/// Result of comparing the registry's deployed code with the known-good builds.
+#[derive(Serialize, Debug, Clone)]
+pub struct RegistryVerification {
+    pub address: String,
+    pub chain_id: u32,
+    /// keccak256 of the deployed code; `None` if there is no code at the address.
+    pub code_hash: Option<String>,
+    /// `shipped build`, `trusted in config`, or `None` when unrecognized.
+    pub matched: Option<&'static str>,
+    /// Whether any code hash is trusted for this network. Without one, code at the address
+    /// can be neither confirmed nor ruled out.
+    pub verifiable: bool,
+}
+
+impl RegistryVerification {
+    pub fn is_genuine(&self) -> bool {
+        self.matched.is_some()
+    }
+
+    /// There is code at the address but no trusted hash to compare it with.
+    pub fn is_unverifiable(&self) -> bool {
+        self.code_hash.is_some() && !self.verifiable
+    }
+
+    fn problem(&self) -> String {
+        match &self.code_hash {
+            None => format!("no contract code at registry address {} on chain {}", self.address, self.chain_id),
+            Some(h) => format!(
+                "registry {} on chain {} runs unrecognized code (keccak256 {}); it may not be a genuine BsecSecretRegistry",
+                self.address, self.chain_id, h
+            ),
+        }
+    }
+}
+
+/// Fetch the registry's code and compare its hash with the known-good builds.
+pub fn verify_registry(conf: &NetworkConfig) -> Result<RegistryVerification> {
+    registry_address(conf)?;
+    let code_hash = eth::code_hash(conf, &conf.registry_address)?;
+    let known = known_registry_hashes(conf);
+    let matched = code_hash.as_ref().and_then(|h| {
+        if SHIPPED_REGISTRY_CODE_HASHES.contains(&h.as_str()) {
+            Some("shipped build")
+        } else if known.contains(h) {
+            Some("trusted in config")
+        } else {
+            None
+        }
+    });
+    Ok(RegistryVerification {
+        address: conf.registry_address.clone(),
+        chain_id: conf.chain_id,
+        code_hash,
+        matched,
+        verifiable: !known.is_empty(),
+    })
+}
+
+/// Registries already checked in this process (`chain:address`), so each command pays for
+/// one `eth_getCode` at most and warns once.
+static CHECKED_REGISTRIES: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+/// Apply `conf.registry_check` before the registry is used. `enforce` refuses any registry
+/// whose code matches no known hash; `warn` also lets through, with a warning, one that
+/// cannot be verified because no code hash is known at all.
+fn ensure_registry_trusted(conf: &NetworkConfig) -> Result<()> {
+    if conf.registry_check == RegistryCheck::Off {
+        return Ok(());
+    }
+    let key = format!("{}:{}", conf.chain_id, conf.registry_address.to_lowercase());
+    if CHECKED_REGISTRIES.lock().is_ok_and(|c| c.contains(&key)) {
+        return Ok(());
+    }
+    let problem = match verify_registry(conf) {
+        Ok(v) if v.is_genuine() => None,
+        Ok(v) if v.is_unverifiable() && conf.registry_check == RegistryCheck::Warn => {
+            log::warn!(
+                "Registry {} on chain {} cannot be verified: no code hash is trusted for this network (see `bsec config --trust-registry-code-hash`)",
+                v.address,
+                v.chain_id
+            );
+            None
+        }
+        Ok(v) => Some(v.problem()),
+        Err(e) => Some(format!("could not verify registry {}: {}", conf.registry_address, e)),
+    };
+    if let Some(problem) = problem {
+        if conf.registry_check == RegistryCheck::Enforce {
+            return Err(crate::errors::BsecError::ConfigError(format!(
+                "{} (registry_check = enforce; see `bsec config verify-registry`)",
+                problem
+            ))
+            .into());
+        }
+        log::warn!("{}. Run `bsec config verify-registry` for details.", problem);
+    }
+    if let Ok(mut c) = CHECKED_REGISTRIES.lock() {
+        c.push(key);
+    }
+    Ok(())
+}
+
 pub fn list_secrets_on_chain(
     user_address: &str,
     filter_user: Option<&str>,
@@ -318,6 +845,137 @@ mod tests {
         assert_eq!(out, expected);
     }
 
+    fn artifact(source_sha: &str, bytecode: &str, bytecode_sha: &str) -> String {
+        serde_json::json!({
+            "contract": "BsecSecretRegistry",
+            "compiler": "solc 0.8.24",
+            "source_sha256": source_sha,
+            "bytecode_sha256": bytecode_sha,
+            "bytecode": bytecode,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn embedded_artifact_is_checked_against_source_and_hash() {
+        let src = "contract X {}";
+        let code = "0x6080604052";
+        let good = artifact(&sha256_hex(src.as_bytes()), code, &sha256_hex(code.as_bytes()));
+        let (bytes, compiler) = artifact_bytecode(&good, src).unwrap();
+        assert_eq!((bytes, compiler.as_str()), (vec![0x60, 0x80, 0x60, 0x40, 0x52], "solc 0.8.24"));
+
+        let empty = artifact(&sha256_hex(src.as_bytes()), "", "");
+        assert!(artifact_bytecode(&empty, src).unwrap_err().to_string().contains("--bytecode"));
+        let stale = artifact_bytecode(&good, "contract X { uint y; }").unwrap_err();
+        assert!(stale.to_string().contains("different BsecSecretRegistry.sol"), "{}", stale);
+        let tampered = artifact(&sha256_hex(src.as_bytes()), "0x6080604053", &sha256_hex(code.as_bytes()));
+        assert!(artifact_bytecode(&tampered, src).unwrap_err().to_string().contains("recorded hash"));
+    }
+
+    #[test]
+    fn shipped_artifact_matches_the_checked_in_source() {
+        let shipped: RegistryArtifact = serde_json::from_str(REGISTRY_ARTIFACT).unwrap();
+        assert_eq!(shipped.source_sha256, sha256_hex(REGISTRY_SOURCE.as_bytes()), "run scripts/build-contract.sh");
+        if !shipped.bytecode.is_empty() {
+            assert_eq!(shipped.bytecode_sha256, sha256_hex(shipped.bytecode.as_bytes()));
+            assert!(artifact_bytecode(REGISTRY_ARTIFACT, REGISTRY_SOURCE).is_ok());
+        }
+        let json: serde_json::Value = serde_json::from_str(REGISTRY_ARTIFACT).unwrap();
+        let runtime_hex = json["runtime_bytecode"].as_str().unwrap_or_default();
+        if !runtime_hex.is_empty() {
+            let runtime = crate::wallet::hex_to_bytes(runtime_hex).unwrap();
+            let hash = format!("0x{}", crate::wallet::bytes_to_hex(&keccak256(&runtime)));
+            assert!(SHIPPED_REGISTRY_CODE_HASHES.contains(&hash.as_str()), "add {} to SHIPPED_REGISTRY_CODE_HASHES", hash);
+        }
+    }
+
+    /// Chain 31337 node whose `eth_getCode` returns `code` for every address.
+    fn node_with_code(code: &'static str) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                let req = String::from_utf8_lossy(&buf[..n]);
+                let result = if req.contains("eth_chainId") { "\"0x7a69\"".to_string() } else { format!("\"{}\"", code) };
+                let body = format!(r#"{{"jsonrpc":"2.0","id":1,"result":{}}}"#, result);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        url
+    }
+
+    fn local_conf(rpc_url: String, registry: &str) -> NetworkConfig {
+        NetworkConfig {
+            chain_id: 31337,
+            rpc_url,
+            registry_address: registry.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn registry_verification_matches_trusted_hashes_and_applies_policy() {
+        let trusted_hash = format!("0x{}", crate::wallet::bytes_to_hex(&keccak256(&[0x60, 0x80])));
+        let node = node_with_code("0x6080");
+
+        let mut conf = local_conf(node.clone(), "0x1000000000000000000000000000000000000001");
+        let v = verify_registry(&conf).unwrap();
+        assert_eq!(v.code_hash.as_deref(), Some(trusted_hash.as_str()));
+        assert!(!v.is_genuine());
+        assert_eq!(v.is_unverifiable(), SHIPPED_REGISTRY_CODE_HASHES.is_empty());
+        // `enforce` refuses code it cannot confirm, whether or not any hash is known.
+        conf.registry_check = RegistryCheck::Enforce;
+        let err = ensure_registry_trusted(&conf).unwrap_err();
+        assert_eq!(err.downcast_ref::<crate::errors::BsecError>().map(|e| e.exit_code()), Some(4));
+        conf.registry_check = RegistryCheck::Warn;
+
+        conf.trusted_registry_code_hashes.push(trusted_hash.clone());
+        let v = verify_registry(&conf).unwrap();
+        assert_eq!(v.matched, Some("trusted in config"));
+        assert!(!v.is_unverifiable());
+
+        // Unknown code: `warn` lets the command continue, `enforce` refuses with a config error.
+        let mut unknown = local_conf(node, "0x1000000000000000000000000000000000000002");
+        unknown.trusted_registry_code_hashes.push(format!("0x{}", "ab".repeat(32)));
+        assert!(ensure_registry_trusted(&unknown).is_ok());
+        unknown.registry_address = "0x1000000000000000000000000000000000000003".into();
+        unknown.registry_check = RegistryCheck::Enforce;
+        let err = ensure_registry_trusted(&unknown).unwrap_err();
+        assert_eq!(err.downcast_ref::<crate::errors::BsecError>().map(|e| e.exit_code()), Some(4));
+        assert!(err.to_string().contains("unrecognized code"), "{}", err);
+
+        // No code at all is reported as such.
+        let empty = local_conf(node_with_code("0x"), "0x1000000000000000000000000000000000000004");
+        let v = verify_registry(&empty).unwrap();
+        assert!(v.code_hash.is_none() && !v.is_genuine());
+        assert!(v.problem().contains("no contract code"));
+    }
+
+    #[test]
+    fn bytecode_files_accept_hex_and_compiler_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, body: &str| {
+            let p = dir.path().join(name);
+            fs::write(&p, body).unwrap();
+            p
+        };
+        let expected = vec![0x60, 0x80];
+        assert_eq!(bytecode_from_file(&write("a.bin", "6080\n")).unwrap(), expected);
+        assert_eq!(bytecode_from_file(&write("b.json", r#"{"bytecode":{"object":"0x6080"}}"#)).unwrap(), expected);
+        assert_eq!(bytecode_from_file(&write("c.json", r#"{"bytecode":"0x6080"}"#)).unwrap(), expected);
+        assert!(bytecode_from_file(&write("d.json", r#"{"abi":[]}"#)).is_err());
+        assert!(bytecode_from_file(&write("e.bin", "0x")).unwrap_err().to_string().contains("empty"));
+    }
+
     #[test]
     fn checksum_address_eip55_known_vectors() {
         // Parse a 40-hex string into a fixed 20-byte address.
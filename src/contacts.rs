@@ -0,0 +1,69 @@
+//! Named public keys for `encrypt --to`, kept in `<BSEC_HOME>/contacts.json` as
+//! `{ "name": "0x04..." }`.
+
+use anyhow::{anyhow, Result};
+use k256::PublicKey;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::errors::BsecError;
+use crate::wallet::{get_app_dir, hex_to_bytes, write_secure_file};
+
+fn contacts_path() -> PathBuf {
+    get_app_dir().join("contacts.json")
+}
+
+pub fn load_contacts() -> Result<BTreeMap<String, String>> {
+    match fs::read_to_string(contacts_path()) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| BsecError::ConfigError(format!("{}: {}", contacts_path().display(), e)).into()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_contacts(contacts: &BTreeMap<String, String>) -> Result<()> {
+    write_secure_file(&contacts_path(), serde_json::to_string_pretty(contacts)?.as_bytes())
+}
+
+/// A SEC1 public key in hex (`0x04...` uncompressed, `0x02...`/`0x03...` compressed).
+pub fn parse_public_key(hex: &str) -> Result<PublicKey> {
+    let bytes = hex_to_bytes(hex).map_err(|_| BsecError::InvalidRecipient(format!("'{}' is not a hex public key", hex)))?;
+    PublicKey::from_sec1_bytes(&bytes)
+        .map_err(|_| BsecError::InvalidRecipient(format!("'{}' is not a secp256k1 public key", hex)).into())
+}
+
+/// Save `pubkey` under `name`, replacing any key the name had.
+pub fn add_contact(name: &str, pubkey: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with("0x") {
+        return Err(anyhow!("contact names must be non-empty and not start with 0x"));
+    }
+    parse_public_key(pubkey)?;
+    let mut contacts = load_contacts()?;
+    contacts.insert(name.to_string(), pubkey.to_string());
+    save_contacts(&contacts)
+}
+
+/// Returns whether there was such a contact.
+pub fn remove_contact(name: &str) -> Result<bool> {
+    let mut contacts = load_contacts()?;
+    if contacts.remove(name).is_none() {
+        return Ok(false);
+    }
+    save_contacts(&contacts)?;
+    Ok(true)
+}
+
+/// The public key a `--to` argument names: a contact, or a hex public key.
+pub fn resolve_recipient(recipient: &str) -> Result<PublicKey> {
+    match load_contacts()?.get(recipient) {
+        Some(pubkey) => parse_public_key(pubkey),
+        None if recipient.starts_with("0x") || recipient.starts_with("04") => parse_public_key(recipient),
+        None => Err(BsecError::InvalidRecipient(format!(
+            "'{}' is neither a contact nor a public key (add it with `bsec contact add`)",
+            recipient
+        ))
+        .into()),
+    }
+}
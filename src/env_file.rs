@@ -1,58 +1,41 @@
 use anyhow::{anyhow, Result};
-use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
 use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
 use base64::prelude::*;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::{PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use zeroize::Zeroizing;
 
+use crate::dotenv::{Document, SyntaxError};
+use crate::errors::BsecError;
+use crate::secrets::{decrypt_text, derive_ecdh_key, encrypt_text};
+use crate::wallet::{bytes_to_hex, hex_to_bytes};
 use crate::wallet::write_secure_file;
 
-pub fn parse_env_content(content: &str) -> BTreeMap<String, String> {
-    let mut map = BTreeMap::new();
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            continue;
-        }
-        let clean_line = trimmed.strip_prefix("export ").unwrap_or(trimmed).trim();
-        if let Some((key, val)) = clean_line.split_once('=') {
-            let key = key.trim().to_string();
-            let mut val_str = val.trim();
-            let is_single_quoted = val_str.starts_with('\'') && val_str.ends_with('\'') && val_str.len() >= 2;
-            let is_double_quoted = val_str.starts_with('"') && val_str.ends_with('"') && val_str.len() >= 2;
-
-            let parsed_val = if is_single_quoted {
-                let inner = &val_str[1..val_str.len() - 1];
-                inner.replace("\\'", "'")
-            } else if is_double_quoted {
-                let inner = &val_str[1..val_str.len() - 1];
-                inner
-                    .replace("\\n", "\n")
-                    .replace("\\t", "\t")
-                    .replace("\\r", "\r")
-                    .replace("\\\"", "\"")
-                    .replace("\\\\", "\\")
-            } else {
-                if let Some(pos) = val_str.find(" #") {
-                    val_str = val_str[..pos].trim();
-                } else if let Some(pos) = val_str.find('#') {
-                    if pos == 0 {
-                        val_str = "";
-                    } else if val_str.as_bytes()[pos - 1].is_ascii_whitespace() {
-                        val_str = val_str[..pos].trim();
-                    }
-                }
-                val_str.to_string()
-            };
+/// Parse `.env` content, expanding references (see `dotenv`).
+pub fn parse_env_content(content: &str) -> Result<BTreeMap<String, String>> {
+    crate::dotenv::parse(content).map_err(|e| BsecError::ParseError(e.to_string()).into())
+}
 
-            map.insert(key, parsed_val);
-        }
-    }
-    map
+fn located(path: &Path, e: SyntaxError) -> anyhow::Error {
+    BsecError::ParseError(format!("{}:{}", path.display(), e)).into()
+}
+
+/// `parse_env_content` for the contents of `path`, naming it in errors.
+fn parse_env_file(path: &Path, content: &str) -> Result<BTreeMap<String, String>> {
+    crate::dotenv::parse(content).map_err(|e| located(path, e))
+}
+
+/// The contents of `path` as an editable `Document`, naming it in errors. Commands that
+/// rewrite an env file go through this so the rest of the file comes back as it was.
+fn parse_env_document(path: &Path, content: &str) -> Result<Document> {
+    Document::parse(content).map_err(|e| located(path, e))
 }
 
 pub fn parse_json_content(content: &str) -> Result<BTreeMap<String, String>> {
@@ -94,27 +77,16 @@ pub fn convert_env_file(
         .unwrap_or("")
         .to_lowercase();
 
-    let mut map = match ext.as_str() {
-        "env" | "local" | "dev" | "prod" => parse_env_content(&content),
-        "json" => parse_json_content(&content)?,
-        _ => parse_env_content(&content),
-    };
-
-    if let Some(pref) = prefix {
-        let mut new_map = BTreeMap::new();
-        for (k, v) in map {
-            new_map.insert(format!("{}{}", pref, k), v);
+    let (map, doc) = match ext.as_str() {
+        "json" => (parse_json_content(&content)?, None),
+        _ => {
+            let doc = parse_env_document(input_file, &content)?;
+            (doc.values().map_err(|e| located(input_file, e))?, Some(doc))
         }
-        map = new_map;
-    }
+    };
 
-    if let Some(suf) = suffix {
-        let mut new_map = BTreeMap::new();
-        for (k, v) in map {
-            new_map.insert(format!("{}{}", k, suf), v);
-        }
-        map = new_map;
-    }
+    let rename = |k: &str| format!("{}{}{}", prefix.unwrap_or(""), k, suffix.unwrap_or(""));
+    let map: BTreeMap<String, String> = map.into_iter().map(|(k, v)| (rename(&k), v)).collect();
 
     let output_str = if let Some(embed_prefix) = embed {
         let mut lines = Vec::new();
@@ -135,11 +107,23 @@ pub fn convert_env_file(
             }
             "yaml" | "yml" => yaml_serde::to_string(&map)?,
             _ => {
-                let mut lines = Vec::new();
-                for (k, v) in map {
-                    lines.push(format!("{}='{}'", k, v));
-                }
-                lines.join("\n")
+                // An env input keeps its comments and order. References are resolved first, as
+                // renamed keys would no longer match them.
+                let doc = match doc {
+                    Some(mut doc) => {
+                        doc.resolve_references().map_err(|e| located(input_file, e))?;
+                        doc.rename_keys(rename);
+                        doc
+                    }
+                    None => {
+                        let mut doc = Document::default();
+                        for (k, v) in &map {
+                            doc.set(k, v);
+                        }
+                        doc
+                    }
+                };
+                doc.to_string()
             }
         }
     };
@@ -150,74 +134,125 @@ pub fn convert_env_file(
     Ok(())
 }
 
+/// Check `env_path` against the schema at `schema_path` (see `schema`), reporting every
+/// problem as `file:line: KEY: problem` on stderr. Missing keys with a default (every key of a
+/// key-only schema) are appended first; a missing env file is created from the schema. A JSON
+/// Schema document is checked by `validate_against_json_schema` instead.
 pub fn validate_env_file(schema_path: &Path, env_path: &Path) -> Result<()> {
     if !schema_path.exists() {
         return Err(anyhow!("Schema file '{}' does not exist.", schema_path.display()));
     }
 
     let schema_content = fs::read_to_string(schema_path)?;
-    let schema_map = parse_env_content(&schema_content);
+    if let Some(json_schema) = crate::json_schema::JsonSchema::detect(&schema_content)
+        .map_err(|e| BsecError::ConfigError(format!("{}: {}", schema_path.display(), e)))?
+    {
+        return validate_against_json_schema(&json_schema, env_path);
+    }
+    let schema = crate::schema::Schema::parse(schema_path, &schema_content)?;
 
     if !env_path.exists() {
         println!("Environment file '{}' does not exist. Creating from schema...", env_path.display());
-        let mut lines = Vec::new();
-        for k in schema_map.keys() {
-            lines.push(format!("{}=", k));
-        }
         // Derived from an env file; use the hardened writer (mode 0600, O_NOFOLLOW) so any
         // future secret values live in a file that is never group/world readable.
-        write_secure_file(env_path, lines.join("\n").as_bytes())?;
+        write_secure_file(env_path, schema.template().to_string().as_bytes())?;
         println!("Environment file created successfully.");
-        return Ok(());
     }
 
     let env_content = fs::read_to_string(env_path)?;
-    let env_map = parse_env_content(&env_content);
-
-    let mut missing_keys = Vec::new();
-    for k in schema_map.keys() {
-        if !env_map.contains_key(k) {
-            missing_keys.push(k);
-        }
-    }
-
-    if !missing_keys.is_empty() {
+    let mut env_doc = parse_env_document(env_path, &env_content)?;
+    let env_keys = env_doc.keys();
+
+    let fixable: Vec<(&str, &str)> = schema
+        .vars
+        .iter()
+        .filter(|v| !env_keys.contains(&v.name))
+        .filter_map(|v| v.default.as_deref().map(|d| (v.name.as_str(), d)))
+        .collect();
+    if !fixable.is_empty() {
         println!("Warning: The following keys are missing in '{}':", env_path.display());
-        for key in &missing_keys {
+        for (key, _) in &fixable {
             println!("  - {}", key);
         }
-        let trimmed_content = env_content.trim_end();
-        let mut new_lines = if trimmed_content.is_empty() {
-            Vec::new()
-        } else {
-            vec![trimmed_content.to_string()]
-        };
-        for key in missing_keys {
-            new_lines.push(format!("{}=", key));
+        for (key, default) in &fixable {
+            env_doc.set(key, default);
         }
         // Rewrites the existing .env with its real values plus appended keys; use the
         // hardened writer (mode 0600, O_NOFOLLOW) to preserve restrictive perms and reject symlinks.
-        write_secure_file(env_path, new_lines.join("\n").as_bytes())?;
+        write_secure_file(env_path, env_doc.to_string().as_bytes())?;
         println!("Updated '{}' with missing keys.", env_path.display());
-    } else {
-        println!("Environment file '{}' is valid and matches schema.", env_path.display());
     }
 
+    let values = env_doc.values().map_err(|e| located(env_path, e))?;
+    let lines = env_doc.key_lines();
+    let mut problems = Vec::new();
+    for var in &schema.vars {
+        let at = match lines.get(&var.name) {
+            Some(line) => format!("{}:{}", env_path.display(), line),
+            None => env_path.display().to_string(),
+        };
+        match values.get(&var.name).map(String::as_str) {
+            None | Some("") if var.required => {
+                problems.push(format!("{}: {}: required but {}", at, var.name, if lines.contains_key(&var.name) { "empty" } else { "not set" }))
+            }
+            None | Some("") => {}
+            Some(value) => problems.extend(var.check(value).into_iter().map(|p| format!("{}: {}: {}", at, var.name, p))),
+        }
+    }
+
+    report_violations(env_path, &problems)
+}
+
+/// Print each problem on stderr and fail with `SchemaViolation` if there are any.
+fn report_violations(env_path: &Path, problems: &[String]) -> Result<()> {
+    if !problems.is_empty() {
+        for problem in problems {
+            eprintln!("{}", problem);
+        }
+        return Err(BsecError::SchemaViolation { file: env_path.display().to_string(), count: problems.len() }.into());
+    }
+    println!("Environment file '{}' is valid and matches schema.", env_path.display());
     Ok(())
 }
 
+/// Check the env map of `env_path` against a JSON Schema (see `json_schema`), reporting each
+/// violation as `file:line: /pointer: problem`, with the line of the key the value came from.
+fn validate_against_json_schema(schema: &crate::json_schema::JsonSchema, env_path: &Path) -> Result<()> {
+    let content = fs::read_to_string(env_path)
+        .map_err(|e| anyhow!("Environment file '{}' could not be read: {}", env_path.display(), e))?;
+    let doc = parse_env_document(env_path, &content)?;
+    let values = doc.values().map_err(|e| located(env_path, e))?;
+    let lines = doc.key_lines();
+    let (instance, sources, conflicts) = schema.instance(&values);
+
+    let problems: Vec<String> = conflicts
+        .into_iter()
+        .chain(schema.validate(&instance))
+        .map(|v| {
+            // The env key of the value, or of the value it sits in (`/HOSTS/1` -> `HOSTS`).
+            let line = sources
+                .iter()
+                .filter(|(p, _)| v.pointer == **p || v.pointer.starts_with(&format!("{}/", p)))
+                .find_map(|(_, key)| lines.get(key));
+            let at = match line {
+                Some(line) => format!("{}:{}", env_path.display(), line),
+                None => env_path.display().to_string(),
+            };
+            let pointer = if v.pointer.is_empty() { "(root)" } else { v.pointer.as_str() };
+            format!("{}: {}: {}", at, pointer, v.message)
+        })
+        .collect();
+    report_violations(env_path, &problems)
+}
+
 pub fn generate_template(env_path: &Path, output_path: &Path) -> Result<()> {
     let content = fs::read_to_string(env_path)?;
-    let map = parse_env_content(&content);
-
-    let mut lines = Vec::new();
-    for (k, _) in map {
-        lines.push(format!("{}=#Your {} here", k, k));
-    }
+    let doc = parse_env_document(env_path, &content)?;
+    let template = doc.to_template(|k| Some(format!("Your {} here", k)));
 
     // Derived from an env file; route through the hardened writer (mode 0600, O_NOFOLLOW)
     // so anything produced from env-file contents keeps restrictive perms and rejects symlinks.
-    write_secure_file(output_path, lines.join("\n").as_bytes())?;
+    write_secure_file(output_path, template.to_string().as_bytes())?;
     Ok(())
 }
 
@@ -232,7 +267,7 @@ pub fn log_env_var(var_name: &str, file_path: &Path) -> Result<()> {
     let map = if ext == "json" {
         parse_json_content(&content)?
     } else {
-        parse_env_content(&content)
+        parse_env_file(file_path, &content)?
     };
 
     if let Some(val) = map.get(var_name) {
@@ -290,47 +325,44 @@ pub fn get_encryption_password(env_file_name: &str, provided_pwd: Option<&str>)
     ))
 }
 
-pub fn encrypt_env_file(input_file: &Path, output_file: Option<&Path>, password: Option<&str>) -> Result<PathBuf> {
-    let content = Zeroizing::new(fs::read_to_string(input_file)?);
-    let file_name = input_file.to_string_lossy().to_string();
-    let pwd = Zeroizing::new(get_encryption_password(&file_name, password)?);
-
-    let mut salt = [0u8; 16];
-    aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut aes_gcm::aead::OsRng, &mut salt);
-    let key = Zeroizing::new(crate::wallet::derive_key(&pwd, &salt)?);
-
-    let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|_| anyhow!("key init failed"))?;
-    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
-    let cipher_text = cipher
-        .encrypt(&nonce, content.as_bytes())
-        .map_err(|_| anyhow!("encryption failed"))?;
-
-    let payload = format!(
-        "{}:{}:{}",
-        BASE64_STANDARD.encode(salt),
-        BASE64_STANDARD.encode(nonce),
-        BASE64_STANDARD.encode(cipher_text)
-    );
+/// Whether `path` names an encrypted env file (`.enc` or `.encrypted`).
+pub fn is_encrypted(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".enc") || name.ends_with(".encrypted")
+}
 
-    let target_path = match output_file {
-        Some(p) => p.to_path_buf(),
-        None => PathBuf::from(format!("{}.enc", input_file.display())),
-    };
+/// `path` without its `.enc`/`.encrypted` suffix: the plaintext's name, which also picks the
+/// password (see `get_encryption_password`).
+fn plain_name(path: &Path) -> String {
+    let name = path.to_string_lossy();
+    name.trim_end_matches(".enc").trim_end_matches(".encrypted").to_string()
+}
 
-    write_secure_file(&target_path, payload.as_bytes())?;
-    Ok(target_path)
+/// Encrypt env file content as a v2 `Envelope`: AES-256-GCM under an Argon2id key from `pwd`,
+/// with the current cost parameters recorded next to a fresh salt.
+fn seal(plain: &str, pwd: &str) -> Result<String> {
+    let kdf = Kdf::new();
+    let key = kdf.derive(pwd)?;
+    Envelope::v2(Some(kdf), Vec::new(), encrypt_text(plain, &key)?)
 }
 
-pub fn decrypt_env_file(input_file: &Path, output_file: Option<&Path>, password: Option<&str>) -> Result<PathBuf> {
-    let payload = fs::read_to_string(input_file)?;
-    let file_name = input_file.to_string_lossy().to_string();
-    let clean_name = file_name.trim_end_matches(".enc").trim_end_matches(".encrypted");
-    let pwd = Zeroizing::new(get_encryption_password(clean_name, password)?);
+/// Decrypt a `seal` payload: a v2 `Envelope`, or v1's `salt:nonce:ciphertext` (base64, with
+/// the default Argon2 parameters).
+fn open(payload: &str, pwd: &str) -> Result<Zeroizing<String>> {
+    if let Some(envelope) = Envelope::detect(payload) {
+        envelope.check()?;
+        let Some(kdf) = &envelope.kdf else {
+            return Err(anyhow!("this file is encrypted to wallets, not a password"));
+        };
+        let key = kdf.derive(pwd)?;
+        let plain = decrypt_text(&envelope.content, &key).map_err(|_| anyhow!("Decryption failed. Check your password."))?;
+        return Ok(Zeroizing::new(plain));
+    }
 
     let parts: Vec<&str> = payload.trim().split(':').collect();
     let (key, nonce_b64, cipher_b64) = if parts.len() == 3 {
         let salt = BASE64_STANDARD.decode(parts[0]).map_err(|_| anyhow!("Invalid salt"))?;
-        let key = Zeroizing::new(crate::wallet::derive_key(&pwd, &salt)?);
+        let key = Zeroizing::new(crate::wallet::derive_key(pwd, &salt)?);
         (key, parts[1], parts[2])
     } else {
         return Err(anyhow!("Invalid encrypted payload format"));
@@ -346,44 +378,498 @@ pub fn decrypt_env_file(input_file: &Path, output_file: Option<&Path>, password:
             .decrypt(nonce, cipher_bytes.as_ref())
             .map_err(|_| anyhow!("Decryption failed. Check your password."))?,
     );
-    let plain_str = Zeroizing::new(String::from_utf8(plain_bytes.to_vec())?);
+    Ok(Zeroizing::new(String::from_utf8(plain_bytes.to_vec())?))
+}
+
+/// Prefix of a per-value encrypted value; see `ValueSealer`.
+const SEALED_VALUE: &str = "enc:v2:";
+/// Prefix of per-value encrypted values written before the KDF parameters were recorded in
+/// them, which `derive_key`'s defaults open.
+const SEALED_VALUE_V1: &str = "enc:v1:";
+
+fn is_sealed_value(value: &str) -> bool {
+    value.starts_with(SEALED_VALUE) || value.starts_with(SEALED_VALUE_V1)
+}
+
+/// How an env file is protected on disk.
+#[derive(Clone, Copy, PartialEq)]
+enum Sealing {
+    Plain,
+    /// One `seal` payload for the whole file: a v2 `Envelope` with a `kdf`, or a v1 `.enc`.
+    Whole,
+    /// Cleartext keys with `enc:v2:` (or `enc:v1:`) values.
+    Values,
+    /// An `Envelope` with `recipients` (`encrypt --to`).
+    Recipients,
+}
+
+fn sealing(path: &Path, content: &str) -> Sealing {
+    if let Some(envelope) = Envelope::detect(content) {
+        if envelope.recipients.is_empty() { Sealing::Whole } else { Sealing::Recipients }
+    } else if has_sealed_values(content) {
+        Sealing::Values
+    } else if is_encrypted(path) {
+        Sealing::Whole
+    } else {
+        Sealing::Plain
+    }
+}
+
+/// Whether `content` is an env file with at least one `KEY=enc:v2:...` (or v1) value;
+/// mentioning the prefix in a comment or inside another value does not count.
+fn has_sealed_values(content: &str) -> bool {
+    (content.contains(SEALED_VALUE) || content.contains(SEALED_VALUE_V1))
+        && Document::parse(content).is_ok_and(|doc| doc.raw_values().any(|(_, v)| is_sealed_value(v)))
+}
+
+/// The key derivation, nonce and ciphertext of a sealed value: `enc:v2:` records its Argon2id
+/// costs, `enc:v1:` used `derive_key`'s. `None` if it is malformed.
+fn parse_sealed_value(sealed: &str) -> Option<(Kdf, &str, &str)> {
+    if let Some(rest) = sealed.strip_prefix(SEALED_VALUE) {
+        let [m_cost, t_cost, p_cost, salt, nonce, cipher_text] = rest.split(':').collect::<Vec<_>>()[..] else {
+            return None;
+        };
+        let kdf = Kdf {
+            id: KDF_ARGON2ID.to_string(),
+            m_cost: m_cost.parse().ok()?,
+            t_cost: t_cost.parse().ok()?,
+            p_cost: p_cost.parse().ok()?,
+            salt: salt.to_string(),
+        };
+        return Some((kdf, nonce, cipher_text));
+    }
+    let [salt, nonce, cipher_text] = sealed.strip_prefix(SEALED_VALUE_V1)?.split(':').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    Some((Kdf::with_salt(argon2::Params::default(), salt.to_string()), nonce, cipher_text))
+}
+
+/// Per-value encryption: `KEY=enc:v2:<m_cost>:<t_cost>:<p_cost>:<salt>:<nonce>:<ciphertext>`
+/// (salt, nonce and ciphertext in base64), AES-256-GCM with an Argon2id key from the file's
+/// password and the key name as associated data, so a value moved to another key fails to
+/// decrypt. The Argon2 costs are recorded like a v2 `Envelope`'s `kdf`, so raising the
+/// defaults does not break values already written; `enc:v1:` values, which lack them, still
+/// open. The value is sealed as written, quotes and `$` references included, and comes back
+/// the same way. Values sealed together share one salt and costs, so the key is derived once
+/// per file. Opening remembers each ciphertext and sealing reuses it for a value that did not
+/// change, so editing one value changes one line of the file.
+struct ValueSealer {
+    pwd: Zeroizing<String>,
+    /// Derived keys by `Kdf::spec`.
+    keys: HashMap<String, Zeroizing<[u8; 32]>>,
+    kdf: Option<Kdf>,
+    /// `(key, value as written)` -> the sealed value it was opened from.
+    opened: HashMap<(String, String), String>,
+}
+
+impl ValueSealer {
+    fn new(pwd: &str) -> Self {
+        ValueSealer { pwd: Zeroizing::new(pwd.to_string()), keys: HashMap::new(), kdf: None, opened: HashMap::new() }
+    }
+
+    fn cipher(&mut self, kdf: &Kdf) -> Result<Aes256Gcm> {
+        let spec = kdf.spec();
+        if !self.keys.contains_key(&spec) {
+            let key = kdf.derive(&self.pwd)?;
+            self.keys.insert(spec.clone(), key);
+        }
+        Aes256Gcm::new_from_slice(self.keys[&spec].as_ref()).map_err(|_| anyhow!("key init failed"))
+    }
+
+    fn open(&mut self, name: &str, sealed: &str) -> Result<String> {
+        let invalid = || anyhow!("{}: invalid encrypted value", name);
+        let (kdf, nonce, cipher_text) = parse_sealed_value(sealed).ok_or_else(invalid)?;
+        let nonce = BASE64_STANDARD.decode(nonce).map_err(|_| invalid())?;
+        let cipher_text = BASE64_STANDARD.decode(cipher_text).map_err(|_| invalid())?;
+        if nonce.len() != 12 || BASE64_STANDARD.decode(&kdf.salt).is_err() {
+            return Err(invalid());
+        }
+        let plain = self
+            .cipher(&kdf)?
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &cipher_text, aad: name.as_bytes() })
+            .map_err(|_| anyhow!("Decryption of {} failed. Check your password.", name))?;
+        let plain = String::from_utf8(plain)?;
+        if sealed.starts_with(SEALED_VALUE) {
+            self.kdf.get_or_insert(kdf);
+        }
+        self.opened.insert((name.to_string(), plain.clone()), sealed.to_string());
+        Ok(plain)
+    }
+
+    fn seal(&mut self, name: &str, plain: &str) -> Result<String> {
+        if let Some(sealed) = self.opened.get(&(name.to_string(), plain.to_string())) {
+            return Ok(sealed.clone());
+        }
+        let kdf = self.kdf.get_or_insert_with(Kdf::new).clone();
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let cipher_text = self
+            .cipher(&kdf)?
+            .encrypt(&nonce, Payload { msg: plain.as_bytes(), aad: name.as_bytes() })
+            .map_err(|_| anyhow!("encryption failed"))?;
+        Ok(format!(
+            "{}{}:{}:{}",
+            SEALED_VALUE,
+            kdf.spec(),
+            BASE64_STANDARD.encode(nonce),
+            BASE64_STANDARD.encode(cipher_text)
+        ))
+    }
+
+    /// Decrypt every `enc:v2:` and `enc:v1:` value of `doc` in place. Any other non-empty
+    /// value is refused: it carries no MAC, so anyone able to write the file could have
+    /// planted it among the sealed ones.
+    fn open_all(&mut self, doc: &mut Document) -> Result<()> {
+        doc.map_raw_values(|k, v| {
+            if v.is_empty() {
+                Ok(None)
+            } else if is_sealed_value(v) {
+                self.open(k, v).map(Some)
+            } else {
+                Err(BsecError::ParseError(format!(
+                    "{}: value is not encrypted, but the file's other values are; remove the line and add it back with `bsec set`",
+                    k
+                ))
+                .into())
+            }
+        })
+    }
+
+    /// Encrypt every other non-empty value of `doc` in place.
+    fn seal_all(&mut self, doc: &mut Document) -> Result<()> {
+        doc.map_raw_values(|k, v| {
+            if v.is_empty() || is_sealed_value(v) {
+                return Ok(None);
+            }
+            self.seal(k, v).map(Some)
+        })
+    }
+}
+
+/// `format` of an encrypted env file `Envelope`.
+const ENVELOPE_FORMAT: &str = "bsec-env";
+/// The `Envelope` version this build writes, and the newest it reads.
+const ENVELOPE_VERSION: u32 = 2;
+/// `format` of `encrypt --to` files written before v2: an `Envelope` with only
+/// `recipients` and `content`.
+const RECIPIENTS_FORMAT: &str = "bsec-recipients-v1";
+const CIPHER_AES_256_GCM: &str = "aes-256-gcm";
+const KDF_ARGON2ID: &str = "argon2id";
+/// Largest Argon2 costs a file may ask for (2 GiB, 64 passes, 16 lanes), so a crafted header
+/// cannot make opening it exhaust memory or time.
+const MAX_KDF_COST: (u32, u32, u32) = (2 * 1024 * 1024, 64, 16);
+
+/// An encrypted env file, format v2: a JSON header with the magic, the version, the cipher,
+/// and how the content key is obtained, either derived from a password through `kdf` or
+/// wrapped for each of `recipients`. Readers check the version and algorithm ids, and use the
+/// recorded KDF parameters, so raising the defaults does not break files already written.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cipher: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<Kdf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    recipients: Vec<WrappedKey>,
+    /// `nonce:ciphertext` under the content key.
+    content: String,
+}
+
+/// Argon2id parameters and salt (base64) of a password-sealed `Envelope` or sealed value.
+#[derive(Serialize, Deserialize, Clone)]
+struct Kdf {
+    id: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+}
+
+impl Kdf {
+    /// The current default parameters with a fresh salt.
+    fn new() -> Self {
+        let mut salt = [0u8; 16];
+        aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut OsRng, &mut salt);
+        Self::with_salt(argon2::Params::default(), BASE64_STANDARD.encode(salt))
+    }
+
+    fn with_salt(params: argon2::Params, salt: String) -> Self {
+        Kdf {
+            id: KDF_ARGON2ID.to_string(),
+            m_cost: params.m_cost(),
+            t_cost: params.t_cost(),
+            p_cost: params.p_cost(),
+            salt,
+        }
+    }
+
+    /// `m_cost:t_cost:p_cost:salt`, as an `enc:v2:` value records it.
+    fn spec(&self) -> String {
+        format!("{}:{}:{}:{}", self.m_cost, self.t_cost, self.p_cost, self.salt)
+    }
+
+    fn derive(&self, pwd: &str) -> Result<Zeroizing<[u8; 32]>> {
+        if self.id != KDF_ARGON2ID {
+            return Err(BsecError::ParseError(format!("unsupported key derivation '{}'", self.id)).into());
+        }
+        let (m_cost, t_cost, p_cost) = MAX_KDF_COST;
+        if self.m_cost > m_cost || self.t_cost > t_cost || self.p_cost > p_cost {
+            return Err(BsecError::ParseError(format!(
+                "refusing Argon2 costs m={} t={} p={} (at most m={} t={} p={})",
+                self.m_cost, self.t_cost, self.p_cost, m_cost, t_cost, p_cost
+            ))
+            .into());
+        }
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| BsecError::ParseError(format!("invalid Argon2 parameters: {}", e)))?;
+        let salt = BASE64_STANDARD.decode(&self.salt).map_err(|_| anyhow!("Invalid salt"))?;
+        Ok(Zeroizing::new(crate::wallet::derive_key_with(pwd, &salt, params)?))
+    }
+}
+
+impl Envelope {
+    fn v2(kdf: Option<Kdf>, recipients: Vec<WrappedKey>, content: String) -> Result<String> {
+        let envelope = Envelope {
+            format: ENVELOPE_FORMAT.to_string(),
+            version: Some(ENVELOPE_VERSION),
+            cipher: Some(CIPHER_AES_256_GCM.to_string()),
+            kdf,
+            recipients,
+            content,
+        };
+        Ok(serde_json::to_string_pretty(&envelope)? + "\n")
+    }
+
+    /// `content` as an envelope, if it is one (of any version).
+    fn detect(content: &str) -> Option<Self> {
+        if !content.trim_start().starts_with('{') {
+            return None;
+        }
+        serde_json::from_str::<Envelope>(content)
+            .ok()
+            .filter(|e| e.format == ENVELOPE_FORMAT || e.format == RECIPIENTS_FORMAT)
+    }
+
+    /// Fail unless this build can open the envelope.
+    fn check(&self) -> Result<()> {
+        if self.format == RECIPIENTS_FORMAT {
+            return Ok(());
+        }
+        match self.version {
+            Some(ENVELOPE_VERSION) => {}
+            Some(v) if v > ENVELOPE_VERSION => {
+                return Err(BsecError::ParseError(format!(
+                    "encrypted with format version {}, newer than this bsec reads ({}); upgrade bsec",
+                    v, ENVELOPE_VERSION
+                ))
+                .into());
+            }
+            v => return Err(BsecError::ParseError(format!("unknown encrypted file version {:?}", v)).into()),
+        }
+        if self.cipher.as_deref() != Some(CIPHER_AES_256_GCM) {
+            return Err(BsecError::ParseError(format!("unsupported cipher {:?}", self.cipher)).into());
+        }
+        if self.kdf.is_some() != self.recipients.is_empty() {
+            return Err(BsecError::ParseError("an encrypted file needs either a kdf or recipients".into()).into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct WrappedKey {
+    /// The recipient's uncompressed SEC1 public key.
+    pubkey: String,
+    ephemeral_pubkey: String,
+    /// The base64 file key, `nonce:ciphertext` under the ECDH-derived key.
+    key: String,
+}
+
+fn pubkey_hex(pk: &PublicKey) -> String {
+    format!("0x{}", bytes_to_hex(pk.to_encoded_point(false).as_bytes()))
+}
+
+/// Encrypt to wallet public keys (`encrypt --to`) as an `Envelope` with recipients: the content
+/// is sealed with a random file key, and the file key is wrapped for each recipient under an
+/// ephemeral ECDH key through HKDF, as `share` wraps a secret's content key.
+fn seal_to(recipients: &[PublicKey], plain: &str) -> Result<String> {
+    let mut file_key = Zeroizing::new([0u8; 32]);
+    aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut OsRng, file_key.as_mut());
+    let file_key_b64 = Zeroizing::new(BASE64_STANDARD.encode(file_key.as_ref()));
+    let mut wrapped: Vec<WrappedKey> = Vec::new();
+    for pk in recipients {
+        if wrapped.iter().any(|w| w.pubkey == pubkey_hex(pk)) {
+            continue;
+        }
+        let ephemeral = SecretKey::random(&mut OsRng);
+        let shared = k256::ecdh::diffie_hellman(ephemeral.to_nonzero_scalar(), pk.as_affine());
+        let wrapper = Zeroizing::new(derive_ecdh_key(shared.raw_secret_bytes())?);
+        wrapped.push(WrappedKey {
+            pubkey: pubkey_hex(pk),
+            ephemeral_pubkey: pubkey_hex(&ephemeral.public_key()),
+            key: encrypt_text(&file_key_b64, &wrapper)?,
+        });
+    }
+    Envelope::v2(None, wrapped, encrypt_text(plain, &file_key)?)
+}
+
+/// Decrypt an `Envelope` with recipients with the local wallet, unlocked with `password` if it is
+/// encrypted, through the entry for the wallet's public key.
+fn open_with_wallet(path: &Path, envelope: &Envelope, password: Option<&str>) -> Result<Zeroizing<String>> {
+    let wallet = crate::wallet::get_wallet_info(password)?;
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet.private_key)?);
+    let secret = SecretKey::from_slice(&priv_bytes).map_err(|e| anyhow!("Invalid wallet private key: {}", e))?;
+    let mine = pubkey_hex(&secret.public_key());
+    let entry = envelope
+        .recipients
+        .iter()
+        .find(|r| crate::contacts::parse_public_key(&r.pubkey).is_ok_and(|pk| pubkey_hex(&pk) == mine))
+        .ok_or_else(|| {
+            BsecError::PermissionDenied(format!("wallet {} is not a recipient of '{}'", wallet.address, path.display()))
+        })?;
+    let ephemeral = crate::contacts::parse_public_key(&entry.ephemeral_pubkey)?;
+    let shared = k256::ecdh::diffie_hellman(secret.to_nonzero_scalar(), ephemeral.as_affine());
+    let wrapper = Zeroizing::new(derive_ecdh_key(shared.raw_secret_bytes())?);
+    let key_b64 = Zeroizing::new(decrypt_text(&entry.key, &wrapper).map_err(|_| anyhow!("Failed to unwrap the file key"))?);
+    let key_vec = Zeroizing::new(BASE64_STANDARD.decode(key_b64.as_str()).map_err(|_| anyhow!("Failed to decode the file key"))?);
+    let file_key: Zeroizing<[u8; 32]> =
+        Zeroizing::new(key_vec.as_slice().try_into().map_err(|_| anyhow!("Invalid file key length"))?);
+    Ok(Zeroizing::new(decrypt_text(&envelope.content, &file_key)?))
+}
+
+/// An env file's plaintext, with what it takes to seal an edited version the same way.
+struct Opened {
+    sealing: Sealing,
+    text: Zeroizing<String>,
+    pwd: Zeroizing<String>,
+    values: Option<ValueSealer>,
+    recipients: Vec<PublicKey>,
+}
+
+impl Opened {
+    /// Decrypt `content` sealed as `sealing`. Password-sealed files take their password from
+    /// `get_encryption_password`; recipient-sealed ones need the wallet, unlocked with
+    /// `password`. Empty content opens as an empty file.
+    fn open(path: &Path, sealing: Sealing, content: &str, password: Option<&str>) -> Result<Self> {
+        let mut opened = Opened {
+            sealing,
+            text: Zeroizing::new(content.to_string()),
+            pwd: Zeroizing::new(String::new()),
+            values: None,
+            recipients: Vec::new(),
+        };
+        if matches!(sealing, Sealing::Whole | Sealing::Values) {
+            opened.pwd = Zeroizing::new(get_encryption_password(&plain_name(path), password)?);
+        }
+        match sealing {
+            Sealing::Plain => {}
+            Sealing::Whole if content.is_empty() => {}
+            Sealing::Whole => opened.text = open(content, &opened.pwd)?,
+            Sealing::Values => {
+                let mut doc = parse_env_document(path, content)?;
+                let mut sealer = ValueSealer::new(&opened.pwd);
+                sealer.open_all(&mut doc)?;
+                opened.text = Zeroizing::new(doc.to_string());
+                opened.values = Some(sealer);
+            }
+            Sealing::Recipients => {
+                let envelope = Envelope::detect(content)
+                    .ok_or_else(|| BsecError::ParseError(format!("{}: not an encrypted env file", path.display())))?;
+                envelope.check()?;
+                opened.text = open_with_wallet(path, &envelope, password)?;
+                opened.recipients = envelope
+                    .recipients
+                    .iter()
+                    .map(|r| crate::contacts::parse_public_key(&r.pubkey))
+                    .collect::<Result<_>>()?;
+            }
+        }
+        Ok(opened)
+    }
+
+    /// The file content for the edited plaintext `text`: sealed as before, for the same
+    /// password or recipients, re-encrypting only changed values of a per-value file.
+    fn reseal(&mut self, path: &Path, text: &str) -> Result<String> {
+        match self.sealing {
+            Sealing::Plain => Ok(text.to_string()),
+            Sealing::Whole => seal(text, &self.pwd),
+            Sealing::Values => {
+                let mut doc = parse_env_document(path, text)?;
+                self.values.get_or_insert_with(|| ValueSealer::new(&self.pwd)).seal_all(&mut doc)?;
+                Ok(doc.to_string())
+            }
+            Sealing::Recipients => seal_to(&self.recipients, text),
+        }
+    }
+}
+
+/// Encrypt `input_file` with a password as one payload, or with `per_value` each value on its
+/// own (see `ValueSealer`) so the result stays diffable. With `to` (contacts or public keys)
+/// it is encrypted to those wallets instead, and needs no password.
+pub fn encrypt_env_file(
+    input_file: &Path,
+    output_file: Option<&Path>,
+    password: Option<&str>,
+    per_value: bool,
+    to: &[String],
+) -> Result<PathBuf> {
+    let content = Zeroizing::new(fs::read_to_string(input_file)?);
+    let payload = if !to.is_empty() {
+        let recipients = to.iter().map(|r| crate::contacts::resolve_recipient(r)).collect::<Result<Vec<_>>>()?;
+        parse_env_file(input_file, &content)?;
+        seal_to(&recipients, &content)?
+    } else {
+        let file_name = input_file.to_string_lossy().to_string();
+        let pwd = Zeroizing::new(get_encryption_password(&file_name, password)?);
+        if per_value {
+            let mut doc = parse_env_document(input_file, &content)?;
+            ValueSealer::new(&pwd).seal_all(&mut doc)?;
+            doc.to_string()
+        } else {
+            seal(&content, &pwd)?
+        }
+    };
+
+    let target_path = match output_file {
+        Some(p) => p.to_path_buf(),
+        None => PathBuf::from(format!("{}.enc", input_file.display())),
+    };
+
+    write_secure_file(&target_path, payload.as_bytes())?;
+    Ok(target_path)
+}
+
+pub fn decrypt_env_file(input_file: &Path, output_file: Option<&Path>, password: Option<&str>) -> Result<PathBuf> {
+    let payload = fs::read_to_string(input_file)?;
+    let clean_name = plain_name(input_file);
+    // Asked to decrypt, a file with no other marks is a whole-file payload whatever its name.
+    let sealing = match sealing(input_file, &payload) {
+        Sealing::Plain => Sealing::Whole,
+        other => other,
+    };
+    let plain_str = Opened::open(input_file, sealing, &payload, password)?.text;
 
     let target_path = match output_file {
         Some(p) => p.to_path_buf(),
         None => PathBuf::from(clean_name),
     };
+    if target_path == input_file {
+        return Err(anyhow!("'{}' has no .enc suffix; pass --out to choose where to decrypt it", input_file.display()));
+    }
 
     write_secure_file(&target_path, plain_str.as_bytes())?;
     Ok(target_path)
 }
 
 pub fn load_and_parse_env(env_path: &Path, password: Option<&str>) -> Result<BTreeMap<String, String>> {
-    let file_str = env_path.to_string_lossy();
-    if file_str.ends_with(".enc") || file_str.ends_with(".encrypted") {
-        let payload = fs::read_to_string(env_path)?;
-        let clean_name = file_str.trim_end_matches(".enc").trim_end_matches(".encrypted");
-        let pwd = get_encryption_password(clean_name, password)?;
-
-        let parts: Vec<&str> = payload.trim().split(':').collect();
-        let (key, nonce_b64, cipher_b64) = if parts.len() == 3 {
-            let salt = BASE64_STANDARD.decode(parts[0]).map_err(|_| anyhow!("Invalid salt"))?;
-            let key = crate::wallet::derive_key(&pwd, &salt)?;
-            (key, parts[1], parts[2])
-        } else {
-            return Err(anyhow!("Invalid encrypted payload format"));
-        };
-
-        let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("key init failed"))?;
-        let nonce_bytes = BASE64_STANDARD.decode(nonce_b64)?;
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        let cipher_bytes = BASE64_STANDARD.decode(cipher_b64)?;
-        let plain_bytes = cipher
-            .decrypt(nonce, cipher_bytes.as_ref())
-            .map_err(|_| anyhow!("Decryption failed. Check password."))?;
-        let plain_str = String::from_utf8(plain_bytes)?;
-        Ok(parse_env_content(&plain_str))
+    let content = fs::read_to_string(env_path)?;
+    let sealing = sealing(env_path, &content);
+    if sealing != Sealing::Plain {
+        let plain_str = Opened::open(env_path, sealing, &content, password)?.text;
+        parse_env_file(env_path, &plain_str)
     } else {
-        let content = fs::read_to_string(env_path)?;
         let ext = env_path
             .extension()
             .and_then(|e| e.to_str())
@@ -392,11 +878,279 @@ pub fn load_and_parse_env(env_path: &Path, password: Option<&str>) -> Result<BTr
         if ext == "json" {
             parse_json_content(&content)
         } else {
-            Ok(parse_env_content(&content))
+            parse_env_file(env_path, &content)
         }
     }
 }
 
+/// Rewrite the env file at `path` through `edit`, which sees plaintext. An encrypted file is
+/// decrypted in memory and sealed again the same way (see `Opened::reseal`); plaintext never
+/// touches the disk. A missing file starts out empty, and a file `edit` leaves unchanged is
+/// not rewritten.
+fn edit_env_file(path: &Path, password: Option<&str>, edit: impl FnOnce(&mut Document) -> Result<()>) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => Zeroizing::new(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Zeroizing::new(String::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut opened = Opened::open(path, sealing(path, &content), &content, password)?;
+    let mut doc = parse_env_document(path, &opened.text)?;
+    edit(&mut doc)?;
+    let updated = Zeroizing::new(doc.to_string());
+    if *updated != *opened.text {
+        write_secure_file(path, opened.reseal(path, &updated)?.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// The editor command: `$VISUAL`, then `$EDITOR`, then `vi`, split on whitespace so values
+/// like `code --wait` work.
+fn editor_command() -> Vec<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    editor.split_whitespace().map(str::to_string).collect()
+}
+
+/// Ask whether to reopen the editor after the edited text failed to parse. Without a
+/// terminal the answer is no.
+fn retry_edit(err: &anyhow::Error) -> bool {
+    use std::io::{BufRead, IsTerminal, Write};
+    eprintln!("{}", err);
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    eprint!("Reopen the editor? [Y/n] ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    !answer.trim().eq_ignore_ascii_case("n")
+}
+
+/// Edit an encrypted env file in `$EDITOR` (`bsec edit`). The plaintext is staged in a 0700
+/// `StagedDir`, removed on return and on SIGINT/SIGTERM. The result must parse; the file is
+/// re-encrypted in place only if it changed (per-value: only the changed values). Returns
+/// whether it did.
+pub fn edit_encrypted_env_file(path: &Path, password: Option<&str>) -> Result<bool> {
+    let content = fs::read_to_string(path)?;
+    let sealing = sealing(path, &content);
+    if sealing == Sealing::Plain {
+        return Err(anyhow!(
+            "'{}' is not an encrypted env file (.enc); edit it directly or use `bsec set`",
+            path.display()
+        ));
+    }
+    let clean_name = plain_name(path);
+    let mut opened = Opened::open(path, sealing, &content, password)?;
+    let original = opened.text.clone();
+
+    let staged = crate::materialize::StagedDir::new()?;
+    // The plaintext's own name, so the editor picks dotenv highlighting.
+    let name = Path::new(&clean_name).file_name().map(|n| n.to_string_lossy().to_string());
+    let file = staged.stage_file(name.as_deref().unwrap_or(".env"), original.as_bytes())?;
+    let editor = editor_command();
+
+    let edited = loop {
+        let status = Command::new(&editor[0])
+            .args(&editor[1..])
+            .arg(&file)
+            .status()
+            .map_err(|e| anyhow!("could not start editor '{}': {}", editor[0], e))?;
+        if !status.success() {
+            return Err(anyhow!("editor '{}' exited with {}; '{}' left unchanged", editor[0], status, path.display()));
+        }
+        let edited = Zeroizing::new(fs::read_to_string(&file)?);
+        match parse_env_file(path, &edited) {
+            Ok(_) => break edited,
+            Err(e) if retry_edit(&e) => continue,
+            Err(e) => return Err(e),
+        }
+    };
+
+    if *edited == *original {
+        return Ok(false);
+    }
+    write_secure_file(path, opened.reseal(path, &edited)?.as_bytes())?;
+    Ok(true)
+}
+
+fn check_key(key: &str) -> Result<()> {
+    if !crate::dotenv::is_key(key) {
+        return Err(BsecError::ParseError(format!("'{}' is not a valid variable name", key)).into());
+    }
+    Ok(())
+}
+
+fn key_not_found(key: &str, path: &Path) -> anyhow::Error {
+    BsecError::KeyNotFound { key: key.to_string(), file: path.display().to_string() }.into()
+}
+
+/// The expanded value of `key` in an env, `.enc` or JSON file (`bsec get`).
+pub fn get_env_var(path: &Path, key: &str, password: Option<&str>) -> Result<String> {
+    load_and_parse_env(path, password)?.remove(key).ok_or_else(|| key_not_found(key, path))
+}
+
+/// Set `key` to the literal `value`, in place if it is defined, else on a new last line
+/// (`bsec set`).
+pub fn set_env_var(path: &Path, key: &str, value: &str, password: Option<&str>) -> Result<()> {
+    check_key(key)?;
+    edit_env_file(path, password, |doc| {
+        doc.set(key, value);
+        Ok(())
+    })
+}
+
+/// Remove every definition of `key` (`bsec unset`).
+pub fn unset_env_var(path: &Path, key: &str, password: Option<&str>) -> Result<()> {
+    edit_env_file(path, password, |doc| {
+        if !doc.remove(key) {
+            return Err(key_not_found(key, path));
+        }
+        Ok(())
+    })
+}
+
+/// Rename every definition of `old` to `new` (`bsec rename`). References to `old` in other
+/// values are left as written.
+pub fn rename_env_var(path: &Path, old: &str, new: &str, password: Option<&str>) -> Result<()> {
+    check_key(new)?;
+    edit_env_file(path, password, |doc| {
+        let keys = doc.keys();
+        if !keys.iter().any(|k| k == old) {
+            return Err(key_not_found(old, path));
+        }
+        if keys.iter().any(|k| k == new) {
+            return Err(anyhow!("'{}' is already set in '{}'", new, path.display()));
+        }
+        doc.rename_keys(|k| if k == old { new.to_string() } else { k.to_string() });
+        Ok(())
+    })
+}
+
+/// What `rekey_env_files` did with one file.
+pub enum Rekeyed {
+    Rotated,
+    Skipped(&'static str),
+    Failed(anyhow::Error),
+}
+
+/// Directories a recursive `rekey` does not descend into: VCS metadata and build or package
+/// output, which hold no env files of their own and can be very large.
+const REKEY_SKIPPED_DIRS: &[&str] = &[".git", "target", "node_modules"];
+
+/// Files above this size are not env files and are not read by a recursive `rekey`.
+const REKEY_MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Whether `content` is v1's `salt:nonce:ciphertext` whole-file payload.
+fn is_v1_payload(content: &str) -> bool {
+    let fields: Vec<&str> = content.trim().split(':').collect();
+    let [salt, nonce, cipher_text] = fields.as_slice() else {
+        return false;
+    };
+    BASE64_STANDARD.decode(salt).is_ok()
+        && BASE64_STANDARD.decode(nonce).is_ok_and(|n| n.len() == 12)
+        && BASE64_STANDARD.decode(cipher_text).is_ok()
+}
+
+/// Whether a file met in a recursive walk is an encrypted env file: a parsed `Envelope`, a
+/// parsed env file with sealed values, or a `.enc` file holding a v1 payload. The name alone
+/// is not enough.
+fn is_sealed_file(path: &Path, content: &str) -> bool {
+    match sealing(path, content) {
+        Sealing::Plain => false,
+        Sealing::Whole => Envelope::detect(content).is_some() || is_v1_payload(content),
+        Sealing::Values | Sealing::Recipients => true,
+    }
+}
+
+/// The files `rekey` covers: each file in `paths`, and with `recursive` the encrypted env
+/// files under each directory. Symlinks, `REKEY_SKIPPED_DIRS`, and files that are large or
+/// not UTF-8 are passed over.
+fn rekey_targets(paths: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+        let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let kind = entry.file_type()?;
+            let path = entry.path();
+            if kind.is_dir() {
+                if !REKEY_SKIPPED_DIRS.iter().any(|d| entry.file_name() == *d) {
+                    walk(&path, out)?;
+                }
+            } else if kind.is_file()
+                && entry.metadata()?.len() <= REKEY_MAX_FILE_BYTES
+                && let Ok(content) = fs::read_to_string(&path)
+                && is_sealed_file(&path, &content)
+            {
+                out.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut targets = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            targets.push(path.clone());
+        } else if recursive {
+            walk(path, &mut targets)?;
+        } else {
+            return Err(anyhow!("'{}' is a directory; pass --recursive to rekey the files in it", path.display()));
+        }
+    }
+    Ok(targets)
+}
+
+/// Decrypt `path` with its current password and seal it again under `new_password`, with a
+/// fresh salt and nonce for every value. The result is checked to open before it atomically
+/// replaces the file.
+fn rekey_env_file(path: &Path, old_password: Option<&str>, new_password: &str) -> Result<Rekeyed> {
+    let content = Zeroizing::new(fs::read_to_string(path)?);
+    let sealing = sealing(path, &content);
+    match sealing {
+        Sealing::Plain => return Ok(Rekeyed::Skipped("not encrypted")),
+        Sealing::Recipients => return Ok(Rekeyed::Skipped("encrypted to wallets, not a password")),
+        Sealing::Whole | Sealing::Values => {}
+    }
+    let mut opened = Opened::open(path, sealing, &content, old_password)?;
+    opened.pwd = Zeroizing::new(new_password.to_string());
+    opened.values = None;
+    let text = opened.text.clone();
+    let payload = opened.reseal(path, &text)?;
+
+    let check = Opened::open(path, sealing, &payload, Some(new_password))?;
+    if *check.text != *text {
+        return Err(anyhow!("'{}' did not decrypt back after rekeying; left unchanged", path.display()));
+    }
+    crate::wallet::replace_secure_file(path, payload.as_bytes())?;
+    Ok(Rekeyed::Rotated)
+}
+
+/// Rotate the password of each encrypted env file in `paths` (`bsec rekey`). The old password
+/// is resolved per file by `get_encryption_password`. A failing file does not stop the rest;
+/// the outcome of each is returned in order.
+pub fn rekey_env_files(
+    paths: &[PathBuf],
+    recursive: bool,
+    old_password: Option<&str>,
+    new_password: &str,
+) -> Result<Vec<(PathBuf, Rekeyed)>> {
+    if new_password.is_empty() {
+        return Err(anyhow!("the new password must not be empty"));
+    }
+    Ok(rekey_targets(paths, recursive)?
+        .into_iter()
+        .map(|path| {
+            let outcome = rekey_env_file(&path, old_password, new_password).unwrap_or_else(Rekeyed::Failed);
+            (path, outcome)
+        })
+        .collect())
+}
+
 /// Environment variable names that must never be injected into the child from a shared
 /// secret or env file. These alter the dynamic loader, the command search path, or the
 /// shell/interpreter startup, and would let attacker-controlled secret content achieve
@@ -453,6 +1207,7 @@ pub fn run_with_envs(
     secret_id: Option<&str>,
     command_and_args: &[String],
     password: Option<&str>,
+    tx: &crate::eth::TxOptions,
 ) -> Result<i32> {
     if command_and_args.is_empty() {
         return Err(anyhow!("No command provided. Usage: bsec run -- <command>"));
@@ -477,7 +1232,7 @@ pub fn run_with_envs(
     if let Some(sec_id) = target_secret_id {
         let user_addr = crate::wallet::get_wallet_info(password)?.address.clone();
         // One decrypt / one on-chain read, then decide: stage files or inject vars.
-        let payload = crate::secrets::view_payload(sec_id, &user_addr, password)?;
+        let payload = crate::secrets::view_payload(sec_id, &user_addr, password, tx)?;
         match crate::materialize::stage_and_envs(&payload)? {
             Some((staged, envs)) => {
                 _staged_guard = Some(staged);
@@ -487,7 +1242,7 @@ pub fn run_with_envs(
                 let vars = if payload.content.trim_start().starts_with('{') {
                     parse_json_content(&payload.content)?
                 } else {
-                    parse_env_content(&payload.content)
+                    parse_env_content(&payload.content)?
                 };
                 env_map.extend(vars);
             }
@@ -592,3 +1347,226 @@ mod env_name_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod value_sealing_tests {
+    use super::{sealing, Document, Sealing, ValueSealer, SEALED_VALUE};
+    use std::path::Path;
+
+    const PLAIN: &str = "# db\nDB_USER=app\nDB_PASS='p@ss word'\nDB_URL=pg://${DB_USER}:${DB_PASS}@db\nEMPTY=\n";
+
+    fn sealed() -> Document {
+        let mut doc = Document::parse(PLAIN).unwrap();
+        ValueSealer::new("pw").seal_all(&mut doc).unwrap();
+        doc
+    }
+
+    #[test]
+    fn keys_and_empty_values_stay_clear_and_references_are_sealed() {
+        let text = sealed().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "# db");
+        for (line, key) in lines[1..4].iter().zip(["DB_USER", "DB_PASS", "DB_URL"]) {
+            assert!(line.starts_with(&format!("{}={}", key, SEALED_VALUE)), "{}", line);
+        }
+        assert_eq!(lines[4], "EMPTY=");
+        assert!(!text.contains("p@ss") && !text.contains("${"));
+
+        let mut doc = Document::parse(&text).unwrap();
+        ValueSealer::new("pw").open_all(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), PLAIN);
+        assert_eq!(doc.values().unwrap()["DB_URL"], "pg://app:p@ss word@db");
+        assert!(ValueSealer::new("wrong").open_all(&mut Document::parse(&text).unwrap()).is_err());
+    }
+
+    #[test]
+    fn values_with_dollar_signs_are_sealed_as_written() {
+        let plain = "DB_URL=postgres://admin:hunter2@${DB_HOST}/db # prod\nPW=pa$word\n";
+        let mut doc = Document::parse(plain).unwrap();
+        ValueSealer::new("pw").seal_all(&mut doc).unwrap();
+        let text = doc.to_string();
+        assert!(!text.contains("hunter2") && !text.contains("pa$"));
+        assert!(text.lines().next().unwrap().ends_with(" # prod"));
+
+        let mut doc = Document::parse(&text).unwrap();
+        ValueSealer::new("pw").open_all(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), plain);
+    }
+
+    #[test]
+    fn only_values_that_start_with_the_prefix_mark_a_file() {
+        let path = Path::new(".env");
+        assert!(sealing(path, &sealed().to_string()) == Sealing::Values);
+        assert!(sealing(path, "# values look like enc:v1:...\nNOTE=see enc:v1:x\n") == Sealing::Plain);
+        assert!(sealing(path, "A='unterminated enc:v1:\n") == Sealing::Plain);
+    }
+
+    #[test]
+    fn a_plaintext_value_among_sealed_ones_is_refused() {
+        let text = format!("{}INJECTED=http://attacker.example\n", sealed());
+        let err = ValueSealer::new("pw").open_all(&mut Document::parse(&text).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("INJECTED: value is not encrypted"));
+        assert_eq!(err.downcast_ref::<crate::errors::BsecError>().map(|e| e.exit_code()), Some(4));
+    }
+
+    #[test]
+    fn a_value_moved_to_another_key_does_not_decrypt() {
+        let text = sealed().to_string();
+        let pass = text.lines().find_map(|l| l.strip_prefix("DB_PASS=")).unwrap();
+        let mut doc = Document::parse(&format!("API_KEY={}\n", pass)).unwrap();
+        let err = ValueSealer::new("pw").open_all(&mut doc).unwrap_err();
+        assert!(err.to_string().contains("Decryption of API_KEY failed"));
+    }
+
+    #[test]
+    fn resealing_changes_only_edited_values() {
+        let before = sealed().to_string();
+        let mut doc = Document::parse(&before).unwrap();
+        let mut sealer = ValueSealer::new("pw");
+        sealer.open_all(&mut doc).unwrap();
+        doc.set("DB_PASS", "rotated");
+        sealer.seal_all(&mut doc).unwrap();
+        let after = doc.to_string();
+
+        let changed: Vec<(&str, &str)> = before.lines().zip(after.lines()).filter(|(a, b)| a != b).collect();
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].1.starts_with(&format!("DB_PASS={}", SEALED_VALUE)));
+        // One salt and set of costs per file, so the key is derived once.
+        let spec = |line: &str| line.split(':').skip(2).take(4).collect::<Vec<_>>().join(":");
+        assert_eq!(spec(changed[0].0), spec(changed[0].1));
+    }
+
+    #[test]
+    fn values_record_their_argon2_costs() {
+        let text = sealed().to_string();
+        let line = text.lines().find_map(|l| l.strip_prefix("DB_USER=")).unwrap();
+        let params = argon2::Params::default();
+        let costs = format!("{}{}:{}:{}:", SEALED_VALUE, params.m_cost(), params.t_cost(), params.p_cost());
+        assert!(line.starts_with(&costs), "{}", line);
+
+        // Other costs derive another key; absurd ones are refused before deriving.
+        let edit = |costs_to: &str| Document::parse(&format!("DB_USER={}\n", line.replacen(&costs, costs_to, 1))).unwrap();
+        let t_cost = format!("{}{}:{}:{}:", SEALED_VALUE, params.m_cost(), params.t_cost() + 1, params.p_cost());
+        assert!(ValueSealer::new("pw").open_all(&mut edit(&t_cost)).is_err());
+        let costly = format!("{}{}:1:1:", SEALED_VALUE, 64u64 << 20);
+        let err = ValueSealer::new("pw").open_all(&mut edit(&costly)).unwrap_err();
+        assert!(err.to_string().contains("refusing Argon2 costs"), "{}", err);
+    }
+
+    #[test]
+    fn v1_values_still_open_and_edits_write_v2() {
+        use aes_gcm::aead::{Aead, KeyInit, Payload};
+        use aes_gcm::{Aes256Gcm, Nonce};
+        use base64::prelude::*;
+
+        let salt = [7u8; 16];
+        let nonce = [9u8; 12];
+        let key = crate::wallet::derive_key("pw", &salt).unwrap();
+        let cipher_text = Aes256Gcm::new_from_slice(&key)
+            .unwrap()
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: b"app", aad: b"DB_USER" })
+            .unwrap();
+        let v1 = format!(
+            "DB_USER=enc:v1:{}:{}:{}\n",
+            BASE64_STANDARD.encode(salt),
+            BASE64_STANDARD.encode(nonce),
+            BASE64_STANDARD.encode(cipher_text)
+        );
+        assert!(sealing(Path::new(".env"), &v1) == Sealing::Values);
+
+        let mut doc = Document::parse(&v1).unwrap();
+        let mut sealer = ValueSealer::new("pw");
+        sealer.open_all(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), "DB_USER=app\n");
+        sealer.seal_all(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), v1, "an unchanged value keeps its ciphertext");
+        doc.set("DB_USER", "web");
+        sealer.seal_all(&mut doc).unwrap();
+        assert!(doc.to_string().starts_with(&format!("DB_USER={}", SEALED_VALUE)));
+    }
+}
+
+#[cfg(test)]
+mod envelope_tests {
+    use super::{open, seal, Envelope, Sealing};
+    use serde_json::Value;
+    use std::path::Path;
+
+    /// `API_KEY=k-123\n# note\n` under "fixture-pass", as `bsec encrypt` wrote it before v2.
+    const V1_PAYLOAD: &str =
+        "gIi+TMuWVw0KuhDpQW6XhQ==:ftsD/UdFpDV4aBJJ:EsqSLN5u3Z96gVm/jz8m5Xil42EFNCjWZQb53ftoDY1e+0QkWg==";
+
+    #[test]
+    fn v1_payloads_still_open() {
+        assert_eq!(*open(V1_PAYLOAD, "fixture-pass").unwrap(), "API_KEY=k-123\n# note\n");
+        assert!(open(V1_PAYLOAD, "wrong").is_err());
+        assert!(super::sealing(Path::new(".env.enc"), V1_PAYLOAD) == Sealing::Whole);
+    }
+
+    #[test]
+    fn v2_records_format_and_kdf_parameters() {
+        let sealed = seal("A=1\n", "pw").unwrap();
+        let header: Value = serde_json::from_str(&sealed).unwrap();
+        assert_eq!((header["format"].as_str(), header["version"].as_u64()), (Some("bsec-env"), Some(2)));
+        assert_eq!(header["cipher"], "aes-256-gcm");
+        let params = argon2::Params::default();
+        assert_eq!(header["kdf"]["id"], "argon2id");
+        assert_eq!(header["kdf"]["m_cost"].as_u64(), Some(params.m_cost() as u64));
+        assert!(header.get("recipients").is_none());
+
+        // Found by content whatever the file is called.
+        assert!(super::sealing(Path::new("secrets"), &sealed) == Sealing::Whole);
+        assert_eq!(*open(&sealed, "pw").unwrap(), "A=1\n");
+        assert!(open(&sealed, "wrong").unwrap_err().to_string().contains("Check your password"));
+        assert_ne!(seal("A=1\n", "pw").unwrap(), sealed, "fresh salt and nonce");
+    }
+
+    #[test]
+    fn the_recorded_parameters_are_used_and_checked() {
+        let sealed = seal("A=1\n", "pw").unwrap();
+        let edit = |f: &dyn Fn(&mut Value)| {
+            let mut header: Value = serde_json::from_str(&sealed).unwrap();
+            f(&mut header);
+            header.to_string()
+        };
+        // Other costs derive another key.
+        assert!(open(&edit(&|h| h["kdf"]["t_cost"] = 3.into()), "pw").is_err());
+
+        let newer = open(&edit(&|h| h["version"] = 3.into()), "pw").unwrap_err();
+        assert!(newer.to_string().contains("newer than this bsec reads"));
+        let costly = open(&edit(&|h| h["kdf"]["m_cost"] = (64u64 << 20).into()), "pw").unwrap_err();
+        assert!(costly.to_string().contains("refusing Argon2 costs"));
+        assert!(open(&edit(&|h| h["cipher"] = "des".into()), "pw").is_err());
+        assert!(Envelope::detect(&edit(&|h| h["format"] = "other".into())).is_none());
+    }
+}
+
+#[cfg(test)]
+mod rekey_tests {
+    use super::{rekey_targets, seal, REKEY_MAX_FILE_BYTES};
+    use std::fs;
+
+    #[test]
+    fn a_recursive_walk_picks_only_files_that_parse_as_sealed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let sealed = seal("A=1\n", "pw").unwrap();
+        for sub in ["app", ".git", "target", "node_modules/pkg"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+            fs::write(root.join(sub).join(".env.enc"), &sealed).unwrap();
+        }
+        fs::write(root.join("app/.env"), "A=enc:v1:c2FsdA==:AAAAAAAAAAAAAAAA:Y3Q=\n").unwrap();
+        // Mentions of the prefix, a name alone, non-UTF-8 and oversized files are not env files.
+        fs::write(root.join("app/README.md"), "Values look like `enc:v1:...`.\n").unwrap();
+        fs::write(root.join("app/notes.env"), "# A=enc:v1:x\nB=\"see enc:v1:\"\n").unwrap();
+        fs::write(root.join("app/junk.enc"), "not a payload").unwrap();
+        fs::write(root.join("app/blob.bin"), [0xff, 0xfe, b'{', 0x00]).unwrap();
+        let mut big = sealed.clone().into_bytes();
+        big.resize(REKEY_MAX_FILE_BYTES as usize + 1, b' ');
+        fs::write(root.join("app/big.enc"), big).unwrap();
+
+        let targets = rekey_targets(&[root.to_path_buf()], true).unwrap();
+        let names: Vec<_> = targets.iter().map(|p| p.strip_prefix(root).unwrap().to_path_buf()).collect();
+        assert_eq!(names, [std::path::Path::new("app/.env"), std::path::Path::new("app/.env.enc")]);
+    }
+}
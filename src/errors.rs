@@ -18,6 +18,39 @@ pub enum BsecError {
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
 
+    #[error("Secret '{0}' already exists on-chain.")]
+    SecretAlreadyExists(String),
+
+    #[error("Secret '{id}' expired at {expires_at} (chain time {now}).")]
+    SecretExpiredAt { id: String, expires_at: u64, now: u64 },
+
+    #[error("Secret '{id}': read limit reached ({read_count}/{max_reads}).")]
+    ReadLimitReached { id: String, read_count: u32, max_reads: u32 },
+
+    #[error("Secret '{0}' has been revoked.")]
+    SecretRevoked(String),
+
+    #[error("Permission denied: {viewer} is not a recipient of secret '{id}'.")]
+    UnauthorizedViewer { id: String, viewer: String },
+
+    #[error("Permission denied: only the sender may revoke secret '{id}' (caller {caller}).")]
+    UnauthorizedRevoker { id: String, caller: String },
+
+    #[error("Registry rejected the parameters (empty ID or CID, or an expiry in the past).")]
+    InvalidParameters,
+
+    #[error("Transaction reverted: {0}")]
+    Reverted(String),
+
+    #[error("worst-case fee {max_cost} exceeds --fee-cap {cap} (native units); not sending")]
+    FeeCapExceeded { max_cost: String, cap: String },
+
+    #[error("'{key}' is not set in '{file}'.")]
+    KeyNotFound { key: String, file: String },
+
+    #[error("'{file}' does not match its schema ({count} problem(s)).")]
+    SchemaViolation { file: String, count: usize },
+
     #[error("Invalid BIP-39 mnemonic phrase: {0}")]
     InvalidMnemonic(String),
 
@@ -47,9 +80,16 @@ impl BsecError {
             BsecError::InvalidPassword => 2,
             BsecError::SecretExpired
             | BsecError::SecretNotFound(_)
-            | BsecError::PermissionDenied(_) => 3,
+            | BsecError::PermissionDenied(_)
+            | BsecError::SecretExpiredAt { .. }
+            | BsecError::ReadLimitReached { .. }
+            | BsecError::SecretRevoked(_)
+            | BsecError::UnauthorizedViewer { .. }
+            | BsecError::UnauthorizedRevoker { .. }
+            | BsecError::KeyNotFound { .. } => 3,
             BsecError::IoError(_) | BsecError::ParseError(_) | BsecError::ConfigError(_) => 4,
             BsecError::InvalidMnemonic(_) | BsecError::InvalidRecipient(_) => 5,
+            BsecError::SchemaViolation { .. } => 6,
             _ => 1,
         }
     }
@@ -68,10 +108,17 @@ mod tests {
         assert_eq!(BsecError::SecretExpired.exit_code(), 3);
         assert_eq!(BsecError::SecretNotFound("id".into()).exit_code(), 3);
         assert_eq!(BsecError::PermissionDenied("denied".into()).exit_code(), 3);
+        assert_eq!(BsecError::ReadLimitReached { id: "id".into(), read_count: 3, max_reads: 3 }.exit_code(), 3);
+        assert_eq!(BsecError::SecretRevoked("id".into()).exit_code(), 3);
+        assert_eq!(BsecError::KeyNotFound { key: "K".into(), file: ".env".into() }.exit_code(), 3);
+        assert_eq!(BsecError::SecretAlreadyExists("id".into()).exit_code(), 1);
+        assert_eq!(BsecError::Reverted("reason".into()).exit_code(), 1);
+        assert_eq!(BsecError::FeeCapExceeded { max_cost: "1".into(), cap: "0.5".into() }.exit_code(), 1);
         assert_eq!(BsecError::ParseError("parse".into()).exit_code(), 4);
         assert_eq!(BsecError::ConfigError("cfg".into()).exit_code(), 4);
         assert_eq!(BsecError::InvalidMnemonic("mnem".into()).exit_code(), 5);
         assert_eq!(BsecError::InvalidRecipient("recip".into()).exit_code(), 5);
+        assert_eq!(BsecError::SchemaViolation { file: ".env".into(), count: 2 }.exit_code(), 6);
         assert_eq!(BsecError::WalletNotFound.exit_code(), 1);
         assert_eq!(BsecError::CryptoError("crypto".into()).exit_code(), 1);
         assert_eq!(BsecError::Generic("gen".into()).exit_code(), 1);
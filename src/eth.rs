@@ -4,17 +4,22 @@
 //! transaction signing (secp256k1 via k256), and receipt polling. Chains that report a
 //! `baseFeePerGas` (post-London) are sent EIP-1559 type-2 transactions; chains without one
 //! fall back to EIP-155 legacy signing.
-//! No fabricated results: every call hits the configured RPC endpoint or returns an error.
+//! No fabricated results: every call hits a configured RPC endpoint or returns an error.
+//! Endpoints fail over in order (`rpc_url`, then `rpc_urls`) on transport errors and rate
+//! limiting, and each is checked to serve the configured chain ID before it is used.
 
 use anyhow::{anyhow, Result};
 use k256::SecretKey;
 use k256::ecdsa::SigningKey;
 use reqwest::blocking::Client;
 use serde_json::{json, Value};
+use std::sync::Mutex;
 use std::time::Duration;
 
 use crate::blockchain::keccak256;
+use crate::errors::BsecError;
 use crate::network_config::NetworkConfig;
+use crate::tx_journal::{self, JournalLock, TxRecord, TxStatus};
 use crate::wallet::{bytes_to_hex, hex_to_bytes};
 
 const RPC_TIMEOUT_SECS: u64 = 20;
@@ -28,23 +33,184 @@ fn http_client() -> Result<Client> {
         .map_err(|e| anyhow!("failed to build HTTP client: {}", e))
 }
 
-/// Perform a JSON-RPC 2.0 call against the configured node. Surfaces RPC-level errors.
-pub fn rpc(conf: &NetworkConfig, method: &str, params: Value) -> Result<Value> {
-    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
-    let res = http_client()?
-        .post(&conf.rpc_url)
-        .json(&body)
+/// Process-wide failover state: the endpoint that last answered (tried first next time, so
+/// nonce reads, broadcast and receipt polling stay on one node) and the endpoints whose
+/// `eth_chainId` has been checked against the config.
+struct RpcState {
+    active: Option<String>,
+    verified: Vec<String>,
+}
+
+static RPC_STATE: Mutex<RpcState> = Mutex::new(RpcState { active: None, verified: Vec::new() });
+
+/// Why one endpoint could not serve a call. `Failover` moves on to the next endpoint;
+/// `Fatal` is the node's definitive answer and is returned as-is.
+enum CallError {
+    Failover(String),
+    Fatal(anyhow::Error),
+}
+
+/// JSON-RPC `limit exceeded` (EIP-1474), used by several providers instead of HTTP 429.
+const RPC_LIMIT_EXCEEDED: i64 = -32005;
+
+fn call_endpoint(client: &Client, url: &str, body: &Value) -> std::result::Result<Value, CallError> {
+    let res = client
+        .post(url)
+        .json(body)
         .send()
-        .map_err(|e| anyhow!("RPC connection error to {}: {}", conf.rpc_url, e))?;
+        .map_err(|e| CallError::Failover(format!("connection error: {}", e)))?;
+    if res.status().as_u16() == 429 {
+        return Err(CallError::Failover("rate limited (HTTP 429)".into()));
+    }
     let parsed: Value = res
         .json()
-        .map_err(|e| anyhow!("RPC response decode error from {}: {}", conf.rpc_url, e))?;
+        .map_err(|e| CallError::Failover(format!("response decode error: {}", e)))?;
     if let Some(err) = parsed.get("error") {
-        return Err(anyhow!("RPC error from {}: {}", conf.rpc_url, err));
+        if err.get("code").and_then(|c| c.as_i64()) == Some(RPC_LIMIT_EXCEEDED) {
+            return Err(CallError::Failover(format!("rate limited: {}", err)));
+        }
+        if let Some(decoded) = revert_data(err).and_then(|d| decode_revert(&d)) {
+            return Err(CallError::Fatal(decoded.into()));
+        }
+        return Err(CallError::Fatal(anyhow!("RPC error from {}: {}", url, err)));
     }
     Ok(parsed.get("result").cloned().unwrap_or(Value::Null))
 }
 
+/// Confirm `url` serves `expected` before it is used. A mismatch is not fatal for the command
+/// (another endpoint may be right) but that endpoint is never used.
+fn verify_chain_id(client: &Client, url: &str, expected: u32) -> std::result::Result<(), CallError> {
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "params": [] });
+    let got = call_endpoint(client, url, &body)?;
+    let got = got
+        .as_str()
+        .and_then(|s| hex_to_u128(s).ok())
+        .ok_or_else(|| CallError::Failover(format!("eth_chainId returned {}", got)))?;
+    if got != expected as u128 {
+        return Err(CallError::Failover(format!(
+            "chain ID mismatch: endpoint reports {}, config expects {}",
+            got, expected
+        )));
+    }
+    Ok(())
+}
+
+/// Configured endpoints with the last working one first.
+fn ordered_endpoints(conf: &NetworkConfig) -> Vec<String> {
+    let mut urls = conf.effective_rpc_urls();
+    let active = RPC_STATE.lock().map(|s| s.active.clone()).unwrap_or(None);
+    if let Some(pos) = active.and_then(|a| urls.iter().position(|u| *u == a)) {
+        let a = urls.remove(pos);
+        urls.insert(0, a);
+    }
+    urls
+}
+
+/// Perform a JSON-RPC 2.0 call, failing over across `rpc_url` and `rpc_urls` on transport
+/// errors and rate limiting. Each endpoint's chain ID is checked against `chain_id` before
+/// its first use, so no call (and in particular no transaction) reaches a node on another
+/// chain. RPC-level errors from a healthy node are surfaced immediately.
+pub fn rpc(conf: &NetworkConfig, method: &str, params: Value) -> Result<Value> {
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let client = http_client()?;
+    let mut failures = Vec::new();
+    for url in ordered_endpoints(conf) {
+        let verified = RPC_STATE.lock().map(|s| s.verified.contains(&url)).unwrap_or(false);
+        if !verified {
+            match verify_chain_id(&client, &url, conf.chain_id) {
+                Ok(()) => {
+                    if let Ok(mut s) = RPC_STATE.lock() {
+                        s.verified.push(url.clone());
+                    }
+                }
+                Err(CallError::Failover(why)) => {
+                    log::warn!("RPC endpoint {} skipped: {}", url, why);
+                    failures.push(format!("{}: {}", url, why));
+                    continue;
+                }
+                Err(CallError::Fatal(e)) => {
+                    failures.push(format!("{}: {}", url, e));
+                    continue;
+                }
+            }
+        }
+        match call_endpoint(&client, &url, &body) {
+            Ok(v) => {
+                if let Ok(mut s) = RPC_STATE.lock() {
+                    s.active = Some(url);
+                }
+                return Ok(v);
+            }
+            Err(CallError::Failover(why)) => {
+                log::warn!("RPC endpoint {} failed ({}), trying next", url, why);
+                failures.push(format!("{}: {}", url, why));
+            }
+            Err(CallError::Fatal(e)) => return Err(e),
+        }
+    }
+    Err(anyhow!("no usable RPC endpoint for {}: {}", method, failures.join("; ")))
+}
+
+/// One row of `bsec config check`.
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct EndpointReport {
+    pub url: String,
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub chain_id: Option<u64>,
+    pub chain_id_matches: bool,
+    pub head_block: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Probe one endpoint with `eth_chainId` + `eth_blockNumber`, bypassing failover.
+pub fn check_endpoint(url: &str, expected_chain_id: u32) -> EndpointReport {
+    let mut report = EndpointReport {
+        url: url.to_string(),
+        ok: false,
+        latency_ms: 0,
+        chain_id: None,
+        chain_id_matches: false,
+        head_block: None,
+        error: None,
+    };
+    let client = match http_client() {
+        Ok(c) => c,
+        Err(e) => {
+            report.error = Some(e.to_string());
+            return report;
+        }
+    };
+    let call = |method: &str| {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": [] });
+        match call_endpoint(&client, url, &body) {
+            Ok(v) => v
+                .as_str()
+                .and_then(|s| hex_to_u128(s).ok())
+                .map(|n| n as u64)
+                .ok_or_else(|| format!("{} returned {}", method, v)),
+            Err(CallError::Failover(why)) => Err(why),
+            Err(CallError::Fatal(e)) => Err(e.to_string()),
+        }
+    };
+    let started = std::time::Instant::now();
+    let result = call("eth_chainId").and_then(|id| Ok((id, call("eth_blockNumber")?)));
+    report.latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok((id, head)) => {
+            report.chain_id = Some(id);
+            report.chain_id_matches = id == expected_chain_id as u64;
+            report.head_block = Some(head);
+            report.ok = report.chain_id_matches;
+            if !report.chain_id_matches {
+                report.error = Some(format!("chain ID {} does not match configured {}", id, expected_chain_id));
+            }
+        }
+        Err(e) => report.error = Some(e),
+    }
+    report
+}
+
 fn hex_to_u128(s: &str) -> Result<u128> {
     let t = s.trim().trim_start_matches("0x");
     let t = if t.is_empty() { "0" } else { t };
@@ -136,7 +302,7 @@ pub fn parse_address(addr: &str) -> Result<[u8; 20]> {
     Ok(out)
 }
 
-fn addr_hex(addr: &[u8; 20]) -> String {
+pub fn addr_hex(addr: &[u8; 20]) -> String {
     format!("0x{}", bytes_to_hex(addr))
 }
 
@@ -177,9 +343,22 @@ pub fn encode_share_secret(
     max_reads: u32,
     is_public: bool,
 ) -> Vec<u8> {
-    let sel = selector("shareSecret(bytes32,address,string,uint64,uint32,bool)");
+    let mut out = selector("shareSecret(bytes32,address,string,uint64,uint32,bool)").to_vec();
+    out.extend(encode_share_args(secret_id, recipient, ipfs_cid, expires_at, max_reads, is_public));
+    out
+}
+
+/// shareSecret's arguments without the selector; also the encoding of the
+/// `ShareRequest` tuple taken by shareSecretBySig.
+fn encode_share_args(
+    secret_id: &[u8; 32],
+    recipient: &[u8; 20],
+    ipfs_cid: &str,
+    expires_at: u64,
+    max_reads: u32,
+    is_public: bool,
+) -> Vec<u8> {
     let mut out = Vec::new();
-    out.extend_from_slice(&sel);
 
     // 6 head words; the string is dynamic, its head slot holds the tail offset.
     let head_words = 6usize;
@@ -199,6 +378,66 @@ pub fn encode_share_secret(
     out
 }
 
+/// ABI-encode shareSecretBySig((bytes32,address,string,uint64,uint32,bool),address,uint256,bytes).
+#[allow(clippy::too_many_arguments)]
+pub fn encode_share_secret_by_sig(
+    secret_id: &[u8; 32],
+    recipient: &[u8; 20],
+    ipfs_cid: &str,
+    expires_at: u64,
+    max_reads: u32,
+    is_public: bool,
+    signer: &[u8; 20],
+    deadline: u64,
+    signature: &[u8],
+) -> Vec<u8> {
+    let request = encode_share_args(secret_id, recipient, ipfs_cid, expires_at, max_reads, is_public);
+    let mut out = selector("shareSecretBySig((bytes32,address,string,uint64,uint32,bool),address,uint256,bytes)").to_vec();
+    // 4 head words: offset to the request tuple, signer, deadline, offset to the signature.
+    out.extend_from_slice(&word_u128(4 * 32));
+    out.extend_from_slice(&word_addr(signer));
+    out.extend_from_slice(&word_u128(deadline as u128));
+    out.extend_from_slice(&word_u128((4 * 32 + request.len()) as u128));
+    out.extend(request);
+    out.extend(encode_bytes_tail(signature));
+    out
+}
+
+/// ABI-encode recordReadBySig / revokeSecretBySig (bytes32,address,uint256,bytes).
+pub fn encode_bytes32_call_by_sig(signature: &str, secret_id: &[u8; 32], signer: &[u8; 20], deadline: u64, sig: &[u8]) -> Vec<u8> {
+    let mut out = selector(signature).to_vec();
+    out.extend_from_slice(secret_id);
+    out.extend_from_slice(&word_addr(signer));
+    out.extend_from_slice(&word_u128(deadline as u128));
+    out.extend_from_slice(&word_u128(4 * 32)); // offset to the signature
+    out.extend(encode_bytes_tail(sig));
+    out
+}
+
+/// Length word plus zero-padded bytes of a dynamic `bytes` argument.
+fn encode_bytes_tail(bytes: &[u8]) -> Vec<u8> {
+    let mut out = word_u128(bytes.len() as u128).to_vec();
+    out.extend_from_slice(bytes);
+    out.extend(std::iter::repeat_n(0u8, (32 - bytes.len() % 32) % 32));
+    out
+}
+
+/// ABI-encode multicall(bytes[]): each element is the calldata of one registry write.
+pub fn encode_multicall(calls: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = selector("multicall(bytes[])").to_vec();
+    out.extend_from_slice(&word_u128(32)); // offset to the array
+    out.extend_from_slice(&word_u128(calls.len() as u128));
+    // Element offsets count from just after the length word.
+    let mut offset = 32 * calls.len();
+    let tails: Vec<Vec<u8>> = calls.iter().map(|c| encode_bytes_tail(c)).collect();
+    for tail in &tails {
+        out.extend_from_slice(&word_u128(offset as u128));
+        offset += tail.len();
+    }
+    out.extend(tails.into_iter().flatten());
+    out
+}
+
 /// ABI-encode a single-bytes32-arg call (recordRead / revokeSecret / getSecretInfo).
 pub fn encode_bytes32_call(signature: &str, secret_id: &[u8; 32]) -> Vec<u8> {
     let mut out = Vec::new();
@@ -207,11 +446,203 @@ pub fn encode_bytes32_call(signature: &str, secret_id: &[u8; 32]) -> Vec<u8> {
     out
 }
 
+/// Registry functions bsec sends, for naming journaled transactions.
+const REGISTRY_WRITES: [&str; 7] = [
+    "shareSecret(bytes32,address,string,uint64,uint32,bool)",
+    "recordRead(bytes32)",
+    "revokeSecret(bytes32)",
+    "shareSecretBySig((bytes32,address,string,uint64,uint32,bool),address,uint256,bytes)",
+    "recordReadBySig(bytes32,address,uint256,bytes)",
+    "revokeSecretBySig(bytes32,address,uint256,bytes)",
+    "multicall(bytes[])",
+];
+
+// ---------------------------------------------------------------------------
+// EIP-712 signatures
+// ---------------------------------------------------------------------------
+
+/// EIP-712 type strings of the registry's signed intents.
+pub const SHARE_INTENT_TYPE: &str = "ShareSecret(bytes32 secretId,address recipient,string ipfsCid,uint64 expiresAt,uint32 maxReads,bool isPublic,uint256 nonce,uint256 deadline)";
+pub const READ_INTENT_TYPE: &str = "RecordRead(bytes32 secretId,uint256 nonce,uint256 deadline)";
+pub const REVOKE_INTENT_TYPE: &str = "RevokeSecret(bytes32 secretId,uint256 nonce,uint256 deadline)";
+
+/// EIP-712 domain separator for (name, version, chainId, verifyingContract).
+pub fn eip712_domain_separator(name: &str, version: &str, chain_id: u64, verifying_contract: &[u8; 20]) -> [u8; 32] {
+    let mut enc = keccak256(b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)").to_vec();
+    enc.extend_from_slice(&keccak256(name.as_bytes()));
+    enc.extend_from_slice(&keccak256(version.as_bytes()));
+    enc.extend_from_slice(&word_u128(chain_id as u128));
+    enc.extend_from_slice(&word_addr(verifying_contract));
+    keccak256(&enc)
+}
+
+/// The registry's domain, as computed by `BsecSecretRegistry.domainSeparator()`.
+pub fn registry_domain_separator(chain_id: u32, registry: &[u8; 20]) -> [u8; 32] {
+    eip712_domain_separator("BsecSecretRegistry", "1", chain_id as u64, registry)
+}
+
+/// Digest to sign: keccak256("\x19\x01" || domainSeparator || structHash).
+pub fn eip712_digest(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    let mut enc = vec![0x19, 0x01];
+    enc.extend_from_slice(domain_separator);
+    enc.extend_from_slice(struct_hash);
+    keccak256(&enc)
+}
+
+/// hashStruct of a ShareSecret intent.
+#[allow(clippy::too_many_arguments)]
+pub fn share_intent_hash(
+    secret_id: &[u8; 32],
+    recipient: &[u8; 20],
+    ipfs_cid: &str,
+    expires_at: u64,
+    max_reads: u32,
+    is_public: bool,
+    nonce: u64,
+    deadline: u64,
+) -> [u8; 32] {
+    let mut enc = keccak256(SHARE_INTENT_TYPE.as_bytes()).to_vec();
+    enc.extend_from_slice(secret_id);
+    enc.extend_from_slice(&word_addr(recipient));
+    enc.extend_from_slice(&keccak256(ipfs_cid.as_bytes()));
+    enc.extend_from_slice(&word_u128(expires_at as u128));
+    enc.extend_from_slice(&word_u128(max_reads as u128));
+    enc.extend_from_slice(&word_bool(is_public));
+    enc.extend_from_slice(&word_u128(nonce as u128));
+    enc.extend_from_slice(&word_u128(deadline as u128));
+    keccak256(&enc)
+}
+
+/// hashStruct of a RecordRead or RevokeSecret intent (`type_string` picks which).
+pub fn bytes32_intent_hash(type_string: &str, secret_id: &[u8; 32], nonce: u64, deadline: u64) -> [u8; 32] {
+    let mut enc = keccak256(type_string.as_bytes()).to_vec();
+    enc.extend_from_slice(secret_id);
+    enc.extend_from_slice(&word_u128(nonce as u128));
+    enc.extend_from_slice(&word_u128(deadline as u128));
+    keccak256(&enc)
+}
+
+/// Sign a 32-byte digest: 65 bytes r || s || v with v = 27 or 28, as `ecrecover` expects.
+/// k256 produces low-s signatures, which the registry requires.
+pub fn sign_digest(priv_bytes: &[u8], digest: &[u8; 32]) -> Result<[u8; 65]> {
+    let signing_key = SigningKey::from_slice(priv_bytes).map_err(|e| anyhow!("invalid signing key: {}", e))?;
+    let (sig, recid) = signing_key
+        .sign_prehash_recoverable(digest)
+        .map_err(|e| anyhow!("signing failed: {}", e))?;
+    let mut out = [0u8; 65];
+    out[..64].copy_from_slice(&sig.to_bytes());
+    out[64] = 27 + recid.to_byte();
+    Ok(out)
+}
+
+/// Address that produced `signature` (r || s || v) over `digest`.
+pub fn recover_signer(digest: &[u8; 32], signature: &[u8]) -> Result<[u8; 20]> {
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    if signature.len() != 65 {
+        return Err(anyhow!("signature must be 65 bytes, got {}", signature.len()));
+    }
+    let sig = Signature::from_slice(&signature[..64]).map_err(|e| anyhow!("invalid signature: {}", e))?;
+    let recid = RecoveryId::from_byte(signature[64].wrapping_sub(27)).ok_or_else(|| anyhow!("invalid signature v byte"))?;
+    let key = VerifyingKey::recover_from_prehash(digest, &sig, recid).map_err(|e| anyhow!("invalid signature: {}", e))?;
+    let hash = keccak256(&key.to_encoded_point(false).as_bytes()[1..]);
+    let mut addr = [0u8; 20];
+    addr.copy_from_slice(&hash[12..32]);
+    Ok(addr)
+}
+
+/// `describe_calldata` for a call, `deploy` for a contract creation.
+fn describe_tx(is_call: bool, data_hex: &str) -> String {
+    if is_call { describe_calldata(data_hex) } else { "deploy".to_string() }
+}
+
+/// Function name for 0x-hex calldata: `shareSecret`, ..., `transfer` for empty calldata
+/// (a cancellation), or the raw selector if unknown.
+pub fn describe_calldata(data_hex: &str) -> String {
+    let data = hex_to_bytes(data_hex).unwrap_or_default();
+    if data.len() < 4 {
+        return "transfer".to_string();
+    }
+    REGISTRY_WRITES
+        .iter()
+        .find(|sig| selector(sig) == data[..4])
+        .and_then(|sig| sig.split('(').next())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("0x{}", bytes_to_hex(&data[..4])))
+}
+
+// ---------------------------------------------------------------------------
+// Revert decoding
+// ---------------------------------------------------------------------------
+
+/// Revert payload of a JSON-RPC error: `error.data` as a hex string, or nested one level
+/// (`error.data.data`, as some providers wrap it).
+fn revert_data(err: &Value) -> Option<Vec<u8>> {
+    let data = err.get("data")?;
+    let hex = data.as_str().or_else(|| data.get("data").and_then(|d| d.as_str()))?;
+    hex_to_bytes(hex).ok().filter(|b| b.len() >= 4)
+}
+
+/// A bytes32 secret ID as the user typed it: short text IDs are stored left-aligned and
+/// zero-padded, generated IDs are full 32-byte hex.
+pub fn display_secret_id(w: &[u8]) -> String {
+    let end = w.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    let text = &w[..end];
+    if end < 32 && !text.is_empty() && text.iter().all(|b| b.is_ascii_graphic()) {
+        String::from_utf8_lossy(text).into_owned()
+    } else {
+        format!("0x{}", bytes_to_hex(w))
+    }
+}
+
+/// Decode revert data from BsecSecretRegistry (its custom errors) or Solidity's built-in
+/// `Error(string)` / `Panic(uint256)` into a `BsecError`. `None` if there is nothing to decode.
+pub fn decode_revert(data: &[u8]) -> Option<BsecError> {
+    let (sel, args) = data.split_at_checked(4)?;
+    let word = |i: usize| word_at(args, i).ok();
+    let id = || word(0).map(display_secret_id);
+    let addr = |i: usize| word(i).map(|w| crate::blockchain::bytes_to_checksum_address(&word_to_addr(w)));
+
+    let decoded = if sel == selector("SecretAlreadyExists(bytes32)") {
+        BsecError::SecretAlreadyExists(id()?)
+    } else if sel == selector("SecretNotFound(bytes32)") {
+        BsecError::SecretNotFound(id()?)
+    } else if sel == selector("SecretExpired(bytes32,uint64,uint64)") {
+        BsecError::SecretExpiredAt { id: id()?, expires_at: word_to_u64(word(1)?), now: word_to_u64(word(2)?) }
+    } else if sel == selector("ReadLimitExceeded(bytes32,uint32,uint32)") {
+        BsecError::ReadLimitReached { id: id()?, read_count: word_to_u32(word(1)?), max_reads: word_to_u32(word(2)?) }
+    } else if sel == selector("SecretIsRevoked(bytes32)") {
+        BsecError::SecretRevoked(id()?)
+    } else if sel == selector("UnauthorizedViewer(bytes32,address)") {
+        BsecError::UnauthorizedViewer { id: id()?, viewer: addr(1)? }
+    } else if sel == selector("UnauthorizedRevoker(bytes32,address)") {
+        BsecError::UnauthorizedRevoker { id: id()?, caller: addr(1)? }
+    } else if sel == selector("InvalidParameters()") {
+        BsecError::InvalidParameters
+    } else if sel == selector("InvalidSignature()") {
+        BsecError::Reverted("invalid signature (wrong signer, or its nonce was already used)".into())
+    } else if sel == selector("SignatureExpired(uint256)") {
+        BsecError::Reverted(format!("signature expired at {}", word_to_u64(word(0)?)))
+    } else if sel == selector("Error(string)") {
+        // Offset and length come from the revert data: a hostile one must not overflow.
+        let offset = word_to_u64(word(0)?) as usize;
+        let start = offset.checked_add(32)?;
+        let len = word_to_u64(args.get(offset..start)?) as usize;
+        let reason = args.get(start..start.checked_add(len)?)?;
+        BsecError::Reverted(String::from_utf8_lossy(reason).into_owned())
+    } else if sel == selector("Panic(uint256)") {
+        BsecError::Reverted(format!("panic code 0x{:02x}", word_to_u64(word(0)?)))
+    } else {
+        BsecError::Reverted(format!("unknown custom error 0x{}", bytes_to_hex(sel)))
+    };
+    Some(decoded)
+}
+
 // ---------------------------------------------------------------------------
 // ABI decoding for getSecretInfo return tuple
 // ---------------------------------------------------------------------------
 
 /// Decoded getSecretInfo() return values.
+#[derive(Debug)]
 pub struct DecodedSecretInfo {
     pub sender: [u8; 20],
     pub recipient: [u8; 20],
@@ -300,13 +731,23 @@ pub fn decode_secret_info(data: &[u8]) -> Result<DecodedSecretInfo> {
 
 /// eth_call against the registry; returns raw return bytes.
 pub fn eth_call(conf: &NetworkConfig, to: &[u8; 20], data: &[u8]) -> Result<Vec<u8>> {
-    let obj = json!({ "to": addr_hex(to), "data": format!("0x{}", bytes_to_hex(data)) });
+    call_with(conf, json!({ "to": addr_hex(to), "data": format!("0x{}", bytes_to_hex(data)) }))
+}
+
+/// `eth_call` as sent by `from`, to dry-run a write that checks msg.sender. A revert comes
+/// back as the decoded registry error where there is one.
+pub fn eth_call_from(conf: &NetworkConfig, from: &[u8; 20], to: &[u8; 20], data: &[u8]) -> Result<Vec<u8>> {
+    call_with(conf, json!({ "from": addr_hex(from), "to": addr_hex(to), "data": format!("0x{}", bytes_to_hex(data)) }))
+}
+
+fn call_with(conf: &NetworkConfig, obj: Value) -> Result<Vec<u8>> {
     let res = rpc(conf, "eth_call", json!([obj, "latest"]))?;
     let s = res.as_str().ok_or_else(|| anyhow!("eth_call returned non-string result"))?;
     hex_to_bytes(s)
 }
 
-/// Sign an EIP-155 legacy transaction and return the 0x-prefixed raw tx.
+/// Sign an EIP-155 legacy transaction and return the 0x-prefixed raw tx. An empty `to`
+/// makes it a contract creation.
 // A legacy tx has nine RLP fields; passing them individually is clearer than a wrapper struct.
 #[allow(clippy::too_many_arguments)]
 fn sign_legacy_tx(
@@ -314,7 +755,7 @@ fn sign_legacy_tx(
     nonce: u128,
     gas_price: u128,
     gas_limit: u128,
-    to: &[u8; 20],
+    to: &[u8],
     value: u128,
     data: &[u8],
     chain_id: u64,
@@ -388,7 +829,7 @@ fn eip1559_field_list(
     max_priority_fee: u128,
     max_fee: u128,
     gas_limit: u128,
-    to: &[u8; 20],
+    to: &[u8],
     value: u128,
     data: &[u8],
     sig: Option<(u8, &[u8], &[u8])>,
@@ -427,7 +868,7 @@ fn sign_eip1559_tx(
     max_priority_fee: u128,
     max_fee: u128,
     gas_limit: u128,
-    to: &[u8; 20],
+    to: &[u8],
     value: u128,
     data: &[u8],
 ) -> Result<String> {
@@ -462,81 +903,767 @@ fn sign_eip1559_tx(
     Ok(format!("0x{}", bytes_to_hex(&tx)))
 }
 
+/// Per-command overrides for a registry write. Amounts are in wei; `None` keeps the automatic
+/// choice (estimateGas + 20%, 2x base fee + node tip, or `eth_gasPrice` on legacy chains).
+#[derive(Debug, Clone, Default)]
+pub struct TxOptions {
+    /// EIP-1559 max fee per gas, or the gas price on legacy chains.
+    pub max_fee_per_gas: Option<u128>,
+    pub priority_fee_per_gas: Option<u128>,
+    pub gas_limit: Option<u128>,
+    /// Refuse to broadcast when the worst-case fee (gas limit x max fee) exceeds this.
+    pub fee_cap: Option<u128>,
+    /// Estimate only; commands print the estimate instead of sending.
+    pub dry_run: bool,
+    /// Submit registry writes as signed intents through this relayer (`bsec relay serve`),
+    /// which pays the gas.
+    pub relayer: Option<String>,
+}
+
+/// Fees a transaction would be sent with, and what it may cost.
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct TxEstimate {
+    /// `eip1559` or `legacy`.
+    pub kind: &'static str,
+    pub gas_limit: u128,
+    /// Max fee per gas (EIP-1559) or gas price (legacy), in wei.
+    pub max_fee_per_gas: u128,
+    pub priority_fee_per_gas: Option<u128>,
+    pub base_fee_per_gas: Option<u128>,
+    /// Cost at the current base fee: gas limit x min(max fee, base fee + tip).
+    pub expected_cost_wei: u128,
+    /// Worst case: gas limit x max fee. This is what `--fee-cap` is checked against.
+    pub max_cost_wei: u128,
+}
+
+impl TxEstimate {
+    pub fn exceeds_cap(&self, opts: &TxOptions) -> bool {
+        opts.fee_cap.is_some_and(|cap| self.max_cost_wei > cap)
+    }
+}
+
+/// Parse a decimal amount into integer base units with `decimals` fractional digits
+/// (`"1.5"` gwei with 9 decimals = 1_500_000_000 wei).
+fn parse_units(s: &str, decimals: u32) -> Result<u128> {
+    let t = s.trim();
+    let (int, frac) = t.split_once('.').unwrap_or((t, ""));
+    let digits_ok = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+    if (int.is_empty() && frac.is_empty()) || !digits_ok(int) || !digits_ok(frac) {
+        return Err(anyhow!("invalid amount {:?}", s));
+    }
+    if frac.len() > decimals as usize {
+        return Err(anyhow!("{:?} has more than {} decimal places", s, decimals));
+    }
+    let scale = 10u128.pow(decimals);
+    let int_part = if int.is_empty() { 0 } else { int.parse::<u128>()? };
+    let frac_part = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<u128>()? * 10u128.pow(decimals - frac.len() as u32)
+    };
+    int_part
+        .checked_mul(scale)
+        .and_then(|v| v.checked_add(frac_part))
+        .ok_or_else(|| anyhow!("amount {:?} is too large", s))
+}
+
+/// Gwei amount (CLI fee flags) to wei.
+pub fn parse_gwei(s: &str) -> Result<u128> {
+    parse_units(s, 9)
+}
+
+/// Native-token amount (ETH, POL, ...) to wei.
+pub fn parse_native(s: &str) -> Result<u128> {
+    parse_units(s, 18)
+}
+
+/// Render base units as a trimmed decimal (`format_units(1_500_000_000, 9)` = "1.5").
+pub fn format_units(v: u128, decimals: u32) -> String {
+    let scale = 10u128.pow(decimals);
+    let frac = v % scale;
+    if frac == 0 {
+        return (v / scale).to_string();
+    }
+    let frac = format!("{:0width$}", frac, width = decimals as usize);
+    format!("{}.{}", v / scale, frac.trim_end_matches('0'))
+}
+
+/// Work out gas limit and fees for a contract call, applying `opts` over the node's
+/// suggestions. Errors if the call would revert or the overrides are inconsistent.
+pub fn estimate_contract_tx(
+    conf: &NetworkConfig,
+    priv_bytes: &[u8],
+    to: &[u8; 20],
+    data: &[u8],
+    opts: &TxOptions,
+) -> Result<TxEstimate> {
+    estimate_tx(conf, &address_bytes_from_secret(priv_bytes)?, Some(to), data, opts)
+}
+
+/// `estimate_contract_tx` from `from` for a call (`Some(to)`) or a contract creation (`None`).
+fn estimate_tx(
+    conf: &NetworkConfig,
+    from: &[u8; 20],
+    to: Option<&[u8; 20]>,
+    data: &[u8],
+    opts: &TxOptions,
+) -> Result<TxEstimate> {
+    let from_hex = addr_hex(from);
+    let gas_limit = match opts.gas_limit {
+        Some(g) => g,
+        None => {
+            let mut call_obj = json!({
+                "from": from_hex,
+                "data": format!("0x{}", bytes_to_hex(data)),
+            });
+            if let Some(to) = to {
+                call_obj["to"] = json!(addr_hex(to));
+            }
+            match rpc(conf, "eth_estimateGas", json!([call_obj])) {
+                Ok(v) => match v.as_str() {
+                    Some(s) => hex_to_u128(s)?.saturating_mul(12) / 10, // +20% headroom
+                    None => 300_000,
+                },
+                // A decoded registry error says exactly why; pass it through unwrapped.
+                Err(e) if e.is::<BsecError>() => return Err(e),
+                // estimateGas reverting usually means the tx itself would revert on-chain.
+                Err(e) => return Err(anyhow!("gas estimation failed (transaction would revert): {}", e)),
+            }
+        }
+    };
+
+    // Post-London chains report a base fee -> EIP-1559 type-2. Pre-London chains have none
+    // -> EIP-155 legacy, where --max-fee is the gas price and a tip has no meaning.
+    match latest_base_fee(conf)? {
+        Some(base_fee) => {
+            let priority = opts.priority_fee_per_gas.unwrap_or_else(|| suggested_priority_fee(conf));
+            // Standard headroom: cover up to a doubling of the base fee before the tip.
+            let max_fee = opts
+                .max_fee_per_gas
+                .unwrap_or_else(|| base_fee.saturating_mul(2).saturating_add(priority));
+            if priority > max_fee {
+                return Err(anyhow!(
+                    "priority fee {} gwei is above the max fee {} gwei",
+                    format_units(priority, 9),
+                    format_units(max_fee, 9)
+                ));
+            }
+            if max_fee < base_fee {
+                return Err(anyhow!(
+                    "max fee {} gwei is below the current base fee {} gwei; the transaction would not be included",
+                    format_units(max_fee, 9),
+                    format_units(base_fee, 9)
+                ));
+            }
+            let effective = max_fee.min(base_fee.saturating_add(priority));
+            Ok(TxEstimate {
+                kind: "eip1559",
+                gas_limit,
+                max_fee_per_gas: max_fee,
+                priority_fee_per_gas: Some(priority),
+                base_fee_per_gas: Some(base_fee),
+                expected_cost_wei: gas_limit.saturating_mul(effective),
+                max_cost_wei: gas_limit.saturating_mul(max_fee),
+            })
+        }
+        None => {
+            if opts.priority_fee_per_gas.is_some() {
+                log::warn!("--priority-fee ignored: this chain has no base fee (legacy transactions)");
+            }
+            let gas_price = match opts.max_fee_per_gas {
+                Some(p) => p,
+                None => hex_to_u128(
+                    rpc(conf, "eth_gasPrice", json!([]))?
+                        .as_str()
+                        .ok_or_else(|| anyhow!("eth_gasPrice returned non-string"))?,
+                )?,
+            };
+            let cost = gas_limit.saturating_mul(gas_price);
+            Ok(TxEstimate {
+                kind: "legacy",
+                gas_limit,
+                max_fee_per_gas: gas_price,
+                priority_fee_per_gas: None,
+                base_fee_per_gas: None,
+                expected_cost_wei: cost,
+                max_cost_wei: cost,
+            })
+        }
+    }
+}
+
 /// Build, sign, and broadcast a contract-call transaction; wait for a successful receipt.
-/// Returns the transaction hash.
+/// Returns the transaction hash. Refuses to send when the worst-case fee exceeds
+/// `opts.fee_cap`. The transaction is journaled before waiting, so one that is still
+/// unmined at the timeout can be tracked and replaced with `bsec tx`.
 pub fn send_contract_tx(
     conf: &NetworkConfig,
     priv_bytes: &[u8],
     to: &[u8; 20],
     data: &[u8],
+    opts: &TxOptions,
 ) -> Result<String> {
+    send_tx(conf, priv_bytes, Some(to), data, opts)
+}
+
+/// Relayers only carry signed registry intents (share, read, revoke); everything else is
+/// sent from the wallet, which must be refused rather than silently paid for.
+fn check_no_relayer(opts: &TxOptions) -> Result<()> {
+    match &opts.relayer {
+        Some(url) => Err(anyhow!("--relayer {} only applies to share, view and revoke; this transaction must be sent from your wallet", url)),
+        None => Ok(()),
+    }
+}
+
+/// `send_contract_tx` for a call (`Some(to)`) or a contract creation (`None`).
+fn send_tx(
+    conf: &NetworkConfig,
+    priv_bytes: &[u8],
+    to: Option<&[u8; 20]>,
+    data: &[u8],
+    opts: &TxOptions,
+) -> Result<String> {
+    if opts.dry_run {
+        return Err(anyhow!("dry run: refusing to broadcast"));
+    }
+    check_no_relayer(opts)?;
     let from = address_bytes_from_secret(priv_bytes)?;
-    let from_hex = addr_hex(&from);
+    let est = estimate_tx(conf, &from, to, data, opts)?;
+    check_fee_cap(&est, opts)?;
+
+    let tx_hash = {
+        let lock = JournalLock::acquire()?;
+        let nonce = pick_nonce(conf, &lock, &addr_hex(&from))?;
+        let signed = PortableTx::new(conf.chain_id, &from, to, data, nonce, &est).sign(priv_bytes)?;
+        submit(conf, &lock, &signed, None)?
+    };
+    await_mined(conf, &tx_hash, &addr_hex(&from), to, data)
+}
 
-    let nonce = hex_to_u128(
-        rpc(conf, "eth_getTransactionCount", json!([from_hex, "pending"]))?
+/// Wait for a journaled transaction's receipt and record the outcome. A revert is replayed
+/// to recover its decoded reason.
+fn await_mined(conf: &NetworkConfig, tx_hash: &str, from_hex: &str, to: Option<&[u8; 20]>, data: &[u8]) -> Result<String> {
+    match wait_for_receipt(conf, tx_hash)? {
+        Some(status) => {
+            set_journal_status(tx_hash, status);
+            if status == TxStatus::Reverted {
+                let reason = match to {
+                    Some(to) => revert_reason(conf, from_hex, to, data),
+                    None => None,
+                };
+                return Err(match reason {
+                    Some(reason) => {
+                        log::warn!("Transaction {} reverted on-chain.", tx_hash);
+                        reason
+                    }
+                    None => anyhow!("transaction {} reverted on-chain", tx_hash),
+                });
+            }
+            Ok(tx_hash.to_string())
+        }
+        None => Err(anyhow!(
+            "transaction {} was not mined within {} s; it is still pending. Check it with `bsec tx status {}` or replace it with `bsec tx speedup {}`",
+            tx_hash,
+            RECEIPT_POLL_ATTEMPTS as u64 * RECEIPT_POLL_INTERVAL_SECS,
+            tx_hash,
+            tx_hash
+        )),
+    }
+}
+
+/// Deploy `init_code` (creation bytecode plus any encoded constructor arguments) and wait for
+/// the receipt. Returns the transaction hash and the address the contract was created at,
+/// after checking that code is actually there.
+pub fn deploy_contract(
+    conf: &NetworkConfig,
+    priv_bytes: &[u8],
+    init_code: &[u8],
+    opts: &TxOptions,
+) -> Result<(String, [u8; 20])> {
+    let tx_hash = send_tx(conf, priv_bytes, None, init_code, opts)?;
+    let receipt = rpc(conf, "eth_getTransactionReceipt", json!([tx_hash]))?;
+    let address = parse_address(
+        receipt
+            .get("contractAddress")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("receipt of {} has no contractAddress", tx_hash))?,
+    )?;
+    let code = rpc(conf, "eth_getCode", json!([addr_hex(&address), "latest"]))?;
+    if code.as_str().is_none_or(|c| c == "0x") {
+        return Err(anyhow!("deployment {} left no code at {}", tx_hash, addr_hex(&address)));
+    }
+    Ok((tx_hash, address))
+}
+
+/// keccak256 of the code deployed at `address` (0x hex), or `None` if there is none.
+pub fn code_hash(conf: &NetworkConfig, address: &str) -> Result<Option<String>> {
+    let code = rpc(conf, "eth_getCode", json!([address, "latest"]))?;
+    let code = hex_to_bytes(code.as_str().ok_or_else(|| anyhow!("eth_getCode returned non-string"))?)?;
+    Ok((!code.is_empty()).then(|| format!("0x{}", bytes_to_hex(&keccak256(&code)))))
+}
+
+/// Fee estimate for `deploy_contract`; nothing is sent.
+pub fn estimate_deploy(conf: &NetworkConfig, priv_bytes: &[u8], init_code: &[u8], opts: &TxOptions) -> Result<TxEstimate> {
+    estimate_tx(conf, &address_bytes_from_secret(priv_bytes)?, None, init_code, opts)
+}
+
+/// Why a mined transaction reverted: the call is replayed with `eth_call` (receipts carry
+/// no revert data), which usually fails the same way against the current state.
+fn revert_reason(conf: &NetworkConfig, from_hex: &str, to: &[u8; 20], data: &[u8]) -> Option<anyhow::Error> {
+    let call_obj = json!({ "from": from_hex, "to": addr_hex(to), "data": format!("0x{}", bytes_to_hex(data)) });
+    rpc(conf, "eth_call", json!([call_obj, "latest"])).err().filter(|e| e.is::<BsecError>())
+}
+
+fn check_fee_cap(est: &TxEstimate, opts: &TxOptions) -> Result<()> {
+    if est.exceeds_cap(opts) {
+        return Err(BsecError::FeeCapExceeded {
+            max_cost: format_units(est.max_cost_wei, 18),
+            cap: format_units(opts.fee_cap.unwrap_or_default(), 18),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn tx_count(conf: &NetworkConfig, from_hex: &str, block: &str) -> Result<u128> {
+    hex_to_u128(
+        rpc(conf, "eth_getTransactionCount", json!([from_hex, block]))?
             .as_str()
             .ok_or_else(|| anyhow!("eth_getTransactionCount returned non-string"))?,
-    )?;
+    )
+}
+
+/// Nonce for the next transaction from `from_hex`, chosen under the journal lock after
+/// settling (and re-broadcasting) its pending entries.
+fn pick_nonce(conf: &NetworkConfig, lock: &JournalLock, from_hex: &str) -> Result<u64> {
+    resync_pending(conf, lock, from_hex);
+    let node_pending = u64::try_from(tx_count(conf, from_hex, "pending")?)?;
+    Ok(tx_journal::next_nonce(&lock.entries(), conf.chain_id, from_hex, node_pending))
+}
 
-    let call_obj = json!({
-        "from": from_hex,
-        "to": addr_hex(to),
-        "data": format!("0x{}", bytes_to_hex(data)),
+/// Broadcast a signed transaction and journal it (marking `replaces` as replaced by it).
+/// Returns the hash reported by the node.
+fn submit(conf: &NetworkConfig, lock: &JournalLock, tx: &PortableTx, replaces: Option<&str>) -> Result<String> {
+    let raw = tx.raw.clone().ok_or_else(|| anyhow!("transaction is not signed"))?;
+    let tx_hash = broadcast_raw(conf, &raw)?;
+    let record = TxRecord {
+        hash: tx_hash.clone(),
+        chain_id: tx.chain_id,
+        from: tx.from.clone(),
+        to: tx.to.clone(),
+        nonce: tx.nonce,
+        data: tx.data.clone(),
+        kind: tx.kind.clone(),
+        gas_limit: tx.gas_limit,
+        max_fee_per_gas: tx.max_fee_per_gas,
+        priority_fee_per_gas: tx.priority_fee_per_gas,
+        raw,
+        submitted_at: crate::wallet::current_timestamp(),
+        status: TxStatus::Pending,
+        replaces: replaces.map(str::to_string),
+        replaced_by: None,
+    };
+    let res = lock.update(|entries| {
+        if let Some(old) = replaces.and_then(|h| entries.iter_mut().find(|r| r.hash == h)) {
+            old.replaced_by = Some(tx_hash.clone());
+        }
+        entries.push(record);
     });
-    let gas_limit = match rpc(conf, "eth_estimateGas", json!([call_obj])) {
-        Ok(v) => match v.as_str() {
-            Some(s) => hex_to_u128(s)?.saturating_mul(12) / 10, // +20% headroom
-            None => 300_000,
-        },
-        Err(e) => {
-            // estimateGas reverting usually means the tx itself would revert on-chain.
-            return Err(anyhow!("gas estimation failed (transaction would revert): {}", e));
+    if let Err(e) = res {
+        log::warn!("transaction {} was sent but could not be journaled: {}", tx_hash, e);
+    }
+    Ok(tx_hash)
+}
+
+fn set_journal_status(tx_hash: &str, status: TxStatus) {
+    let res = JournalLock::acquire().and_then(|lock| {
+        lock.update(|entries| {
+            if let Some(r) = entries.iter_mut().find(|r| r.hash == tx_hash) {
+                r.status = status;
+            }
+        })
+    });
+    if let Err(e) = res {
+        log::warn!("could not update journal entry for {}: {}", tx_hash, e);
+    }
+}
+
+/// Current status of a pending journal entry: mined (by receipt), superseded (its nonce was
+/// consumed by another transaction), or still pending.
+fn probe_status(conf: &NetworkConfig, rec: &TxRecord) -> Result<TxStatus> {
+    let receipt = rpc(conf, "eth_getTransactionReceipt", json!([rec.hash]))?;
+    if !receipt.is_null() {
+        let status = receipt.get("status").and_then(|s| s.as_str()).unwrap_or("0x1");
+        return Ok(if status == "0x0" { TxStatus::Reverted } else { TxStatus::Confirmed });
+    }
+    if tx_count(conf, &rec.from, "latest")? > rec.nonce as u128 {
+        return Ok(if rec.replaced_by.is_some() { TxStatus::Replaced } else { TxStatus::Dropped });
+    }
+    Ok(TxStatus::Pending)
+}
+
+/// Refresh this chain's pending journal entries (optionally only `from`'s) against the
+/// node and save the result.
+fn refresh_pending(conf: &NetworkConfig, lock: &JournalLock, from: Option<&str>) -> Result<Vec<TxRecord>> {
+    lock.update(|entries| {
+        for rec in entries.iter_mut() {
+            let ours = rec.chain_id == conf.chain_id && from.is_none_or(|f| rec.from.eq_ignore_ascii_case(f));
+            if ours && rec.status == TxStatus::Pending {
+                match probe_status(conf, rec) {
+                    Ok(status) => rec.status = status,
+                    Err(e) => log::warn!("could not check transaction {}: {}", rec.hash, e),
+                }
+            }
         }
-    };
+        entries.clone()
+    })
+}
 
-    let chain_id = conf.chain_id as u64;
-    // Post-London chains report a base fee -> send EIP-1559 type-2. Pre-London chains have
-    // none -> fall back to EIP-155 legacy signing.
-    let raw = match latest_base_fee(conf)? {
-        Some(base_fee) => {
-            let priority = suggested_priority_fee(conf);
-            // Standard headroom: cover up to a doubling of the base fee before the tip.
-            let max_fee = base_fee.saturating_mul(2).saturating_add(priority);
-            sign_eip1559_tx(priv_bytes, chain_id, nonce, priority, max_fee, gas_limit, to, 0, data)?
+/// Before picking a nonce: settle pending entries, and re-broadcast any the node does not
+/// know about (it may have been evicted, or a failover node never saw it) so their nonces
+/// are not left as gaps.
+fn resync_pending(conf: &NetworkConfig, lock: &JournalLock, from_hex: &str) {
+    let Ok(entries) = refresh_pending(conf, lock, Some(from_hex)) else { return };
+    for rec in entries.iter().filter(|r| {
+        r.status == TxStatus::Pending && r.replaced_by.is_none() && r.chain_id == conf.chain_id && r.from == from_hex
+    }) {
+        let known = rpc(conf, "eth_getTransactionByHash", json!([rec.hash])).is_ok_and(|v| !v.is_null());
+        if !known && let Err(e) = broadcast_raw(conf, &rec.raw) {
+            log::warn!("could not re-broadcast pending transaction {}: {}", rec.hash, e);
+        }
+    }
+}
+
+/// Journal entries after refreshing this chain's pending ones.
+pub fn tx_journal_refreshed(conf: &NetworkConfig) -> Result<Vec<TxRecord>> {
+    let lock = JournalLock::acquire()?;
+    refresh_pending(conf, &lock, None)
+}
+
+/// What to send in place of a stuck transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    /// Same call, higher fees.
+    SpeedUp,
+    /// Zero-value transfer to self, which consumes the nonce and does nothing.
+    Cancel,
+}
+
+/// Fees for a replacement of `old`: at least `tx_journal::bump_fee` above the original, and
+/// at least what a fresh transaction would pay now. Explicit overrides below the minimum
+/// bump are rejected because nodes would refuse the replacement.
+fn replacement_estimate(conf: &NetworkConfig, old: &TxRecord, gas_limit: u128, opts: &TxOptions) -> Result<TxEstimate> {
+    let min_max_fee = tx_journal::bump_fee(old.max_fee_per_gas);
+    let min_priority = old.priority_fee_per_gas.map(tx_journal::bump_fee);
+    if let Some(fee) = opts.max_fee_per_gas.filter(|f| *f < min_max_fee) {
+        return Err(anyhow!(
+            "--max-fee {} gwei is below the minimum replacement fee {} gwei",
+            format_units(fee, 9),
+            format_units(min_max_fee, 9)
+        ));
+    }
+    if let (Some(fee), Some(min)) = (opts.priority_fee_per_gas, min_priority)
+        && fee < min
+    {
+        return Err(anyhow!(
+            "--priority-fee {} gwei is below the minimum replacement tip {} gwei",
+            format_units(fee, 9),
+            format_units(min, 9)
+        ));
+    }
+
+    let base_fee = match old.priority_fee_per_gas {
+        Some(_) => latest_base_fee(conf)?,
+        None => None,
+    };
+    let (max_fee, priority) = match min_priority {
+        Some(min_priority) => {
+            let priority = opts
+                .priority_fee_per_gas
+                .unwrap_or_else(|| min_priority.max(suggested_priority_fee(conf)));
+            let fresh = base_fee.unwrap_or(0).saturating_mul(2).saturating_add(priority);
+            let max_fee = opts.max_fee_per_gas.unwrap_or_else(|| min_max_fee.max(fresh));
+            if priority > max_fee {
+                return Err(anyhow!(
+                    "priority fee {} gwei is above the max fee {} gwei",
+                    format_units(priority, 9),
+                    format_units(max_fee, 9)
+                ));
+            }
+            (max_fee, Some(priority))
         }
         None => {
-            let gas_price = hex_to_u128(
-                rpc(conf, "eth_gasPrice", json!([]))?
-                    .as_str()
-                    .ok_or_else(|| anyhow!("eth_gasPrice returned non-string"))?,
-            )?;
-            sign_legacy_tx(priv_bytes, nonce, gas_price, gas_limit, to, 0, data, chain_id)?
+            let gas_price = match opts.max_fee_per_gas {
+                Some(p) => p,
+                None => {
+                    let current = rpc(conf, "eth_gasPrice", json!([]))
+                        .ok()
+                        .and_then(|v| v.as_str().and_then(|s| hex_to_u128(s).ok()))
+                        .unwrap_or(0);
+                    min_max_fee.max(current)
+                }
+            };
+            (gas_price, None)
         }
     };
+    let effective = match (priority, base_fee) {
+        (Some(p), Some(b)) => max_fee.min(b.saturating_add(p)),
+        _ => max_fee,
+    };
+    Ok(TxEstimate {
+        kind: if priority.is_some() { "eip1559" } else { "legacy" },
+        gas_limit,
+        max_fee_per_gas: max_fee,
+        priority_fee_per_gas: priority,
+        base_fee_per_gas: base_fee,
+        expected_cost_wei: gas_limit.saturating_mul(effective),
+        max_cost_wei: gas_limit.saturating_mul(max_fee),
+    })
+}
 
-    let tx_hash = rpc(conf, "eth_sendRawTransaction", json!([raw]))?
-        .as_str()
-        .ok_or_else(|| anyhow!("eth_sendRawTransaction returned non-string"))?
-        .to_string();
+/// Replace the pending journal transaction `hash` at the same nonce. With `opts.dry_run`
+/// only the fees are worked out; otherwise the replacement is broadcast and journaled (not
+/// awaited). Returns the replacement's estimate and, when sent, its record.
+pub fn replace_tx(
+    conf: &NetworkConfig,
+    priv_bytes: &[u8],
+    hash: &str,
+    how: Replacement,
+    opts: &TxOptions,
+) -> Result<(TxEstimate, Option<TxRecord>)> {
+    check_no_relayer(opts)?;
+    let from_bytes = address_bytes_from_secret(priv_bytes)?;
+    let from_hex = addr_hex(&from_bytes);
+    let lock = JournalLock::acquire()?;
+    let entries = refresh_pending(conf, &lock, Some(&from_hex))?;
+    let old = tx_journal::find(&entries, hash)?.clone();
+    if old.chain_id != conf.chain_id {
+        return Err(anyhow!("transaction {} was sent on chain {}, not the configured chain {}", old.hash, old.chain_id, conf.chain_id));
+    }
+    if !old.from.eq_ignore_ascii_case(&from_hex) {
+        return Err(anyhow!("transaction {} was sent from {}, not this wallet", old.hash, old.from));
+    }
+    if old.status != TxStatus::Pending {
+        return Err(anyhow!("transaction {} is no longer pending ({})", old.hash, old.status.as_str()));
+    }
+    if let Some(newer) = &old.replaced_by {
+        return Err(anyhow!("transaction {} was already replaced by {}; replace that one instead", old.hash, newer));
+    }
 
-    wait_for_receipt(conf, &tx_hash)?;
-    Ok(tx_hash)
+    // An empty `to` is a contract creation, re-sent as one.
+    let (to, data, gas_limit) = match how {
+        Replacement::SpeedUp => (
+            if old.to.is_empty() { None } else { Some(parse_address(&old.to)?) },
+            hex_to_bytes(&old.data)?,
+            opts.gas_limit.unwrap_or(old.gas_limit),
+        ),
+        Replacement::Cancel => (Some(from_bytes), Vec::new(), 21_000),
+    };
+    let est = replacement_estimate(conf, &old, gas_limit, opts)?;
+    if opts.dry_run {
+        return Ok((est, None));
+    }
+    check_fee_cap(&est, opts)?;
+
+    let signed = PortableTx::new(conf.chain_id, &from_bytes, to.as_ref(), &data, old.nonce, &est).sign(priv_bytes)?;
+    let new_hash = submit(conf, &lock, &signed, Some(&old.hash))?;
+    let record = lock.entries().into_iter().find(|r| r.hash == new_hash);
+    Ok((est, record))
+}
+
+// ---------------------------------------------------------------------------
+// Offline signing
+// ---------------------------------------------------------------------------
+
+/// Version tag of the files written by `bsec tx build` / `bsec tx sign`.
+pub const PORTABLE_TX_FORMAT: &str = "bsec-tx/1";
+
+/// A transaction with nonce, fees and gas fixed, portable between an online host (which
+/// builds and broadcasts it) and an offline one (which signs it). `raw` and `hash` are
+/// filled in by signing.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct PortableTx {
+    pub format: String,
+    pub chain_id: u32,
+    /// Lowercase 0x-prefixed sender address; signing refuses any other key.
+    pub from: String,
+    /// Empty for a contract creation.
+    pub to: String,
+    pub nonce: u64,
+    /// 0x-prefixed calldata.
+    pub data: String,
+    /// Function called (`shareSecret`, `revokeSecret`, ...), shown before signing.
+    pub description: String,
+    /// `eip1559` or `legacy`.
+    pub kind: String,
+    pub gas_limit: u128,
+    /// Max fee per gas (EIP-1559) or gas price (legacy), in wei.
+    pub max_fee_per_gas: u128,
+    pub priority_fee_per_gas: Option<u128>,
+    /// Worst case: gas limit x max fee.
+    pub max_cost_wei: u128,
+    pub built_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
 }
 
-fn wait_for_receipt(conf: &NetworkConfig, tx_hash: &str) -> Result<()> {
+impl PortableTx {
+    fn new(chain_id: u32, from: &[u8; 20], to: Option<&[u8; 20]>, data: &[u8], nonce: u64, est: &TxEstimate) -> Self {
+        let data = format!("0x{}", bytes_to_hex(data));
+        Self {
+            format: PORTABLE_TX_FORMAT.to_string(),
+            chain_id,
+            from: addr_hex(from),
+            to: to.map(addr_hex).unwrap_or_default(),
+            nonce,
+            description: describe_tx(to.is_some(), &data),
+            data,
+            kind: est.kind.to_string(),
+            gas_limit: est.gas_limit,
+            max_fee_per_gas: est.max_fee_per_gas,
+            priority_fee_per_gas: est.priority_fee_per_gas,
+            max_cost_wei: est.gas_limit.saturating_mul(est.max_fee_per_gas),
+            built_at: crate::wallet::current_timestamp(),
+            raw: None,
+            hash: None,
+        }
+    }
+
+    /// Parse a file written by `bsec tx build` or `bsec tx sign`. The `description` and
+    /// `max_cost_wei` shown before signing must be what `data` and the gas fields work out to.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let tx: Self = serde_json::from_str(json).map_err(|e| anyhow!("not a bsec transaction file: {}", e))?;
+        if tx.format != PORTABLE_TX_FORMAT {
+            return Err(anyhow!("unsupported transaction file format {:?} (expected {})", tx.format, PORTABLE_TX_FORMAT));
+        }
+        tx.check_summary()?;
+        Ok(tx)
+    }
+
+    /// Function called, from the calldata.
+    pub fn describe(&self) -> String {
+        describe_tx(!self.to.is_empty(), &self.data)
+    }
+
+    /// Worst-case cost in wei, from the gas limit and max fee.
+    pub fn max_cost(&self) -> u128 {
+        self.gas_limit.saturating_mul(self.max_fee_per_gas)
+    }
+
+    fn check_summary(&self) -> Result<()> {
+        let tampered = |what: &str, stored: String, actual: String| {
+            anyhow!("transaction file says {} is {}, but it is {}; the file was modified", what, stored, actual)
+        };
+        if self.description != self.describe() {
+            return Err(tampered("the call", format!("{:?}", self.description), format!("{:?}", self.describe())));
+        }
+        if self.max_cost_wei != self.max_cost() {
+            return Err(tampered("the max cost", format!("{} wei", self.max_cost_wei), format!("{} wei", self.max_cost())));
+        }
+        Ok(())
+    }
+
+    /// Sign with `priv_bytes`, which must belong to `from`. Needs no network access.
+    pub fn sign(&self, priv_bytes: &[u8]) -> Result<PortableTx> {
+        if self.raw.is_some() {
+            return Err(anyhow!("transaction is already signed"));
+        }
+        self.check_summary()?;
+        let signer = addr_hex(&address_bytes_from_secret(priv_bytes)?);
+        if !signer.eq_ignore_ascii_case(&self.from) {
+            return Err(anyhow!("transaction is from {}, but this wallet is {}", self.from, signer));
+        }
+        let to = if self.to.is_empty() { Vec::new() } else { parse_address(&self.to)?.to_vec() };
+        let data = hex_to_bytes(&self.data)?;
+        let (chain_id, nonce) = (self.chain_id as u64, self.nonce as u128);
+        let raw = match (self.kind.as_str(), self.priority_fee_per_gas) {
+            ("eip1559", Some(priority)) => sign_eip1559_tx(
+                priv_bytes,
+                chain_id,
+                nonce,
+                priority,
+                self.max_fee_per_gas,
+                self.gas_limit,
+                &to,
+                0,
+                &data,
+            )?,
+            ("legacy", None) => sign_legacy_tx(priv_bytes, nonce, self.max_fee_per_gas, self.gas_limit, &to, 0, &data, chain_id)?,
+            (kind, _) => return Err(anyhow!("inconsistent transaction kind {:?} and priority fee", kind)),
+        };
+        let hash = format!("0x{}", bytes_to_hex(&keccak256(&hex_to_bytes(&raw)?)));
+        Ok(PortableTx { raw: Some(raw), hash: Some(hash), ..self.clone() })
+    }
+}
+
+/// Build an unsigned transaction from `from` (no key needed): estimate gas and fees, check
+/// `opts.fee_cap`, and take the next nonce unless `nonce` is given. Nothing is journaled
+/// until broadcast, so several transactions built ahead of time need explicit nonces.
+pub fn build_tx(
+    conf: &NetworkConfig,
+    from: &[u8; 20],
+    to: Option<&[u8; 20]>,
+    data: &[u8],
+    opts: &TxOptions,
+    nonce: Option<u64>,
+) -> Result<PortableTx> {
+    check_no_relayer(opts)?;
+    let est = estimate_tx(conf, from, to, data, opts)?;
+    check_fee_cap(&est, opts)?;
+    let nonce = match nonce {
+        Some(n) => n,
+        None => pick_nonce(conf, &JournalLock::acquire()?, &addr_hex(from))?,
+    };
+    Ok(PortableTx::new(conf.chain_id, from, to, data, nonce, &est))
+}
+
+/// Broadcast a transaction signed offline, journal it, and wait for it to be mined.
+pub fn broadcast_signed(conf: &NetworkConfig, tx: &PortableTx) -> Result<String> {
+    let raw = tx.raw.as_deref().ok_or_else(|| anyhow!("transaction is not signed; run `bsec tx sign` first"))?;
+    if tx.chain_id != conf.chain_id {
+        return Err(anyhow!("transaction is for chain {}, but the configured network is chain {}", tx.chain_id, conf.chain_id));
+    }
+    let local_hash = format!("0x{}", bytes_to_hex(&keccak256(&hex_to_bytes(raw)?)));
+    if tx.hash.as_deref().is_some_and(|h| !h.eq_ignore_ascii_case(&local_hash)) {
+        return Err(anyhow!("signed transaction does not match its recorded hash; the file was modified"));
+    }
+    let to = if tx.to.is_empty() { None } else { Some(parse_address(&tx.to)?) };
+    let tx_hash = submit(conf, &JournalLock::acquire()?, tx, None)?;
+    await_mined(conf, &tx_hash, &tx.from, to.as_ref(), &hex_to_bytes(&tx.data)?)
+}
+
+/// Broadcast a signed transaction. If a failover re-sends it to a node that already has it
+/// (the first node accepted it but the response was lost), the locally computed hash is used.
+fn broadcast_raw(conf: &NetworkConfig, raw: &str) -> Result<String> {
+    match rpc(conf, "eth_sendRawTransaction", json!([raw])) {
+        Ok(v) => Ok(v
+            .as_str()
+            .ok_or_else(|| anyhow!("eth_sendRawTransaction returned non-string"))?
+            .to_string()),
+        Err(e) => {
+            let msg = e.to_string().to_lowercase();
+            if msg.contains("already known") || msg.contains("known transaction") {
+                Ok(format!("0x{}", bytes_to_hex(&keccak256(&hex_to_bytes(raw)?))))
+            } else {
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Poll for the receipt. `None` means the transaction was not mined within the polling window.
+fn wait_for_receipt(conf: &NetworkConfig, tx_hash: &str) -> Result<Option<TxStatus>> {
     for _ in 0..RECEIPT_POLL_ATTEMPTS {
         let receipt = rpc(conf, "eth_getTransactionReceipt", json!([tx_hash]))?;
         if !receipt.is_null() {
             let status = receipt.get("status").and_then(|s| s.as_str()).unwrap_or("0x1");
-            if status == "0x0" {
-                return Err(anyhow!("transaction {} reverted on-chain", tx_hash));
-            }
-            return Ok(());
+            return Ok(Some(if status == "0x0" { TxStatus::Reverted } else { TxStatus::Confirmed }));
         }
         std::thread::sleep(Duration::from_secs(RECEIPT_POLL_INTERVAL_SECS));
     }
-    Err(anyhow!("timed out waiting for receipt of transaction {}", tx_hash))
+    Ok(None)
 }
 
 #[cfg(test)]
@@ -595,6 +1722,17 @@ mod tests {
         assert!(list.contains(&0xc0));
     }
 
+    #[test]
+    fn contract_creation_encodes_empty_to() {
+        // chain 1, nonce/fees/gas/value 0: each a 0x80 empty string; `to` empty is 0x80 too,
+        // where a call would have 0x94 followed by 20 address bytes.
+        let create = eip1559_field_list(1, 0, 0, 0, 0, &[], 0, b"", None);
+        assert_eq!(create, vec![0xc9, 0x01, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xc0]);
+        let legacy = sign_legacy_tx(&[0x11u8; 32], 0, 1, 21_000, &[], 0, &[0x60, 0x00], 1).unwrap();
+        let call = sign_legacy_tx(&[0x11u8; 32], 0, 1, 21_000, &[0u8; 20], 0, &[0x60, 0x00], 1).unwrap();
+        assert_eq!(call.len() - legacy.len(), 40, "20 address bytes, hex-encoded");
+    }
+
     #[test]
     fn address_from_known_key() {
         // Well-known test vector: private key 0x0000...0001
@@ -706,4 +1844,417 @@ mod tests {
     fn decode_secret_info_rejects_short_buffer() {
         assert!(decode_secret_info(&[0u8; 100]).is_err());
     }
+
+    /// JSON-RPC mock answering `handler(method)` with (HTTP status, JSON body).
+    fn mock_rpc(handler: fn(&str) -> (u16, String)) -> String {
+        use std::io::{BufRead, BufReader, Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut len = 0usize;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some(v) = line.to_lowercase().strip_prefix("content-length:") {
+                        len = v.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0u8; len];
+                reader.read_exact(&mut body).unwrap();
+                let req: Value = serde_json::from_slice(&body).unwrap();
+                let (status, reply) = handler(req["method"].as_str().unwrap());
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+            }
+        });
+        url
+    }
+
+    fn anvil_like(method: &str) -> (u16, String) {
+        match method {
+            "eth_chainId" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x7a69"}"#.into()),
+            "eth_blockNumber" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x2a"}"#.into()),
+            _ => (200, r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"execution reverted"}}"#.into()),
+        }
+    }
+
+    fn conf_for(urls: &[&str]) -> NetworkConfig {
+        NetworkConfig {
+            chain_id: 31337,
+            rpc_url: urls[0].to_string(),
+            rpc_urls: urls[1..].iter().map(|u| u.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn closed_port_url() -> String {
+        let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", l.local_addr().unwrap())
+    }
+
+    #[test]
+    fn rpc_fails_over_on_transport_error_and_rate_limits() {
+        let limited = mock_rpc(|_| (429, "{}".into()));
+        let quota = mock_rpc(|_| (200, r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"limit exceeded"}}"#.into()));
+        let good = mock_rpc(anvil_like);
+        let conf = conf_for(&[&closed_port_url(), &limited, &quota, &good]);
+        assert_eq!(rpc(&conf, "eth_blockNumber", json!([])).unwrap(), json!("0x2a"));
+    }
+
+    #[test]
+    fn rpc_skips_endpoints_on_another_chain() {
+        let mainnet = mock_rpc(|_| (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#.into()));
+        let good = mock_rpc(anvil_like);
+        assert_eq!(rpc(&conf_for(&[&mainnet, &good]), "eth_blockNumber", json!([])).unwrap(), json!("0x2a"));
+
+        let err = rpc(&conf_for(&[&mainnet]), "eth_blockNumber", json!([])).unwrap_err();
+        assert!(err.to_string().contains("chain ID mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn rpc_error_from_healthy_node_does_not_fail_over() {
+        let reverting = mock_rpc(anvil_like);
+        let other = mock_rpc(|m| match m {
+            "eth_call" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x"}"#.into()),
+            m => anvil_like(m),
+        });
+        let err = rpc(&conf_for(&[&reverting, &other]), "eth_call", json!([])).unwrap_err();
+        assert!(err.to_string().contains("execution reverted"));
+    }
+
+    #[test]
+    fn check_endpoint_reports_head_and_chain() {
+        let good = mock_rpc(anvil_like);
+        let r = check_endpoint(&good, 31337);
+        assert!(r.ok && r.chain_id_matches);
+        assert_eq!((r.chain_id, r.head_block), (Some(31337), Some(42)));
+
+        let wrong = check_endpoint(&good, 1);
+        assert!(!wrong.ok && !wrong.chain_id_matches);
+
+        let down = check_endpoint(&closed_port_url(), 31337);
+        assert!(!down.ok && down.error.is_some() && down.head_block.is_none());
+    }
+
+    #[test]
+    fn fee_units_parse_and_format() {
+        assert_eq!(parse_gwei("1.5").unwrap(), 1_500_000_000);
+        assert_eq!(parse_gwei("30").unwrap(), 30_000_000_000);
+        assert_eq!(parse_gwei(".25").unwrap(), 250_000_000);
+        assert_eq!(parse_native("0.01").unwrap(), 10_000_000_000_000_000);
+        assert!(parse_gwei("1.0000000001").is_err());
+        assert!(parse_gwei("-1").is_err());
+        assert!(parse_gwei("").is_err());
+        assert!(parse_gwei("1e9").is_err());
+        assert_eq!(format_units(1_500_000_000, 9), "1.5");
+        assert_eq!(format_units(21_000 * 30_000_000_000, 18), "0.00063");
+        assert_eq!(format_units(2_000_000_000_000_000_000, 18), "2");
+    }
+
+    #[test]
+    fn estimate_applies_overrides_and_checks_consistency() {
+        let node = mock_rpc(|m| match m {
+            "eth_chainId" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x7a69"}"#.into()),
+            "eth_estimateGas" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x186a0"}"#.into()),
+            "eth_getBlockByNumber" => (200, r#"{"jsonrpc":"2.0","id":1,"result":{"baseFeePerGas":"0x3b9aca00"}}"#.into()),
+            "eth_maxPriorityFeePerGas" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x77359400"}"#.into()),
+            _ => (200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into()),
+        });
+        let conf = conf_for(&[&node]);
+        let key = [0x11u8; 32];
+        let to = [0x22u8; 20];
+
+        // Automatic: 100k gas + 20%, base 1 gwei, tip 2 gwei, max = 2*base + tip.
+        let auto = estimate_contract_tx(&conf, &key, &to, &[], &TxOptions::default()).unwrap();
+        assert_eq!(auto.kind, "eip1559");
+        assert_eq!(auto.gas_limit, 120_000);
+        assert_eq!(auto.max_fee_per_gas, 4_000_000_000);
+        assert_eq!(auto.expected_cost_wei, 120_000 * 3_000_000_000);
+        assert_eq!(auto.max_cost_wei, 120_000 * 4_000_000_000);
+
+        let opts = TxOptions {
+            max_fee_per_gas: Some(1_500_000_000),
+            priority_fee_per_gas: Some(100_000_000),
+            gas_limit: Some(50_000),
+            fee_cap: Some(50_000 * 1_000_000_000),
+            dry_run: false,
+            relayer: None,
+        };
+        let manual = estimate_contract_tx(&conf, &key, &to, &[], &opts).unwrap();
+        assert_eq!((manual.gas_limit, manual.max_fee_per_gas), (50_000, 1_500_000_000));
+        assert_eq!(manual.expected_cost_wei, 50_000 * 1_100_000_000);
+        assert!(manual.exceeds_cap(&opts));
+        let refused = send_contract_tx(&conf, &key, &to, &[], &opts).unwrap_err();
+        assert!(refused.to_string().contains("--fee-cap"), "{}", refused);
+
+        let tip_too_high = TxOptions { max_fee_per_gas: Some(2_000_000_000), priority_fee_per_gas: Some(3_000_000_000), ..Default::default() };
+        assert!(estimate_contract_tx(&conf, &key, &to, &[], &tip_too_high).is_err());
+        let below_base = TxOptions { max_fee_per_gas: Some(500_000_000), priority_fee_per_gas: Some(0), ..Default::default() };
+        assert!(estimate_contract_tx(&conf, &key, &to, &[], &below_base).unwrap_err().to_string().contains("base fee"));
+    }
+
+    #[test]
+    fn portable_tx_is_built_without_a_key_and_signed_offline() {
+        let node = mock_rpc(|m| match m {
+            "eth_chainId" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x7a69"}"#.into()),
+            "eth_estimateGas" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x186a0"}"#.into()),
+            "eth_getBlockByNumber" => (200, r#"{"jsonrpc":"2.0","id":1,"result":{"baseFeePerGas":"0x3b9aca00"}}"#.into()),
+            "eth_maxPriorityFeePerGas" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x77359400"}"#.into()),
+            _ => (200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into()),
+        });
+        let conf = conf_for(&[&node]);
+        let key = [0x11u8; 32];
+        let from = address_bytes_from_secret(&key).unwrap();
+        let data = encode_bytes32_call("revokeSecret(bytes32)", &[0xabu8; 32]);
+
+        let unsigned = build_tx(&conf, &from, Some(&[0x22u8; 20]), &data, &TxOptions::default(), Some(7)).unwrap();
+        assert_eq!((unsigned.nonce, unsigned.gas_limit, unsigned.max_fee_per_gas), (7, 120_000, 4_000_000_000));
+        assert_eq!(unsigned.description, "revokeSecret");
+        assert!(unsigned.raw.is_none());
+
+        // The file round-trips, and only the wallet it names can sign it.
+        let unsigned = PortableTx::from_json(&serde_json::to_string(&unsigned).unwrap()).unwrap();
+        assert!(unsigned.sign(&[0x12u8; 32]).unwrap_err().to_string().contains("this wallet is"));
+        let signed = unsigned.sign(&key).unwrap();
+        let raw = signed.raw.clone().unwrap();
+        let expected = sign_eip1559_tx(&key, 31337, 7, 2_000_000_000, 4_000_000_000, 120_000, &[0x22u8; 20], 0, &data).unwrap();
+        assert_eq!(raw, expected);
+        assert_eq!(signed.hash, Some(format!("0x{}", bytes_to_hex(&keccak256(&hex_to_bytes(&raw).unwrap())))));
+        assert!(signed.sign(&key).unwrap_err().to_string().contains("already signed"));
+
+        let mut other_format = serde_json::to_value(&signed).unwrap();
+        other_format["format"] = json!("bsec-tx/9");
+        assert!(PortableTx::from_json(&other_format.to_string()).unwrap_err().to_string().contains("unsupported"));
+
+        // A summary that disagrees with the calldata or gas fields is refused, not shown.
+        let mut relabeled = serde_json::to_value(&unsigned).unwrap();
+        relabeled["description"] = json!("revokeSecret (harmless)");
+        let err = PortableTx::from_json(&relabeled.to_string()).unwrap_err().to_string();
+        assert!(err.contains("the call") && err.contains("modified"), "{}", err);
+        let mut cheaper = serde_json::to_value(&unsigned).unwrap();
+        cheaper["max_cost_wei"] = json!(1);
+        assert!(PortableTx::from_json(&cheaper.to_string()).unwrap_err().to_string().contains("the max cost"));
+        let costlier = PortableTx { max_fee_per_gas: 9_000_000_000, ..unsigned.clone() };
+        assert!(costlier.sign(&key).unwrap_err().to_string().contains("the max cost"));
+
+        // Broadcast refuses unsigned, wrong-chain and tampered files before touching the journal.
+        assert!(broadcast_signed(&conf, &unsigned).unwrap_err().to_string().contains("not signed"));
+        let other_chain = PortableTx { chain_id: 1, ..signed.clone() };
+        assert!(broadcast_signed(&conf, &other_chain).unwrap_err().to_string().contains("chain 1"));
+        let tampered = PortableTx { hash: Some(format!("0x{}", "00".repeat(32))), ..signed };
+        assert!(broadcast_signed(&conf, &tampered).unwrap_err().to_string().contains("modified"));
+    }
+
+    fn journaled(nonce: u64, max_fee: u128, priority: Option<u128>) -> TxRecord {
+        TxRecord {
+            hash: "0xabc123".to_string(),
+            chain_id: 31337,
+            from: "0x1111111111111111111111111111111111111111".to_string(),
+            to: "0x2222222222222222222222222222222222222222".to_string(),
+            nonce,
+            data: "0x".to_string(),
+            kind: if priority.is_some() { "eip1559" } else { "legacy" }.to_string(),
+            gas_limit: 100_000,
+            max_fee_per_gas: max_fee,
+            priority_fee_per_gas: priority,
+            raw: "0x".to_string(),
+            submitted_at: 0,
+            status: TxStatus::Pending,
+            replaces: None,
+            replaced_by: None,
+        }
+    }
+
+    #[test]
+    fn replacement_fees_clear_the_bump_and_track_the_market() {
+        // Base fee 1 gwei, node tip 2 gwei.
+        let node = mock_rpc(|m| match m {
+            "eth_chainId" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x7a69"}"#.into()),
+            "eth_getBlockByNumber" => (200, r#"{"jsonrpc":"2.0","id":1,"result":{"baseFeePerGas":"0x3b9aca00"}}"#.into()),
+            "eth_maxPriorityFeePerGas" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x77359400"}"#.into()),
+            _ => (200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into()),
+        });
+        let conf = conf_for(&[&node]);
+
+        // Original far above market: the bump dominates.
+        let high = journaled(3, 40_000_000_000, Some(8_000_000_000));
+        let est = replacement_estimate(&conf, &high, 100_000, &TxOptions::default()).unwrap();
+        assert_eq!(est.max_fee_per_gas, 45_000_000_000);
+        assert_eq!(est.priority_fee_per_gas, Some(9_000_000_000));
+
+        // Original below market: the fresh estimate (2 x base + tip) dominates.
+        let low = journaled(3, 1_000_000_000, Some(100_000_000));
+        let est = replacement_estimate(&conf, &low, 21_000, &TxOptions::default()).unwrap();
+        assert_eq!(est.priority_fee_per_gas, Some(2_000_000_000));
+        assert_eq!(est.max_fee_per_gas, 4_000_000_000);
+        assert_eq!(est.max_cost_wei, 21_000 * 4_000_000_000);
+
+        let too_low = TxOptions { max_fee_per_gas: Some(41_000_000_000), ..Default::default() };
+        let err = replacement_estimate(&conf, &high, 100_000, &too_low).unwrap_err();
+        assert!(err.to_string().contains("minimum replacement fee"), "{}", err);
+    }
+
+    #[test]
+    fn probe_status_tells_mined_replaced_dropped_and_pending_apart() {
+        // Latest nonce 5; only 0xabc123 has a receipt.
+        let node = mock_rpc(|m| match m {
+            "eth_chainId" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x7a69"}"#.into()),
+            "eth_getTransactionCount" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x5"}"#.into()),
+            _ => (200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into()),
+        });
+        let conf = conf_for(&[&node]);
+        let mut rec = journaled(4, 1, Some(1));
+        assert_eq!(probe_status(&conf, &rec).unwrap(), TxStatus::Dropped);
+        rec.replaced_by = Some("0xdef".to_string());
+        assert_eq!(probe_status(&conf, &rec).unwrap(), TxStatus::Replaced);
+        assert_eq!(probe_status(&conf, &journaled(5, 1, Some(1))).unwrap(), TxStatus::Pending);
+
+        let mined = mock_rpc(|m| match m {
+            "eth_chainId" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x7a69"}"#.into()),
+            "eth_getTransactionReceipt" => (200, r#"{"jsonrpc":"2.0","id":1,"result":{"status":"0x0"}}"#.into()),
+            _ => (200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into()),
+        });
+        assert_eq!(probe_status(&conf_for(&[&mined]), &rec).unwrap(), TxStatus::Reverted);
+    }
+
+    #[test]
+    fn calldata_is_named_by_selector() {
+        let id = [0u8; 32];
+        let revoke = format!("0x{}", bytes_to_hex(&encode_bytes32_call("revokeSecret(bytes32)", &id)));
+        assert_eq!(describe_calldata(&revoke), "revokeSecret");
+        let share = format!("0x{}", bytes_to_hex(&encode_share_secret(&id, &[0u8; 20], "Qm", 0, 1, true)));
+        assert_eq!(describe_calldata(&share), "shareSecret");
+        assert_eq!(describe_calldata("0x"), "transfer");
+        assert_eq!(describe_calldata("0xdeadbeef00"), "0xdeadbeef");
+    }
+
+    #[test]
+    fn eip712_matches_the_spec_example_and_signatures_recover() {
+        // The "Ether Mail" example from EIP-712, signed with keccak256("cow").
+        let contract = parse_address("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC").unwrap();
+        let domain = eip712_domain_separator("Ether Mail", "1", 1, &contract);
+        assert_eq!(bytes_to_hex(&domain), "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f");
+        let mail: [u8; 32] = hex_to_bytes("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e").unwrap().try_into().unwrap();
+        let digest = eip712_digest(&domain, &mail);
+        assert_eq!(bytes_to_hex(&digest), "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2");
+
+        let key = keccak256(b"cow");
+        let sig = sign_digest(&key, &digest).unwrap();
+        assert_eq!(
+            bytes_to_hex(&sig),
+            "4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d\
+             07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562\
+             1c"
+        );
+        let signer = address_bytes_from_secret(&key).unwrap();
+        assert_eq!(addr_hex(&signer), "0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826");
+        assert_eq!(recover_signer(&digest, &sig).unwrap(), signer);
+
+        let mut other = digest;
+        other[0] ^= 1;
+        assert_ne!(recover_signer(&other, &sig).unwrap(), signer);
+        assert!(recover_signer(&digest, &sig[..64]).is_err());
+    }
+
+    #[test]
+    fn multicall_packs_each_call_as_a_bytes_element() {
+        let a = encode_bytes32_call("revokeSecret(bytes32)", &[0x01; 32]); // 36 bytes -> 2 words padded
+        let b = vec![0xaa, 0xbb];
+        let data = encode_multicall(&[a.clone(), b.clone()]);
+        assert_eq!(describe_calldata(&format!("0x{}", bytes_to_hex(&data))), "multicall");
+        let word = |i: usize| u128::from_be_bytes(data[4 + 32 * i + 16..4 + 32 * (i + 1)].try_into().unwrap());
+        assert_eq!((word(0), word(1)), (32, 2)); // array offset, length
+        assert_eq!((word(2), word(3)), (64, 64 + 32 + 64)); // element offsets after the length word
+        assert_eq!(word(4), a.len() as u128);
+        assert_eq!(&data[4 + 32 * 5..4 + 32 * 5 + a.len()], a.as_slice());
+        assert_eq!(word(7), 2);
+        assert_eq!(&data[4 + 32 * 8..4 + 32 * 8 + 2], b.as_slice());
+        assert_eq!(data.len(), 4 + 32 * 9);
+        assert_eq!(encode_multicall(&[]).len(), 4 + 64);
+    }
+
+    fn error_data(signature: &str, words: &[[u8; 32]]) -> Vec<u8> {
+        let mut out = selector(signature).to_vec();
+        for w in words {
+            out.extend_from_slice(w);
+        }
+        out
+    }
+
+    #[test]
+    fn registry_custom_errors_decode_with_arguments() {
+        let mut id = [0u8; 32];
+        id[..4].copy_from_slice(b"prod");
+        let read_limit = error_data("ReadLimitExceeded(bytes32,uint32,uint32)", &[id, word_u128(3), word_u128(3)]);
+        let err = decode_revert(&read_limit).unwrap();
+        assert_eq!(err.to_string(), "Secret 'prod': read limit reached (3/3).");
+        assert_eq!(err.exit_code(), 3);
+
+        let hashed = [0xabu8; 32];
+        let expired = error_data("SecretExpired(bytes32,uint64,uint64)", &[hashed, word_u128(100), word_u128(250)]);
+        match decode_revert(&expired).unwrap() {
+            BsecError::SecretExpiredAt { id, expires_at, now } => {
+                assert_eq!(id, format!("0x{}", "ab".repeat(32)));
+                assert_eq!((expires_at, now), (100, 250));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let viewer = error_data("UnauthorizedViewer(bytes32,address)", &[id, word_addr(&[0x11u8; 20])]);
+        assert!(matches!(decode_revert(&viewer), Some(BsecError::UnauthorizedViewer { ref viewer, .. }) if viewer.ends_with("1111")));
+        assert!(matches!(decode_revert(&selector("InvalidParameters()")), Some(BsecError::InvalidParameters)));
+        assert!(matches!(decode_revert(&error_data("SecretIsRevoked(bytes32)", &[id])), Some(BsecError::SecretRevoked(ref s)) if s == "prod"));
+
+        // Error(string) "nope": offset, length, padded body.
+        let mut body = [0u8; 32];
+        body[..4].copy_from_slice(b"nope");
+        let reason = error_data("Error(string)", &[word_u128(32), word_u128(4), body]);
+        assert_eq!(decode_revert(&reason).unwrap().to_string(), "Transaction reverted: nope");
+        assert!(decode_revert(&[0xde, 0xad, 0xbe, 0xef]).unwrap().to_string().contains("unknown custom error 0xdeadbeef"));
+
+        // Truncated arguments or no selector: nothing to decode.
+        assert!(decode_revert(&read_limit[..40]).is_none());
+        // Offset or length words that would overflow are rejected, not wrapped.
+        let max = [0xffu8; 32];
+        assert!(decode_revert(&error_data("Error(string)", &[max, word_u128(4), body])).is_none());
+        assert!(decode_revert(&error_data("Error(string)", &[word_u128(32), max, body])).is_none());
+        assert!(decode_revert(&[0x01]).is_none());
+    }
+
+    #[test]
+    fn estimate_surfaces_decoded_revert_instead_of_generic_failure() {
+        let node = mock_rpc(|m| match m {
+            "eth_chainId" => (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x7a69"}"#.into()),
+            "eth_estimateGas" => {
+                let data = error_data("SecretAlreadyExists(bytes32)", &[[0x42u8; 32]]);
+                (
+                    200,
+                    format!(
+                        r#"{{"jsonrpc":"2.0","id":1,"error":{{"code":3,"message":"execution reverted","data":"0x{}"}}}}"#,
+                        bytes_to_hex(&data)
+                    ),
+                )
+            }
+            _ => (200, r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into()),
+        });
+        let err = estimate_contract_tx(&conf_for(&[&node]), &[0x11u8; 32], &[0x22u8; 20], &[], &TxOptions::default()).unwrap_err();
+        assert!(matches!(err.downcast_ref::<BsecError>(), Some(BsecError::SecretAlreadyExists(_))), "{}", err);
+        assert!(!err.to_string().contains("gas estimation failed"));
+
+        // Revert data nested one level down, as some providers send it.
+        let nested = json!({ "code": -32000, "data": { "data": format!("0x{}", bytes_to_hex(&selector("InvalidParameters()"))) } });
+        assert_eq!(revert_data(&nested).unwrap(), selector("InvalidParameters()").to_vec());
+        assert!(revert_data(&json!({ "code": -32000, "message": "execution reverted" })).is_none());
+    }
 }
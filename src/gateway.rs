@@ -0,0 +1,293 @@
+//! IPFS gateway racing with retries and health tracking.
+//!
+//! `race` requests a CID from every configured gateway concurrently and returns the first
+//! response that parses as JSON (every bsec payload is JSON, so an HTML error page or a
+//! truncated body never wins). Transport errors, 429 and 5xx are retried with exponential
+//! backoff; other 4xx answers are final for that gateway.
+//!
+//! Outcomes are recorded in `~/.bsec/gateway_health.json`. A gateway that failed
+//! `DEMOTE_AFTER_FAILURES` times in a row is demoted: it starts after the healthy ones have
+//! had a head start and gets a single attempt. Demotion lapses after a cooldown or on the
+//! gateway's next success.
+
+use anyhow::{anyhow, Result};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+const MAX_ATTEMPTS: u32 = 3;
+const BACKOFF_BASE_MS: u64 = 250;
+const DEMOTE_AFTER_FAILURES: u32 = 3;
+const DEMOTED_HEAD_START_MS: u64 = 1500;
+const DEMOTION_COOLDOWN_SECS: u64 = 600;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GatewayHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    /// Unix time of the most recent failure (0 = never).
+    pub last_failure: u64,
+    /// Latency of the most recent successful fetch.
+    pub last_latency_ms: u64,
+}
+
+impl GatewayHealth {
+    pub fn is_demoted(&self, now: u64) -> bool {
+        self.consecutive_failures >= DEMOTE_AFTER_FAILURES
+            && now.saturating_sub(self.last_failure) < DEMOTION_COOLDOWN_SECS
+    }
+
+    fn record(&mut self, ok: bool, latency_ms: u64, now: u64) {
+        if ok {
+            self.successes += 1;
+            self.consecutive_failures = 0;
+            self.last_latency_ms = latency_ms;
+        } else {
+            self.failures += 1;
+            self.consecutive_failures += 1;
+            self.last_failure = now;
+        }
+    }
+}
+
+/// Gateway base URL -> health counters.
+pub type HealthTable = BTreeMap<String, GatewayHealth>;
+
+fn health_file() -> PathBuf {
+    crate::wallet::get_app_dir().join("gateway_health.json")
+}
+
+pub fn load_health() -> HealthTable {
+    std::fs::read_to_string(health_file())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_health(table: &HealthTable) {
+    if let Ok(json) = serde_json::to_string_pretty(table) {
+        let _ = crate::wallet::write_secure_file(&health_file(), json.as_bytes());
+    }
+}
+
+/// When and how hard to try one gateway.
+#[derive(Debug, Clone, PartialEq)]
+struct Plan {
+    base: String,
+    delay: Duration,
+    attempts: u32,
+}
+
+/// Healthy gateways first in configured order, then demoted ones with a late start.
+fn plan(gateways: &[String], health: &HealthTable, now: u64) -> Vec<Plan> {
+    let demoted = |g: &String| health.get(g).is_some_and(|h| h.is_demoted(now));
+    let healthy = gateways.iter().filter(|g| !demoted(g)).map(|g| Plan {
+        base: g.clone(),
+        delay: Duration::ZERO,
+        attempts: MAX_ATTEMPTS,
+    });
+    let late = gateways.iter().filter(|g| demoted(g)).map(|g| Plan {
+        base: g.clone(),
+        delay: Duration::from_millis(DEMOTED_HEAD_START_MS),
+        attempts: 1,
+    });
+    healthy.chain(late).collect()
+}
+
+/// Delay before retry number `retry` (0-based): 250 ms, 500 ms, 1 s, ...
+fn backoff(retry: u32) -> Duration {
+    Duration::from_millis(BACKOFF_BASE_MS.saturating_mul(1u64 << retry.min(16)))
+}
+
+fn is_valid_payload(body: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(body).is_ok()
+}
+
+fn fetch_with_retries(client: &Client, url: &str, attempts: u32) -> Result<String, String> {
+    let mut last_err = String::from("no attempt made");
+    for attempt in 0..attempts {
+        if attempt > 0 {
+            std::thread::sleep(backoff(attempt - 1));
+        }
+        match client.get(url).send() {
+            Ok(res) if res.status().is_success() => {
+                return match res.text() {
+                    Ok(body) if is_valid_payload(&body) => Ok(body),
+                    Ok(_) => Err("response is not a bsec payload".to_string()),
+                    Err(e) => Err(format!("failed to read body: {}", e)),
+                };
+            }
+            Ok(res) => {
+                let status = res.status();
+                last_err = format!("HTTP {}", status);
+                if !(status.is_server_error() || status.as_u16() == 429) {
+                    break;
+                }
+            }
+            Err(e) => last_err = e.to_string(),
+        }
+    }
+    Err(last_err)
+}
+
+/// Outcome of one gateway in a race: base URL, success, latency in ms, error text.
+type Outcome = (String, bool, u64, String);
+
+/// Race `plans` for `cid` until one returns a valid payload or `deadline` passes. Returns the
+/// winning body (if any) and every outcome observed up to that point.
+fn race_plans(client: &Client, plans: Vec<Plan>, cid: &str, deadline: Duration) -> (Option<String>, Vec<Outcome>) {
+    let (tx, rx) = mpsc::channel();
+    let started = Instant::now();
+    for p in plans {
+        let tx = tx.clone();
+        let client = client.clone();
+        let url = format!("{}{}", p.base, cid);
+        std::thread::spawn(move || {
+            std::thread::sleep(p.delay);
+            let t0 = Instant::now();
+            let res = fetch_with_retries(&client, &url, p.attempts);
+            let _ = tx.send((p.base, res, t0.elapsed().as_millis() as u64));
+        });
+    }
+    drop(tx);
+
+    let mut outcomes = Vec::new();
+    loop {
+        let remaining = deadline.saturating_sub(started.elapsed());
+        match rx.recv_timeout(remaining) {
+            Ok((base, Ok(body), ms)) => {
+                outcomes.push((base, true, ms, String::new()));
+                return (Some(body), outcomes);
+            }
+            Ok((base, Err(e), ms)) => outcomes.push((base, false, ms, e)),
+            Err(_) => return (None, outcomes),
+        }
+    }
+}
+
+/// Fetch `cid` from the fastest healthy gateway, updating the health table with every
+/// outcome observed before the winner arrived.
+pub fn race(client: &Client, gateways: &[String], cid: &str, deadline: Duration) -> Result<String> {
+    if gateways.is_empty() {
+        return Err(anyhow!("no IPFS gateways configured"));
+    }
+    let now = crate::wallet::current_timestamp();
+    let mut health = load_health();
+    let (winner, outcomes) = race_plans(client, plan(gateways, &health, now), cid, deadline);
+
+    for (base, ok, ms, _) in &outcomes {
+        health.entry(base.clone()).or_default().record(*ok, *ms, now);
+    }
+    save_health(&health);
+
+    winner.ok_or_else(|| {
+        let detail: Vec<String> = outcomes.iter().map(|(b, _, _, e)| format!("{}: {}", b, e)).collect();
+        if detail.is_empty() {
+            anyhow!("all gateways timed out")
+        } else {
+            anyhow!("{}", detail.join("; "))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve canned HTTP responses on a local port, one per connection, in order; the last
+    /// response repeats. `delay` is applied before each reply.
+    fn serve(responses: Vec<(u16, &'static str)>, delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/ipfs/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else { break };
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                std::thread::sleep(delay);
+                let (status, body) = responses[i.min(responses.len() - 1)];
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        base
+    }
+
+    fn client() -> Client {
+        Client::builder().timeout(Duration::from_secs(5)).build().unwrap()
+    }
+
+    fn full(base: &str) -> Plan {
+        Plan { base: base.to_string(), delay: Duration::ZERO, attempts: MAX_ATTEMPTS }
+    }
+
+    #[test]
+    fn fastest_valid_gateway_wins() {
+        let slow = serve(vec![(200, r#"{"from":"slow"}"#)], Duration::from_millis(1500));
+        let fast = serve(vec![(200, r#"{"from":"fast"}"#)], Duration::ZERO);
+        let started = Instant::now();
+        let (body, outcomes) = race_plans(&client(), vec![full(&slow), full(&fast)], "QmX", Duration::from_secs(5));
+        assert_eq!(body.as_deref(), Some(r#"{"from":"fast"}"#));
+        assert!(started.elapsed() < Duration::from_millis(1400));
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].0, fast);
+    }
+
+    #[test]
+    fn invalid_body_does_not_win() {
+        let html = serve(vec![(200, "<html>gateway error</html>")], Duration::ZERO);
+        let good = serve(vec![(200, "{}")], Duration::from_millis(200));
+        let (body, outcomes) = race_plans(&client(), vec![full(&html), full(&good)], "QmX", Duration::from_secs(5));
+        assert_eq!(body.as_deref(), Some("{}"));
+        assert!(outcomes.iter().any(|(b, ok, _, _)| b == &html && !ok));
+    }
+
+    #[test]
+    fn server_errors_are_retried_but_not_found_is_final() {
+        let flaky = serve(vec![(503, ""), (200, "{}")], Duration::ZERO);
+        assert_eq!(fetch_with_retries(&client(), &format!("{}QmX", flaky), 3).unwrap(), "{}");
+
+        let missing = serve(vec![(404, ""), (200, "{}")], Duration::ZERO);
+        assert_eq!(fetch_with_retries(&client(), &format!("{}QmX", missing), 3).unwrap_err(), "HTTP 404 Not Found");
+    }
+
+    #[test]
+    fn repeated_failures_demote_until_success_or_cooldown() {
+        let mut h = GatewayHealth::default();
+        for _ in 0..DEMOTE_AFTER_FAILURES {
+            assert!(!h.is_demoted(1000));
+            h.record(false, 0, 1000);
+        }
+        assert!(h.is_demoted(1000));
+        assert!(!h.is_demoted(1000 + DEMOTION_COOLDOWN_SECS));
+
+        let gateways = vec!["https://bad/".to_string(), "https://good/".to_string()];
+        let table: HealthTable = [("https://bad/".to_string(), h.clone())].into_iter().collect();
+        let p = plan(&gateways, &table, 1000);
+        assert_eq!(p[0].base, "https://good/");
+        assert_eq!((p[1].base.as_str(), p[1].attempts), ("https://bad/", 1));
+        assert!(p[1].delay > Duration::ZERO);
+
+        h.record(true, 42, 1001);
+        assert!(!h.is_demoted(1001));
+        assert_eq!(h.last_latency_ms, 42);
+    }
+
+    #[test]
+    fn backoff_doubles() {
+        assert_eq!(backoff(0), Duration::from_millis(250));
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_millis(1000));
+    }
+}
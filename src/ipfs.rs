@@ -1,22 +1,43 @@
 //! Real IPFS storage.
 //!
-//! Upload: Pinata `pinFileToIPFS` when a JWT is configured, otherwise a Kubo RPC daemon
-//! (`/api/v0/add`). Fetch: local cache, then Kubo `cat`, then the configured and public
-//! gateways. No mock CIDs, no fabricated success — an unreachable backend returns an error.
+//! Upload: the hosted pinning providers selected in `ipfs.pinning_service` (see `pinning`),
+//! falling back to a Kubo RPC daemon (`/api/v0/add`). Fetch: local cache, then Kubo `cat`,
+//! then a concurrent race across the configured gateway list (see `gateway`). No fabricated
+//! success — an unreachable backend returns an error. These go through a `PayloadStore`: on
+//! the `mock` and `team` networks the registry's own directory (see `mock_chain`,
+//! `team_registry`) replaces providers, Kubo and gateways.
+//! Unpin: revocation and `bsec gc` remove the provider pins, the Kubo pin (`pin/rm`) and the
+//! local cache entry for a CID; each step is best-effort and reported individually.
+//!
+//! Cache: `~/.bsec/ipfs_cache` is size-capped (`ipfs.cache_max_bytes`) with least-recently-used
+//! eviction by mtime, which a cache hit refreshes. With `ipfs.encrypt_cache` on, entries are
+//! sealed with AES-256-GCM under an HKDF key derived from the wallet private key, with the CID
+//! as associated data; an entry that fails to open is treated as a miss.
 //!
 //! Integrity: payloads are AES-256-GCM encrypted, so a tampered or substituted blob fails
 //! AEAD authentication (or JSON parsing) downstream rather than silently compromising the
 //! secret. Reproducing IPFS dag-pb chunking to recompute the CID is therefore unnecessary.
 
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
 use anyhow::{anyhow, Result};
+use base64::prelude::*;
 use reqwest::blocking::Client;
+use serde::Serialize;
 use std::fs;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use zeroize::Zeroizing;
 
 use crate::network_config::NetworkConfig;
 
 const IPFS_TIMEOUT_SECS: u64 = 15;
+/// Overall budget for the gateway race, retries included.
+const GATEWAY_RACE_SECS: u64 = 20;
+const CACHE_SEAL_PREFIX: &str = "bsec-cache-v1:";
+
+/// Key for sealing cache entries at rest. See `cache_key_from_wallet`.
+pub type CacheKey = Zeroizing<[u8; 32]>;
 
 pub fn get_ipfs_cache_dir() -> PathBuf {
     let dir = crate::wallet::get_app_dir().join("ipfs_cache");
@@ -54,37 +75,183 @@ fn valid_cid(cid: &str) -> bool {
 /// bypassed. Both write (upload) and read (fetch) MUST use this same
 /// derivation so the same CID maps to the same file.
 fn cache_file_for(cid: &str) -> PathBuf {
-    get_ipfs_cache_dir().join(format!(
-        "{}.json",
-        crate::wallet::bytes_to_hex(&crate::wallet::hash_digest(cid.as_bytes()))
+    cache_file_in(&get_ipfs_cache_dir(), cid)
+}
+
+fn cache_file_in(dir: &Path, cid: &str) -> PathBuf {
+    dir.join(format!("{}.json", crate::wallet::bytes_to_hex(&crate::wallet::hash_digest(cid.as_bytes()))))
+}
+
+/// Derive the cache key from the wallet private key, or `None` when `ipfs.encrypt_cache` is off.
+pub fn cache_key_from_wallet(private_key_hex: &str) -> Result<Option<CacheKey>> {
+    if !crate::network_config::load_network_config().ipfs.encrypt_cache {
+        return Ok(None);
+    }
+    let priv_bytes = Zeroizing::new(crate::wallet::hex_to_bytes(private_key_hex)?);
+    let hk = hkdf::Hkdf::<sha2::Sha256>::new(None, &priv_bytes);
+    let mut key = Zeroizing::new([0u8; 32]);
+    hk.expand(b"bsec-ipfs-cache-v1", key.as_mut())
+        .map_err(|_| anyhow!("HKDF expand failed"))?;
+    Ok(Some(key))
+}
+
+fn seal_cache_entry(cid: &str, text: &str, key: &CacheKey) -> Result<String> {
+    let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!("{}", e))?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ct = cipher
+        .encrypt(&nonce, Payload { msg: text.as_bytes(), aad: cid.as_bytes() })
+        .map_err(|_| anyhow!("cache encryption failed"))?;
+    Ok(format!(
+        "{}{}:{}",
+        CACHE_SEAL_PREFIX,
+        BASE64_STANDARD.encode(nonce),
+        BASE64_STANDARD.encode(ct)
     ))
 }
 
-fn cache_payload(cid: &str, payload_json: &str) {
-    let cache_file = cache_file_for(cid);
-    let _ = crate::wallet::write_secure_file(&cache_file, payload_json.as_bytes());
+fn open_cache_entry(cid: &str, stored: &str, key: Option<&CacheKey>) -> Option<String> {
+    let Some(sealed) = stored.strip_prefix(CACHE_SEAL_PREFIX) else {
+        return Some(stored.to_string());
+    };
+    let (nonce_b64, ct_b64) = sealed.split_once(':')?;
+    let nonce = BASE64_STANDARD.decode(nonce_b64).ok()?;
+    let ct = BASE64_STANDARD.decode(ct_b64).ok()?;
+    if nonce.len() != 12 {
+        return None;
+    }
+    let cipher = Aes256Gcm::new_from_slice(key?.as_ref()).ok()?;
+    let pt = cipher
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ct, aad: cid.as_bytes() })
+        .ok()?;
+    String::from_utf8(pt).ok()
 }
 
-fn pin_via_pinata(client: &Client, jwt: &str, payload_json: &str) -> Result<String> {
-    let form = reqwest::blocking::multipart::Form::new()
-        .text("file", payload_json.to_string());
-    let res = client
-        .post("https://api.pinata.cloud/pinning/pinFileToIPFS")
-        .bearer_auth(jwt)
-        .multipart(form)
-        .send()
-        .map_err(|e| anyhow!("Pinata request failed: {}", e))?;
-    if !res.status().is_success() {
-        let status = res.status();
-        let body = res.text().unwrap_or_default();
-        return Err(anyhow!("Pinata pinning failed ({}): {}", status, body));
+fn cache_payload(cid: &str, payload_json: &str, key: Option<&CacheKey>) {
+    let body = match key {
+        Some(k) => match seal_cache_entry(cid, payload_json, k) {
+            Ok(sealed) => sealed,
+            Err(_) => return,
+        },
+        None => payload_json.to_string(),
+    };
+    let _ = crate::wallet::write_secure_file(&cache_file_for(cid), body.as_bytes());
+    let max = crate::network_config::load_network_config().ipfs.cache_max_bytes;
+    let _ = enforce_cache_limit_in(&get_ipfs_cache_dir(), max);
+}
+
+/// Read a cache entry, refreshing its mtime for LRU. A plaintext entry found while a key is
+/// configured is re-sealed in place.
+fn read_cached(cid: &str, key: Option<&CacheKey>) -> Option<String> {
+    let path = cache_file_for(cid);
+    let stored = fs::read_to_string(&path).ok()?;
+    let text = open_cache_entry(cid, &stored, key)?;
+    if key.is_some() && !stored.starts_with(CACHE_SEAL_PREFIX) {
+        cache_payload(cid, &text, key);
+    } else if let Ok(f) = fs::File::options().write(true).open(&path) {
+        let _ = f.set_modified(SystemTime::now());
     }
-    let json: serde_json::Value = res.json().map_err(|e| anyhow!("Pinata response decode error: {}", e))?;
-    let cid = json
-        .get("IpfsHash")
-        .and_then(|h| h.as_str())
-        .ok_or_else(|| anyhow!("Pinata response missing IpfsHash"))?;
-    Ok(cid.to_string())
+    Some(text)
+}
+
+/// Snapshot of the local IPFS cache for `bsec cache stats`.
+#[derive(Serialize, Debug, Clone)]
+pub struct CacheStats {
+    pub dir: PathBuf,
+    pub entries: usize,
+    pub encrypted_entries: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub encrypt_cache: bool,
+}
+
+struct CacheFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+fn cache_files_in(dir: &Path) -> Vec<CacheFile> {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    rd.flatten()
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            if !meta.is_file() {
+                return None;
+            }
+            Some(CacheFile {
+                path: e.path(),
+                size: meta.len(),
+                modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            })
+        })
+        .collect()
+}
+
+pub fn cache_stats() -> CacheStats {
+    let conf = crate::network_config::load_network_config();
+    let dir = get_ipfs_cache_dir();
+    let files = cache_files_in(&dir);
+    let encrypted_entries = files
+        .iter()
+        .filter(|f| {
+            let mut buf = [0u8; CACHE_SEAL_PREFIX.len()];
+            fs::File::open(&f.path)
+                .and_then(|mut h| std::io::Read::read_exact(&mut h, &mut buf))
+                .is_ok()
+                && buf == CACHE_SEAL_PREFIX.as_bytes()
+        })
+        .count();
+    CacheStats {
+        entries: files.len(),
+        encrypted_entries,
+        total_bytes: files.iter().map(|f| f.size).sum(),
+        max_bytes: conf.ipfs.cache_max_bytes,
+        encrypt_cache: conf.ipfs.encrypt_cache,
+        dir,
+    }
+}
+
+/// Evict least-recently-used entries until the directory fits in `max_bytes` (0 = no cap).
+/// Returns `(entries_removed, bytes_freed)`.
+fn enforce_cache_limit_in(dir: &Path, max_bytes: u64) -> (usize, u64) {
+    if max_bytes == 0 {
+        return (0, 0);
+    }
+    let mut files = cache_files_in(dir);
+    let mut total: u64 = files.iter().map(|f| f.size).sum();
+    files.sort_by_key(|f| f.modified);
+    let mut removed = (0, 0);
+    for f in files {
+        if total <= max_bytes {
+            break;
+        }
+        if fs::remove_file(&f.path).is_ok() {
+            total -= f.size;
+            removed.0 += 1;
+            removed.1 += f.size;
+        }
+    }
+    removed
+}
+
+/// Apply the configured size cap to the cache now.
+pub fn enforce_cache_limit() -> (usize, u64) {
+    let max = crate::network_config::load_network_config().ipfs.cache_max_bytes;
+    enforce_cache_limit_in(&get_ipfs_cache_dir(), max)
+}
+
+/// Delete every cache entry. Returns `(entries_removed, bytes_freed)`.
+pub fn clear_cache() -> (usize, u64) {
+    let mut removed = (0, 0);
+    for f in cache_files_in(&get_ipfs_cache_dir()) {
+        if fs::remove_file(&f.path).is_ok() {
+            removed.0 += 1;
+            removed.1 += f.size;
+        }
+    }
+    removed
 }
 
 fn add_via_kubo(client: &Client, api_url: &str, payload_json: &str) -> Result<String> {
@@ -107,41 +274,175 @@ fn add_via_kubo(client: &Client, api_url: &str, payload_json: &str) -> Result<St
     Ok(cid.to_string())
 }
 
-/// Upload a payload to IPFS. Returns a real CID. Errors if no backend is reachable.
-pub fn upload_to_ipfs(payload_json: &str) -> Result<String> {
-    let conf = crate::network_config::load_network_config();
-    let client = client()?;
-
-    // 1. Hosted pinning (Pinata) if a JWT is configured — persists across machines.
-    if let Some(jwt) = resolve_pinning_jwt(&conf) {
-        match pin_via_pinata(&client, &jwt, payload_json) {
-            Ok(cid) => {
-                if !valid_cid(&cid) {
-                    return Err(anyhow!("invalid IPFS CID {:?}", cid));
+/// Where payloads are stored for the configured network.
+pub trait PayloadStore {
+    /// Store a payload; returns its CID.
+    fn put(&self, payload: &str) -> Result<String>;
+    /// The payload of a CID that passed `valid_cid`.
+    fn get(&self, cid: &str) -> Result<String>;
+    /// Drop a CID that passed `valid_cid`, recording in `report` what held it. Best-effort:
+    /// failures are logged.
+    fn remove(&self, cid: &str, report: &mut UnpinReport);
+}
+
+/// IPFS through the pinning providers, a Kubo daemon and the gateways.
+struct IpfsStore {
+    conf: NetworkConfig,
+}
+
+impl PayloadStore for IpfsStore {
+    /// The CID comes from the first selected pinning provider that accepts content, else the
+    /// Kubo daemon; every other selected provider is then asked to pin it for redundancy.
+    fn put(&self, payload_json: &str) -> Result<String> {
+        let conf = &self.conf;
+        let client = client()?;
+        let providers = crate::pinning::resolve_providers(conf, resolve_pinning_jwt(conf));
+
+        // 1. Upload through the first provider that accepts content — persists across machines.
+        let mut uploaded = None;
+        for p in &providers {
+            match p.upload(&client, payload_json) {
+                Some(Ok(cid)) => {
+                    uploaded = Some((cid, p.name().to_string()));
+                    break;
                 }
-                cache_payload(&cid, payload_json);
-                return Ok(cid);
+                Some(Err(e)) => log::warn!("{} upload failed, trying next backend: {}", p.name(), e),
+                None => {}
             }
-            Err(e) => log::warn!("Pinata pinning failed, trying local IPFS daemon: {}", e),
         }
+
+        // 2. Local / self-hosted Kubo daemon.
+        let (cid, uploaded_by) = match uploaded {
+            Some((cid, name)) => (cid, Some(name)),
+            None => match add_via_kubo(&client, &conf.ipfs.api_url, payload_json) {
+                Ok(cid) => (cid, None),
+                Err(e) => {
+                    return Err(anyhow!(
+                        "No IPFS backend available. Pinning Service API providers can only pin an \
+                         existing CID, so uploading needs a provider that accepts content (a Pinata JWT \
+                         in ipfs.pinning_jwt or BSEC_PINATA_JWT) or a local IPFS daemon reachable at {}. \
+                         Last error: {}",
+                        conf.ipfs.api_url,
+                        e
+                    ));
+                }
+            },
+        };
+        if !valid_cid(&cid) {
+            return Err(anyhow!("invalid IPFS CID {:?}", cid));
+        }
+
+        // 3. Redundant pins on the remaining providers.
+        for p in providers.iter().filter(|p| Some(p.name()) != uploaded_by.as_deref()) {
+            if let Err(e) = p.pin(&client, &cid) {
+                log::warn!("{} did not pin {}: {}", p.name(), cid, e);
+            }
+        }
+        Ok(cid)
+    }
+
+    /// Kubo daemon, then the gateway race.
+    fn get(&self, cid: &str) -> Result<String> {
+        let client = client()?;
+
+        // 1. Kubo daemon cat.
+        if let Some(text) = cat_via_kubo(&client, &self.conf.ipfs.api_url, cid) {
+            return Ok(text);
+        }
+
+        // 2. Configured gateways, raced concurrently; first valid payload wins.
+        crate::gateway::race(&client, &self.conf.ipfs.effective_gateways(), cid, Duration::from_secs(GATEWAY_RACE_SECS))
+            .map_err(|e| anyhow!("Failed to fetch payload for IPFS CID '{}' from daemon or gateways ({}).", cid, e))
     }
 
-    // 2. Local / self-hosted Kubo daemon.
-    match add_via_kubo(&client, &conf.ipfs.api_url, payload_json) {
-        Ok(cid) => {
-            if !valid_cid(&cid) {
-                return Err(anyhow!("invalid IPFS CID {:?}", cid));
+    fn remove(&self, cid: &str, report: &mut UnpinReport) {
+        let client = match client() {
+            Ok(c) => c,
+            Err(e) => return log::warn!("IPFS unpin of {} not performed: {}", cid, e),
+        };
+        for p in crate::pinning::resolve_providers(&self.conf, resolve_pinning_jwt(&self.conf)) {
+            match p.unpin(&client, cid) {
+                Ok(true) => report.providers.push(p.name().to_string()),
+                Ok(false) => {}
+                Err(e) => log::warn!("{} unpin of {} not performed: {}", p.name(), cid, e),
             }
-            cache_payload(&cid, payload_json);
-            Ok(cid)
         }
-        Err(e) => Err(anyhow!(
-            "No IPFS backend available. Configure a Pinata JWT (ipfs.pinning_jwt or \
-             BSEC_PINATA_JWT) or run a local IPFS daemon reachable at {}. Last error: {}",
-            conf.ipfs.api_url,
-            e
-        )),
+        match unpin_via_kubo(&client, &self.conf.ipfs.api_url, cid) {
+            Ok(()) => report.kubo = true,
+            Err(e) => log::warn!("IPFS daemon unpin of {} not performed: {}", cid, e),
+        }
+    }
+}
+
+/// Store for the configured network: IPFS, or the registry's directory on `mock` and `team`.
+fn payload_store() -> Result<Box<dyn PayloadStore>> {
+    let conf = crate::network_config::load_network_config();
+    Ok(if conf.is_mock() {
+        Box::new(crate::mock_chain::MockStore::open(&conf)?)
+    } else if conf.is_team() {
+        Box::new(crate::team_registry::TeamStore::open(&conf)?)
+    } else {
+        Box::new(IpfsStore { conf })
+    })
+}
+
+/// Upload a payload to the configured store and cache it. Returns a real CID. Errors if no
+/// backend is reachable.
+pub fn upload_to_ipfs(payload_json: &str, cache_key: Option<&CacheKey>) -> Result<String> {
+    let cid = payload_store()?.put(payload_json)?;
+    cache_payload(&cid, payload_json, cache_key);
+    Ok(cid)
+}
+
+/// What `unpin_from_ipfs` removed for one CID. `false` means the backend was unconfigured,
+/// unreachable, or did not hold the CID — none of which blocks the other steps.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct UnpinReport {
+    pub cid: String,
+    /// Pinning providers that dropped a pin.
+    pub providers: Vec<String>,
+    pub kubo: bool,
+    pub cache: bool,
+    /// Why unpinning did not run at all; the other fields are then empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn unpin_via_kubo(client: &Client, api_url: &str, cid: &str) -> Result<()> {
+    let url = format!("{}/api/v0/pin/rm?arg={}", api_url.trim_end_matches('/'), cid);
+    let res = client
+        .post(&url)
+        .send()
+        .map_err(|e| anyhow!("IPFS daemon request to {} failed: {}", url, e))?;
+    if !res.status().is_success() {
+        // Kubo answers 500 with "not pinned or pinned indirectly" for a CID it does not hold.
+        let status = res.status();
+        let body = res.text().unwrap_or_default();
+        return Err(anyhow!("IPFS daemon pin/rm failed ({}): {}", status, body.trim()));
     }
+    Ok(())
+}
+
+/// Delete the local cache entry for a CID. Returns whether a file was removed.
+pub fn remove_cached(cid: &str) -> bool {
+    remove_cached_in(&get_ipfs_cache_dir(), cid)
+}
+
+fn remove_cached_in(dir: &Path, cid: &str) -> bool {
+    valid_cid(cid) && fs::remove_file(cache_file_in(dir, cid)).is_ok()
+}
+
+/// Unpin a CID from the configured store (pinning providers and Kubo daemon on IPFS) and drop
+/// its cache entry. Every step is attempted; failures are logged, not fatal, because the
+/// on-chain revocation that precedes this is what actually denies access.
+pub fn unpin_from_ipfs(cid: &str) -> Result<UnpinReport> {
+    if !valid_cid(cid) {
+        return Err(anyhow!("invalid IPFS CID {:?}", cid));
+    }
+    let mut report = UnpinReport { cid: cid.to_string(), ..Default::default() };
+    payload_store()?.remove(cid, &mut report);
+    report.cache = remove_cached(cid);
+    Ok(report)
 }
 
 fn cat_via_kubo(client: &Client, api_url: &str, cid: &str) -> Option<String> {
@@ -152,58 +453,22 @@ fn cat_via_kubo(client: &Client, api_url: &str, cid: &str) -> Option<String> {
     }
 }
 
-/// Fetch a payload by CID: local cache, then Kubo daemon, then gateways.
-pub fn fetch_from_ipfs(cid: &str) -> Result<String> {
+/// Fetch a payload by CID: local cache, then the configured store.
+pub fn fetch_from_ipfs(cid: &str, cache_key: Option<&CacheKey>) -> Result<String> {
     // Reject attacker-controlled, malformed CIDs before any filesystem or
     // network use.
     if !valid_cid(cid) {
         return Err(anyhow!("invalid IPFS CID {:?}", cid));
     }
 
-    let conf = crate::network_config::load_network_config();
-
-    // 1. Local cache. Path is hash-derived so it is always contained in the
-    //    cache dir regardless of the CID contents.
-    let cache_file = cache_file_for(cid);
-    if let Ok(cached) = fs::read_to_string(&cache_file) {
+    // Local cache. Path is hash-derived so it is always contained in the cache dir
+    // regardless of the CID contents.
+    if let Some(cached) = read_cached(cid, cache_key) {
         return Ok(cached);
     }
-
-    let client = client()?;
-
-    // 2. Kubo daemon cat.
-    if let Some(text) = cat_via_kubo(&client, &conf.ipfs.api_url, cid) {
-        let _ = crate::wallet::write_secure_file(&cache_file, text.as_bytes());
-        return Ok(text);
-    }
-
-    // 3. Configured + public gateways.
-    let configured = format!(
-        "{}{}",
-        conf.ipfs.gateway.trim_end_matches('/'),
-        "/",
-    );
-    let gateways = [
-        configured,
-        "https://ipfs.io/ipfs/".to_string(),
-        "https://dweb.link/ipfs/".to_string(),
-    ];
-
-    for gw in gateways {
-        let url = format!("{}{}", gw, cid);
-        if let Ok(res) = client.get(&url).send()
-            && res.status().is_success()
-            && let Ok(text) = res.text()
-        {
-            let _ = crate::wallet::write_secure_file(&cache_file, text.as_bytes());
-            return Ok(text);
-        }
-    }
-
-    Err(anyhow!(
-        "Failed to fetch payload for IPFS CID '{}' from daemon or gateways.",
-        cid
-    ))
+    let text = payload_store()?.get(cid)?;
+    cache_payload(cid, &text, cache_key);
+    Ok(text)
 }
 
 #[cfg(test)]
@@ -235,6 +500,18 @@ mod tests {
         assert!(!valid_cid(&"a".repeat(100)));
     }
 
+    #[test]
+    fn remove_cached_deletes_only_existing_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cid = "QmUnpinTest";
+        fs::write(cache_file_in(dir.path(), cid), "{}").unwrap();
+        assert!(remove_cached_in(dir.path(), cid));
+        assert!(!cache_file_in(dir.path(), cid).exists());
+        // A second removal (or a malformed CID) reports that nothing was deleted.
+        assert!(!remove_cached_in(dir.path(), cid));
+        assert!(!remove_cached_in(dir.path(), "../etc"));
+    }
+
     #[test]
     fn cache_file_for_stays_inside_cache_dir() {
         let malicious = "/etc/passwd";
@@ -245,4 +522,37 @@ mod tests {
             path
         );
     }
+
+    #[test]
+    fn sealed_cache_entry_is_bound_to_key_and_cid() {
+        let key: CacheKey = Zeroizing::new([7u8; 32]);
+        let sealed = seal_cache_entry("QmA", r#"{"content":"x"}"#, &key).unwrap();
+        assert!(sealed.starts_with(CACHE_SEAL_PREFIX));
+        assert!(!sealed.contains("content"));
+        assert_eq!(open_cache_entry("QmA", &sealed, Some(&key)).unwrap(), r#"{"content":"x"}"#);
+        // Wrong CID (entry swapped between files), wrong key or no key: treated as a miss.
+        assert!(open_cache_entry("QmB", &sealed, Some(&key)).is_none());
+        assert!(open_cache_entry("QmA", &sealed, Some(&Zeroizing::new([8u8; 32]))).is_none());
+        assert!(open_cache_entry("QmA", &sealed, None).is_none());
+        // Legacy plaintext entries still read.
+        assert_eq!(open_cache_entry("QmA", "{}", Some(&key)).unwrap(), "{}");
+    }
+
+    #[test]
+    fn cache_limit_evicts_least_recently_used_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = SystemTime::now() - Duration::from_secs(100);
+        for (i, name) in ["old", "mid", "new"].iter().enumerate() {
+            let path = dir.path().join(format!("{}.json", name));
+            fs::write(&path, [0u8; 100]).unwrap();
+            let f = fs::File::options().write(true).open(&path).unwrap();
+            f.set_modified(base + Duration::from_secs(10 * i as u64)).unwrap();
+        }
+        assert_eq!(enforce_cache_limit_in(dir.path(), 0), (0, 0));
+        assert_eq!(enforce_cache_limit_in(dir.path(), 250), (1, 100));
+        assert!(!dir.path().join("old.json").exists());
+        assert!(dir.path().join("mid.json").exists());
+        assert_eq!(enforce_cache_limit_in(dir.path(), 100), (1, 100));
+        assert!(dir.path().join("new.json").exists());
+    }
 }
@@ -0,0 +1,715 @@
+//! JSON Schema (draft 2020-12) validation for `bsec validate`, so a service that already
+//! describes its config as JSON Schema needs no second schema. The env map is the instance:
+//! `__` in a key nests (`DB__PORT` is `/DB/PORT`), a key matches a property of another case
+//! (`PORT` fills `port`), and values are coerced to the type the schema declares for them.
+//!
+//! Supported: boolean schemas, `type`, `enum`, `const`, numeric bounds and `multipleOf`,
+//! string lengths, `pattern` and `format` (`email`, `uri`, `hostname`, `ipv4`, `ipv6`,
+//! `uuid`, `date`), object and array keywords, `allOf`/`anyOf`/`oneOf`/`not`,
+//! `if`/`then`/`else`, and local `$ref`s (`#`, `#/$defs/...`). `writeOnly` values are never
+//! printed. Other keywords are annotations and ignored.
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::{Map, Number, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// How deep `$ref`s and subschemas may nest before a schema is taken to loop.
+const MAX_DEPTH: usize = 64;
+
+pub struct JsonSchema {
+    root: Value,
+    patterns: HashMap<String, Regex>,
+}
+
+/// An env key and its value, on its way into an instance.
+struct Leaf<'a> {
+    key: &'a String,
+    value: &'a str,
+}
+
+/// A failed keyword: the JSON pointer of the value in the instance, and why.
+pub struct Violation {
+    pub pointer: String,
+    pub message: String,
+}
+
+impl JsonSchema {
+    /// `content` as a JSON Schema, if it is one: a JSON object with `$schema`, or with
+    /// `"type": "object"` and `properties`. Anything else is `None`.
+    pub fn detect(content: &str) -> Result<Option<JsonSchema>> {
+        let Ok(root @ Value::Object(_)) = serde_json::from_str::<Value>(content) else {
+            return Ok(None);
+        };
+        let is_schema = root.get("$schema").is_some()
+            || (root.get("type") == Some(&Value::from("object")) && root.get("properties").is_some_and(Value::is_object));
+        if !is_schema {
+            return Ok(None);
+        }
+        let mut patterns = HashMap::new();
+        collect_patterns(&root, &mut patterns)?;
+        Ok(Some(JsonSchema { root, patterns }))
+    }
+
+    /// The env map as an instance of this schema, with the env key each leaf came from by its
+    /// JSON pointer. A key that is both a value and the parent of nested keys (`A` and `A__B`)
+    /// keeps the first one set, and the other is returned as a violation.
+    pub fn instance(&self, env: &BTreeMap<String, String>) -> (Value, BTreeMap<String, String>, Vec<Violation>) {
+        let mut root = Map::new();
+        let mut sources = BTreeMap::new();
+        let mut conflicts = Vec::new();
+        for (key, value) in env {
+            let segments: Vec<&str> = match key.split("__").collect::<Vec<_>>() {
+                segments if segments.iter().all(|s| !s.is_empty()) => segments,
+                _ => vec![key.as_str()],
+            };
+            let leaf = Leaf { key, value };
+            conflicts.extend(self.insert(&mut root, Some(&self.root), &segments, &leaf, String::new(), &mut sources));
+        }
+        (Value::Object(root), sources, conflicts)
+    }
+
+    fn insert(
+        &self,
+        object: &mut Map<String, Value>,
+        schema: Option<&Value>,
+        segments: &[&str],
+        leaf: &Leaf,
+        pointer: String,
+        sources: &mut BTreeMap<String, String>,
+    ) -> Option<Violation> {
+        let (name, sub) = match schema {
+            Some(schema) => self.property(schema, segments[0], 0),
+            None => (segments[0].to_string(), None),
+        };
+        let pointer = child(&pointer, &name);
+        let conflict = |other: Option<&String>| Violation {
+            message: format!(
+                "{} is both a value and the parent of nested keys ({} is ignored)",
+                other.map_or_else(|| "this".to_string(), |o| format!("set by {}, and", o)),
+                leaf.key
+            ),
+            pointer: pointer.clone(),
+        };
+        if segments.len() == 1 {
+            if object.get(&name).is_some_and(Value::is_object) {
+                let nested = sources.iter().find(|(p, _)| p.starts_with(&format!("{}/", pointer))).map(|(_, k)| k);
+                return Some(conflict(nested));
+            }
+            object.insert(name, self.coerce(leaf.value, sub));
+            sources.insert(pointer, leaf.key.clone());
+            return None;
+        }
+        let entry = object.entry(name).or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(child) = entry else {
+            return Some(conflict(sources.get(&pointer)));
+        };
+        self.insert(child, sub, &segments[1..], leaf, pointer, sources)
+    }
+
+    /// The property of `schema` an env key segment names, and its schema: a declared property
+    /// of the same name, or else of any case, or the segment as is.
+    fn property<'a>(&'a self, schema: &'a Value, segment: &str, depth: usize) -> (String, Option<&'a Value>) {
+        let schema = self.resolve(schema);
+        if let Some(props) = schema.get("properties").and_then(Value::as_object) {
+            if let Some(sub) = props.get(segment) {
+                return (segment.to_string(), Some(sub));
+            }
+            if let Some((name, sub)) = props.iter().find(|(name, _)| name.eq_ignore_ascii_case(segment)) {
+                return (name.clone(), Some(sub));
+            }
+        }
+        if depth < MAX_DEPTH {
+            for sub in schema.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+                if let (name, Some(found)) = self.property(sub, segment, depth + 1) {
+                    return (name, Some(found));
+                }
+            }
+        }
+        let additional = schema.get("additionalProperties").filter(|a| a.is_object());
+        (segment.to_string(), additional)
+    }
+
+    /// `value` as the first type `schema` allows that it reads as; a string otherwise.
+    fn coerce(&self, value: &str, schema: Option<&Value>) -> Value {
+        let mut types = Vec::new();
+        if let Some(schema) = schema {
+            self.types(schema, 0, &mut types);
+        }
+        for t in types {
+            let coerced = match t {
+                "integer" => value.parse::<i64>().ok().map(Value::from),
+                "number" => value.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number),
+                "boolean" => value.parse::<bool>().ok().map(Value::Bool),
+                "null" => (value.is_empty() || value == "null").then_some(Value::Null),
+                "array" => serde_json::from_str::<Value>(value).ok().filter(Value::is_array),
+                "object" => serde_json::from_str::<Value>(value).ok().filter(Value::is_object),
+                _ => None,
+            };
+            if let Some(coerced) = coerced {
+                return coerced;
+            }
+        }
+        Value::String(value.to_string())
+    }
+
+    /// The types `schema` could accept, in order: its `type`, the types of its `const` and
+    /// `enum` values, then those of its `allOf`/`anyOf`/`oneOf` and `then`/`else` branches.
+    fn types<'a>(&'a self, schema: &'a Value, depth: usize, out: &mut Vec<&'a str>) {
+        let schema = self.resolve(schema);
+        let add = |t: &'a str, out: &mut Vec<&'a str>| {
+            if !out.contains(&t) {
+                out.push(t);
+            }
+        };
+        match schema.get("type") {
+            Some(Value::String(t)) => add(t, out),
+            Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).for_each(|t| add(t, out)),
+            _ => {}
+        }
+        let values = schema.get("const").into_iter().chain(schema.get("enum").and_then(Value::as_array).into_iter().flatten());
+        for value in values {
+            add(type_of(value), out);
+        }
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        for key in ["allOf", "anyOf", "oneOf"] {
+            for sub in schema.get(key).and_then(Value::as_array).into_iter().flatten() {
+                self.types(sub, depth + 1, out);
+            }
+        }
+        for key in ["then", "else"] {
+            if let Some(sub) = schema.get(key) {
+                self.types(sub, depth + 1, out);
+            }
+        }
+    }
+
+    /// The schema a local `$ref` points to, followed to the end; `schema` if it has none.
+    fn resolve<'a>(&'a self, mut schema: &'a Value) -> &'a Value {
+        for _ in 0..MAX_DEPTH {
+            match schema.get("$ref").and_then(Value::as_str).and_then(|r| self.target(r)) {
+                Some(target) => schema = target,
+                None => break,
+            }
+        }
+        schema
+    }
+
+    fn target(&self, reference: &str) -> Option<&Value> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+
+    /// Every violation of the schema by `instance`.
+    pub fn validate(&self, instance: &Value) -> Vec<Violation> {
+        let mut out = Vec::new();
+        self.check(&self.root, instance, "", false, 0, &mut out);
+        out
+    }
+
+    fn is_valid(&self, schema: &Value, instance: &Value, depth: usize) -> bool {
+        let mut out = Vec::new();
+        self.check(schema, instance, "", true, depth, &mut out);
+        out.is_empty()
+    }
+
+    fn check(&self, schema: &Value, instance: &Value, pointer: &str, hidden: bool, depth: usize, out: &mut Vec<Violation>) {
+        let mut fail = |message: String| out.push(Violation { pointer: pointer.to_string(), message });
+        if depth > MAX_DEPTH {
+            fail("the schema nests too deeply (a $ref loop?)".to_string());
+            return;
+        }
+        let s = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return fail(format!("{} is not allowed here", shown(instance, hidden))),
+            Value::Object(s) => s,
+            _ => return,
+        };
+        let hidden = hidden || s.get("writeOnly") == Some(&Value::Bool(true));
+        let value = shown(instance, hidden);
+
+        if let Some(reference) = s.get("$ref").and_then(Value::as_str) {
+            match self.target(reference) {
+                Some(target) => self.check(target, instance, pointer, hidden, depth + 1, out),
+                None => out.push(Violation { pointer: pointer.to_string(), message: format!("unresolved $ref '{}'", reference) }),
+            }
+        }
+        let mut fail = |message: String| out.push(Violation { pointer: pointer.to_string(), message });
+
+        if let Some(types) = s.get("type") {
+            let names: Vec<&str> = match types {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !names.iter().any(|t| has_type(instance, t)) {
+                return fail(format!("{} is not of type {}", value, names.join(" or ")));
+            }
+        }
+        if let Some(Value::Array(options)) = s.get("enum")
+            && !options.contains(instance)
+        {
+            let options: Vec<String> = options.iter().map(Value::to_string).collect();
+            fail(format!("{} is not one of {}", value, options.join(", ")));
+        }
+        if let Some(expected) = s.get("const")
+            && expected != instance
+        {
+            fail(format!("{} is not {}", value, expected));
+        }
+
+        if let Some(n) = instance.as_f64() {
+            let bound = |key: &str| s.get(key).and_then(Value::as_f64);
+            if let Some(min) = bound("minimum")
+                && n < min
+            {
+                fail(format!("{} is less than the minimum {}", value, min));
+            }
+            if let Some(max) = bound("maximum")
+                && n > max
+            {
+                fail(format!("{} is greater than the maximum {}", value, max));
+            }
+            if let Some(min) = bound("exclusiveMinimum")
+                && n <= min
+            {
+                fail(format!("{} is not greater than {}", value, min));
+            }
+            if let Some(max) = bound("exclusiveMaximum")
+                && n >= max
+            {
+                fail(format!("{} is not less than {}", value, max));
+            }
+            if let Some(m) = bound("multipleOf")
+                && m > 0.0
+                && ((n / m) - (n / m).round()).abs() > 1e-9
+            {
+                fail(format!("{} is not a multiple of {}", value, m));
+            }
+        }
+
+        if let Some(text) = instance.as_str() {
+            let length = text.chars().count() as u64;
+            if let Some(min) = s.get("minLength").and_then(Value::as_u64)
+                && length < min
+            {
+                fail(format!("{} is shorter than {} characters", value, min));
+            }
+            if let Some(max) = s.get("maxLength").and_then(Value::as_u64)
+                && length > max
+            {
+                fail(format!("{} is longer than {} characters", value, max));
+            }
+            if let Some(pattern) = s.get("pattern").and_then(Value::as_str) {
+                match self.patterns.get(pattern) {
+                    Some(regex) if !regex.is_match(text) => fail(format!("{} does not match {}", value, pattern)),
+                    Some(_) => {}
+                    None => fail(format!("pattern '{}' is not in a place this validator reads schemas from", pattern)),
+                }
+            }
+            if let Some(format) = s.get("format").and_then(Value::as_str)
+                && !has_format(text, format)
+            {
+                fail(format!("{} is not a valid {}", value, format));
+            }
+        }
+
+        if let Some(object) = instance.as_object() {
+            let count = object.len() as u64;
+            if let Some(min) = s.get("minProperties").and_then(Value::as_u64)
+                && count < min
+            {
+                fail(format!("has {} properties, fewer than {}", count, min));
+            }
+            if let Some(max) = s.get("maxProperties").and_then(Value::as_u64)
+                && count > max
+            {
+                fail(format!("has {} properties, more than {}", count, max));
+            }
+            let mut missing: Vec<&str> = s
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .filter(|name| !object.contains_key(*name))
+                .collect();
+            if let Some(Value::Object(dependent)) = s.get("dependentRequired") {
+                for (_, needs) in dependent.iter().filter(|(name, _)| object.contains_key(*name)) {
+                    missing.extend(needs.as_array().into_iter().flatten().filter_map(Value::as_str).filter(|n| !object.contains_key(*n)));
+                }
+            }
+            for name in missing {
+                out.push(Violation { pointer: child(pointer, name), message: "is required but not set".to_string() });
+            }
+
+            let properties = s.get("properties").and_then(Value::as_object);
+            let pattern_properties = s.get("patternProperties").and_then(Value::as_object);
+            for (name, item) in object {
+                let at = child(pointer, name);
+                let mut matched = false;
+                if let Some(sub) = properties.and_then(|p| p.get(name)) {
+                    matched = true;
+                    self.check(sub, item, &at, hidden, depth + 1, out);
+                }
+                for (pattern, sub) in pattern_properties.into_iter().flatten() {
+                    match self.patterns.get(pattern) {
+                        Some(regex) if regex.is_match(name) => {
+                            matched = true;
+                            self.check(sub, item, &at, hidden, depth + 1, out);
+                        }
+                        Some(_) => {}
+                        None => out.push(Violation {
+                            pointer: at.clone(),
+                            message: format!("pattern '{}' is not in a place this validator reads schemas from", pattern),
+                        }),
+                    }
+                }
+                if !matched && let Some(additional) = s.get("additionalProperties") {
+                    if additional == &Value::Bool(false) {
+                        out.push(Violation { pointer: at, message: "is not allowed (additionalProperties)".to_string() });
+                    } else {
+                        self.check(additional, item, &at, hidden, depth + 1, out);
+                    }
+                }
+                if let Some(names) = s.get("propertyNames")
+                    && !self.is_valid(names, &Value::from(name.as_str()), depth + 1)
+                {
+                    out.push(Violation { pointer: child(pointer, name), message: "is not an allowed property name".to_string() });
+                }
+            }
+        }
+
+        if let Some(array) = instance.as_array() {
+            let mut fail = |message: String| out.push(Violation { pointer: pointer.to_string(), message });
+            let count = array.len() as u64;
+            if let Some(min) = s.get("minItems").and_then(Value::as_u64)
+                && count < min
+            {
+                fail(format!("has {} items, fewer than {}", count, min));
+            }
+            if let Some(max) = s.get("maxItems").and_then(Value::as_u64)
+                && count > max
+            {
+                fail(format!("has {} items, more than {}", count, max));
+            }
+            if s.get("uniqueItems") == Some(&Value::Bool(true))
+                && array.iter().enumerate().any(|(i, a)| array[..i].contains(a))
+            {
+                fail("has duplicate items".to_string());
+            }
+            if let Some(contains) = s.get("contains")
+                && !array.iter().any(|item| self.is_valid(contains, item, depth + 1))
+            {
+                fail("has no item matching contains".to_string());
+            }
+            let prefix = s.get("prefixItems").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+            for (i, item) in array.iter().enumerate() {
+                let sub = match prefix.get(i) {
+                    Some(sub) => Some(sub),
+                    None => s.get("items"),
+                };
+                if let Some(sub) = sub {
+                    self.check(sub, item, &child(pointer, &i.to_string()), hidden, depth + 1, out);
+                }
+            }
+        }
+
+        for sub in s.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+            self.check(sub, instance, pointer, hidden, depth + 1, out);
+        }
+        let mut fail = |message: String| out.push(Violation { pointer: pointer.to_string(), message });
+        if let Some(Value::Array(options)) = s.get("anyOf")
+            && !options.iter().any(|sub| self.is_valid(sub, instance, depth + 1))
+        {
+            fail(format!("{} matches none of the anyOf schemas", value));
+        }
+        if let Some(Value::Array(options)) = s.get("oneOf") {
+            let matching = options.iter().filter(|sub| self.is_valid(sub, instance, depth + 1)).count();
+            if matching != 1 {
+                fail(format!("{} matches {} of the oneOf schemas, not exactly one", value, matching));
+            }
+        }
+        if let Some(not) = s.get("not")
+            && self.is_valid(not, instance, depth + 1)
+        {
+            fail(format!("{} must not match the schema in not", value));
+        }
+        if let Some(condition) = s.get("if") {
+            let branch = if self.is_valid(condition, instance, depth + 1) { s.get("then") } else { s.get("else") };
+            if let Some(branch) = branch {
+                self.check(branch, instance, pointer, hidden, depth + 1, out);
+            }
+        }
+    }
+}
+
+/// Compile every `pattern` and `patternProperties` key of `schema`, refusing what does not
+/// compile and `$ref`s outside the document. Only keywords that hold subschemas are walked, so
+/// a property named `default` or `enum` is a schema like any other, and values under `enum`,
+/// `const`, `default` or `examples` are data.
+fn collect_patterns(schema: &Value, patterns: &mut HashMap<String, Regex>) -> Result<()> {
+    let compile = |pattern: &str, patterns: &mut HashMap<String, Regex>| -> Result<()> {
+        let regex = Regex::new(pattern).map_err(|e| anyhow!("invalid pattern '{}': {}", pattern, e))?;
+        patterns.insert(pattern.to_string(), regex);
+        Ok(())
+    };
+    let Value::Object(s) = schema else {
+        return Ok(());
+    };
+    for (key, sub) in s {
+        match (key.as_str(), sub) {
+            ("pattern", Value::String(pattern)) => compile(pattern, patterns)?,
+            ("$ref", Value::String(reference)) if !reference.starts_with('#') => {
+                return Err(anyhow!("only local $refs (#/...) are supported, not '{}'", reference));
+            }
+            ("properties" | "patternProperties" | "$defs" | "definitions" | "dependentSchemas", Value::Object(subs)) => {
+                for (name, sub) in subs {
+                    if key == "patternProperties" {
+                        compile(name, patterns)?;
+                    }
+                    collect_patterns(sub, patterns)?;
+                }
+            }
+            ("allOf" | "anyOf" | "oneOf" | "prefixItems" | "items", Value::Array(subs)) => {
+                for sub in subs {
+                    collect_patterns(sub, patterns)?;
+                }
+            }
+            ("items" | "additionalProperties" | "not" | "if" | "then" | "else" | "contains" | "propertyNames", _) => {
+                collect_patterns(sub, patterns)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn child(pointer: &str, name: &str) -> String {
+    format!("{}/{}", pointer, name.replace('~', "~0").replace('/', "~1"))
+}
+
+/// `instance` for a message: its JSON, or just "value" when it is `writeOnly`.
+fn shown(instance: &Value, hidden: bool) -> String {
+    if hidden { "value".to_string() } else { instance.to_string() }
+}
+
+/// The JSON Schema type name of `value`.
+fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "integer" => instance.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => instance.is_number(),
+        "string" => instance.is_string(),
+        "boolean" => instance.is_boolean(),
+        "null" => instance.is_null(),
+        "array" => instance.is_array(),
+        "object" => instance.is_object(),
+        _ => false,
+    }
+}
+
+/// Whether `text` is a `format`; formats this validator does not know pass, as annotations.
+fn has_format(text: &str, format: &str) -> bool {
+    let all = |s: &str, f: fn(char) -> bool| !s.is_empty() && s.chars().all(f);
+    match format {
+        "email" => crate::schema::is_email(text),
+        "uri" => text.split_once(':').is_some_and(|(scheme, rest)| {
+            scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                && all(scheme, |c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+                && !rest.is_empty()
+                && !text.chars().any(char::is_whitespace)
+        }),
+        "hostname" => {
+            text.len() <= 253
+                && text.split('.').all(|label| {
+                    label.len() <= 63
+                        && all(label, |c| c.is_ascii_alphanumeric() || c == '-')
+                        && !label.starts_with('-')
+                        && !label.ends_with('-')
+                })
+        }
+        "ipv4" => text.parse::<std::net::Ipv4Addr>().is_ok(),
+        "ipv6" => text.parse::<std::net::Ipv6Addr>().is_ok(),
+        "uuid" => {
+            let groups: Vec<&str> = text.split('-').collect();
+            groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12]) && groups.iter().all(|g| all(g, |c| c.is_ascii_hexdigit()))
+        }
+        "date" => {
+            let parts: Vec<&str> = text.split('-').collect();
+            parts.iter().map(|p| p.len()).eq([4, 2, 2])
+                && parts.iter().all(|p| all(p, |c| c.is_ascii_digit()))
+                && (1..=12).contains(&parts[1].parse::<u32>().unwrap_or(0))
+                && (1..=31).contains(&parts[2].parse::<u32>().unwrap_or(0))
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r##"{
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "required": ["port", "db"],
+        "properties": {
+            "port": { "type": "integer", "minimum": 1, "maximum": 65535 },
+            "debug": { "type": "boolean" },
+            "hosts": { "type": "array", "items": { "format": "hostname" }, "uniqueItems": true },
+            "api_key": { "type": "string", "pattern": "^sk_", "writeOnly": true },
+            "db": { "$ref": "#/$defs/db" }
+        },
+        "$defs": {
+            "db": {
+                "type": "object",
+                "required": ["url"],
+                "properties": {
+                    "url": { "type": "string", "format": "uri" },
+                    "pool": { "type": ["integer", "null"], "exclusiveMinimum": 0 }
+                },
+                "additionalProperties": false
+            }
+        }
+    }"##;
+
+    fn env(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn problems(pairs: &[(&str, &str)]) -> Vec<String> {
+        let schema = JsonSchema::detect(SCHEMA).unwrap().unwrap();
+        let (instance, _, _) = schema.instance(&env(pairs));
+        schema.validate(&instance).into_iter().map(|v| format!("{}: {}", v.pointer, v.message)).collect()
+    }
+
+    #[test]
+    fn only_schema_documents_are_detected() {
+        assert!(JsonSchema::detect(SCHEMA).unwrap().is_some());
+        assert!(JsonSchema::detect(r#"{"PORT": "port"}"#).unwrap().is_none());
+        assert!(JsonSchema::detect("PORT: port").unwrap().is_none());
+        assert!(JsonSchema::detect(r#"{"$schema": "x", "pattern": "("}"#).is_err());
+        assert!(JsonSchema::detect(r#"{"$schema": "x", "$ref": "other.json"}"#).is_err());
+    }
+
+    #[test]
+    fn properties_named_like_keywords_are_schemas() {
+        let schema = JsonSchema::detect(
+            r#"{"$schema": "x", "properties": {"default": {"type": "string", "pattern": "^a"}, "enum": {"const": "e"}}}"#,
+        )
+        .unwrap()
+        .unwrap();
+        let (instance, _, _) = schema.instance(&env(&[("DEFAULT", "b"), ("ENUM", "e")]));
+        let problems: Vec<String> = schema.validate(&instance).into_iter().map(|v| format!("{}: {}", v.pointer, v.message)).collect();
+        assert_eq!(problems, ["/default: \"b\" does not match ^a"]);
+        // A pattern in a value (not a schema) is data, and is not compiled.
+        assert!(JsonSchema::detect(r#"{"$schema": "x", "default": {"pattern": "("}}"#).unwrap().is_some());
+    }
+
+    #[test]
+    fn env_keys_nest_match_case_and_coerce() {
+        let schema = JsonSchema::detect(SCHEMA).unwrap().unwrap();
+        let (instance, sources, conflicts) = schema.instance(&env(&[
+            ("PORT", "8080"),
+            ("DEBUG", "true"),
+            ("HOSTS", r#"["a.example", "b.example"]"#),
+            ("DB__URL", "postgres://db/app"),
+            ("DB__POOL", ""),
+            ("EXTRA", "x"),
+        ]));
+        assert_eq!(
+            instance,
+            serde_json::json!({
+                "port": 8080, "debug": true, "hosts": ["a.example", "b.example"],
+                "db": { "url": "postgres://db/app", "pool": null }, "EXTRA": "x"
+            })
+        );
+        assert_eq!(sources["/db/url"], "DB__URL");
+        assert!(conflicts.is_empty());
+        assert!(schema.validate(&instance).is_empty());
+    }
+
+    #[test]
+    fn violations_carry_pointers_and_hide_write_only_values() {
+        assert_eq!(
+            problems(&[
+                ("PORT", "70000"),
+                ("DEBUG", "maybe"),
+                ("HOSTS", r#"["a", "-b", "a"]"#),
+                ("API_KEY", "hunter2"),
+                ("DB__POOL", "0"),
+                ("DB__TIMEOUT", "5"),
+            ]),
+            [
+                "/api_key: value does not match ^sk_",
+                "/db/url: is required but not set",
+                "/db/TIMEOUT: is not allowed (additionalProperties)",
+                "/db/pool: 0 is not greater than 0",
+                "/debug: \"maybe\" is not of type boolean",
+                "/hosts: has duplicate items",
+                "/hosts/1: \"-b\" is not a valid hostname",
+                "/port: 70000 is greater than the maximum 65535",
+            ]
+        );
+        assert_eq!(problems(&[("DB__URL", "x:y")]), ["/port: is required but not set"]);
+    }
+
+    #[test]
+    fn combinators_and_conditionals() {
+        let schema = JsonSchema::detect(
+            r#"{
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "properties": {
+                    "mode": { "enum": ["dev", "prod"] },
+                    "level": { "oneOf": [{ "type": "integer" }, { "const": "auto" }] },
+                    "name": { "not": { "const": "root" } }
+                },
+                "if": { "properties": { "mode": { "const": "prod" } } },
+                "then": { "required": ["tls"] }
+            }"#,
+        )
+        .unwrap()
+        .unwrap();
+        let check = |pairs: &[(&str, &str)]| {
+            let (instance, _, _) = schema.instance(&env(pairs));
+            schema.validate(&instance).into_iter().map(|v| format!("{}: {}", v.pointer, v.message)).collect::<Vec<_>>()
+        };
+        assert!(check(&[("MODE", "dev"), ("LEVEL", "auto")]).is_empty());
+        // The oneOf branch's integer type coerces the value.
+        assert!(check(&[("MODE", "dev"), ("LEVEL", "5")]).is_empty());
+        assert_eq!(
+            check(&[("MODE", "prod"), ("LEVEL", "high"), ("NAME", "root")]),
+            [
+                "/level: \"high\" matches 0 of the oneOf schemas, not exactly one",
+                "/name: \"root\" must not match the schema in not",
+                "/tls: is required but not set",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_value_that_is_also_a_parent_is_reported() {
+        let schema = JsonSchema::detect(SCHEMA).unwrap().unwrap();
+        let (instance, _, conflicts) = schema.instance(&env(&[("DB", "x"), ("DB__URL", "postgres://db/app")]));
+        assert_eq!(instance["db"], "x");
+        let conflicts: Vec<String> = conflicts.into_iter().map(|v| format!("{}: {}", v.pointer, v.message)).collect();
+        assert_eq!(conflicts, ["/db: set by DB, and is both a value and the parent of nested keys (DB__URL is ignored)"]);
+
+        let (instance, _, conflicts) = schema.instance(&env(&[("DB__URL", "postgres://db/app"), ("db", "x")]));
+        assert_eq!(instance["db"]["url"], "postgres://db/app");
+        assert_eq!(conflicts[0].message, "set by DB__URL, and is both a value and the parent of nested keys (db is ignored)");
+    }
+}
@@ -1,18 +1,28 @@
 use clap::{CommandFactory, Parser, Subcommand};
 use std::io::IsTerminal;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 mod bip39_words;
 mod blockchain;
+mod contacts;
+mod dotenv;
 mod env_file;
 mod errors;
 mod eth;
+mod gateway;
 mod helpers;
 mod ipfs;
+mod json_schema;
 mod materialize;
+mod mock_chain;
 mod network_config;
+mod pinning;
 mod project_config;
+mod relay;
+mod schema;
 mod secrets;
+mod team_registry;
+mod tx_journal;
 mod wallet;
 
 #[derive(Parser, Debug)]
@@ -62,26 +72,66 @@ enum Commands {
 
     /// Configure network and storage settings
     Config {
-        /// Set blockchain network (ethereum, polygon, goerli, mumbai)
+        /// Set blockchain network (ethereum, polygon, goerli, mumbai; mock for an in-process test chain, team for a shared-directory registry)
         #[arg(long)]
         network: Option<String>,
 
-        /// Set custom RPC endpoint URL
+        /// Set custom RPC endpoint URL (with --network team: the directory holding the registry)
         #[arg(long)]
         rpc: Option<String>,
 
+        /// Set fallback RPC endpoints tried in order when the primary fails (comma-separated; "" clears)
+        #[arg(long, value_delimiter = ',')]
+        rpc_fallbacks: Option<Vec<String>>,
+
+        /// Set the expected chain ID (for custom networks)
+        #[arg(long)]
+        chain_id: Option<u32>,
+
         /// Set deployed BsecSecretRegistry contract address
         #[arg(long)]
         registry: Option<String>,
 
+        /// What to do if the registry's code is not a known build: warn, enforce or off
+        #[arg(long, value_name = "POLICY", value_parser = network_config::parse_registry_check)]
+        registry_check: Option<network_config::RegistryCheck>,
+
+        /// Also accept registry runtime code with this keccak256 hash
+        #[arg(long, value_name = "HASH", value_parser = network_config::parse_code_hash)]
+        trust_registry_code_hash: Option<String>,
+
+        /// Set the directory holding the mock chain (--network mock; "" for ~/.bsec/mock)
+        #[arg(long, value_name = "DIR")]
+        mock_dir: Option<String>,
+
         /// Set IPFS gateway URL
         #[arg(long)]
         ipfs_gateway: Option<String>,
 
-        /// Set IPFS pinning service
+        /// Set the ordered list of gateways raced on fetch (comma-separated; "" resets)
+        #[arg(long, value_delimiter = ',')]
+        ipfs_gateways: Option<Vec<String>>,
+
+        /// Select hosted pinning providers, comma-separated (pinata, a --add-pinning-provider name, or none)
         #[arg(long)]
         ipfs_pinning: Option<String>,
 
+        /// Add an IPFS Pinning Service API provider as NAME=ENDPOINT (token from BSEC_PSA_TOKEN_<NAME>)
+        #[arg(long, value_name = "NAME=ENDPOINT")]
+        add_pinning_provider: Option<String>,
+
+        /// Remove a Pinning Service API provider by name
+        #[arg(long, value_name = "NAME")]
+        remove_pinning_provider: Option<String>,
+
+        /// Cap the local IPFS cache size (e.g. 64M, 1G; 0 = unlimited)
+        #[arg(long, value_parser = network_config::parse_size)]
+        cache_max_size: Option<u64>,
+
+        /// Encrypt the local IPFS cache at rest with a wallet-derived key
+        #[arg(long)]
+        encrypt_cache: Option<bool>,
+
         /// Show current configuration
         #[arg(long)]
         show: bool,
@@ -89,6 +139,9 @@ enum Commands {
         /// Output in JSON format
         #[arg(long)]
         json: bool,
+
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
     },
 
     /// Generate shell auto-completion script (bash, zsh, fish, powershell, elvish)
@@ -100,33 +153,8 @@ enum Commands {
 
     /// Share a secret securely
     Share {
-        /// Positional secret text content
-        secret: Option<String>,
-
-        /// Secret text content to share
-        #[arg(long)]
-        content: Option<String>,
-
-        /// Path to file containing the secret. With --as or a known extension the secret is
-        /// tagged for file materialization; otherwise its text is shared as-is (legacy).
-        #[arg(short, long)]
-        file: Option<PathBuf>,
-
-        /// Tag the secret's file kind: env | pem | json | cred (implies materializable).
-        #[arg(long = "as")]
-        as_kind: Option<String>,
-
-        /// Suggested output basename when materialized (default: basename of --file).
-        #[arg(long)]
-        filename: Option<String>,
-
-        /// Path to a bundle manifest JSON packing multiple files into one secret.
-        #[arg(long)]
-        bundle: Option<PathBuf>,
-
-        /// Seal the secret: refuse all file materialization (terminal view only).
-        #[arg(long = "no-export")]
-        no_export: bool,
+        #[command(flatten)]
+        input: ShareContentArgs,
 
         /// Time-to-live (e.g. 1m, 2h, 1d, 7d)
         #[arg(short, long, default_value = "24h")]
@@ -136,13 +164,17 @@ enum Commands {
         #[arg(short = 'm', long = "max-reads", default_value = "1")]
         max_reads: u32,
 
-        /// Recipient wallet address or user ID
+        /// Recipient wallet address or user ID; repeat to share with several recipients in
+        /// one transaction
         #[arg(short = 'u', long = "to")]
-        to: Option<String>,
+        to: Vec<String>,
 
         /// Password to unlock wallet if required
         #[arg(short, long)]
         password: Option<String>,
+
+        #[command(flatten)]
+        tx: TxArgs,
     },
 
     /// Materialize a shared secret to real file(s) on disk
@@ -169,6 +201,9 @@ enum Commands {
         /// Overwrite existing files
         #[arg(long)]
         force: bool,
+
+        #[command(flatten)]
+        tx: TxArgs,
     },
 
     /// View a shared secret
@@ -187,6 +222,9 @@ enum Commands {
         /// Output in JSON format
         #[arg(long)]
         json: bool,
+
+        #[command(flatten)]
+        tx: TxArgs,
     },
 
     /// List active or expired secrets
@@ -223,11 +261,23 @@ enum Commands {
     /// Revoke access to a shared secret
     Revoke {
         /// Secret ID to revoke
-        secret_id: String,
+        #[arg(required_unless_present = "all_from_me", conflicts_with = "all_from_me")]
+        secret_id: Option<String>,
+
+        /// Revoke every readable secret you shared, in one transaction
+        #[arg(long)]
+        all_from_me: bool,
+
+        /// With --all-from-me, only secrets shared with this recipient (address, public key or `public`)
+        #[arg(long, requires = "all_from_me")]
+        recipient: Option<String>,
 
         /// Password to unlock wallet if required
         #[arg(short, long)]
         password: Option<String>,
+
+        #[command(flatten)]
+        tx: TxArgs,
     },
 
     /// Hide shared secret(s)
@@ -246,6 +296,50 @@ enum Commands {
         /// Password to unlock wallet if required
         #[arg(short, long)]
         password: Option<String>,
+
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+
+    /// Unpin and evict payloads of revoked or expired secrets in the local index
+    Gc {
+        /// Only list what would be collected
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Inspect and manage the local IPFS payload cache
+    Cache {
+        #[command(subcommand)]
+        sub: CacheCommands,
+    },
+
+    /// Name the public keys of teammates and CI wallets for `encrypt --to`
+    Contact {
+        #[command(subcommand)]
+        sub: ContactCommands,
+    },
+
+    /// Track, speed up or cancel transactions bsec has sent
+    Tx {
+        #[command(subcommand)]
+        sub: TxCommands,
+    },
+
+    /// Deploy the BsecSecretRegistry contract
+    Registry {
+        #[command(subcommand)]
+        sub: RegistryCommands,
+    },
+
+    /// Relay other users' signed registry writes, paying their gas
+    Relay {
+        #[command(subcommand)]
+        sub: RelayCommands,
     },
 
     /// Convert between environment file formats (JSON, YAML, .env)
@@ -312,6 +406,16 @@ enum Commands {
         #[arg(short, long)]
         out: Option<PathBuf>,
 
+        /// Encrypt each value on its own (`KEY=enc:v2:...`), keeping keys in cleartext so the
+        /// file stays diffable and mergeable
+        #[arg(long, conflicts_with = "to")]
+        per_value: bool,
+
+        /// Encrypt to these wallets (contact names or 0x04... public keys, repeatable) instead
+        /// of a password; each decrypts with their own wallet
+        #[arg(long, num_args = 1..)]
+        to: Vec<String>,
+
         /// Encryption password
         #[arg(short, long)]
         password: Option<String>,
@@ -331,6 +435,35 @@ enum Commands {
         password: Option<String>,
     },
 
+    /// Edit an encrypted env file (.env.enc) in $EDITOR without leaving plaintext behind
+    Edit {
+        /// Encrypted env file to edit
+        file: PathBuf,
+
+        /// Decryption password
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Re-encrypt env files under a new password (fresh salt and nonce, written atomically)
+    Rekey {
+        /// Encrypted env files, or directories with --recursive
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Rekey every encrypted env file under the given directories
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Current password (default: looked up per file, as for decrypt)
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// New password (or set $DOTENV_NEW_PASS)
+        #[arg(long)]
+        new_password: Option<String>,
+    },
+
     /// Run a command with injected environment variables or shared secret
     Run {
         /// Path to environment file (.env, .env.local, .json, .env.enc)
@@ -345,6 +478,9 @@ enum Commands {
         #[arg(short = 'p', long)]
         password: Option<String>,
 
+        #[command(flatten)]
+        tx: TxArgs,
+
         /// Command and arguments to execute
         #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
         command: Vec<String>,
@@ -360,6 +496,65 @@ enum Commands {
         file: PathBuf,
     },
 
+    /// Print the value of a variable in an env file (.env, .env.enc, .json)
+    Get {
+        /// Variable name
+        key: String,
+
+        /// Target file path
+        #[arg(short, long, default_value = ".env.local")]
+        file: PathBuf,
+
+        /// Password for an encrypted env file
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Set a variable in an env file (.env or .env.enc), keeping the rest of the file as is
+    Set {
+        /// `KEY=VALUE`, or `KEY` alone to read the value from stdin (keeps it out of shell history)
+        assignment: String,
+
+        /// Target file path (created if missing)
+        #[arg(short, long, default_value = ".env.local")]
+        file: PathBuf,
+
+        /// Password for an encrypted env file
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Remove a variable from an env file (.env or .env.enc)
+    Unset {
+        /// Variable name
+        key: String,
+
+        /// Target file path
+        #[arg(short, long, default_value = ".env.local")]
+        file: PathBuf,
+
+        /// Password for an encrypted env file
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Rename a variable in an env file (.env or .env.enc)
+    Rename {
+        /// Current name
+        old: String,
+
+        /// New name
+        new: String,
+
+        /// Target file path
+        #[arg(short, long, default_value = ".env.local")]
+        file: PathBuf,
+
+        /// Password for an encrypted env file
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
     /// Search for pattern in a file
     Search {
         /// Pattern to search for
@@ -371,6 +566,319 @@ enum Commands {
     },
 }
 
+/// What to share: text, a file, or a bundle (`share` and `tx build share`).
+#[derive(clap::Args, Debug, Clone)]
+struct ShareContentArgs {
+    /// Positional secret text content
+    secret: Option<String>,
+
+    /// Secret text content to share
+    #[arg(long)]
+    content: Option<String>,
+
+    /// Path to file containing the secret. With --as or a known extension the secret is
+    /// tagged for file materialization; otherwise its text is shared as-is (legacy).
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+
+    /// Tag the secret's file kind: env | pem | json | cred (implies materializable).
+    #[arg(long = "as")]
+    as_kind: Option<String>,
+
+    /// Suggested output basename when materialized (default: basename of --file).
+    #[arg(long)]
+    filename: Option<String>,
+
+    /// Path to a bundle manifest JSON packing multiple files into one secret.
+    #[arg(long)]
+    bundle: Option<PathBuf>,
+
+    /// Seal the secret: refuse all file materialization (terminal view only).
+    #[arg(long = "no-export")]
+    no_export: bool,
+}
+
+/// Fee and gas overrides shared by every command that writes to the registry.
+#[derive(clap::Args, Debug, Clone)]
+struct TxArgs {
+    /// Max fee per gas in gwei (the gas price on legacy chains)
+    #[arg(long, value_name = "GWEI", value_parser = eth::parse_gwei)]
+    max_fee: Option<u128>,
+
+    /// Priority fee (tip) per gas in gwei
+    #[arg(long, value_name = "GWEI", value_parser = eth::parse_gwei)]
+    priority_fee: Option<u128>,
+
+    /// Gas limit, replacing the estimate + 20% headroom
+    #[arg(long)]
+    gas_limit: Option<u64>,
+
+    /// Refuse to send if the worst-case fee exceeds this amount of the native token (e.g. 0.01)
+    #[arg(long, value_name = "AMOUNT", value_parser = eth::parse_native)]
+    fee_cap: Option<u128>,
+
+    /// Print the estimated transaction cost without broadcasting anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Have this relayer submit registry writes and pay their gas (see `bsec relay serve`)
+    #[arg(long, value_name = "URL")]
+    relayer: Option<String>,
+}
+
+impl TxArgs {
+    fn options(&self) -> eth::TxOptions {
+        eth::TxOptions {
+            max_fee_per_gas: self.max_fee,
+            priority_fee_per_gas: self.priority_fee,
+            gas_limit: self.gas_limit.map(u128::from),
+            fee_cap: self.fee_cap,
+            dry_run: self.dry_run,
+            relayer: self.relayer.clone(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Probe every RPC endpoint: latency, chain ID and head block
+    Check {
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check that the configured registry runs genuine BsecSecretRegistry code
+    VerifyRegistry {
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RegistryCommands {
+    /// Deploy the registry from your wallet and make it the configured registry
+    Deploy {
+        /// Deploy this bytecode (hex, or a solc/forge JSON artifact) instead of the embedded build
+        #[arg(long, value_name = "FILE")]
+        bytecode: Option<PathBuf>,
+
+        /// Print the address without writing it into the network config
+        #[arg(long)]
+        no_save: bool,
+
+        /// Password to unlock wallet if required
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RelayCommands {
+    /// Accept EIP-712 intents over HTTP and submit them from this wallet
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8790")]
+        listen: String,
+
+        /// Only relay for this signer address (repeatable; required unless --open)
+        #[arg(long, value_name = "ADDRESS", required_unless_present = "open")]
+        allow: Vec<String>,
+
+        /// Relay for any signer, paying their gas from this wallet
+        #[arg(long, conflicts_with = "allow")]
+        open: bool,
+
+        /// Password to unlock wallet if required
+        #[arg(short, long)]
+        password: Option<String>,
+
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TxCommands {
+    /// List journaled transactions on the current network (pending only unless --all)
+    List {
+        /// Include confirmed, reverted, replaced and dropped transactions
+        #[arg(long)]
+        all: bool,
+
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check a journaled transaction against the chain
+    Status {
+        /// Transaction hash (or a unique prefix)
+        hash: String,
+
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Re-send a pending transaction at the same nonce with higher fees
+    Speedup {
+        /// Transaction hash (or a unique prefix)
+        hash: String,
+
+        /// Password to unlock wallet if required
+        #[arg(short, long)]
+        password: Option<String>,
+
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+
+    /// Replace a pending transaction with a zero-value transfer to yourself
+    Cancel {
+        /// Transaction hash (or a unique prefix)
+        hash: String,
+
+        /// Password to unlock wallet if required
+        #[arg(short, long)]
+        password: Option<String>,
+
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+
+    /// Build an unsigned registry transaction for an offline wallet (no key needed)
+    Build {
+        #[command(subcommand)]
+        call: Box<TxBuildCommands>,
+    },
+
+    /// Sign a built transaction with the local wallet; works without network access
+    Sign {
+        /// Unsigned transaction file from `bsec tx build`
+        file: PathBuf,
+
+        /// Write the signed transaction here instead of stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+
+        /// Password to unlock wallet if required
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Broadcast a signed transaction and wait for it to be mined
+    Broadcast {
+        /// Signed transaction file from `bsec tx sign`
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TxBuildCommands {
+    /// Encrypt and upload a secret, then build its shareSecret transaction
+    Share {
+        #[command(flatten)]
+        input: ShareContentArgs,
+
+        /// Time-to-live (e.g. 1m, 2h, 1d, 7d)
+        #[arg(short, long, default_value = "24h")]
+        ttl: String,
+
+        /// Maximum number of reads before auto-destruction
+        #[arg(short = 'm', long = "max-reads", default_value = "1")]
+        max_reads: u32,
+
+        /// Recipient public key (0x04...) or 'public'
+        #[arg(short = 'u', long = "to")]
+        to: String,
+
+        #[command(flatten)]
+        build: TxBuildArgs,
+    },
+
+    /// Build a revokeSecret transaction
+    Revoke {
+        /// Secret ID to revoke
+        secret_id: String,
+
+        #[command(flatten)]
+        build: TxBuildArgs,
+    },
+}
+
+/// Sender, nonce and output of `bsec tx build`.
+#[derive(clap::Args, Debug, Clone)]
+struct TxBuildArgs {
+    /// Address of the offline wallet that will sign
+    #[arg(long)]
+    from: String,
+
+    /// Nonce to use instead of the next one (needed when building several before broadcasting any)
+    #[arg(long)]
+    nonce: Option<u64>,
+
+    /// Write the unsigned transaction here instead of stdout
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+
+    #[command(flatten)]
+    tx: TxArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommands {
+    /// Show entry count, size and encryption status
+    Stats {
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Evict entries of revoked/expired secrets, then enforce the size limit
+    Prune {
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Delete every cached payload
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+enum ContactCommands {
+    /// Save a wallet public key (`bsec wallet info` prints it) under a name
+    Add {
+        /// Contact name
+        name: String,
+
+        /// SEC1 public key (0x04... or compressed 0x02.../0x03...)
+        pubkey: String,
+    },
+
+    /// List saved contacts
+    List {
+        /// Output in JSON format
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Remove a contact
+    Remove {
+        /// Contact name
+        name: String,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum WalletCommands {
     /// Display wallet information
@@ -456,6 +964,309 @@ fn get_password_or_prompt(provided: Option<String>, prompt_msg: &str) -> Option<
     }
 }
 
+/// The password for `bsec get/set/unset/rename`: only an encrypted file needs one.
+fn env_file_password(file: &Path, provided: Option<String>) -> Option<String> {
+    if env_file::is_encrypted(file) {
+        get_password_or_prompt(provided, "Enter env file password: ")
+    } else {
+        provided
+    }
+}
+
+/// The new password for `bsec rekey`: `--new-password`, then `$DOTENV_NEW_PASS`, then typed
+/// twice at a terminal.
+fn read_new_password(provided: Option<String>) -> zeroize::Zeroizing<String> {
+    if provided.is_some() {
+        eprintln!("Warning: Passing passwords via CLI flags may expose credentials in process lists.");
+    }
+    if let Some(p) = provided.or_else(|| std::env::var("DOTENV_NEW_PASS").ok()) {
+        return zeroize::Zeroizing::new(p);
+    }
+    if !std::io::stdin().is_terminal() {
+        eprintln!("Error: No new password. Provide --new-password or set $DOTENV_NEW_PASS.");
+        std::process::exit(2);
+    }
+    eprint!("Enter new password: ");
+    let first = zeroize::Zeroizing::new(rpassword::read_password().unwrap_or_default());
+    eprint!("Repeat new password: ");
+    let second = zeroize::Zeroizing::new(rpassword::read_password().unwrap_or_default());
+    if *first != *second {
+        eprintln!("Error: The passwords do not match.");
+        std::process::exit(2);
+    }
+    first
+}
+
+/// A value for `bsec set KEY`: typed without echo at a terminal, else all of stdin less one
+/// trailing line break, so `bsec set TLS_KEY < key.pem` keeps the PEM's inner lines.
+fn read_value_from_stdin(key: &str) -> zeroize::Zeroizing<String> {
+    if std::io::stdin().is_terminal() {
+        eprint!("Value for {}: ", key);
+        return zeroize::Zeroizing::new(rpassword::read_password().unwrap_or_default());
+    }
+    let mut value = zeroize::Zeroizing::new(String::new());
+    if let Err(e) = std::io::Read::read_to_string(&mut std::io::stdin(), &mut value) {
+        handle_cli_error("Error reading value from stdin", e.into());
+    }
+    let len = value.strip_suffix('\n').map_or(value.len(), |v| v.strip_suffix('\r').unwrap_or(v).len());
+    value.truncate(len);
+    value
+}
+
+fn print_tx_estimate(estimate: anyhow::Result<eth::TxEstimate>, opts: &eth::TxOptions) {
+    let est = match estimate {
+        Ok(e) => e,
+        Err(e) => handle_cli_error("Error estimating transaction", e),
+    };
+    let symbol = network_config::load_network_config().native_symbol();
+    let gwei = |w: u128| eth::format_units(w, 9);
+    println!("Dry run: nothing was broadcast.");
+    println!("  Transaction type: {}", est.kind);
+    println!("  Gas limit: {}", est.gas_limit);
+    match (est.priority_fee_per_gas, est.base_fee_per_gas) {
+        (Some(tip), Some(base)) => println!(
+            "  Max fee: {} gwei (priority {} gwei, current base {} gwei)",
+            gwei(est.max_fee_per_gas),
+            gwei(tip),
+            gwei(base)
+        ),
+        _ => println!("  Gas price: {} gwei", gwei(est.max_fee_per_gas)),
+    }
+    println!("  Expected cost: {} {}", eth::format_units(est.expected_cost_wei, 18), symbol);
+    println!("  Max cost: {} {}", eth::format_units(est.max_cost_wei, 18), symbol);
+    if est.exceeds_cap(opts) {
+        println!(
+            "  Exceeds --fee-cap {} {}: a real send would be refused.",
+            eth::format_units(opts.fee_cap.unwrap_or_default(), 18),
+            symbol
+        );
+    }
+}
+
+fn print_tx_record(rec: &tx_journal::TxRecord) {
+    println!(
+        "{}  {:<9}  nonce {:<5} {:<12} sent {}",
+        rec.hash,
+        rec.status.as_str(),
+        rec.nonce,
+        if rec.to.is_empty() { "deploy".to_string() } else { eth::describe_calldata(&rec.data) },
+        rec.submitted_at
+    );
+    if let Some(newer) = &rec.replaced_by {
+        println!("    replaced by {}", newer);
+    }
+}
+
+/// Prompt for the wallet password if needed and return the wallet's private key.
+fn unlock_wallet_key(password: Option<String>) -> zeroize::Zeroizing<Vec<u8>> {
+    let pwd = get_password_or_prompt(password, "Enter wallet password (if encrypted): ");
+    let wallet_info = match wallet::get_wallet_info(pwd.as_deref()) {
+        Ok(w) => w,
+        Err(e) => handle_cli_error("Error loading wallet", e),
+    };
+    match wallet::hex_to_bytes(&wallet_info.private_key) {
+        Ok(b) => zeroize::Zeroizing::new(b),
+        Err(e) => handle_cli_error("Error loading wallet", e),
+    }
+}
+
+fn run_tx_replacement(hash: &str, password: Option<String>, tx: &TxArgs, how: eth::Replacement) {
+    let opts = tx.options();
+    let priv_bytes = unlock_wallet_key(password);
+    let conf = network_config::load_network_config();
+    match eth::replace_tx(&conf, &priv_bytes, hash, how, &opts) {
+        Ok((est, None)) => print_tx_estimate(Ok(est), &opts),
+        Ok((_, Some(rec))) => {
+            let verb = if how == eth::Replacement::Cancel { "Cancellation" } else { "Replacement" };
+            println!("{} sent: {}", verb, rec.hash);
+            println!("Check it with `bsec tx status {}`.", rec.hash);
+        }
+        Err(e) => handle_cli_error("Error replacing transaction", e),
+    }
+}
+
+/// Read the content and file metadata of a share, exiting on invalid input.
+fn resolve_share_content(input: ShareContentArgs) -> (String, secrets::ShareMeta) {
+    let ShareContentArgs { secret, content, file, as_kind, filename, bundle, no_export } = input;
+    let (secret_content, mut meta) = if let Some(ref manifest) = bundle {
+        if content.is_some() || file.is_some() || secret.is_some() {
+            eprintln!("Error: --bundle is mutually exclusive with --content/--file/positional secret.");
+            std::process::exit(1);
+        }
+        match materialize::load_bundle_members(manifest) {
+            Ok(members) => (
+                String::new(),
+                secrets::ShareMeta { members: Some(members), ..Default::default() },
+            ),
+            Err(e) => handle_cli_error("Error reading bundle manifest", e),
+        }
+    } else if let Some(c) = content {
+        (c, secrets::ShareMeta::default())
+    } else if let Some(ref path) = file {
+        // A file share becomes a tagged, materializable secret when --as is given or
+        // the extension is recognizable; otherwise it stays a plain text share.
+        match materialize::read_file_body(path) {
+            Ok((body, encoding)) => {
+                let kind = match as_kind {
+                    Some(ref k) => match materialize::parse_kind(k) {
+                        Ok(kind) => Some(kind),
+                        Err(e) => handle_cli_error("Error parsing --as", e),
+                    },
+                    None => Some(materialize::infer_kind(path)),
+                };
+                let fname = filename.clone().unwrap_or_else(|| {
+                    path.file_name().and_then(|n| n.to_str()).unwrap_or("secret").to_string()
+                });
+                let fname = match materialize::sanitize_basename(&fname) {
+                    Ok(f) => f,
+                    Err(e) => handle_cli_error("Error with --filename", e),
+                };
+                (
+                    body,
+                    secrets::ShareMeta {
+                        kind,
+                        filename: Some(fname),
+                        content_encoding: Some(encoding),
+                        ..Default::default()
+                    },
+                )
+            }
+            Err(e) => handle_cli_error("Error reading file", e),
+        }
+    } else if let Some(s) = secret {
+        (s, secrets::ShareMeta::default())
+    } else {
+        eprintln!("Error: Content to share is required. Use --content, --file, --bundle, or positional secret text.");
+        std::process::exit(1);
+    };
+    meta.no_export = no_export;
+    (secret_content, meta)
+}
+
+fn warn_public_share() {
+    eprintln!(
+        "Warning: 'public' secrets are wrapped with a well-known key and stored on \
+         public IPFS — anyone with the secret ID can read the content. Do not use \
+         --to public for data that must stay confidential; specify a recipient public key."
+    );
+}
+
+fn run_tx_build(call: TxBuildCommands) {
+    let (built, args) = match call {
+        TxBuildCommands::Share { input, ttl, max_reads, to, build } => {
+            let opts = tx_build_options(&build);
+            let (content, meta) = resolve_share_content(input);
+            if to == "public" {
+                warn_public_share();
+            }
+            match secrets::build_share(&content, &ttl, max_reads, &to, &build.from, meta, &opts, build.nonce) {
+                Ok((rec, unsigned)) => {
+                    eprintln!("Secret ID: {}", rec.id);
+                    eprintln!("Expires At: {}", rec.expires_at);
+                    (unsigned, build)
+                }
+                Err(e) => handle_cli_error("Error building share", e),
+            }
+        }
+        TxBuildCommands::Revoke { secret_id, build } => {
+            let opts = tx_build_options(&build);
+            let from = match eth::parse_address(&build.from) {
+                Ok(a) => a,
+                Err(e) => handle_cli_error("Error with --from", e),
+            };
+            match blockchain::build_revoke(&from, &secret_id, &opts, build.nonce) {
+                Ok(unsigned) => (unsigned, build),
+                Err(e) => handle_cli_error("Error building revocation", e),
+            }
+        }
+    };
+    print_portable_tx(&built);
+    write_portable_tx(&built, args.out.as_deref());
+    eprintln!("Sign it offline with `bsec tx sign <file>`.");
+}
+
+fn tx_build_options(build: &TxBuildArgs) -> eth::TxOptions {
+    if build.tx.dry_run {
+        eprintln!("Error: tx build never broadcasts; --dry-run does not apply.");
+        std::process::exit(1);
+    }
+    build.tx.options()
+}
+
+fn read_portable_tx(path: &Path) -> eth::PortableTx {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path.display(), e))
+        .and_then(|json| eth::PortableTx::from_json(&json));
+    match parsed {
+        Ok(tx) => tx,
+        Err(e) => handle_cli_error("Error reading transaction file", e),
+    }
+}
+
+fn write_portable_tx(tx: &eth::PortableTx, out: Option<&Path>) {
+    let json = match serde_json::to_string_pretty(tx) {
+        Ok(j) => j,
+        Err(e) => handle_cli_error("Error encoding transaction", e.into()),
+    };
+    match out {
+        Some(path) => match std::fs::write(path, json + "\n") {
+            Ok(()) => eprintln!("Wrote {}", path.display()),
+            Err(e) => handle_cli_error("Error writing transaction file", e.into()),
+        },
+        None => println!("{}", json),
+    }
+}
+
+/// What a portable transaction does, on stderr so stdout stays the JSON.
+fn print_portable_tx(tx: &eth::PortableTx) {
+    eprintln!("{} on chain {} (nonce {})", tx.describe(), tx.chain_id, tx.nonce);
+    eprintln!("  From: {}", tx.from);
+    eprintln!("  To: {}", if tx.to.is_empty() { "(contract creation)" } else { &tx.to });
+    eprintln!("  Max cost: {} (native units)", eth::format_units(tx.max_cost(), 18));
+}
+
+fn format_cache_limit(max_bytes: u64) -> String {
+    if max_bytes == 0 {
+        "unlimited".to_string()
+    } else {
+        format!("{} bytes", max_bytes)
+    }
+}
+
+/// Name the batch transaction(s) and exit 1 if any item failed.
+fn finish_batch(items: &[blockchain::BatchItem]) {
+    let mut hashes: Vec<&str> = items.iter().filter_map(|i| i.tx_hash.as_deref()).collect();
+    hashes.dedup();
+    match hashes.as_slice() {
+        [] => {}
+        [one] => println!("Transaction: {}", one),
+        many => println!("Transactions: {}", many.join(", ")),
+    }
+    let failed = items.iter().filter(|i| i.error.is_some()).count();
+    if failed > 0 {
+        eprintln!("Error: {} of {} items failed", failed, items.len());
+        std::process::exit(1);
+    }
+}
+
+/// Print which copies of a payload were removed by an unpin.
+fn print_unpin_report(report: &ipfs::UnpinReport) {
+    let mut removed: Vec<String> = report.providers.iter().map(|p| format!("{} pin", p)).collect();
+    if report.kubo {
+        removed.push("IPFS daemon pin".to_string());
+    }
+    if report.cache {
+        removed.push("local cache".to_string());
+    }
+    if let Some(e) = &report.error {
+        println!("  {}: not unpinned: {}", report.cid, e);
+    } else if removed.is_empty() {
+        println!("  {}: nothing to remove", report.cid);
+    } else {
+        println!("  {}: removed {}", report.cid, removed.join(", "));
+    }
+}
+
 fn handle_cli_error(prefix: &str, err: anyhow::Error) -> ! {
     eprintln!("{}: {}", prefix, err);
     if let Some(bsec_err) = err.downcast_ref::<errors::BsecError>() {
@@ -562,6 +1373,7 @@ fn main() {
             }
         }
 
+        #[allow(clippy::nonminimal_bool)]
         Some(Commands::Wallet {
             sub:
                 WalletCommands::Export {
@@ -589,13 +1401,82 @@ fn main() {
         Some(Commands::Config {
             network,
             rpc,
+            rpc_fallbacks,
+            chain_id,
             registry,
+            registry_check,
+            trust_registry_code_hash,
+            mock_dir,
             ipfs_gateway,
+            ipfs_gateways,
             ipfs_pinning,
+            add_pinning_provider,
+            remove_pinning_provider,
+            cache_max_size,
+            encrypt_cache,
             show,
             json,
+            action,
         }) => {
-            if show {
+            if let Some(ConfigAction::VerifyRegistry { json: verify_json }) = action {
+                let conf = network_config::load_network_config();
+                let v = match blockchain::verify_registry(&conf) {
+                    Ok(v) => v,
+                    Err(e) => handle_cli_error("Error verifying registry", e),
+                };
+                if verify_json {
+                    if let Ok(j) = serde_json::to_string_pretty(&v) {
+                        println!("{}", j);
+                    }
+                } else {
+                    println!("Registry: {} (chain ID {})", v.address, v.chain_id);
+                    println!("Code hash: {}", v.code_hash.as_deref().unwrap_or("none (no contract at this address)"));
+                    match v.matched {
+                        Some(source) => println!("Genuine: yes ({})", source),
+                        None if v.is_unverifiable() => println!(
+                            "Genuine: unknown. No code hash is known for this network; add the one above with \
+                             `bsec config --trust-registry-code-hash` once you have checked it."
+                        ),
+                        None => println!("Genuine: NO. Do not use this registry unless you deployed it yourself."),
+                    }
+                    println!("Policy: {}", conf.registry_check.as_str());
+                }
+                // Only `enforce` treats a registry with nothing to compare against as a failure.
+                let lenient = v.is_unverifiable() && conf.registry_check != network_config::RegistryCheck::Enforce;
+                if !v.is_genuine() && !lenient {
+                    std::process::exit(1);
+                }
+                return;
+            }
+            if let Some(ConfigAction::Check { json: check_json }) = action {
+                let conf = network_config::load_network_config();
+                let reports: Vec<eth::EndpointReport> = conf
+                    .effective_rpc_urls()
+                    .iter()
+                    .map(|u| eth::check_endpoint(u, conf.chain_id))
+                    .collect();
+                if check_json {
+                    if let Ok(j) = serde_json::to_string_pretty(&reports) {
+                        println!("{}", j);
+                    }
+                } else {
+                    println!("RPC endpoints for {} (chain ID {}):", conf.network, conf.chain_id);
+                    for r in &reports {
+                        match (&r.error, r.head_block) {
+                            (None, Some(head)) => println!("  OK    {} {} ms, head block {}", r.url, r.latency_ms, head),
+                            (err, _) => println!(
+                                "  FAIL  {} {} ms: {}",
+                                r.url,
+                                r.latency_ms,
+                                err.as_deref().unwrap_or("unknown error")
+                            ),
+                        }
+                    }
+                }
+                if !reports.iter().any(|r| r.ok) {
+                    std::process::exit(1);
+                }
+            } else if show {
                 let conf = network_config::load_network_config();
                 if json {
                     if let Ok(j) = serde_json::to_string_pretty(&conf) {
@@ -607,15 +1488,59 @@ fn main() {
                     println!("Network: {}", conf.network);
                     println!("Chain ID: {}", conf.chain_id);
                     println!("RPC URL: {}", conf.rpc_url);
+                    for fallback in &conf.rpc_urls {
+                        println!("  Fallback RPC: {}", fallback);
+                    }
                     println!("Registry: {}", conf.registry_address);
+                    println!("Registry Check: {}", conf.registry_check.as_str());
+                    for h in &conf.trusted_registry_code_hashes {
+                        println!("  Trusted code hash: {}", h);
+                    }
                     println!("IPFS Gateway: {}", conf.ipfs.gateway);
+                    let health = gateway::load_health();
+                    let now = wallet::current_timestamp();
+                    println!("IPFS Gateways (raced):");
+                    for g in conf.ipfs.effective_gateways() {
+                        match health.get(&g) {
+                            Some(h) => println!(
+                                "  {} [ok {}, failed {}{}]",
+                                g,
+                                h.successes,
+                                h.failures,
+                                if h.is_demoted(now) { ", demoted" } else { "" }
+                            ),
+                            None => println!("  {}", g),
+                        }
+                    }
                     println!(
                         "IPFS Pinning Service: {}",
                         conf.ipfs.pinning_service.as_deref().unwrap_or("None")
                     );
+                    for p in &conf.ipfs.psa_providers {
+                        println!("  Pinning provider {}: {}", p.name, p.endpoint);
+                    }
+                    println!("IPFS Cache Limit: {}", format_cache_limit(conf.ipfs.cache_max_bytes));
+                    println!("IPFS Cache Encrypted: {}", conf.ipfs.encrypt_cache);
                 }
             } else {
-                match network_config::update_network_config(network, rpc, registry, ipfs_gateway, ipfs_pinning) {
+                let update = network_config::ConfigUpdate {
+                    network,
+                    rpc,
+                    rpc_fallbacks,
+                    chain_id,
+                    registry,
+                    registry_check,
+                    trust_registry_code_hash,
+                    mock_dir,
+                    ipfs_gateway,
+                    ipfs_gateways,
+                    ipfs_pinning,
+                    add_psa_provider: add_pinning_provider,
+                    remove_psa_provider: remove_pinning_provider,
+                    cache_max_bytes: cache_max_size,
+                    encrypt_cache,
+                };
+                match network_config::update_network_config(update) {
                     Ok(conf) => {
                         if json {
                             if let Ok(j) = serde_json::to_string_pretty(&conf) {
@@ -631,87 +1556,58 @@ fn main() {
         }
 
         Some(Commands::Share {
-            secret,
-            content,
-            file,
-            as_kind,
-            filename,
-            bundle,
-            no_export,
+            input,
             ttl,
             max_reads,
             to,
             password,
+            tx,
         }) => {
-            // Resolve the secret content and its file-materialization metadata.
-            let (secret_content, mut meta) = if let Some(ref manifest) = bundle {
-                if content.is_some() || file.is_some() || secret.is_some() {
-                    eprintln!("Error: --bundle is mutually exclusive with --content/--file/positional secret.");
-                    std::process::exit(1);
-                }
-                match materialize::load_bundle_members(manifest) {
-                    Ok(members) => (
-                        String::new(),
-                        secrets::ShareMeta { members: Some(members), ..Default::default() },
-                    ),
-                    Err(e) => handle_cli_error("Error reading bundle manifest", e),
-                }
-            } else if let Some(c) = content {
-                (c, secrets::ShareMeta::default())
-            } else if let Some(ref path) = file {
-                // A file share becomes a tagged, materializable secret when --as is given or
-                // the extension is recognizable; otherwise it stays a plain text share.
-                match materialize::read_file_body(path) {
-                    Ok((body, encoding)) => {
-                        let kind = match as_kind {
-                            Some(ref k) => match materialize::parse_kind(k) {
-                                Ok(kind) => Some(kind),
-                                Err(e) => handle_cli_error("Error parsing --as", e),
-                            },
-                            None => Some(materialize::infer_kind(path)),
-                        };
-                        let fname = filename.clone().unwrap_or_else(|| {
-                            path.file_name().and_then(|n| n.to_str()).unwrap_or("secret").to_string()
-                        });
-                        let fname = match materialize::sanitize_basename(&fname) {
-                            Ok(f) => f,
-                            Err(e) => handle_cli_error("Error with --filename", e),
-                        };
-                        (
-                            body,
-                            secrets::ShareMeta {
-                                kind,
-                                filename: Some(fname),
-                                content_encoding: Some(encoding),
-                                ..Default::default()
-                            },
-                        )
-                    }
-                    Err(e) => handle_cli_error("Error reading file", e),
-                }
-            } else if let Some(s) = secret {
-                (s, secrets::ShareMeta::default())
-            } else {
-                eprintln!("Error: Content to share is required. Use --content, --file, --bundle, or positional secret text.");
-                std::process::exit(1);
-            };
-            meta.no_export = no_export;
+            let tx = tx.options();
+            let (secret_content, meta) = resolve_share_content(input);
 
             let pwd = get_password_or_prompt(password, "Enter wallet password (if encrypted): ");
-            let recipient = to.unwrap_or_else(|| "public".to_string());
-            if recipient == "public" {
-                eprintln!(
-                    "Warning: 'public' secrets are wrapped with a well-known key and stored on \
-                     public IPFS — anyone with the secret ID can read the content. Do not use \
-                     --to public for data that must stay confidential; specify a recipient public key."
-                );
+            let mut recipients = to;
+            if recipients.is_empty() {
+                recipients.push("public".to_string());
+            }
+            if recipients.iter().any(|r| r == "public") {
+                warn_public_share();
             }
             let sender = match wallet::get_wallet_info(pwd.as_deref()) {
                 Ok(w) => w.address.clone(),
                 Err(e) => handle_cli_error("Error getting wallet info", e),
             };
 
-            match secrets::share_secret(&secret_content, &ttl, max_reads, &recipient, &sender, pwd.as_deref(), meta) {
+            if recipients.len() > 1 {
+                if tx.dry_run {
+                    print_tx_estimate(secrets::estimate_share_many(&ttl, max_reads, &recipients, pwd.as_deref(), &tx), &tx);
+                    return;
+                }
+                match secrets::share_secret_many(&secret_content, &ttl, max_reads, &recipients, &sender, pwd.as_deref(), meta, &tx) {
+                    Ok(shared) => {
+                        let items: Vec<blockchain::BatchItem> = shared.iter().map(|(_, item)| item.clone()).collect();
+                        println!("Shared with {} of {} recipients.", items.iter().filter(|i| i.error.is_none()).count(), items.len());
+                        for (rec, item) in &shared {
+                            match &item.error {
+                                None => println!("  {}: {}", rec.recipient, rec.id),
+                                Some(e) => println!("  {}: failed: {}", rec.recipient, e),
+                            }
+                        }
+                        finish_batch(&items);
+                    }
+                    Err(e) => handle_cli_error("Error sharing secrets", e),
+                }
+                return;
+            }
+            let recipient = recipients.remove(0);
+
+            if tx.dry_run {
+                print_tx_estimate(secrets::estimate_share(&ttl, max_reads, &recipient, pwd.as_deref(), &tx), &tx);
+                return;
+            }
+
+            match secrets::share_secret(&secret_content, &ttl, max_reads, &recipient, &sender, pwd.as_deref(), meta, &tx) {
                 Ok(rec) => {
                     println!("Secret shared successfully!");
                     println!("Secret ID: {}", rec.id);
@@ -728,14 +1624,20 @@ fn main() {
             output,
             password,
             json,
+            tx,
         }) => {
+            let tx = tx.options();
             let pwd = get_password_or_prompt(password, "Enter wallet password (if encrypted): ");
+            if tx.dry_run {
+                print_tx_estimate(secrets::estimate_read(&secret_id, pwd.as_deref(), &tx), &tx);
+                return;
+            }
             let user_addr = match wallet::get_wallet_info(pwd.as_deref()) {
                 Ok(w) => w.address.clone(),
                 Err(e) => handle_cli_error("Error loading wallet", e),
             };
 
-            match secrets::view_payload(&secret_id, &user_addr, pwd.as_deref()) {
+            match secrets::view_payload(&secret_id, &user_addr, pwd.as_deref(), &tx) {
                 Ok(payload) => {
                     let content = payload.content;
                     if output.is_some() && payload.no_export {
@@ -779,15 +1681,21 @@ fn main() {
             as_fmt,
             password,
             force,
+            tx,
         }) => {
+            let tx = tx.options();
             let pwd = get_password_or_prompt(password, "Enter wallet password (if encrypted): ");
+            if tx.dry_run {
+                print_tx_estimate(secrets::estimate_read(&secret_id, pwd.as_deref(), &tx), &tx);
+                return;
+            }
             let user_addr = match wallet::get_wallet_info(pwd.as_deref()) {
                 Ok(w) => w.address.clone(),
                 Err(e) => handle_cli_error("Error loading wallet", e),
             };
 
             // One materialize = one authorized read (a bundle counts as one, not N).
-            let payload = match secrets::view_payload(&secret_id, &user_addr, pwd.as_deref()) {
+            let payload = match secrets::view_payload(&secret_id, &user_addr, pwd.as_deref(), &tx) {
                 Ok(p) => p,
                 Err(e) => handle_cli_error("Error reading secret", e),
             };
@@ -881,28 +1789,275 @@ fn main() {
             }
         }
 
-        Some(Commands::Revoke { secret_id, password }) => {
+        Some(Commands::Revoke { secret_id, recipient, password, tx, .. }) => {
+            let tx = tx.options();
             let pwd = get_password_or_prompt(password, "Enter wallet password (if encrypted): ");
-            match secrets::revoke_secret(&secret_id, pwd.as_deref()) {
-                Ok(_) => println!("Secret '{}' has been revoked.", secret_id),
+            let Some(secret_id) = secret_id else {
+                if tx.dry_run {
+                    print_tx_estimate(secrets::estimate_revoke_sent(recipient.as_deref(), pwd.as_deref(), &tx), &tx);
+                    return;
+                }
+                match secrets::revoke_sent(recipient.as_deref(), pwd.as_deref(), &tx) {
+                    Ok(revoked) if revoked.is_empty() => println!("No readable secrets shared by this wallet match."),
+                    Ok(revoked) => {
+                        let items: Vec<blockchain::BatchItem> = revoked.iter().map(|(item, _)| item.clone()).collect();
+                        println!("Revoked {} of {} secrets.", items.iter().filter(|i| i.error.is_none()).count(), items.len());
+                        for (item, report) in &revoked {
+                            match (&item.error, report) {
+                                (Some(e), _) => println!("{}: failed: {}", item.secret_id, e),
+                                (None, Some(report)) => {
+                                    println!("{}: revoked", item.secret_id);
+                                    print_unpin_report(report);
+                                }
+                                (None, None) => println!("{}: revoked", item.secret_id),
+                            }
+                        }
+                        finish_batch(&items);
+                    }
+                    Err(e) => handle_cli_error("Error revoking secrets", e),
+                }
+                return;
+            };
+            if tx.dry_run {
+                print_tx_estimate(secrets::estimate_revoke_secret(&secret_id, pwd.as_deref(), &tx), &tx);
+                return;
+            }
+            match secrets::revoke_secret(&secret_id, pwd.as_deref(), &tx) {
+                Ok(report) => {
+                    println!("Secret '{}' has been revoked.", secret_id);
+                    print_unpin_report(&report);
+                }
                 Err(e) => handle_cli_error("Error revoking secret", e),
             }
         }
 
+        Some(Commands::Gc { dry_run, json }) => match secrets::gc_secrets(dry_run) {
+            Ok(swept) => {
+                if json {
+                    if let Ok(j) = serde_json::to_string_pretty(&swept) {
+                        println!("{}", j);
+                    }
+                } else if swept.is_empty() {
+                    println!("Nothing to collect.");
+                } else {
+                    let verb = if dry_run { "Would collect" } else { "Collected" };
+                    println!("{} {} secret(s):", verb, swept.len());
+                    for entry in &swept {
+                        println!("{} ({})", entry.id, entry.reason);
+                        if !dry_run {
+                            print_unpin_report(&entry.report);
+                        }
+                    }
+                }
+            }
+            Err(e) => handle_cli_error("Error collecting secrets", e),
+        },
+
+        Some(Commands::Cache { sub }) => match sub {
+            CacheCommands::Stats { json } => {
+                let stats = ipfs::cache_stats();
+                if json {
+                    if let Ok(j) = serde_json::to_string_pretty(&stats) {
+                        println!("{}", j);
+                    }
+                } else {
+                    println!("Cache directory: {}", stats.dir.display());
+                    println!("Entries: {} ({} encrypted)", stats.entries, stats.encrypted_entries);
+                    println!("Size: {} / {}", stats.total_bytes, format_cache_limit(stats.max_bytes));
+                    println!("Encrypt at rest: {}", stats.encrypt_cache);
+                }
+            }
+            CacheCommands::Prune { json } => match secrets::prune_cache() {
+                Ok(report) => {
+                    if json {
+                        if let Ok(j) = serde_json::to_string_pretty(&report) {
+                            println!("{}", j);
+                        }
+                    } else {
+                        println!(
+                            "Pruned {} unreadable entr{} and {} over-limit entr{} ({} bytes).",
+                            report.unreadable_entries,
+                            if report.unreadable_entries == 1 { "y" } else { "ies" },
+                            report.lru_entries,
+                            if report.lru_entries == 1 { "y" } else { "ies" },
+                            report.lru_bytes
+                        );
+                    }
+                }
+                Err(e) => handle_cli_error("Error pruning cache", e),
+            },
+            CacheCommands::Clear => {
+                let (entries, bytes) = ipfs::clear_cache();
+                println!("Cleared {} cache entr{} ({} bytes).", entries, if entries == 1 { "y" } else { "ies" }, bytes);
+            }
+        },
+
+        Some(Commands::Contact { sub }) => match sub {
+            ContactCommands::Add { name, pubkey } => match contacts::add_contact(&name, &pubkey) {
+                Ok(()) => println!("Saved contact '{}'.", name),
+                Err(e) => handle_cli_error("Error saving contact", e),
+            },
+            ContactCommands::List { json } => {
+                let list = match contacts::load_contacts() {
+                    Ok(list) => list,
+                    Err(e) => handle_cli_error("Error loading contacts", e),
+                };
+                if json {
+                    if let Ok(j) = serde_json::to_string_pretty(&list) {
+                        println!("{}", j);
+                    }
+                } else if list.is_empty() {
+                    println!("No contacts. Add one with `bsec contact add <name> <pubkey>`.");
+                } else {
+                    for (name, pubkey) in &list {
+                        println!("{}  {}", name, pubkey);
+                    }
+                }
+            }
+            ContactCommands::Remove { name } => match contacts::remove_contact(&name) {
+                Ok(true) => println!("Removed contact '{}'.", name),
+                Ok(false) => handle_cli_error("Error removing contact", anyhow::anyhow!("no contact named '{}'", name)),
+                Err(e) => handle_cli_error("Error removing contact", e),
+            },
+        },
+
+        Some(Commands::Registry { sub }) => match sub {
+            RegistryCommands::Deploy { bytecode, no_save, password, json, tx } => {
+                let tx = tx.options();
+                let priv_bytes = unlock_wallet_key(password);
+                if tx.dry_run {
+                    print_tx_estimate(blockchain::estimate_deploy_registry(&priv_bytes, bytecode.as_deref(), &tx), &tx);
+                    return;
+                }
+                match blockchain::deploy_registry(&priv_bytes, bytecode.as_deref(), !no_save, &tx) {
+                    Ok(report) if json => {
+                        if let Ok(j) = serde_json::to_string_pretty(&report) {
+                            println!("{}", j);
+                        }
+                    }
+                    Ok(report) => {
+                        println!("Registry deployed at {} on chain {}.", report.address, report.chain_id);
+                        println!("Transaction: {}", report.tx_hash);
+                        println!("Bytecode: {}", report.bytecode_source);
+                        if report.saved {
+                            println!("Saved as registry_address in the network config.");
+                        }
+                    }
+                    Err(e) => handle_cli_error("Error deploying registry", e),
+                }
+            }
+        },
+
+        Some(Commands::Relay { sub }) => match sub {
+            RelayCommands::Serve { listen, allow, open, password, tx } => {
+                let conf = network_config::load_network_config();
+                let registry = match blockchain::trusted_registry_address(&conf) {
+                    Ok(r) => r,
+                    Err(e) => handle_cli_error("Error checking registry", e),
+                };
+                let allow = match allow.iter().map(|a| eth::parse_address(a)).collect::<anyhow::Result<Vec<_>>>() {
+                    Ok(a) => a,
+                    Err(e) => handle_cli_error("Invalid --allow address", e),
+                };
+                let relayer = relay::Relayer { conf, registry, key: unlock_wallet_key(password), tx: tx.options(), allow, open };
+                if let Err(e) = relay::serve(relayer, &listen) {
+                    handle_cli_error("Error running relayer", e);
+                }
+            }
+        },
+
+        Some(Commands::Tx { sub }) => match sub {
+            TxCommands::List { all, json } => {
+                let conf = network_config::load_network_config();
+                let entries = match eth::tx_journal_refreshed(&conf) {
+                    Ok(e) => e,
+                    Err(e) => handle_cli_error("Error reading transaction journal", e),
+                };
+                let shown: Vec<&tx_journal::TxRecord> = entries
+                    .iter()
+                    .filter(|r| r.chain_id == conf.chain_id && (all || r.status == tx_journal::TxStatus::Pending))
+                    .collect();
+                if json {
+                    if let Ok(j) = serde_json::to_string_pretty(&shown) {
+                        println!("{}", j);
+                    }
+                } else if shown.is_empty() {
+                    println!("No {}transactions on chain {}.", if all { "" } else { "pending " }, conf.chain_id);
+                } else {
+                    for rec in shown {
+                        print_tx_record(rec);
+                    }
+                }
+            }
+            TxCommands::Status { hash, json } => {
+                let conf = network_config::load_network_config();
+                let entries = match eth::tx_journal_refreshed(&conf) {
+                    Ok(e) => e,
+                    Err(e) => handle_cli_error("Error reading transaction journal", e),
+                };
+                match tx_journal::find(&entries, &hash) {
+                    Ok(rec) if json => {
+                        if let Ok(j) = serde_json::to_string_pretty(rec) {
+                            println!("{}", j);
+                        }
+                    }
+                    Ok(rec) => print_tx_record(rec),
+                    Err(e) => handle_cli_error("Error", e),
+                }
+            }
+            TxCommands::Speedup { hash, password, tx } => run_tx_replacement(&hash, password, &tx, eth::Replacement::SpeedUp),
+            TxCommands::Cancel { hash, password, tx } => run_tx_replacement(&hash, password, &tx, eth::Replacement::Cancel),
+            TxCommands::Build { call } => run_tx_build(*call),
+            TxCommands::Sign { file, out, password } => {
+                let unsigned = read_portable_tx(&file);
+                print_portable_tx(&unsigned);
+                let priv_bytes = unlock_wallet_key(password);
+                match unsigned.sign(&priv_bytes) {
+                    Ok(signed) => {
+                        write_portable_tx(&signed, out.as_deref());
+                        eprintln!("Signed {}. Broadcast it from an online host with `bsec tx broadcast <file>`.", signed.hash.as_deref().unwrap_or_default());
+                    }
+                    Err(e) => handle_cli_error("Error signing transaction", e),
+                }
+            }
+            TxCommands::Broadcast { file } => {
+                let signed = read_portable_tx(&file);
+                match blockchain::broadcast_signed_tx(&signed) {
+                    Ok(hash) => println!("Transaction confirmed: {}", hash),
+                    Err(e) => handle_cli_error("Error broadcasting transaction", e),
+                }
+            }
+        },
+
         Some(Commands::Hide {
             secret_id,
             user,
             all,
             password,
+            tx,
         }) => {
+            let tx = tx.options();
             let pwd = get_password_or_prompt(password, "Enter wallet password (if encrypted): ");
+            if tx.dry_run {
+                // Hiding sends one revocation per matching secret; estimate needs a single target.
+                match (all, secret_id.as_deref()) {
+                    (false, Some(id)) => {
+                        print_tx_estimate(secrets::estimate_revoke_secret(id, pwd.as_deref(), &tx), &tx);
+                        return;
+                    }
+                    _ => {
+                        eprintln!("Error: hide --dry-run needs a single secret ID.");
+                        std::process::exit(1);
+                    }
+                }
+            }
             let user_addr = match wallet::get_wallet_info(pwd.as_deref()) {
                 Ok(w) => w.address.clone(),
                 Err(e) => handle_cli_error("Error loading wallet", e),
             };
 
             let target_id = if all { None } else { secret_id.as_deref() };
-            match secrets::hide_secret(target_id, user.as_deref(), &user_addr, pwd.as_deref()) {
+            match secrets::hide_secret(target_id, user.as_deref(), &user_addr, pwd.as_deref(), &tx) {
                 Ok(count) => println!("Hidden {} secret(s).", count),
                 Err(e) => handle_cli_error("Error hiding secret", e),
             }
@@ -956,9 +2111,10 @@ fn main() {
             }
         }
 
-        Some(Commands::Encrypt { file, out, password }) => {
-            let pwd = get_password_or_prompt(password, "Enter encryption password: ");
-            match env_file::encrypt_env_file(&file, out.as_deref(), pwd.as_deref()) {
+        Some(Commands::Encrypt { file, out, per_value, to, password }) => {
+            // Encrypting to wallets needs no password.
+            let pwd = if to.is_empty() { get_password_or_prompt(password, "Enter encryption password: ") } else { None };
+            match env_file::encrypt_env_file(&file, out.as_deref(), pwd.as_deref(), per_value, &to) {
                 Ok(target) => println!("Encrypted file saved to '{}'.", target.display()),
                 Err(e) => handle_cli_error("Error encrypting file", e),
             }
@@ -972,14 +2128,65 @@ fn main() {
             }
         }
 
+        Some(Commands::Edit { file, password }) => {
+            let pwd = get_password_or_prompt(password, "Enter decryption password: ");
+            match env_file::edit_encrypted_env_file(&file, pwd.as_deref()) {
+                Ok(true) => println!("Saved and re-encrypted '{}'.", file.display()),
+                Ok(false) => println!("No changes; '{}' left as it was.", file.display()),
+                Err(e) => handle_cli_error("Error editing file", e),
+            }
+        }
+
+        Some(Commands::Rekey { paths, recursive, password, new_password }) => {
+            let old = get_password_or_prompt(password, "Enter current password (empty to look it up per file): ");
+            let new = read_new_password(new_password);
+            let outcomes = match env_file::rekey_env_files(&paths, recursive, old.as_deref(), &new) {
+                Ok(outcomes) => outcomes,
+                Err(e) => handle_cli_error("Error rekeying", e),
+            };
+            let (mut rotated, mut skipped, mut failed) = (0, 0, 0);
+            for (path, outcome) in &outcomes {
+                match outcome {
+                    env_file::Rekeyed::Rotated => {
+                        rotated += 1;
+                        println!("  rotated  {}", path.display());
+                    }
+                    env_file::Rekeyed::Skipped(why) => {
+                        skipped += 1;
+                        println!("  skipped  {} ({})", path.display(), why);
+                    }
+                    env_file::Rekeyed::Failed(e) => {
+                        failed += 1;
+                        eprintln!("  failed   {}: {}", path.display(), e);
+                    }
+                }
+            }
+            println!("Rotated {} file(s), skipped {}, failed {}.", rotated, skipped, failed);
+            if failed > 0 {
+                std::process::exit(2);
+            }
+        }
+
         Some(Commands::Run {
             env,
             secret,
             password,
+            tx,
             command,
         }) => {
+            let tx = tx.options();
             let pwd = get_password_or_prompt(password, "Enter wallet/env password (if encrypted): ");
-            match env_file::run_with_envs(env.as_deref(), secret.as_deref(), &command, pwd.as_deref()) {
+            if tx.dry_run {
+                let target = secret
+                    .clone()
+                    .or_else(|| project_config::load_project_config().and_then(|p| p.secret_id));
+                match target {
+                    Some(id) => print_tx_estimate(secrets::estimate_read(&id, pwd.as_deref(), &tx), &tx),
+                    None => println!("Dry run: no shared secret to read, so no transaction would be sent."),
+                }
+                return;
+            }
+            match env_file::run_with_envs(env.as_deref(), secret.as_deref(), &command, pwd.as_deref(), &tx) {
                 Ok(code) => std::process::exit(code),
                 Err(e) => handle_cli_error("Error running command", e),
             }
@@ -991,6 +2198,42 @@ fn main() {
             }
         }
 
+        Some(Commands::Get { key, file, password }) => {
+            let pwd = env_file_password(&file, password);
+            match env_file::get_env_var(&file, &key, pwd.as_deref()) {
+                Ok(value) => println!("{}", value),
+                Err(e) => handle_cli_error("Error reading variable", e),
+            }
+        }
+
+        Some(Commands::Set { assignment, file, password }) => {
+            let (key, value) = match assignment.split_once('=') {
+                Some((key, value)) => (key.to_string(), zeroize::Zeroizing::new(value.to_string())),
+                None => (assignment.clone(), read_value_from_stdin(&assignment)),
+            };
+            let pwd = env_file_password(&file, password);
+            match env_file::set_env_var(&file, &key, &value, pwd.as_deref()) {
+                Ok(()) => println!("Set {} in '{}'.", key, file.display()),
+                Err(e) => handle_cli_error("Error setting variable", e),
+            }
+        }
+
+        Some(Commands::Unset { key, file, password }) => {
+            let pwd = env_file_password(&file, password);
+            match env_file::unset_env_var(&file, &key, pwd.as_deref()) {
+                Ok(()) => println!("Removed {} from '{}'.", key, file.display()),
+                Err(e) => handle_cli_error("Error removing variable", e),
+            }
+        }
+
+        Some(Commands::Rename { old, new, file, password }) => {
+            let pwd = env_file_password(&file, password);
+            match env_file::rename_env_var(&file, &old, &new, pwd.as_deref()) {
+                Ok(()) => println!("Renamed {} to {} in '{}'.", old, new, file.display()),
+                Err(e) => handle_cli_error("Error renaming variable", e),
+            }
+        }
+
         Some(Commands::Search { name, path }) => {
             helpers::search_file(&path, &name);
         }
@@ -129,7 +129,7 @@ pub fn extract_schema_from(content: &str, fmt: OutputFormat) -> Result<String> {
             }
         }
         OutputFormat::Env | OutputFormat::Cred => {
-            let map = crate::env_file::parse_env_content(content);
+            let map = crate::env_file::parse_env_content(content)?;
             if map.is_empty() {
                 return Err(anyhow!("secret has no KEY=VALUE pairs; no schema to extract"));
             }
@@ -422,7 +422,8 @@ fn is_file_kind(kind: SecretKind) -> bool {
     matches!(kind, SecretKind::Pem | SecretKind::Json | SecretKind::Cred)
 }
 
-/// RAII temp dir (0700) that wipes itself on Drop; used by `run --secret` staging.
+/// RAII temp dir (0700) that wipes itself on Drop; used by `run --secret` staging and
+/// `bsec edit`.
 pub struct StagedDir {
     path: PathBuf,
 }
@@ -453,10 +454,14 @@ impl StagedDir {
 
     /// Write one already-decrypted member into the staging dir as a 0600 file; return its path.
     pub fn stage_member(&self, m: &BundleMember) -> Result<PathBuf> {
-        let name = sanitize_basename(&m.filename)?;
-        let path = self.path.join(name);
         let bytes = decode_body(&m.content, &m.encoding)?;
-        crate::wallet::write_secure_file(&path, &bytes)?;
+        self.stage_file(&m.filename, &bytes)
+    }
+
+    /// Write plaintext into the staging dir as a 0600 file named `name`; return its path.
+    pub fn stage_file(&self, name: &str, bytes: &[u8]) -> Result<PathBuf> {
+        let path = self.path.join(sanitize_basename(name)?);
+        crate::wallet::write_secure_file(&path, bytes)?;
         // Register the exact file so a SIGINT/SIGTERM handler can unlink it (async-signal-safe)
         // before the staging dir is rmdir'd, even though Drop won't run on the `_exit` path.
         register_staged_file(&path);
@@ -648,7 +653,7 @@ pub fn stage_and_envs(
         }
         // env-kind members also contribute their KEY=VAL pairs to the child environment.
         if m.kind == SecretKind::Env && m.encoding == "utf8" {
-            for (k, v) in crate::env_file::parse_env_content(&m.content) {
+            for (k, v) in crate::env_file::parse_env_content(&m.content)? {
                 envs.insert(k, v);
             }
         }
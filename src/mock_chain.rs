@@ -0,0 +1,388 @@
+//! In-process registry and IPFS store for `network = "mock"`.
+//!
+//! Tests and demos run share -> view -> revoke without a node or an IPFS daemon. The
+//! registry is a JSON file applying the same rules, in the same order, as
+//! `BsecSecretRegistry.sol`, and failing with the same `BsecError`s the contract's custom
+//! errors decode to (`apply`, which `team_registry` replays signed events through). Payloads
+//! are stored by content under a real CIDv1 (raw, sha2-256).
+//!
+//! State lives in the directory named by the config's `mock_dir`, `<BSEC_HOME>/mock` when
+//! empty, so several wallets (`BSEC_HOME`s) can share one mock chain. There are no
+//! fees, nonces or signatures: fee flags and `--relayer` have no effect, and fee estimates,
+//! unsigned builds and relaying need a real chain.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use crate::blockchain::{bytes_to_checksum_address, keccak256, RegistryBackend};
+use crate::errors::BsecError;
+use crate::eth::{self, DecodedSecretInfo, TxOptions};
+use crate::ipfs::{PayloadStore, UnpinReport};
+use crate::network_config::NetworkConfig;
+use crate::relay::Action;
+use crate::wallet::bytes_to_hex;
+
+/// Directory holding the mock chain of `conf`.
+pub fn mock_dir(conf: &NetworkConfig) -> PathBuf {
+    match conf.mock_dir.trim() {
+        "" => crate::wallet::get_app_dir().join("mock"),
+        dir => PathBuf::from(dir),
+    }
+}
+
+/// A secret as the contract stores it. Addresses are lowercase 0x-hex.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SecretRecord {
+    sender: String,
+    recipient: String,
+    ipfs_cid: String,
+    created_at: u64,
+    expires_at: u64,
+    max_reads: u32,
+    read_count: u32,
+    revoked: bool,
+    is_public: bool,
+}
+
+/// Secrets by 0x-hex id.
+pub type Secrets = BTreeMap<String, SecretRecord>;
+
+/// The mock registry of one directory.
+pub struct MockRegistry {
+    dir: PathBuf,
+}
+
+impl MockRegistry {
+    pub fn open(conf: &NetworkConfig) -> Result<Self> {
+        let dir = mock_dir(conf);
+        fs::create_dir_all(&dir).map_err(|e| anyhow!("failed to create mock chain {}: {}", dir.display(), e))?;
+        Ok(Self { dir })
+    }
+
+    fn state_file(&self) -> PathBuf {
+        self.dir.join("registry.json")
+    }
+
+    /// The stored secrets; none before the first write. A file that does not parse is an
+    /// error, not an empty chain that the next write would overwrite.
+    fn load(&self) -> Result<Secrets> {
+        let path = self.state_file();
+        match fs::read_to_string(&path) {
+            Ok(s) => serde_json::from_str(&s)
+                .map_err(|e| BsecError::ParseError(format!("mock chain {}: {}", path.display(), e)).into()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Secrets::new()),
+            Err(e) => Err(anyhow!("failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Apply `f` to the stored secrets under an exclusive lock, saving only if it succeeds,
+    /// so a failed write leaves the state untouched like a reverted transaction.
+    fn transact<T>(&self, f: impl FnOnce(&mut Secrets, u64) -> Result<T>) -> Result<T> {
+        let lock = lock_file(&self.dir.join("registry.lock"))?;
+        let mut secrets = self.load()?;
+        let out = f(&mut secrets, crate::wallet::current_timestamp())?;
+        crate::wallet::write_secure_file(&self.state_file(), serde_json::to_string_pretty(&secrets)?.as_bytes())?;
+        drop(lock);
+        Ok(out)
+    }
+}
+
+fn lock_file(path: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| anyhow!("failed to open {}: {}", path.display(), e))?;
+    file.lock().map_err(|e| anyhow!("failed to lock {}: {}", path.display(), e))?;
+    Ok(file)
+}
+
+fn id_key(id: &[u8; 32]) -> String {
+    format!("0x{}", bytes_to_hex(id))
+}
+
+fn checksum(addr_hex: &str) -> String {
+    eth::parse_address(addr_hex).map(|a| bytes_to_checksum_address(&a)).unwrap_or_else(|_| addr_hex.to_string())
+}
+
+/// Apply `action` by `from` (lowercase 0x-hex) at `now` with the contract's checks, failing
+/// with the error the contract would revert with.
+pub fn apply(secrets: &mut Secrets, now: u64, from: &str, action: &Action) -> Result<()> {
+    let id = action.secret_id()?;
+    match action {
+        Action::ShareSecret { .. } => share(secrets, now, from, action),
+        Action::RecordRead { .. } => record_read(secrets, now, from, &id),
+        Action::RevokeSecret { .. } => revoke(secrets, from, &id),
+    }
+}
+
+/// `getSecretInfo` of `secret_id` at `now`.
+pub fn secret_info_at(secrets: &Secrets, secret_id: &[u8; 32], now: u64) -> Result<DecodedSecretInfo> {
+    let record = secrets
+        .get(&id_key(secret_id))
+        .ok_or_else(|| BsecError::SecretNotFound(eth::display_secret_id(secret_id)))?;
+    Ok(DecodedSecretInfo {
+        sender: eth::parse_address(&record.sender)?,
+        recipient: eth::parse_address(&record.recipient)?,
+        is_expired: now > record.expires_at,
+        limit_reached: !record.is_public && record.read_count >= record.max_reads,
+        ipfs_cid: record.ipfs_cid.clone(),
+        created_at: record.created_at,
+        expires_at: record.expires_at,
+        max_reads: record.max_reads,
+        read_count: record.read_count,
+        revoked: record.revoked,
+        is_public: record.is_public,
+    })
+}
+
+/// `_share`: the sender is the wallet.
+fn share(secrets: &mut Secrets, now: u64, sender: &str, action: &Action) -> Result<()> {
+    let Action::ShareSecret { recipient, ipfs_cid, expires_at, max_reads, is_public, .. } = action else {
+        unreachable!("share() is only called with ShareSecret");
+    };
+    let id = action.secret_id()?;
+    if id == [0u8; 32] || ipfs_cid.is_empty() {
+        return Err(BsecError::InvalidParameters.into());
+    }
+    if secrets.contains_key(&id_key(&id)) {
+        return Err(BsecError::SecretAlreadyExists(eth::display_secret_id(&id)).into());
+    }
+    if *expires_at <= now {
+        return Err(BsecError::InvalidParameters.into());
+    }
+    secrets.insert(
+        id_key(&id),
+        SecretRecord {
+            sender: sender.to_string(),
+            recipient: eth::addr_hex(&eth::parse_address(recipient)?),
+            ipfs_cid: ipfs_cid.clone(),
+            created_at: now,
+            expires_at: *expires_at,
+            max_reads: *max_reads,
+            read_count: 0,
+            revoked: false,
+            is_public: *is_public,
+        },
+    );
+    Ok(())
+}
+
+/// `_recordRead`: public secrets are neither read-limited nor restricted to a viewer.
+fn record_read(secrets: &mut Secrets, now: u64, viewer: &str, id: &[u8; 32]) -> Result<()> {
+    let shown = eth::display_secret_id(id);
+    let record = secrets.get_mut(&id_key(id)).ok_or_else(|| BsecError::SecretNotFound(shown.clone()))?;
+    if record.revoked {
+        return Err(BsecError::SecretRevoked(shown).into());
+    }
+    if now > record.expires_at {
+        return Err(BsecError::SecretExpiredAt { id: shown, expires_at: record.expires_at, now }.into());
+    }
+    if !record.is_public {
+        if record.read_count >= record.max_reads {
+            return Err(BsecError::ReadLimitReached { id: shown, read_count: record.read_count, max_reads: record.max_reads }.into());
+        }
+        if viewer != record.recipient && viewer != record.sender {
+            return Err(BsecError::UnauthorizedViewer { id: shown, viewer: checksum(viewer) }.into());
+        }
+        record.read_count += 1;
+    }
+    Ok(())
+}
+
+/// `_revoke`: only the sender may revoke, once.
+fn revoke(secrets: &mut Secrets, caller: &str, id: &[u8; 32]) -> Result<()> {
+    let shown = eth::display_secret_id(id);
+    let record = secrets.get_mut(&id_key(id)).ok_or_else(|| BsecError::SecretNotFound(shown.clone()))?;
+    if record.sender != caller {
+        return Err(BsecError::UnauthorizedRevoker { id: shown, caller: checksum(caller) }.into());
+    }
+    if record.revoked {
+        return Err(BsecError::SecretRevoked(shown).into());
+    }
+    record.revoked = true;
+    Ok(())
+}
+
+impl RegistryBackend for MockRegistry {
+    fn send(&self, priv_bytes: &[u8], action: &Action, _tx: &TxOptions) -> Result<String> {
+        let from = eth::addr_hex(&eth::address_bytes_from_secret(priv_bytes)?);
+        self.transact(|secrets, now| apply(secrets, now, &from, action))?;
+        // A stand-in transaction hash, unique per write.
+        let seed = format!("{}:{}:{:?}:{}", from, action.name(), action.secret_id()?, rand::random::<u64>());
+        Ok(format!("0x{}", bytes_to_hex(&keccak256(seed.as_bytes()))))
+    }
+
+    fn secret_info(&self, secret_id: &[u8; 32]) -> Result<DecodedSecretInfo> {
+        secret_info_at(&self.load()?, secret_id, crate::wallet::current_timestamp())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// IPFS store
+// ---------------------------------------------------------------------------
+
+/// RFC 4648 base32, lowercase and unpadded, as multibase `b` uses it.
+fn base32_lower(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+/// CIDv1 of `data` as a single raw block: what `ipfs add --cid-version 1 --raw-leaves`
+/// gives a payload under the 256 KiB chunk size.
+pub fn raw_cid(data: &[u8]) -> String {
+    let mut cid = vec![0x01, 0x55, 0x12, 0x20]; // CIDv1, raw, sha2-256, 32-byte digest
+    cid.extend_from_slice(&Sha256::digest(data));
+    format!("b{}", base32_lower(&cid))
+}
+
+/// The mock IPFS store: payloads by CID in the mock chain's `ipfs` directory.
+pub struct MockStore {
+    dir: PathBuf,
+}
+
+impl MockStore {
+    pub fn open(conf: &NetworkConfig) -> Result<Self> {
+        let dir = mock_dir(conf).join("ipfs");
+        fs::create_dir_all(&dir).map_err(|e| anyhow!("failed to create mock IPFS store {}: {}", dir.display(), e))?;
+        Ok(Self { dir })
+    }
+}
+
+impl PayloadStore for MockStore {
+    fn put(&self, payload: &str) -> Result<String> {
+        let cid = raw_cid(payload.as_bytes());
+        crate::wallet::write_secure_file(&self.dir.join(&cid), payload.as_bytes())?;
+        Ok(cid)
+    }
+
+    fn get(&self, cid: &str) -> Result<String> {
+        fs::read_to_string(self.dir.join(cid)).map_err(|_| anyhow!("CID {} is not in the mock IPFS store", cid))
+    }
+
+    fn remove(&self, cid: &str, report: &mut UnpinReport) {
+        if fs::remove_file(self.dir.join(cid)).is_ok() {
+            report.providers.push("mock".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENDER: [u8; 32] = [0x11; 32];
+    const RECIPIENT: [u8; 32] = [0x22; 32];
+    const STRANGER: [u8; 32] = [0x33; 32];
+
+    fn registry() -> (tempfile::TempDir, MockRegistry) {
+        let dir = tempfile::tempdir().unwrap();
+        let conf = NetworkConfig { network: "mock".into(), mock_dir: dir.path().display().to_string(), ..Default::default() };
+        let reg = MockRegistry::open(&conf).unwrap();
+        (dir, reg)
+    }
+
+    fn addr(key: &[u8; 32]) -> [u8; 20] {
+        eth::address_bytes_from_secret(key).unwrap()
+    }
+
+    fn code(err: anyhow::Error) -> Option<i32> {
+        err.downcast_ref::<BsecError>().map(|e| e.exit_code())
+    }
+
+    #[test]
+    fn mock_registry_enforces_the_contract_rules() {
+        let (_dir, reg) = registry();
+        let tx = TxOptions::default();
+        let secret = [0xab; 32];
+        let later = crate::wallet::current_timestamp() + 3600;
+        let share = Action::share(&secret, &addr(&RECIPIENT), "bafyCid", later, 1, false);
+
+        reg.send(&SENDER, &share, &tx).unwrap();
+        let err = reg.send(&SENDER, &share, &tx).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(BsecError::SecretAlreadyExists(_))), "{}", err);
+        let past = Action::share(&[0x01; 32], &addr(&RECIPIENT), "bafyCid", 1, 1, false);
+        assert!(matches!(reg.send(&SENDER, &past, &tx).unwrap_err().downcast_ref(), Some(BsecError::InvalidParameters)));
+
+        // Only the recipient or sender reads, up to max_reads.
+        let err = reg.send(&STRANGER, &Action::record_read(&secret), &tx).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(BsecError::UnauthorizedViewer { .. })), "{}", err);
+        reg.send(&RECIPIENT, &Action::record_read(&secret), &tx).unwrap();
+        let info = reg.secret_info(&secret).unwrap();
+        assert_eq!((info.read_count, info.limit_reached, info.recipient), (1, true, addr(&RECIPIENT)));
+        let err = reg.send(&RECIPIENT, &Action::record_read(&secret), &tx).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(BsecError::ReadLimitReached { read_count: 1, max_reads: 1, .. })));
+
+        // Only the sender revokes, once; a revoked secret cannot be read.
+        assert!(matches!(reg.send(&RECIPIENT, &Action::revoke(&secret), &tx).unwrap_err().downcast_ref(), Some(BsecError::UnauthorizedRevoker { .. })));
+        reg.send(&SENDER, &Action::revoke(&secret), &tx).unwrap();
+        let again = reg.send(&SENDER, &Action::revoke(&secret), &tx).unwrap_err();
+        assert!(matches!(again.downcast_ref(), Some(BsecError::SecretRevoked(_))));
+        assert_eq!(code(reg.send(&SENDER, &Action::record_read(&secret), &tx).unwrap_err()), Some(3));
+        assert!(reg.secret_info(&secret).unwrap().revoked);
+
+        let missing = reg.secret_info(&[0x09; 32]).unwrap_err();
+        assert!(matches!(missing.downcast_ref(), Some(BsecError::SecretNotFound(_))));
+    }
+
+    #[test]
+    fn public_secrets_are_not_read_limited() {
+        let (_dir, reg) = registry();
+        let tx = TxOptions::default();
+        let later = crate::wallet::current_timestamp() + 3600;
+        reg.send(&SENDER, &Action::share(&[0x05; 32], &[0u8; 20], "bafyCid", later, 1, true), &tx).unwrap();
+        for key in [RECIPIENT, STRANGER, STRANGER] {
+            reg.send(&key, &Action::record_read(&[0x05; 32]), &tx).unwrap();
+        }
+        let info = reg.secret_info(&[0x05; 32]).unwrap();
+        assert_eq!((info.read_count, info.limit_reached), (0, false));
+    }
+
+    #[test]
+    fn a_corrupt_registry_is_an_error_not_an_empty_chain() {
+        let (dir, reg) = registry();
+        let state = dir.path().join("registry.json");
+        fs::write(&state, "{not json").unwrap();
+        assert_eq!(code(reg.secret_info(&[0x05; 32]).unwrap_err()), Some(4));
+        let later = crate::wallet::current_timestamp() + 3600;
+        let share = Action::share(&[0x05; 32], &[0u8; 20], "bafyCid", later, 1, true);
+        assert_eq!(code(reg.send(&SENDER, &share, &TxOptions::default()).unwrap_err()), Some(4));
+        assert_eq!(fs::read_to_string(&state).unwrap(), "{not json");
+    }
+
+    #[test]
+    fn ipfs_store_uses_real_raw_cids() {
+        // `ipfs add --cid-version 1 --raw-leaves` of "hello world".
+        assert_eq!(raw_cid(b"hello world"), "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e");
+
+        let dir = tempfile::tempdir().unwrap();
+        let conf = NetworkConfig { network: "mock".into(), mock_dir: dir.path().display().to_string(), ..Default::default() };
+        let store = MockStore::open(&conf).unwrap();
+        let cid = store.put("{\"content\":\"x\"}").unwrap();
+        assert_eq!(store.get(&cid).unwrap(), "{\"content\":\"x\"}");
+        let mut report = UnpinReport::default();
+        store.remove(&cid, &mut report);
+        assert_eq!(report.providers, ["mock"]);
+        store.remove(&cid, &mut report);
+        assert_eq!(report.providers.len(), 1);
+        assert!(store.get(&cid).is_err());
+    }
+}
@@ -1,45 +1,212 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PsaProviderConfig {
+    pub name: String,
+    /// Pinning Service API base URL (the part before `/pins`).
+    pub endpoint: String,
+    /// Bearer token. Falls back to the BSEC_PSA_TOKEN_<NAME> env var.
+    #[serde(default)]
+    pub access_token: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct IpfsConfig {
     pub gateway: String,
+    /// Ordered gateway list raced by `fetch_from_ipfs`. Empty means `gateway` followed by
+    /// the public ipfs.io and dweb.link gateways.
+    #[serde(default)]
+    pub gateways: Vec<String>,
+    /// Comma-separated hosted pinning providers: `pinata`, names from `psa_providers`, or `none`.
     pub pinning_service: Option<String>,
+    /// IPFS Pinning Service API endpoints selectable in `pinning_service`.
+    #[serde(default)]
+    pub psa_providers: Vec<PsaProviderConfig>,
     /// Kubo RPC API endpoint (local daemon or self-hosted node).
     #[serde(default = "default_ipfs_api_url")]
     pub api_url: String,
     /// Pinata JWT for hosted pinning. Falls back to the BSEC_PINATA_JWT env var.
     #[serde(default)]
     pub pinning_jwt: Option<String>,
+    /// LRU size cap for `~/.bsec/ipfs_cache` in bytes; 0 disables the cap.
+    #[serde(default = "default_cache_max_bytes")]
+    pub cache_max_bytes: u64,
+    /// Encrypt cache entries at rest with a key derived from the wallet.
+    #[serde(default)]
+    pub encrypt_cache: bool,
+}
+
+impl IpfsConfig {
+    /// Gateways to race, in preference order, without duplicates.
+    pub fn effective_gateways(&self) -> Vec<String> {
+        let candidates = if self.gateways.is_empty() {
+            vec![
+                self.gateway.clone(),
+                "https://ipfs.io/ipfs/".to_string(),
+                "https://dweb.link/ipfs/".to_string(),
+            ]
+        } else {
+            self.gateways.clone()
+        };
+        let mut out: Vec<String> = Vec::new();
+        for g in candidates {
+            let g = format!("{}/", g.trim().trim_end_matches('/'));
+            if g.len() > 1 && !out.contains(&g) {
+                out.push(g);
+            }
+        }
+        out
+    }
 }
 
 fn default_ipfs_api_url() -> String {
     "http://127.0.0.1:5001".to_string()
 }
 
+fn default_cache_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+/// Parse a byte size such as `1048576`, `512K`, `64M` or `1G` (binary units).
+pub fn parse_size(s: &str) -> Result<u64> {
+    let t = s.trim();
+    let split = t.find(|c: char| !c.is_ascii_digit()).unwrap_or(t.len());
+    let (num, unit) = t.split_at(split);
+    let n: u64 = num.parse().map_err(|_| anyhow!("invalid size {:?}", s))?;
+    let mult: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        _ => return Err(anyhow!("invalid size unit in {:?} (use K, M or G)", s)),
+    };
+    n.checked_mul(mult).ok_or_else(|| anyhow!("size {:?} is too large", s))
+}
+
+/// What to do when the registry's deployed code is not a known BsecSecretRegistry build.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistryCheck {
+    /// Print a warning and continue.
+    #[default]
+    Warn,
+    /// Refuse to read from or write to the registry.
+    Enforce,
+    /// Skip the check.
+    Off,
+}
+
+impl RegistryCheck {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RegistryCheck::Warn => "warn",
+            RegistryCheck::Enforce => "enforce",
+            RegistryCheck::Off => "off",
+        }
+    }
+}
+
+/// Parse a `--registry-check` policy.
+pub fn parse_registry_check(s: &str) -> Result<RegistryCheck> {
+    match s.trim().to_lowercase().as_str() {
+        "warn" => Ok(RegistryCheck::Warn),
+        "enforce" => Ok(RegistryCheck::Enforce),
+        "off" => Ok(RegistryCheck::Off),
+        _ => Err(anyhow!("invalid registry check policy {:?} (use warn, enforce or off)", s)),
+    }
+}
+
+/// Normalize a keccak256 code hash to lowercase `0x` + 64 hex digits.
+pub fn parse_code_hash(s: &str) -> Result<String> {
+    let t = s.trim().to_lowercase();
+    let hex = t.strip_prefix("0x").unwrap_or(&t);
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(anyhow!("invalid code hash {:?} (expected 32 bytes of hex)", s));
+    }
+    Ok(format!("0x{}", hex))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NetworkConfig {
     pub network: String,
     pub chain_id: u32,
     pub rpc_url: String,
+    /// Fallback endpoints tried in order when `rpc_url` is unreachable or rate-limits.
+    #[serde(default)]
+    pub rpc_urls: Vec<String>,
     pub registry_address: String,
+    #[serde(default)]
+    pub registry_check: RegistryCheck,
+    /// Runtime code hashes (keccak256) accepted in addition to the builds shipped with bsec,
+    /// e.g. a registry deployed from a custom build.
+    #[serde(default)]
+    pub trusted_registry_code_hashes: Vec<String>,
+    /// State directory of the mock chain (`mock_chain`); empty for `<BSEC_HOME>/mock`.
+    #[serde(default)]
+    pub mock_dir: String,
     pub ipfs: IpfsConfig,
 }
 
+impl NetworkConfig {
+    /// The in-process mock chain (`mock_chain`), kept in `mock_dir`.
+    pub fn is_mock(&self) -> bool {
+        self.network == "mock"
+    }
+
+    /// The file-backed team registry (`team_registry`); `rpc_url` names the shared directory.
+    pub fn is_team(&self) -> bool {
+        self.network == "team"
+    }
+
+    /// Whether registry writes are transactions to a deployed contract.
+    pub fn has_registry_contract(&self) -> bool {
+        !self.is_mock() && !self.is_team()
+    }
+
+    /// Ticker of the gas token, for fee output.
+    pub fn native_symbol(&self) -> &'static str {
+        match self.chain_id {
+            137 | 80002 => "POL",
+            _ => "ETH",
+        }
+    }
+
+    /// `rpc_url` followed by the fallbacks, without blanks or duplicates.
+    pub fn effective_rpc_urls(&self) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for u in std::iter::once(&self.rpc_url).chain(self.rpc_urls.iter()) {
+            let u = u.trim();
+            if !u.is_empty() && !out.iter().any(|o| o == u) {
+                out.push(u.to_string());
+            }
+        }
+        out
+    }
+}
+
 impl Default for NetworkConfig {
     fn default() -> Self {
         Self {
             network: "amoy".to_string(),
             chain_id: 80002,
             rpc_url: "https://rpc-amoy.polygon.technology".to_string(),
+            rpc_urls: Vec::new(),
             registry_address: "0x39a13aC4081076bEDdA4cCdC8b8E8d8f07F3bA49".to_string(),
+            registry_check: RegistryCheck::default(),
+            trusted_registry_code_hashes: Vec::new(),
+            mock_dir: String::new(),
             ipfs: IpfsConfig {
                 gateway: "https://ipfs.io/ipfs/".to_string(),
+                gateways: Vec::new(),
                 pinning_service: None,
+                psa_providers: Vec::new(),
                 api_url: default_ipfs_api_url(),
                 pinning_jwt: None,
+                cache_max_bytes: default_cache_max_bytes(),
+                encrypt_cache: false,
             },
         }
     }
@@ -65,16 +232,51 @@ pub fn save_network_config(config: &NetworkConfig) -> Result<()> {
     Ok(())
 }
 
-pub fn update_network_config(
-    network: Option<String>,
-    rpc: Option<String>,
-    registry: Option<String>,
-    ipfs_gateway: Option<String>,
-    ipfs_pinning: Option<String>,
-) -> Result<NetworkConfig> {
+/// Settings changed by `bsec config`; `None` leaves the stored value untouched.
+#[derive(Debug, Default)]
+pub struct ConfigUpdate {
+    pub network: Option<String>,
+    pub rpc: Option<String>,
+    pub rpc_fallbacks: Option<Vec<String>>,
+    pub chain_id: Option<u32>,
+    pub registry: Option<String>,
+    pub registry_check: Option<RegistryCheck>,
+    /// Runtime code hash to add to `trusted_registry_code_hashes`.
+    pub trust_registry_code_hash: Option<String>,
+    pub mock_dir: Option<String>,
+    pub ipfs_gateway: Option<String>,
+    pub ipfs_gateways: Option<Vec<String>>,
+    pub ipfs_pinning: Option<String>,
+    /// `NAME=ENDPOINT` of a Pinning Service API provider to add or replace.
+    pub add_psa_provider: Option<String>,
+    pub remove_psa_provider: Option<String>,
+    pub cache_max_bytes: Option<u64>,
+    pub encrypt_cache: Option<bool>,
+}
+
+pub fn update_network_config(update: ConfigUpdate) -> Result<NetworkConfig> {
+    let ConfigUpdate {
+        network,
+        rpc,
+        rpc_fallbacks,
+        chain_id,
+        registry,
+        registry_check,
+        trust_registry_code_hash,
+        mock_dir,
+        ipfs_gateway,
+        ipfs_gateways,
+        ipfs_pinning,
+        add_psa_provider,
+        remove_psa_provider,
+        cache_max_bytes,
+        encrypt_cache,
+    } = update;
     let mut config = load_network_config();
 
     if let Some(net) = network {
+        // Fallbacks belong to the previous network's chain.
+        config.rpc_urls.clear();
         match net.to_lowercase().as_str() {
             "polygon" => {
                 config.network = "polygon".to_string();
@@ -97,6 +299,20 @@ pub fn update_network_config(
                     config.rpc_url = "http://localhost:8545".to_string();
                 }
             }
+            "mock" => {
+                config.network = "mock".to_string();
+                config.chain_id = 31337;
+                if rpc.is_none() {
+                    config.rpc_url = String::new();
+                }
+            }
+            "team" => {
+                config.network = "team".to_string();
+                config.chain_id = 0;
+                if rpc.is_none() {
+                    config.rpc_url = String::new();
+                }
+            }
             "amoy" => {
                 config.network = "amoy".to_string();
                 config.chain_id = 80002;
@@ -135,18 +351,146 @@ pub fn update_network_config(
         config.rpc_url = r;
     }
 
+    if let Some(list) = rpc_fallbacks {
+        config.rpc_urls = list.into_iter().filter(|u| !u.trim().is_empty()).collect();
+    }
+
+    if let Some(id) = chain_id {
+        config.chain_id = id;
+    }
+
     if let Some(reg) = registry {
         config.registry_address = reg;
     }
 
+    if let Some(policy) = registry_check {
+        config.registry_check = policy;
+    }
+
+    if let Some(hash) = trust_registry_code_hash {
+        let hash = parse_code_hash(&hash)?;
+        if !config.trusted_registry_code_hashes.contains(&hash) {
+            config.trusted_registry_code_hashes.push(hash);
+        }
+    }
+
+    if let Some(dir) = mock_dir {
+        config.mock_dir = dir;
+    }
+
     if let Some(g) = ipfs_gateway {
         config.ipfs.gateway = g;
     }
 
+    if let Some(list) = ipfs_gateways {
+        config.ipfs.gateways = list.into_iter().filter(|g| !g.trim().is_empty()).collect();
+    }
+
     if let Some(p) = ipfs_pinning {
         config.ipfs.pinning_service = Some(p);
     }
 
+    if let Some(spec) = add_psa_provider {
+        let (name, endpoint) = spec
+            .split_once('=')
+            .map(|(n, e)| (n.trim(), e.trim()))
+            .filter(|(n, e)| !n.is_empty() && !n.contains(',') && !e.is_empty())
+            .ok_or_else(|| anyhow!("pinning provider must be NAME=ENDPOINT, got {:?}", spec))?;
+        if name.eq_ignore_ascii_case("pinata") || name.eq_ignore_ascii_case("none") {
+            return Err(anyhow!("'{}' is a reserved pinning provider name", name));
+        }
+        let access_token = config
+            .ipfs
+            .psa_providers
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.access_token.clone());
+        config.ipfs.psa_providers.retain(|p| p.name != name);
+        config.ipfs.psa_providers.push(PsaProviderConfig {
+            name: name.to_string(),
+            endpoint: endpoint.to_string(),
+            access_token,
+        });
+    }
+
+    if let Some(name) = remove_psa_provider {
+        config.ipfs.psa_providers.retain(|p| p.name != name);
+    }
+
+    if let Some(max) = cache_max_bytes {
+        config.ipfs.cache_max_bytes = max;
+    }
+
+    if let Some(enc) = encrypt_cache {
+        config.ipfs.encrypt_cache = enc;
+    }
+
     save_network_config(&config)?;
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("512K").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("64M").unwrap(), 64 * 1024 * 1024);
+        assert_eq!(parse_size("1gb").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_size("12X").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size(&format!("{}G", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn old_config_gets_cache_defaults() {
+        let json = r#"{"network":"local","chain_id":31337,"rpc_url":"http://localhost:8545",
+            "registry_address":"0x00","ipfs":{"gateway":"http://localhost:8080/ipfs/","pinning_service":null}}"#;
+        let conf: NetworkConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(conf.ipfs.cache_max_bytes, 64 * 1024 * 1024);
+        assert!(!conf.ipfs.encrypt_cache);
+        assert_eq!(conf.registry_check, RegistryCheck::Warn);
+        assert!(conf.trusted_registry_code_hashes.is_empty());
+        assert_eq!(
+            conf.ipfs.effective_gateways(),
+            vec!["http://localhost:8080/ipfs/", "https://ipfs.io/ipfs/", "https://dweb.link/ipfs/"]
+        );
+    }
+
+    #[test]
+    fn rpc_fallbacks_follow_primary_without_duplicates() {
+        let conf = NetworkConfig {
+            rpc_url: "https://a".into(),
+            rpc_urls: vec!["https://b".into(), "https://a".into(), " ".into()],
+            ..Default::default()
+        };
+        assert_eq!(conf.effective_rpc_urls(), vec!["https://a", "https://b"]);
+    }
+
+    #[test]
+    fn explicit_gateway_list_is_normalized_and_deduplicated() {
+        let mut conf = NetworkConfig::default();
+        conf.ipfs.gateways = vec![
+            "https://a.example/ipfs".into(),
+            "https://a.example/ipfs/".into(),
+            " ".into(),
+            "https://b.example/ipfs/".into(),
+        ];
+        assert_eq!(
+            conf.ipfs.effective_gateways(),
+            vec!["https://a.example/ipfs/", "https://b.example/ipfs/"]
+        );
+    }
+
+    #[test]
+    fn registry_check_and_code_hash_parsing() {
+        assert_eq!(parse_registry_check(" Enforce ").unwrap(), RegistryCheck::Enforce);
+        assert!(parse_registry_check("strict").is_err());
+        let h = "AB".repeat(32);
+        assert_eq!(parse_code_hash(&h).unwrap(), format!("0x{}", "ab".repeat(32)));
+        assert!(parse_code_hash("0x1234").is_err());
+        assert!(parse_code_hash(&"zz".repeat(32)).is_err());
+    }
+}
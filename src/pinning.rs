@@ -0,0 +1,383 @@
+//! Hosted pinning providers.
+//!
+//! `ipfs.pinning_service` names the providers to pin with, comma-separated: `pinata` (JWT from
+//! `ipfs.pinning_jwt` or `BSEC_PINATA_JWT`) and any entry of `ipfs.psa_providers`, which speak
+//! the IPFS Pinning Service API (`POST /pins`, `GET /pins?cid=`, `DELETE /pins/{requestid}`).
+//! Unset means Pinata when a JWT is available; `none` disables hosted pinning.
+//!
+//! Pinata accepts content; a PSA provider can only pin a CID that is already on the network.
+//! `upload_to_ipfs` therefore gets the CID from the first provider that takes content (or the
+//! Kubo daemon) and then asks every other selected provider to pin it.
+
+use anyhow::{anyhow, Result};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::network_config::{NetworkConfig, PsaProviderConfig};
+
+const PINATA_API: &str = "https://api.pinata.cloud";
+/// Pin name sent to PSA providers so bsec payloads are recognisable in their dashboards.
+const PIN_NAME: &str = "bsec-secret";
+
+pub trait PinningProvider {
+    fn name(&self) -> &str;
+
+    /// Upload and pin `payload_json`, returning its CID. `None` if the provider only pins CIDs.
+    fn upload(&self, _client: &Client, _payload_json: &str) -> Option<Result<String>> {
+        None
+    }
+
+    /// Pin a CID that is already available on the IPFS network.
+    fn pin(&self, client: &Client, cid: &str) -> Result<()>;
+
+    /// Drop every pin this provider holds for `cid`. `Ok(false)` means it held none.
+    fn unpin(&self, client: &Client, cid: &str) -> Result<bool>;
+}
+
+pub struct Pinata {
+    jwt: String,
+}
+
+impl Pinata {
+    fn pin_file(&self, client: &Client, payload_json: &str) -> Result<String> {
+        let form = reqwest::blocking::multipart::Form::new().text("file", payload_json.to_string());
+        let res = client
+            .post(format!("{}/pinning/pinFileToIPFS", PINATA_API))
+            .bearer_auth(&self.jwt)
+            .multipart(form)
+            .send()
+            .map_err(|e| anyhow!("Pinata request failed: {}", e))?;
+        if !res.status().is_success() {
+            return Err(anyhow!("Pinata pinning failed ({})", error_body(res)));
+        }
+        let json: serde_json::Value = res.json().map_err(|e| anyhow!("Pinata response decode error: {}", e))?;
+        json.get("IpfsHash")
+            .and_then(|h| h.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Pinata response missing IpfsHash"))
+    }
+}
+
+fn error_body(res: reqwest::blocking::Response) -> String {
+    let status = res.status();
+    let body = res.text().unwrap_or_default();
+    format!("{}: {}", status, body.trim())
+}
+
+impl PinningProvider for Pinata {
+    fn name(&self) -> &str {
+        "pinata"
+    }
+
+    fn upload(&self, client: &Client, payload_json: &str) -> Option<Result<String>> {
+        Some(self.pin_file(client, payload_json))
+    }
+
+    fn pin(&self, client: &Client, cid: &str) -> Result<()> {
+        let res = client
+            .post(format!("{}/pinning/pinByHash", PINATA_API))
+            .bearer_auth(&self.jwt)
+            .json(&serde_json::json!({ "hashToPin": cid, "pinataMetadata": { "name": PIN_NAME } }))
+            .send()
+            .map_err(|e| anyhow!("Pinata request failed: {}", e))?;
+        if !res.status().is_success() {
+            return Err(anyhow!("Pinata pinByHash failed ({})", error_body(res)));
+        }
+        Ok(())
+    }
+
+    fn unpin(&self, client: &Client, cid: &str) -> Result<bool> {
+        let res = client
+            .delete(format!("{}/pinning/unpin/{}", PINATA_API, cid))
+            .bearer_auth(&self.jwt)
+            .send()
+            .map_err(|e| anyhow!("Pinata request failed: {}", e))?;
+        // Pinata answers 404 for a CID this account does not pin.
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !res.status().is_success() {
+            return Err(anyhow!("Pinata unpin failed ({})", error_body(res)));
+        }
+        Ok(true)
+    }
+}
+
+/// A provider implementing the IPFS Pinning Service API.
+pub struct PinningServiceApi {
+    name: String,
+    endpoint: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct PinStatus {
+    requestid: String,
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct PinResults {
+    #[serde(default)]
+    results: Vec<PinStatus>,
+}
+
+impl PinningServiceApi {
+    pub fn new(name: &str, endpoint: &str, token: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+}
+
+impl PinningProvider for PinningServiceApi {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn pin(&self, client: &Client, cid: &str) -> Result<()> {
+        let res = client
+            .post(format!("{}/pins", self.endpoint))
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "cid": cid, "name": PIN_NAME }))
+            .send()
+            .map_err(|e| anyhow!("{} request failed: {}", self.name, e))?;
+        if !res.status().is_success() {
+            return Err(anyhow!("{} pin failed ({})", self.name, error_body(res)));
+        }
+        let status: PinStatus = res
+            .json()
+            .map_err(|e| anyhow!("{} returned an invalid PinStatus: {}", self.name, e))?;
+        if status.status == "failed" {
+            return Err(anyhow!("{} reported pin request {} as failed", self.name, status.requestid));
+        }
+        Ok(())
+    }
+
+    fn unpin(&self, client: &Client, cid: &str) -> Result<bool> {
+        let res = client
+            .get(format!("{}/pins", self.endpoint))
+            .query(&[("cid", cid), ("status", "queued,pinning,pinned,failed")])
+            .bearer_auth(&self.token)
+            .send()
+            .map_err(|e| anyhow!("{} request failed: {}", self.name, e))?;
+        if !res.status().is_success() {
+            return Err(anyhow!("{} pin lookup failed ({})", self.name, error_body(res)));
+        }
+        let found: PinResults = res
+            .json()
+            .map_err(|e| anyhow!("{} returned an invalid pin list: {}", self.name, e))?;
+        for pin in &found.results {
+            let res = client
+                .delete(format!("{}/pins/{}", self.endpoint, pin.requestid))
+                .bearer_auth(&self.token)
+                .send()
+                .map_err(|e| anyhow!("{} request failed: {}", self.name, e))?;
+            if !res.status().is_success() {
+                return Err(anyhow!("{} unpin of {} failed ({})", self.name, pin.requestid, error_body(res)));
+            }
+        }
+        Ok(!found.results.is_empty())
+    }
+}
+
+/// `BSEC_PSA_TOKEN_<NAME>`, with the name upper-cased and non-alphanumerics mapped to `_`.
+fn token_env_var(provider: &str) -> String {
+    let suffix: String = provider
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("BSEC_PSA_TOKEN_{}", suffix)
+}
+
+fn psa_provider(p: &PsaProviderConfig) -> Option<PinningServiceApi> {
+    let token = p
+        .access_token
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| std::env::var(token_env_var(&p.name)).ok().filter(|s| !s.trim().is_empty()));
+    match token {
+        Some(t) => Some(PinningServiceApi::new(&p.name, &p.endpoint, &t)),
+        None => {
+            log::warn!(
+                "Pinning provider '{}' has no access token (set access_token or {}); skipping",
+                p.name,
+                token_env_var(&p.name)
+            );
+            None
+        }
+    }
+}
+
+/// Names selected by `ipfs.pinning_service`, in order and without duplicates.
+pub fn selected_names(conf: &NetworkConfig) -> Vec<String> {
+    let raw = conf.ipfs.pinning_service.as_deref().unwrap_or("").trim();
+    if raw.is_empty() {
+        return vec!["pinata".to_string()];
+    }
+    let mut names: Vec<String> = Vec::new();
+    for n in raw.split(',').map(|n| n.trim().to_lowercase()) {
+        if n.is_empty() || n == "none" || names.contains(&n) {
+            continue;
+        }
+        names.push(n);
+    }
+    names
+}
+
+/// Build the providers selected by `ipfs.pinning_service`. Providers without credentials and
+/// unknown names are skipped with a warning.
+pub fn resolve_providers(conf: &NetworkConfig, pinata_jwt: Option<String>) -> Vec<Box<dyn PinningProvider>> {
+    let explicit = conf.ipfs.pinning_service.as_deref().is_some_and(|s| !s.trim().is_empty());
+    let mut out: Vec<Box<dyn PinningProvider>> = Vec::new();
+    for name in selected_names(conf) {
+        if name == "pinata" {
+            match &pinata_jwt {
+                Some(jwt) => out.push(Box::new(Pinata { jwt: jwt.clone() })),
+                None if explicit => log::warn!("Pinata selected but no JWT configured; skipping"),
+                None => {}
+            }
+        } else if let Some(p) = conf.ipfs.psa_providers.iter().find(|p| p.name.to_lowercase() == name) {
+            if let Some(provider) = psa_provider(p) {
+                out.push(Box::new(provider));
+            }
+        } else {
+            log::warn!("Unknown pinning provider '{}' in ipfs.pinning_service; skipping", name);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    /// Minimal Pinning Service API mock. Records "METHOD target auth body" per request and
+    /// answers from `route(method, target)`.
+    fn mock_psa(route: fn(&str, &str) -> (u16, String)) -> (String, Log) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/psa", listener.local_addr().unwrap());
+        let log: Log = Arc::new(Mutex::new(Vec::new()));
+        let seen = log.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let (mut len, mut auth) = (0usize, String::new());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let l = line.trim_end();
+                    if l.is_empty() {
+                        break;
+                    }
+                    let lower = l.to_lowercase();
+                    if let Some(v) = lower.strip_prefix("content-length:") {
+                        len = v.trim().parse().unwrap();
+                    }
+                    if lower.starts_with("authorization:") {
+                        auth = l["authorization:".len()..].trim().to_string();
+                    }
+                }
+                let mut body = vec![0u8; len];
+                reader.read_exact(&mut body).unwrap();
+                let mut parts = request_line.split_whitespace();
+                let (method, target) = (parts.next().unwrap().to_string(), parts.next().unwrap().to_string());
+                seen.lock().unwrap().push(format!("{} {} {} {}", method, target, auth, String::from_utf8_lossy(&body)));
+                let (status, reply) = route(&method, &target);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+            }
+        });
+        (endpoint, log)
+    }
+
+    fn client() -> Client {
+        Client::builder().timeout(std::time::Duration::from_secs(5)).build().unwrap()
+    }
+
+    #[test]
+    fn psa_pin_posts_cid_with_bearer_token() {
+        let (endpoint, log) = mock_psa(|_, _| {
+            (202, r#"{"requestid":"r1","status":"queued","created":"2024-01-01T00:00:00Z","pin":{"cid":"QmA"}}"#.into())
+        });
+        let p = PinningServiceApi::new("mock", &format!("{}/", endpoint), "tok");
+        p.pin(&client(), "QmA").unwrap();
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 1);
+        assert!(log[0].starts_with("POST /psa/pins Bearer tok "), "{}", log[0]);
+        assert!(log[0].contains(r#""cid":"QmA""#));
+    }
+
+    #[test]
+    fn psa_pin_reports_failed_status_and_http_errors() {
+        let (endpoint, _) = mock_psa(|_, _| (202, r#"{"requestid":"r1","status":"failed"}"#.into()));
+        let err = PinningServiceApi::new("mock", &endpoint, "tok").pin(&client(), "QmA").unwrap_err();
+        assert!(err.to_string().contains("failed"));
+
+        let (endpoint, _) = mock_psa(|_, _| (401, r#"{"error":{"reason":"UNAUTHORIZED"}}"#.into()));
+        let err = PinningServiceApi::new("mock", &endpoint, "bad").pin(&client(), "QmA").unwrap_err();
+        assert!(err.to_string().contains("401"));
+    }
+
+    #[test]
+    fn psa_unpin_deletes_every_matching_request() {
+        let (endpoint, log) = mock_psa(|method, _| match method {
+            "GET" => (200, r#"{"count":2,"results":[{"requestid":"r1","status":"pinned"},{"requestid":"r2","status":"queued"}]}"#.into()),
+            _ => (202, String::new()),
+        });
+        let p = PinningServiceApi::new("mock", &endpoint, "tok");
+        assert!(p.unpin(&client(), "QmA").unwrap());
+        let log = log.lock().unwrap();
+        assert!(log[0].starts_with("GET /psa/pins?cid=QmA&status=queued%2Cpinning%2Cpinned%2Cfailed "));
+        assert!(log[1].starts_with("DELETE /psa/pins/r1 Bearer tok"));
+        assert!(log[2].starts_with("DELETE /psa/pins/r2 Bearer tok"));
+
+        let (endpoint, _) = mock_psa(|_, _| (200, r#"{"count":0,"results":[]}"#.into()));
+        assert!(!PinningServiceApi::new("mock", &endpoint, "tok").unpin(&client(), "QmA").unwrap());
+    }
+
+    #[test]
+    fn token_env_var_is_shell_safe() {
+        assert_eq!(token_env_var("web3-storage.v2"), "BSEC_PSA_TOKEN_WEB3_STORAGE_V2");
+    }
+
+    #[test]
+    fn pinning_service_selects_providers() {
+        let mut conf = NetworkConfig::default();
+        conf.ipfs.psa_providers = vec![
+            PsaProviderConfig { name: "web3".into(), endpoint: "https://a/psa".into(), access_token: Some("t".into()) },
+            PsaProviderConfig { name: "notoken".into(), endpoint: "https://b/psa".into(), access_token: None },
+        ];
+
+        // Unset: Pinata only, and only when a JWT exists.
+        assert!(resolve_providers(&conf, None).is_empty());
+        assert_eq!(resolve_providers(&conf, Some("jwt".into()))[0].name(), "pinata");
+
+        conf.ipfs.pinning_service = Some("web3, pinata, web3, notoken, bogus".into());
+        assert_eq!(selected_names(&conf), vec!["web3", "pinata", "notoken", "bogus"]);
+        let names: Vec<String> = resolve_providers(&conf, Some("jwt".into()))
+            .iter()
+            .map(|p| p.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["web3", "pinata"]);
+
+        conf.ipfs.pinning_service = Some("none".into());
+        assert!(resolve_providers(&conf, Some("jwt".into())).is_empty());
+    }
+}
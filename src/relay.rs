@@ -0,0 +1,597 @@
+//! Gasless registry writes through a relayer.
+//!
+//! A user signs an EIP-712 intent (share, read or revoke) with their wallet and posts it to a
+//! relayer (`--relayer <URL>`). The relayer (`bsec relay serve`) checks the signature and
+//! submits the registry's `...BySig` function from its own funded wallet, so the user needs no
+//! native tokens. The contract binds each signature to the chain, the registry, the signer's
+//! current nonce and a deadline, so a relayer can neither alter nor replay an intent.
+//!
+//! The relayer speaks a small JSON API:
+//! - `GET /v1/info` returns `{chain_id, registry, relayer}`
+//! - `POST /v1/intents` takes an `Intent` and answers `{tx_hash}` once it is mined, or
+//!   `{error}` with status 400 (rejected) or 422 (the transaction failed).
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::eth::{self, TxOptions};
+use crate::network_config::NetworkConfig;
+use crate::wallet::{bytes_to_hex, hex_to_bytes};
+
+/// How long a signed intent stays valid.
+const INTENT_TTL_SECS: u64 = 600;
+/// Largest request body the relayer reads (an intent is well under 2 KiB).
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// Largest request line plus headers the relayer reads, and most header lines.
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+const MAX_HEADERS: usize = 64;
+/// Each read from a client, and the whole request, must arrive within these.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Connections served at once; more are answered 503 and closed. Each one that reaches the
+/// chain holds its thread until the transaction is mined.
+const MAX_CONNECTIONS: usize = 32;
+/// The relayer answers after the transaction is mined, which can take minutes.
+const CLIENT_TIMEOUT_SECS: u64 = 300;
+
+/// A registry write, as signed by its author.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum Action {
+    ShareSecret {
+        secret_id: String,
+        recipient: String,
+        ipfs_cid: String,
+        expires_at: u64,
+        max_reads: u32,
+        is_public: bool,
+    },
+    RecordRead {
+        secret_id: String,
+    },
+    RevokeSecret {
+        secret_id: String,
+    },
+}
+
+impl Action {
+    pub fn share(
+        secret_id: &[u8; 32],
+        recipient: &[u8; 20],
+        ipfs_cid: &str,
+        expires_at: u64,
+        max_reads: u32,
+        is_public: bool,
+    ) -> Self {
+        Action::ShareSecret {
+            secret_id: format!("0x{}", bytes_to_hex(secret_id)),
+            recipient: eth::addr_hex(recipient),
+            ipfs_cid: ipfs_cid.to_string(),
+            expires_at,
+            max_reads,
+            is_public,
+        }
+    }
+
+    pub fn record_read(secret_id: &[u8; 32]) -> Self {
+        Action::RecordRead { secret_id: format!("0x{}", bytes_to_hex(secret_id)) }
+    }
+
+    pub fn revoke(secret_id: &[u8; 32]) -> Self {
+        Action::RevokeSecret { secret_id: format!("0x{}", bytes_to_hex(secret_id)) }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::ShareSecret { .. } => "shareSecret",
+            Action::RecordRead { .. } => "recordRead",
+            Action::RevokeSecret { .. } => "revokeSecret",
+        }
+    }
+
+    pub fn secret_id(&self) -> Result<[u8; 32]> {
+        let hex = match self {
+            Action::ShareSecret { secret_id, .. } | Action::RecordRead { secret_id } | Action::RevokeSecret { secret_id } => secret_id,
+        };
+        hex_to_bytes(hex)?
+            .try_into()
+            .map_err(|_| anyhow!("secret_id must be 32 bytes"))
+    }
+
+    /// Calldata of the plain call, sent from the author's own wallet.
+    pub fn calldata(&self) -> Result<Vec<u8>> {
+        let id = self.secret_id()?;
+        Ok(match self {
+            Action::ShareSecret { recipient, ipfs_cid, expires_at, max_reads, is_public, .. } => {
+                eth::encode_share_secret(&id, &eth::parse_address(recipient)?, ipfs_cid, *expires_at, *max_reads, *is_public)
+            }
+            Action::RecordRead { .. } => eth::encode_bytes32_call("recordRead(bytes32)", &id),
+            Action::RevokeSecret { .. } => eth::encode_bytes32_call("revokeSecret(bytes32)", &id),
+        })
+    }
+
+    fn struct_hash(&self, nonce: u64, deadline: u64) -> Result<[u8; 32]> {
+        let id = self.secret_id()?;
+        Ok(match self {
+            Action::ShareSecret { recipient, ipfs_cid, expires_at, max_reads, is_public, .. } => eth::share_intent_hash(
+                &id,
+                &eth::parse_address(recipient)?,
+                ipfs_cid,
+                *expires_at,
+                *max_reads,
+                *is_public,
+                nonce,
+                deadline,
+            ),
+            Action::RecordRead { .. } => eth::bytes32_intent_hash(eth::READ_INTENT_TYPE, &id, nonce, deadline),
+            Action::RevokeSecret { .. } => eth::bytes32_intent_hash(eth::REVOKE_INTENT_TYPE, &id, nonce, deadline),
+        })
+    }
+}
+
+/// A signed action, as posted to the relayer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Intent {
+    #[serde(flatten)]
+    pub action: Action,
+    pub chain_id: u32,
+    pub registry: String,
+    pub signer: String,
+    pub nonce: u64,
+    pub deadline: u64,
+    /// 0x-prefixed 65-byte r || s || v.
+    pub signature: String,
+}
+
+impl Intent {
+    /// Sign `action` for the registry at `registry` on `chain_id` with the signer's `nonce`.
+    pub fn sign(action: Action, chain_id: u32, registry: &[u8; 20], priv_bytes: &[u8], nonce: u64, deadline: u64) -> Result<Self> {
+        let digest = eth::eip712_digest(&eth::registry_domain_separator(chain_id, registry), &action.struct_hash(nonce, deadline)?);
+        let signature = eth::sign_digest(priv_bytes, &digest)?;
+        Ok(Intent {
+            action,
+            chain_id,
+            registry: eth::addr_hex(registry),
+            signer: eth::addr_hex(&eth::address_bytes_from_secret(priv_bytes)?),
+            nonce,
+            deadline,
+            signature: format!("0x{}", bytes_to_hex(&signature)),
+        })
+    }
+
+    /// The EIP-712 digest that `signature` signs.
+    pub fn digest(&self) -> Result<[u8; 32]> {
+        let registry = eth::parse_address(&self.registry)?;
+        Ok(eth::eip712_digest(
+            &eth::registry_domain_separator(self.chain_id, &registry),
+            &self.action.struct_hash(self.nonce, self.deadline)?,
+        ))
+    }
+
+    /// Check the signature was made by `signer`; returns the signer's address.
+    pub fn verify(&self) -> Result<[u8; 20]> {
+        let signer = eth::parse_address(&self.signer)?;
+        if eth::recover_signer(&self.digest()?, &hex_to_bytes(&self.signature)?)? != signer {
+            return Err(anyhow!("signature does not match signer {}", self.signer));
+        }
+        Ok(signer)
+    }
+
+    /// Calldata of the `...BySig` call that carries this intent.
+    pub fn calldata(&self) -> Result<Vec<u8>> {
+        let id = self.action.secret_id()?;
+        let signer = eth::parse_address(&self.signer)?;
+        let sig = hex_to_bytes(&self.signature)?;
+        Ok(match &self.action {
+            Action::ShareSecret { recipient, ipfs_cid, expires_at, max_reads, is_public, .. } => eth::encode_share_secret_by_sig(
+                &id,
+                &eth::parse_address(recipient)?,
+                ipfs_cid,
+                *expires_at,
+                *max_reads,
+                *is_public,
+                &signer,
+                self.deadline,
+                &sig,
+            ),
+            Action::RecordRead { .. } => {
+                eth::encode_bytes32_call_by_sig("recordReadBySig(bytes32,address,uint256,bytes)", &id, &signer, self.deadline, &sig)
+            }
+            Action::RevokeSecret { .. } => {
+                eth::encode_bytes32_call_by_sig("revokeSecretBySig(bytes32,address,uint256,bytes)", &id, &signer, self.deadline, &sig)
+            }
+        })
+    }
+}
+
+/// The signer's next EIP-712 nonce on the registry.
+fn signer_nonce(conf: &NetworkConfig, registry: &[u8; 20], signer: &[u8; 20]) -> Result<u64> {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(signer);
+    let ret = eth::eth_call(conf, registry, &eth::encode_bytes32_call("nonces(address)", &word))?;
+    let word = ret.get(..32).ok_or_else(|| anyhow!("registry has no nonces(); it predates gasless intents"))?;
+    Ok(u64::from_be_bytes(word[24..32].try_into()?))
+}
+
+// ---------------------------------------------------------------------------
+// Client
+// ---------------------------------------------------------------------------
+
+/// What a relayer reports about itself.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RelayInfo {
+    pub chain_id: u32,
+    pub registry: String,
+    pub relayer: String,
+}
+
+fn client() -> Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(CLIENT_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| anyhow!("failed to build HTTP client: {}", e))
+}
+
+/// Sign `action` and have the relayer at `relayer_url` submit it. The `...BySig` call is
+/// simulated locally first, so a registry error (expired, read limit, ...) is reported as such
+/// before anything is posted. Returns the transaction hash once mined.
+pub fn submit(relayer_url: &str, conf: &NetworkConfig, registry: &[u8; 20], priv_bytes: &[u8], action: Action) -> Result<String> {
+    let base = relayer_url.trim_end_matches('/');
+    let client = client()?;
+    let info: RelayInfo = client
+        .get(format!("{}/v1/info", base))
+        .send()
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.json())
+        .map_err(|e| anyhow!("relayer {} is not reachable: {}", base, e))?;
+    if info.chain_id != conf.chain_id || !info.registry.eq_ignore_ascii_case(&eth::addr_hex(registry)) {
+        return Err(anyhow!(
+            "relayer {} serves registry {} on chain {}, not {} on chain {}",
+            base,
+            info.registry,
+            info.chain_id,
+            eth::addr_hex(registry),
+            conf.chain_id
+        ));
+    }
+
+    let signer = eth::address_bytes_from_secret(priv_bytes)?;
+    let nonce = signer_nonce(conf, registry, &signer)?;
+    let deadline = crate::wallet::current_timestamp() + INTENT_TTL_SECS;
+    let intent = Intent::sign(action, conf.chain_id, registry, priv_bytes, nonce, deadline)?;
+    eth::eth_call(conf, registry, &intent.calldata()?)?;
+
+    let resp = client
+        .post(format!("{}/v1/intents", base))
+        .json(&intent)
+        .send()
+        .map_err(|e| anyhow!("relayer {} did not answer: {}", base, e))?;
+    let status = resp.status();
+    let body: Value = resp.json().unwrap_or(Value::Null);
+    match body.get("tx_hash").and_then(|h| h.as_str()) {
+        Some(hash) if status.is_success() => Ok(hash.to_string()),
+        _ => Err(anyhow!(
+            "relayer rejected the intent ({}): {}",
+            status,
+            body.get("error").and_then(|e| e.as_str()).unwrap_or("no reason given")
+        )),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Server
+// ---------------------------------------------------------------------------
+
+/// State of a running relayer.
+pub struct Relayer {
+    pub conf: NetworkConfig,
+    pub registry: [u8; 20],
+    pub key: zeroize::Zeroizing<Vec<u8>>,
+    /// Fee overrides and cap applied to every relayed transaction.
+    pub tx: TxOptions,
+    /// Only relay for these signers...
+    pub allow: Vec<[u8; 20]>,
+    /// ...or for anyone (`--open`), who then spends this wallet's gas.
+    pub open: bool,
+}
+
+impl Relayer {
+    fn info(&self) -> Result<RelayInfo> {
+        Ok(RelayInfo {
+            chain_id: self.conf.chain_id,
+            registry: eth::addr_hex(&self.registry),
+            relayer: eth::addr_hex(&eth::address_bytes_from_secret(&self.key)?),
+        })
+    }
+
+    /// Checks that need no RPC: target, allowlist, deadline and signature.
+    fn check(&self, intent: &Intent) -> Result<()> {
+        if intent.chain_id != self.conf.chain_id || !intent.registry.eq_ignore_ascii_case(&eth::addr_hex(&self.registry)) {
+            return Err(anyhow!("this relayer serves registry {} on chain {}", eth::addr_hex(&self.registry), self.conf.chain_id));
+        }
+        let signer = intent.verify()?;
+        if !self.open && !self.allow.contains(&signer) {
+            return Err(anyhow!("signer {} is not allowed on this relayer", intent.signer));
+        }
+        if intent.deadline < crate::wallet::current_timestamp() {
+            return Err(anyhow!("intent expired at {}", intent.deadline));
+        }
+        Ok(())
+    }
+
+    /// Answer one request: (HTTP status, JSON body).
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+        match (method, path) {
+            ("GET", "/v1/info") => match self.info() {
+                Ok(info) => (200, json!(info)),
+                Err(e) => (500, json!({ "error": e.to_string() })),
+            },
+            ("POST", "/v1/intents") => {
+                let intent: Intent = match serde_json::from_slice(body) {
+                    Ok(i) => i,
+                    Err(e) => return (400, json!({ "error": format!("invalid intent: {}", e) })),
+                };
+                if let Err(e) = self.check(&intent) {
+                    return (400, json!({ "error": e.to_string() }));
+                }
+                let sent = intent
+                    .calldata()
+                    .and_then(|data| eth::send_contract_tx(&self.conf, &self.key, &self.registry, &data, &self.tx));
+                match sent {
+                    Ok(tx_hash) => {
+                        log::info!("Relayed {} for {}: {}", intent.action.name(), intent.signer, tx_hash);
+                        (200, json!({ "tx_hash": tx_hash }))
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to relay for {}: {}", intent.signer, e);
+                        (422, json!({ "error": e.to_string() }))
+                    }
+                }
+            }
+            _ => (404, json!({ "error": format!("no route for {} {}", method, path) })),
+        }
+    }
+}
+
+/// Serve the relay API on `listen` until the process is stopped. Each connection gets its
+/// own thread, up to `MAX_CONNECTIONS`; the transaction journal lock keeps their nonces apart.
+pub fn serve(relayer: Relayer, listen: &str) -> Result<()> {
+    let listener = TcpListener::bind(listen).map_err(|e| anyhow!("failed to listen on {}: {}", listen, e))?;
+    let info = relayer.info()?;
+    println!(
+        "Relaying for registry {} on chain {} from {} at http://{}",
+        info.registry,
+        info.chain_id,
+        info.relayer,
+        listener.local_addr()?
+    );
+    let relayer = Arc::new(relayer);
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+            let _ = respond(&mut stream, 503, &json!({ "error": "relayer busy; retry later" }));
+            continue;
+        }
+        let relayer = relayer.clone();
+        let active = active.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = serve_connection(&relayer, stream) {
+                log::warn!("relay connection failed: {}", e);
+            }
+            active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// Method, path and body length of a request.
+#[derive(Debug)]
+struct Head {
+    method: String,
+    path: String,
+    len: usize,
+}
+
+/// Read the request line and headers within `MAX_HEAD_BYTES`, `MAX_HEADERS` and
+/// `REQUEST_TIMEOUT`, so a client cannot hold a connection open by trickling them.
+fn read_head(reader: &mut impl BufRead, started: Instant) -> Result<Head> {
+    let mut budget = MAX_HEAD_BYTES;
+    let mut next_line = || -> Result<String> {
+        if started.elapsed() > REQUEST_TIMEOUT {
+            return Err(anyhow!("request not received within {}s", REQUEST_TIMEOUT.as_secs()));
+        }
+        let mut line = String::new();
+        let n = reader.take(budget as u64 + 1).read_line(&mut line)?;
+        if n > budget {
+            return Err(anyhow!("request headers exceed {} bytes", MAX_HEAD_BYTES));
+        }
+        budget -= n;
+        Ok(line)
+    };
+
+    let request_line = next_line()?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string());
+    let mut len = 0usize;
+    for _ in 0..=MAX_HEADERS {
+        let line = next_line()?;
+        if line.trim_end().is_empty() {
+            return Ok(Head { method, path, len });
+        }
+        if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+            len = v.trim().parse().map_err(|_| anyhow!("invalid Content-Length"))?;
+        }
+    }
+    Err(anyhow!("more than {} request headers", MAX_HEADERS))
+}
+
+fn serve_connection(relayer: &Relayer, mut stream: TcpStream) -> Result<()> {
+    let started = Instant::now();
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_write_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let head = match read_head(&mut reader, started) {
+        Ok(head) => head,
+        Err(e) => {
+            let _ = respond(&mut stream, 400, &json!({ "error": e.to_string() }));
+            return Err(e);
+        }
+    };
+    let (status, reply) = if head.len > MAX_BODY_BYTES {
+        (413, json!({ "error": "request body too large" }))
+    } else {
+        let mut body = vec![0u8; head.len];
+        reader.read_exact(&mut body)?;
+        if started.elapsed() > REQUEST_TIMEOUT {
+            return Err(anyhow!("request not received within {}s", REQUEST_TIMEOUT.as_secs()));
+        }
+        relayer.handle(&head.method, &head.path, &body)
+    };
+    respond(&mut stream, status, &reply)
+}
+
+fn respond(stream: &mut TcpStream, status: u16, reply: &Value) -> Result<()> {
+    let reply = reply.to_string();
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        reply.len(),
+        reply
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [0x11; 32];
+    const REGISTRY: [u8; 20] = [0x22; 20];
+
+    fn share_action() -> Action {
+        Action::share(&[0x33; 32], &[0x44; 20], "QmCid", 1_900_000_000, 3, false)
+    }
+
+    fn relayer(allow: Vec<[u8; 20]>, open: bool) -> Relayer {
+        Relayer {
+            conf: NetworkConfig { chain_id: 31337, rpc_url: "http://127.0.0.1:1".to_string(), ..Default::default() },
+            registry: REGISTRY,
+            key: zeroize::Zeroizing::new(vec![0x55; 32]),
+            tx: TxOptions::default(),
+            allow,
+            open,
+        }
+    }
+
+    #[test]
+    fn intents_verify_and_reject_tampering() {
+        let deadline = crate::wallet::current_timestamp() + 60;
+        let intent = Intent::sign(share_action(), 31337, &REGISTRY, &KEY, 4, deadline).unwrap();
+        let signer = eth::address_bytes_from_secret(&KEY).unwrap();
+        assert_eq!(intent.verify().unwrap(), signer);
+
+        // The wire form is flat JSON tagged by action name, and survives a round trip.
+        let json = serde_json::to_value(&intent).unwrap();
+        assert_eq!(json["action"], "shareSecret");
+        assert_eq!(json["ipfs_cid"], "QmCid");
+        assert_eq!(serde_json::from_value::<Intent>(json).unwrap(), intent);
+
+        let mut redirected = intent.clone();
+        if let Action::ShareSecret { recipient, .. } = &mut redirected.action {
+            *recipient = eth::addr_hex(&[0x66; 20]);
+        }
+        assert!(redirected.verify().is_err());
+        let other_chain = Intent { chain_id: 1, ..intent.clone() };
+        assert!(other_chain.verify().is_err());
+        let replayed = Intent { nonce: 5, ..intent.clone() };
+        assert!(replayed.verify().is_err());
+
+        let revoke = Intent::sign(Action::revoke(&[0x33; 32]), 31337, &REGISTRY, &KEY, 4, deadline).unwrap();
+        assert_eq!(revoke.verify().unwrap(), signer);
+        assert_ne!(revoke.signature, intent.signature);
+    }
+
+    #[test]
+    fn by_sig_calldata_carries_the_request_and_signature() {
+        let intent = Intent::sign(share_action(), 31337, &REGISTRY, &KEY, 0, 2_000_000_000).unwrap();
+        let data = intent.calldata().unwrap();
+        assert_eq!(eth::describe_calldata(&format!("0x{}", bytes_to_hex(&data))), "shareSecretBySig");
+        let word = |i: usize| &data[4 + 32 * i..4 + 32 * (i + 1)];
+        assert_eq!(word(0)[31], 0x80); // request tuple right after the 4 head words
+        assert_eq!(&word(1)[12..], &eth::address_bytes_from_secret(&KEY).unwrap());
+        assert_eq!(word(4), &[0x33; 32]); // request.secretId
+        let sig = hex_to_bytes(&intent.signature).unwrap();
+        assert_eq!(&data[data.len() - 96..data.len() - 31], sig.as_slice());
+
+        let read = Intent::sign(Action::record_read(&[0x33; 32]), 31337, &REGISTRY, &KEY, 0, 2_000_000_000).unwrap();
+        let data = read.calldata().unwrap();
+        assert_eq!(eth::describe_calldata(&format!("0x{}", bytes_to_hex(&data))), "recordReadBySig");
+        assert_eq!(data.len(), 4 + 32 * 5 + 96);
+        assert_eq!(share_action().calldata().unwrap(), eth::encode_share_secret(&[0x33; 32], &[0x44; 20], "QmCid", 1_900_000_000, 3, false));
+    }
+
+    #[test]
+    fn relayer_answers_info_and_rejects_bad_intents_before_sending() {
+        let r = relayer(vec![], true);
+        let (status, info) = r.handle("GET", "/v1/info", b"");
+        assert_eq!(status, 200);
+        assert_eq!(info["chain_id"], 31337);
+        assert_eq!(info["registry"], eth::addr_hex(&REGISTRY));
+        assert_eq!(r.handle("GET", "/nope", b"").0, 404);
+        assert_eq!(r.handle("POST", "/v1/intents", b"{not json").0, 400);
+
+        let deadline = crate::wallet::current_timestamp() + 60;
+        let post = |r: &Relayer, intent: &Intent| r.handle("POST", "/v1/intents", &serde_json::to_vec(intent).unwrap());
+        let wrong_chain = Intent::sign(share_action(), 1, &REGISTRY, &KEY, 0, deadline).unwrap();
+        let (status, body) = post(&r, &wrong_chain);
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("chain 31337"), "{}", body);
+
+        let expired = Intent::sign(share_action(), 31337, &REGISTRY, &KEY, 0, 1).unwrap();
+        assert!(post(&r, &expired).1["error"].as_str().unwrap().contains("expired"));
+
+        let forged = Intent { signer: eth::addr_hex(&[0x77; 20]), ..Intent::sign(share_action(), 31337, &REGISTRY, &KEY, 0, deadline).unwrap() };
+        assert!(post(&r, &forged).1["error"].as_str().unwrap().contains("does not match"));
+
+        let valid = Intent::sign(share_action(), 31337, &REGISTRY, &KEY, 0, deadline).unwrap();
+        let (status, body) = post(&relayer(vec![[0x77; 20]], false), &valid);
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("not allowed"), "{}", body);
+        // Without --open an empty allowlist relays for no one.
+        assert!(post(&relayer(vec![], false), &valid).1["error"].as_str().unwrap().contains("not allowed"));
+    }
+
+    #[test]
+    fn request_heads_are_bounded() {
+        let read = |raw: &[u8]| read_head(&mut std::io::Cursor::new(raw), Instant::now());
+        let head = read(b"POST /v1/intents HTTP/1.1\r\nHost: x\r\nContent-Length: 12\r\n\r\n{}").unwrap();
+        assert_eq!((head.method.as_str(), head.path.as_str(), head.len), ("POST", "/v1/intents", 12));
+
+        let long = format!("GET /v1/info HTTP/1.1\r\nX-Pad: {}\r\n\r\n", "a".repeat(MAX_HEAD_BYTES));
+        assert!(read(long.as_bytes()).unwrap_err().to_string().contains("exceed"));
+        let many = format!("GET /v1/info HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS + 1));
+        assert!(read(many.as_bytes()).unwrap_err().to_string().contains("more than"));
+        let late = read_head(&mut std::io::Cursor::new(&b"GET / HTTP/1.1\r\n\r\n"[..]), Instant::now() - REQUEST_TIMEOUT * 2);
+        assert!(late.is_err());
+    }
+}
@@ -0,0 +1,410 @@
+//! Env schemas for `bsec validate`. A typed schema is a YAML or JSON map from variable name
+//! to its rules:
+//!
+//! ```yaml
+//! PORT: port                  # shorthand for { type: port }
+//! DATABASE_URL:
+//!   type: url
+//!   required: true
+//!   description: Primary database
+//!   sensitive: true
+//! LOG_LEVEL: { type: enum, values: [debug, info, warn], default: info }
+//! ```
+//!
+//! A key-only `.env.schema` (`KEY=` lines) is still read as before: every key is an optional
+//! string, and a missing one is appended empty.
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::dotenv::Document;
+use crate::errors::BsecError;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    #[default]
+    String,
+    Int,
+    Bool,
+    Url,
+    Port,
+    Email,
+    Enum,
+    Duration,
+}
+
+/// A scalar as YAML writes it (`default: 8080`, `values: [1, 2]`), kept as text.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl Scalar {
+    fn into_text(self) -> String {
+        match self {
+            Scalar::Bool(b) => b.to_string(),
+            Scalar::Int(i) => i.to_string(),
+            Scalar::Float(f) => f.to_string(),
+            Scalar::Text(s) => s,
+        }
+    }
+}
+
+/// One variable's rules as written in the schema.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Rules {
+    #[serde(default, rename = "type")]
+    kind: Kind,
+    #[serde(default)]
+    required: bool,
+    default: Option<Scalar>,
+    #[serde(alias = "regex")]
+    pattern: Option<String>,
+    min: Option<f64>,
+    max: Option<f64>,
+    #[serde(default)]
+    values: Vec<Scalar>,
+    description: Option<String>,
+    #[serde(default)]
+    sensitive: bool,
+}
+
+/// A variable of a `Schema`.
+pub struct Var {
+    pub name: String,
+    kind: Kind,
+    /// Must be set to a non-empty value.
+    pub required: bool,
+    /// Appended for a missing key by `bsec validate`.
+    pub default: Option<String>,
+    /// As written, and compiled to match the whole value.
+    pattern: Option<(String, Regex)>,
+    /// Bounds on the number for `int` and `port`, seconds for `duration`, length for `string`.
+    min: Option<f64>,
+    max: Option<f64>,
+    values: Vec<String>,
+    description: Option<String>,
+    /// Its value is never printed in reports.
+    sensitive: bool,
+}
+
+pub struct Schema {
+    pub vars: Vec<Var>,
+    /// A key-only schema as read, so a new env file can copy its layout and comments.
+    pub keys_only: Option<Document>,
+}
+
+impl Schema {
+    /// Read the schema at `path`: YAML or JSON for `.yaml`, `.yml` and `.json`, otherwise a
+    /// key-only env file, or YAML/JSON if it does not parse as one.
+    pub fn parse(path: &Path, content: &str) -> Result<Schema> {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        if !matches!(ext.as_str(), "yaml" | "yml" | "json") {
+            match Document::parse(content) {
+                Ok(doc) => return Ok(Schema::from_keys(doc)),
+                Err(e) => {
+                    if !content.trim_start().starts_with('{') && !content.contains(':') {
+                        return Err(BsecError::ParseError(format!("{}:{}", path.display(), e)).into());
+                    }
+                }
+            }
+        }
+        let invalid = |msg: String| -> anyhow::Error { BsecError::ConfigError(format!("{}: {}", path.display(), msg)).into() };
+        let map: yaml_serde::Mapping = yaml_serde::from_str(content).map_err(|e| invalid(e.to_string()))?;
+        let mut vars = Vec::new();
+        for (name, rules) in map {
+            let name = name.as_str().filter(|n| crate::dotenv::is_key(n)).ok_or_else(|| {
+                invalid(format!("{:?} is not a variable name", name))
+            })?;
+            let rules: Rules = match rules {
+                yaml_serde::Value::Null => Rules::default(),
+                yaml_serde::Value::String(kind) => Rules {
+                    kind: yaml_serde::from_value(yaml_serde::Value::String(kind)).map_err(|e| invalid(format!("{}: {}", name, e)))?,
+                    ..Rules::default()
+                },
+                other => yaml_serde::from_value(other).map_err(|e| invalid(format!("{}: {}", name, e)))?,
+            };
+            vars.push(Var::new(name, rules).map_err(|e| invalid(format!("{}: {}", name, e)))?);
+        }
+        Ok(Schema { vars, keys_only: None })
+    }
+
+    fn from_keys(doc: Document) -> Schema {
+        let vars = doc
+            .keys()
+            .into_iter()
+            .map(|name| Var { default: Some(String::new()), ..Var::string(name) })
+            .collect();
+        Schema { vars, keys_only: Some(doc) }
+    }
+
+    /// A new env file for the schema: each variable with its description as a comment and its
+    /// default, if any.
+    pub fn template(&self) -> Document {
+        if let Some(doc) = &self.keys_only {
+            return doc.to_template(|_| None);
+        }
+        let mut text = String::new();
+        for var in &self.vars {
+            for line in var.description.iter().flat_map(|d| d.lines()) {
+                text.push_str(&format!("# {}\n", line));
+            }
+            text.push_str(&format!("{}=\n", var.name));
+        }
+        let mut doc = Document::parse(&text).unwrap_or_default();
+        for var in &self.vars {
+            if let Some(default) = &var.default {
+                doc.set(&var.name, default);
+            }
+        }
+        doc
+    }
+}
+
+impl Var {
+    fn string(name: String) -> Var {
+        Var {
+            name,
+            kind: Kind::String,
+            required: false,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            values: Vec::new(),
+            description: None,
+            sensitive: false,
+        }
+    }
+
+    fn new(name: &str, rules: Rules) -> Result<Var> {
+        let pattern = match &rules.pattern {
+            Some(p) => Some((p.clone(), Regex::new(&format!("^(?:{})$", p)).map_err(|e| anyhow!("invalid pattern: {}", e))?)),
+            None => None,
+        };
+        let bounded = matches!(rules.kind, Kind::String | Kind::Int | Kind::Port | Kind::Duration);
+        if (rules.min.is_some() || rules.max.is_some()) && !bounded {
+            return Err(anyhow!("min and max apply to string, int, port and duration"));
+        }
+        if let (Some(min), Some(max)) = (rules.min, rules.max)
+            && min > max
+        {
+            return Err(anyhow!("min {} is above max {}", min, max));
+        }
+        if (rules.kind == Kind::Enum) == rules.values.is_empty() {
+            return Err(anyhow!("values are given with, and only with, type enum"));
+        }
+        let var = Var {
+            kind: rules.kind,
+            required: rules.required,
+            default: rules.default.map(Scalar::into_text),
+            pattern,
+            min: rules.min,
+            max: rules.max,
+            values: rules.values.into_iter().map(Scalar::into_text).collect(),
+            description: rules.description,
+            sensitive: rules.sensitive,
+            ..Var::string(name.to_string())
+        };
+        if let Some(default) = &var.default
+            && let Some(problem) = var.check(default).into_iter().next()
+        {
+            return Err(anyhow!("default: {}", problem));
+        }
+        Ok(var)
+    }
+
+    /// What is wrong with the non-empty `value`, if anything. A sensitive value is not quoted.
+    pub fn check(&self, value: &str) -> Vec<String> {
+        let shown = if self.sensitive { "value".to_string() } else { format!("'{}'", value) };
+        let number = match self.kind {
+            Kind::String => Some(value.chars().count() as f64),
+            Kind::Int => value.parse::<i64>().ok().map(|n| n as f64),
+            Kind::Port => value.parse::<u16>().ok().filter(|&p| p != 0).map(f64::from),
+            Kind::Duration => parse_duration(value),
+            Kind::Bool => is_bool(value).then_some(0.0),
+            Kind::Url => is_url(value).then_some(0.0),
+            Kind::Email => is_email(value).then_some(0.0),
+            Kind::Enum => self.values.iter().any(|v| v == value).then_some(0.0),
+        };
+        let mut problems = Vec::new();
+        let Some(number) = number else {
+            let expected = match self.kind {
+                Kind::Enum => format!("one of {}", self.values.join(", ")),
+                Kind::Int => "an int".to_string(),
+                Kind::Port => "a port (1-65535)".to_string(),
+                Kind::Duration => "a duration (e.g. 30s, 1h30m, 250ms)".to_string(),
+                Kind::Bool => "a bool (true/false, 1/0, yes/no, on/off)".to_string(),
+                Kind::Url => "a URL".to_string(),
+                Kind::Email => "an email address".to_string(),
+                Kind::String => unreachable!("any value is a string"),
+            };
+            problems.push(format!("{} is not {}", shown, expected));
+            return problems;
+        };
+        let what = match self.kind {
+            Kind::String => "length",
+            Kind::Duration => "seconds",
+            _ => "value",
+        };
+        if let Some(min) = self.min
+            && number < min
+        {
+            problems.push(format!("{} is below the minimum {} ({} {})", shown, min, what, number));
+        }
+        if let Some(max) = self.max
+            && number > max
+        {
+            problems.push(format!("{} is above the maximum {} ({} {})", shown, max, what, number));
+        }
+        if let Some((pattern, regex)) = &self.pattern
+            && !regex.is_match(value)
+        {
+            problems.push(format!("{} does not match {}", shown, pattern));
+        }
+        problems
+    }
+}
+
+fn is_bool(value: &str) -> bool {
+    ["true", "false", "1", "0", "yes", "no", "on", "off"].contains(&value.to_lowercase().as_str())
+}
+
+/// `scheme://rest`, with no blanks.
+fn is_url(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+        && !rest.is_empty()
+        && !rest.starts_with('/')
+        && !value.chars().any(char::is_whitespace)
+}
+
+/// `local@domain.tld`, with no blanks.
+pub fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.split('.').count() > 1
+        && domain.split('.').all(|label| !label.is_empty())
+        && !value.chars().any(char::is_whitespace)
+}
+
+/// Seconds in a duration such as `90s`, `1h30m` or `250ms` (units `ms`, `s`, `m`, `h`, `d`).
+fn parse_duration(value: &str) -> Option<f64> {
+    let mut total = 0.0;
+    let mut rest = value;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let number: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let scale = match &rest[..unit] {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            _ => return None,
+        };
+        total += number * scale;
+        rest = &rest[unit..];
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(yaml: &str) -> Schema {
+        Schema::parse(Path::new("env.schema.yaml"), yaml).unwrap()
+    }
+
+    fn problems(yaml: &str, value: &str) -> Vec<String> {
+        schema(yaml).vars[0].check(value)
+    }
+
+    #[test]
+    fn key_only_schemas_are_optional_strings() {
+        let s = Schema::parse(Path::new(".env.schema"), "# db\nDATABASE_URL=\nAPI_KEY=x\n").unwrap();
+        let names: Vec<&str> = s.vars.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["DATABASE_URL", "API_KEY"]);
+        assert!(s.vars.iter().all(|v| !v.required && v.default.as_deref() == Some("")));
+        assert_eq!(s.template().to_string(), "# db\nDATABASE_URL=\nAPI_KEY=\n");
+    }
+
+    #[test]
+    fn yaml_and_json_schemas_keep_their_order() {
+        let yaml = "PORT: port\nURL:\n  type: url\n  required: true\n  description: Where to call\nLEVEL: { type: enum, values: [debug, info], default: info }\n";
+        let s = Schema::parse(Path::new(".env.schema"), yaml).unwrap();
+        let names: Vec<&str> = s.vars.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["PORT", "URL", "LEVEL"]);
+        assert!(s.vars[1].required);
+        assert_eq!(s.template().to_string(), "PORT=\n# Where to call\nURL=\nLEVEL=info\n");
+
+        let json = r#"{"B": {"type": "int", "default": 3}, "A": null}"#;
+        let s = Schema::parse(Path::new("schema.json"), json).unwrap();
+        assert_eq!((s.vars[0].name.as_str(), s.vars[0].default.as_deref()), ("B", Some("3")));
+        assert_eq!(s.vars[1].kind, Kind::String);
+    }
+
+    #[test]
+    fn values_are_checked_by_type() {
+        assert!(problems("P: port", "8080").is_empty());
+        assert_eq!(problems("P: port", "70000"), ["'70000' is not a port (1-65535)"]);
+        assert!(problems("N: int", "-4").is_empty() && !problems("N: int", "4.5").is_empty());
+        assert!(problems("B: bool", "Yes").is_empty() && !problems("B: bool", "maybe").is_empty());
+        assert!(problems("U: url", "postgres://db:5432/app").is_empty());
+        assert!(!problems("U: url", "db:5432").is_empty() && !problems("U: url", "http:///x").is_empty());
+        assert!(problems("E: email", "ops@example.com").is_empty() && !problems("E: email", "ops@localhost").is_empty());
+        assert!(problems("D: duration", "1h30m").is_empty() && !problems("D: duration", "30").is_empty());
+        assert_eq!(
+            problems("L: { type: enum, values: [a, b] }", "c"),
+            ["'c' is not one of a, b"]
+        );
+    }
+
+    #[test]
+    fn bounds_patterns_and_sensitive_values() {
+        assert_eq!(
+            problems("T: { type: duration, max: 60 }", "2m"),
+            ["'2m' is above the maximum 60 (seconds 120)"]
+        );
+        assert_eq!(problems("N: { type: int, min: 1 }", "0"), ["'0' is below the minimum 1 (value 0)"]);
+        assert_eq!(
+            problems("K: { min: 8, pattern: 'sk_[a-z]+', sensitive: true }", "sk_1"),
+            ["value is below the minimum 8 (length 4)", "value does not match sk_[a-z]+"]
+        );
+        // The pattern covers the whole value.
+        assert!(!problems("K: { regex: '[a-z]+' }", "abc1").is_empty());
+    }
+
+    #[test]
+    fn invalid_schemas_are_refused() {
+        let err = |yaml: &str| Schema::parse(Path::new("s.yaml"), yaml).err().unwrap().to_string();
+        assert!(err("P: { type: port, default: 0 }").contains("P: default: '0' is not a port"));
+        assert!(err("P: { type: colour }").contains("unknown variant"));
+        assert!(err("P: { type: enum }").contains("type enum"));
+        assert!(err("P: { type: bool, min: 1 }").contains("min and max"));
+        assert!(err("P: { pattern: '(' }").contains("invalid pattern"));
+        assert!(err("P: { typo: 1 }").contains("unknown field"));
+        assert!(err("1BAD: int").contains("not a variable name"));
+    }
+}
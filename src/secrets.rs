@@ -9,10 +9,17 @@ use serde::{Deserialize, Serialize};
 use zeroize::Zeroizing;
 
 use crate::blockchain::{
-    get_secret_info_on_chain, hide_secret_on_chain, list_secrets_on_chain, record_read_on_chain, register_secret_on_chain,
-    revoke_secret_on_chain,
+    build_register_secret, bytes_to_checksum_address, encode_bytes32_hex, estimate_batch, estimate_record_read,
+    estimate_register_secret, estimate_revoke, get_secret_info_on_chain, hide_secret_on_chain, list_secrets_on_chain,
+    record_read_on_chain, register_secret_on_chain, revoke_secret_on_chain, send_batch, BatchItem, OnChainSecretInfo,
+};
+use crate::errors::BsecError;
+use crate::relay;
+use crate::eth::{PortableTx, TxEstimate, TxOptions};
+use crate::ipfs::{
+    cache_key_from_wallet, enforce_cache_limit, fetch_from_ipfs, remove_cached, unpin_from_ipfs, upload_to_ipfs,
+    UnpinReport,
 };
-use crate::ipfs::{fetch_from_ipfs, upload_to_ipfs};
 use crate::wallet::{bytes_to_hex, hash_digest, hex_to_bytes};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -120,7 +127,7 @@ pub fn parse_duration(ttl_str: &str) -> Result<u64> {
 
 /// Derive an AES-256 wrapper key from a raw ECDH shared secret using HKDF-SHA256 with a
 /// fixed domain-separation label, instead of a bare SHA-256 of the shared X coordinate.
-fn derive_ecdh_key(shared_secret: &[u8]) -> Result<[u8; 32]> {
+pub fn derive_ecdh_key(shared_secret: &[u8]) -> Result<[u8; 32]> {
     let hk = hkdf::Hkdf::<sha2::Sha256>::new(None, shared_secret);
     let mut okm = [0u8; 32];
     hk.expand(b"bsec-ecdh-aes256gcm-v1", &mut okm)
@@ -128,7 +135,7 @@ fn derive_ecdh_key(shared_secret: &[u8]) -> Result<[u8; 32]> {
     Ok(okm)
 }
 
-fn encrypt_text(plain: &str, key_bytes: &[u8; 32]) -> Result<String> {
+pub fn encrypt_text(plain: &str, key_bytes: &[u8; 32]) -> Result<String> {
     let cipher = Aes256Gcm::new_from_slice(key_bytes).map_err(|_| anyhow!("key init failed"))?;
     let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
     let cipher_text = cipher
@@ -141,7 +148,7 @@ fn encrypt_text(plain: &str, key_bytes: &[u8; 32]) -> Result<String> {
     ))
 }
 
-fn decrypt_text(cipher_str: &str, key_bytes: &[u8; 32]) -> Result<String> {
+pub fn decrypt_text(cipher_str: &str, key_bytes: &[u8; 32]) -> Result<String> {
     let cipher = Aes256Gcm::new_from_slice(key_bytes).map_err(|_| anyhow!("key init failed"))?;
     let parts: Vec<&str> = cipher_str.split(':').collect();
     if parts.len() != 2 {
@@ -179,7 +186,7 @@ fn open_member_bodies(members: &mut [BundleMember], key: &[u8; 32]) -> Result<()
 }
 
 /// Metadata describing how a shared secret should be materialized to file(s).
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ShareMeta {
     pub kind: Option<SecretKind>,
     pub filename: Option<String>,
@@ -189,8 +196,12 @@ pub struct ShareMeta {
     pub members: Option<Vec<BundleMember>>,
 }
 
-fn resolve_recipient_pubkey(to_address: &str, sender_info: &crate::wallet::WalletInfo) -> Result<Option<PublicKey>> {
-    if to_address.to_lowercase() == sender_info.address.to_lowercase() || to_address == sender_info.public_key {
+/// Recipient public key for `to_address`. Sharing to yourself by address needs the unlocked
+/// wallet; without one (an unsigned build) the public key must be given.
+fn resolve_recipient_pubkey(to_address: &str, sender_info: Option<&crate::wallet::WalletInfo>) -> Result<Option<PublicKey>> {
+    if let Some(sender_info) = sender_info
+        && (to_address.to_lowercase() == sender_info.address.to_lowercase() || to_address == sender_info.public_key)
+    {
         let pub_bytes = hex_to_bytes(&sender_info.public_key)?;
         return PublicKey::from_sec1_bytes(&pub_bytes)
             .map(Some)
@@ -216,15 +227,81 @@ fn resolve_recipient_pubkey(to_address: &str, sender_info: &crate::wallet::Walle
     .into())
 }
 
-pub fn share_secret(
+/// On-chain recipient is an EVM address: zero for public, else derived from the recipient's
+/// public key (real ECDH confidentiality is enforced separately).
+fn onchain_recipient(is_public: bool, recipient_pubkey: Option<&PublicKey>) -> [u8; 20] {
+    let mut addr = [0u8; 20];
+    if let (false, Some(pk)) = (is_public, recipient_pubkey) {
+        let ep = pk.to_encoded_point(false);
+        let hash = crate::blockchain::keccak256(&ep.as_bytes()[1..]);
+        addr.copy_from_slice(&hash[12..32]);
+    }
+    addr
+}
+
+/// Estimate the fees of `share_secret` without uploading or sending anything. The real CID is
+/// not known yet, so a CIDv0-length placeholder stands in for it.
+pub fn estimate_share(
+    ttl_str: &str,
+    max_reads: u32,
+    to_address: &str,
+    password: Option<&str>,
+    tx: &TxOptions,
+) -> Result<TxEstimate> {
+    let ttl_secs = parse_duration(ttl_str)?;
+    let sender_info = crate::wallet::get_wallet_info(password)?;
+    let recipient_pubkey = resolve_recipient_pubkey(to_address, Some(&sender_info))?;
+    let is_public = to_address == "public";
+    let seed = format!("dry-run:{}:{}", sender_info.address, crate::wallet::current_timestamp());
+    let secret_id = format!("0x{}", bytes_to_hex(&hash_digest(seed.as_bytes())));
+    let placeholder_cid = format!("Qm{}", "1".repeat(44));
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&sender_info.private_key)?);
+    estimate_register_secret(
+        &priv_bytes,
+        &secret_id,
+        &onchain_recipient(is_public, recipient_pubkey.as_ref()),
+        &placeholder_cid,
+        crate::wallet::current_timestamp() + ttl_secs,
+        max_reads,
+        is_public,
+        tx,
+    )
+}
+
+/// A share encrypted and uploaded to IPFS, not yet registered on-chain.
+struct SealedShare {
+    record: SecretRecord,
+    recipient_addr: [u8; 20],
+    ipfs_cid: String,
+    is_public: bool,
+}
+
+impl SealedShare {
+    /// The shareSecret write registering this share.
+    fn action(&self) -> Result<relay::Action> {
+        Ok(relay::Action::share(
+            &encode_bytes32_hex(&self.record.id)?,
+            &self.recipient_addr,
+            &self.ipfs_cid,
+            self.record.expires_at,
+            self.record.max_reads,
+            self.is_public,
+        ))
+    }
+}
+
+/// Encrypt `content` for the recipient and upload the payload. Needs no wallet key.
+#[allow(clippy::too_many_arguments)]
+fn seal_share(
     content: &str,
     ttl_str: &str,
     max_reads: u32,
     to_address: &str,
     sender_address: &str,
-    password: Option<&str>,
+    recipient_pubkey_opt: Option<PublicKey>,
     meta: ShareMeta,
-) -> Result<SecretRecord> {
+    cache_key: Option<&crate::ipfs::CacheKey>,
+) -> Result<SealedShare> {
     let members_size: usize = meta
         .members
         .as_ref()
@@ -238,9 +315,6 @@ pub fn share_secret(
     let now = crate::wallet::current_timestamp();
     let expires_at = now.checked_add(ttl_secs).ok_or_else(|| anyhow!("expiry timestamp overflow"))?;
 
-    let sender_info = crate::wallet::get_wallet_info(password)?;
-    let recipient_pubkey_opt = resolve_recipient_pubkey(to_address, &sender_info)?;
-
     let ephemeral_secret = SecretKey::random(&mut OsRng);
     let ephemeral_public = ephemeral_secret.public_key();
     let ephemeral_pub_hex = format!("0x{}", bytes_to_hex(ephemeral_public.to_encoded_point(false).as_bytes()));
@@ -285,7 +359,7 @@ pub fn share_secret(
     };
     let payload_json = serde_json::to_string(&payload)?;
 
-    let ipfs_cid = upload_to_ipfs(&payload_json)?;
+    let ipfs_cid = upload_to_ipfs(&payload_json, cache_key)?;
 
     // Full 256-bit id (0x + 64 hex) so encode_bytes32_hex maps it losslessly onto the
     // contract's bytes32 key. The previous 16-hex-char id was only 64 bits and, being
@@ -296,34 +370,10 @@ pub fn share_secret(
 
     let is_public = to_address == "public";
 
-    // On-chain recipient is an EVM address: zero for public, else derived from the
-    // recipient's public key (real ECDH confidentiality is enforced separately).
-    let recipient_addr: [u8; 20] = if is_public {
-        [0u8; 20]
-    } else if let Some(ref pk) = recipient_pubkey_opt {
-        let ep = pk.to_encoded_point(false);
-        let hash = crate::blockchain::keccak256(&ep.as_bytes()[1..]);
-        let mut addr = [0u8; 20];
-        addr.copy_from_slice(&hash[12..32]);
-        addr
-    } else {
-        [0u8; 20]
-    };
-
-    let priv_bytes = Zeroizing::new(hex_to_bytes(&sender_info.private_key)?);
-
-    register_secret_on_chain(
-        &priv_bytes,
-        &secret_id,
-        &recipient_addr,
-        &ipfs_cid,
-        expires_at,
-        max_reads,
-        is_public,
-    )?;
+    let recipient_addr = onchain_recipient(is_public, recipient_pubkey_opt.as_ref());
 
     let record = SecretRecord {
-        id: secret_id.clone(),
+        id: secret_id,
         sender: sender_address.to_string(),
         recipient: to_address.to_string(),
         content: encrypted_content,
@@ -336,18 +386,175 @@ pub fn share_secret(
         hidden: false,
     };
 
+    Ok(SealedShare { record, recipient_addr, ipfs_cid, is_public })
+}
+
+/// Unpin the payloads of shares that `err` refused to send over the fee cap, so a refusal
+/// leaves nothing pinned behind. Any other error may have come after the transaction went
+/// out, so the payloads stay. Returns `err`.
+fn unpin_if_refused(err: anyhow::Error, cids: &[&str]) -> anyhow::Error {
+    if matches!(err.downcast_ref::<BsecError>(), Some(BsecError::FeeCapExceeded { .. })) {
+        for cid in cids {
+            if let Err(e) = unpin_from_ipfs(cid) {
+                log::warn!("Could not unpin {} after the fee cap refused its share: {}", cid, e);
+            }
+        }
+    }
+    err
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn share_secret(
+    content: &str,
+    ttl_str: &str,
+    max_reads: u32,
+    to_address: &str,
+    sender_address: &str,
+    password: Option<&str>,
+    meta: ShareMeta,
+    tx: &TxOptions,
+) -> Result<SecretRecord> {
+    let sender_info = crate::wallet::get_wallet_info(password)?;
+    let recipient_pubkey_opt = resolve_recipient_pubkey(to_address, Some(&sender_info))?;
+    let cache_key = cache_key_from_wallet(&sender_info.private_key)?;
+    let sealed = seal_share(
+        content,
+        ttl_str,
+        max_reads,
+        to_address,
+        sender_address,
+        recipient_pubkey_opt,
+        meta,
+        cache_key.as_ref(),
+    )?;
+
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&sender_info.private_key)?);
+    let record = sealed.record;
+    register_secret_on_chain(
+        &priv_bytes,
+        &record.id,
+        &sealed.recipient_addr,
+        &sealed.ipfs_cid,
+        record.expires_at,
+        record.max_reads,
+        sealed.is_public,
+        tx,
+    )
+    .map_err(|e| unpin_if_refused(e, &[&sealed.ipfs_cid]))?;
+
     Ok(record)
 }
 
-pub fn view_secret(secret_id: &str, user_address: &str, password: Option<&str>) -> Result<String> {
-    Ok(view_payload(secret_id, user_address, password)?.content)
+/// `share_secret` to several recipients: each gets its own secret, encrypted to them alone,
+/// and all are registered in one multicall transaction. Every recipient is resolved before
+/// anything is uploaded. Returns each record with the outcome of its registration.
+#[allow(clippy::too_many_arguments)]
+pub fn share_secret_many(
+    content: &str,
+    ttl_str: &str,
+    max_reads: u32,
+    recipients: &[String],
+    sender_address: &str,
+    password: Option<&str>,
+    meta: ShareMeta,
+    tx: &TxOptions,
+) -> Result<Vec<(SecretRecord, BatchItem)>> {
+    let sender_info = crate::wallet::get_wallet_info(password)?;
+    let pubkeys = recipients
+        .iter()
+        .map(|to| resolve_recipient_pubkey(to, Some(&sender_info)))
+        .collect::<Result<Vec<_>>>()?;
+    let cache_key = cache_key_from_wallet(&sender_info.private_key)?;
+
+    let mut records = Vec::new();
+    let mut writes = Vec::new();
+    let mut cids = Vec::new();
+    for (to, pubkey) in recipients.iter().zip(pubkeys) {
+        let sealed = seal_share(content, ttl_str, max_reads, to, sender_address, pubkey, meta.clone(), cache_key.as_ref())?;
+        writes.push((sealed.record.id.clone(), sealed.action()?));
+        cids.push(sealed.ipfs_cid);
+        records.push(sealed.record);
+    }
+
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&sender_info.private_key)?);
+    let items = send_batch(&priv_bytes, writes, tx)
+        .map_err(|e| unpin_if_refused(e, &cids.iter().map(String::as_str).collect::<Vec<_>>()))?;
+    Ok(records.into_iter().zip(items).collect())
+}
+
+/// Estimate the fees of `share_secret_many`, with placeholder CIDs as in `estimate_share`.
+pub fn estimate_share_many(
+    ttl_str: &str,
+    max_reads: u32,
+    recipients: &[String],
+    password: Option<&str>,
+    tx: &TxOptions,
+) -> Result<TxEstimate> {
+    let ttl_secs = parse_duration(ttl_str)?;
+    let sender_info = crate::wallet::get_wallet_info(password)?;
+    let now = crate::wallet::current_timestamp();
+    let mut writes = Vec::new();
+    for to in recipients {
+        let recipient_pubkey = resolve_recipient_pubkey(to, Some(&sender_info))?;
+        let is_public = to == "public";
+        let seed = format!("dry-run:{}:{}:{}", sender_info.address, to, now);
+        let id32 = hash_digest(seed.as_bytes());
+        let action = relay::Action::share(
+            &id32,
+            &onchain_recipient(is_public, recipient_pubkey.as_ref()),
+            &format!("Qm{}", "1".repeat(44)),
+            now + ttl_secs,
+            max_reads,
+            is_public,
+        );
+        writes.push((format!("0x{}", bytes_to_hex(&id32)), action));
+    }
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&sender_info.private_key)?);
+    estimate_batch(&priv_bytes, writes, tx)
+}
+
+/// `share_secret` for an air-gapped wallet: encrypt and upload the payload, then return the
+/// unsigned `shareSecret` transaction from `from_address` for `bsec tx sign`. The recipient
+/// must be a public key or `public`.
+#[allow(clippy::too_many_arguments)]
+pub fn build_share(
+    content: &str,
+    ttl_str: &str,
+    max_reads: u32,
+    to_address: &str,
+    from_address: &str,
+    meta: ShareMeta,
+    tx: &TxOptions,
+    nonce: Option<u64>,
+) -> Result<(SecretRecord, PortableTx)> {
+    let from = crate::eth::parse_address(from_address)?;
+    let recipient_pubkey_opt = resolve_recipient_pubkey(to_address, None)?;
+    let sealed = seal_share(content, ttl_str, max_reads, to_address, from_address, recipient_pubkey_opt, meta, None)?;
+    let record = sealed.record;
+    let unsigned = build_register_secret(
+        &from,
+        &record.id,
+        &sealed.recipient_addr,
+        &sealed.ipfs_cid,
+        record.expires_at,
+        record.max_reads,
+        sealed.is_public,
+        tx,
+        nonce,
+    )
+    .map_err(|e| unpin_if_refused(e, &[&sealed.ipfs_cid]))?;
+    Ok((record, unsigned))
+}
+
+pub fn view_secret(secret_id: &str, user_address: &str, password: Option<&str>, tx: &TxOptions) -> Result<String> {
+    Ok(view_payload(secret_id, user_address, password, tx)?.content)
 }
 
 /// Decrypt a secret and return the WHOLE payload (metadata + plaintext content + plaintext
 /// member bodies). Performs authorization, expiry/read-limit checks, and consumes exactly
 /// one on-chain read — a bundle counts as one read, not N. Materialize needs this instead
 /// of the flattened `view_secret` string because it must see kind/filename/members/no_export.
-pub fn view_payload(secret_id: &str, user_address: &str, password: Option<&str>) -> Result<IpfsPayload> {
+pub fn view_payload(secret_id: &str, user_address: &str, password: Option<&str>, tx: &TxOptions) -> Result<IpfsPayload> {
     let onchain_info = get_secret_info_on_chain(secret_id)?;
 
     let wallet_info = crate::wallet::get_wallet_info(password)?;
@@ -365,21 +572,36 @@ pub fn view_payload(secret_id: &str, user_address: &str, password: Option<&str>)
         return Err(crate::errors::BsecError::PermissionDenied("you may not view this secret".into()).into());
     }
 
+    // A payload that can no longer be read has no business staying in the local cache.
     if onchain_info.revoked {
-        return Err(anyhow!("Secret with ID '{}' has been revoked.", secret_id));
+        remove_cached(&onchain_info.ipfs_cid);
+        return Err(crate::errors::BsecError::SecretRevoked(secret_id.to_string()).into());
     }
 
     let now = crate::wallet::current_timestamp();
     if now > onchain_info.expires_at {
-        return Err(crate::errors::BsecError::SecretExpired.into());
+        remove_cached(&onchain_info.ipfs_cid);
+        return Err(crate::errors::BsecError::SecretExpiredAt {
+            id: secret_id.to_string(),
+            expires_at: onchain_info.expires_at,
+            now,
+        }
+        .into());
     }
 
     // Public secrets are not read-limited (the contract does not enforce maxReads for them).
     if !onchain_info.is_public && onchain_info.read_count >= onchain_info.max_reads {
-        return Err(crate::errors::BsecError::SecretExpired.into());
+        remove_cached(&onchain_info.ipfs_cid);
+        return Err(crate::errors::BsecError::ReadLimitReached {
+            id: secret_id.to_string(),
+            read_count: onchain_info.read_count,
+            max_reads: onchain_info.max_reads,
+        }
+        .into());
     }
 
-    let payload_str = fetch_from_ipfs(&onchain_info.ipfs_cid)?;
+    let cache_key = cache_key_from_wallet(&wallet_info.private_key)?;
+    let payload_str = fetch_from_ipfs(&onchain_info.ipfs_cid, cache_key.as_ref())?;
     let payload: IpfsPayload = serde_json::from_str(&payload_str)?;
 
     let wrapper_key = if onchain_info.is_public || onchain_info.recipient == "public" {
@@ -434,7 +656,7 @@ pub fn view_payload(secret_id: &str, user_address: &str, password: Option<&str>)
     };
 
     let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet_info.private_key)?);
-    record_read_on_chain(&priv_bytes, secret_id)?;
+    record_read_on_chain(&priv_bytes, secret_id, tx)?;
     crate::blockchain::index_note(secret_id, "recipient");
 
     Ok(IpfsPayload {
@@ -450,16 +672,30 @@ pub fn view_payload(secret_id: &str, user_address: &str, password: Option<&str>)
     })
 }
 
+/// Estimate the `recordRead` transaction a view/materialize/run of this secret would send.
+/// The contract's own checks run in `eth_estimateGas`, so an expired, exhausted or foreign
+/// secret fails here just as the real read would.
+pub fn estimate_read(secret_id: &str, password: Option<&str>, tx: &TxOptions) -> Result<TxEstimate> {
+    let wallet_info = crate::wallet::get_wallet_info(password)?;
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet_info.private_key)?);
+    estimate_record_read(&priv_bytes, secret_id, tx)
+}
+
 /// Decrypt a secret and flatten it into a KEY=VALUE map (env or JSON object). Retained helper
 /// for env-style consumers; `run --secret` uses `view_payload` directly so it can also stage
 /// file-kind and bundle secrets (calling this would double-count the on-chain read).
 #[allow(dead_code)]
-pub fn load_secret_as_env(secret_id: &str, user_address: &str, password: Option<&str>) -> Result<std::collections::BTreeMap<String, String>> {
-    let decrypted_content = view_secret(secret_id, user_address, password)?;
+pub fn load_secret_as_env(
+    secret_id: &str,
+    user_address: &str,
+    password: Option<&str>,
+    tx: &TxOptions,
+) -> Result<std::collections::BTreeMap<String, String>> {
+    let decrypted_content = view_secret(secret_id, user_address, password, tx)?;
     if decrypted_content.trim().starts_with('{') {
         crate::env_file::parse_json_content(&decrypted_content)
     } else {
-        Ok(crate::env_file::parse_env_content(&decrypted_content))
+        crate::env_file::parse_env_content(&decrypted_content)
     }
 }
 
@@ -492,10 +728,192 @@ pub fn list_secrets(
     Ok(results)
 }
 
-pub fn revoke_secret(secret_id: &str, password: Option<&str>) -> Result<()> {
+/// Revoke a secret on-chain, then unpin its payload and drop the local cache entry.
+/// The revocation is authoritative; unpinning is best-effort and described by the report.
+pub fn revoke_secret(secret_id: &str, password: Option<&str>, tx: &TxOptions) -> Result<UnpinReport> {
     let wallet_info = crate::wallet::get_wallet_info(password)?;
     let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet_info.private_key)?);
-    revoke_secret_on_chain(&priv_bytes, secret_id)
+    let info = get_secret_info_on_chain(secret_id)?;
+    revoke_secret_on_chain(&priv_bytes, secret_id, tx)?;
+    // The revocation is already on-chain: an unpin failure belongs in the report, not in
+    // an error that would read as if the secret were still live.
+    let report = unpin_from_ipfs(&info.ipfs_cid).unwrap_or_else(|e| UnpinReport {
+        cid: info.ipfs_cid.clone(),
+        error: Some(e.to_string()),
+        ..Default::default()
+    });
+    crate::blockchain::index_set_collected(secret_id);
+    Ok(report)
+}
+
+/// How `recipient` (`public`, an address, or a public key) appears as an on-chain recipient.
+fn onchain_recipient_label(recipient: &str) -> String {
+    match resolve_recipient_pubkey(recipient, None) {
+        Ok(Some(pk)) => bytes_to_checksum_address(&onchain_recipient(false, Some(&pk))),
+        _ => recipient.to_string(),
+    }
+}
+
+/// Readable secrets in the local index that this wallet shared, optionally only those
+/// shared with `recipient`.
+fn sent_secrets(sender_address: &str, recipient: Option<&str>) -> Result<Vec<(String, OnChainSecretInfo)>> {
+    let recipient = recipient.map(|r| onchain_recipient_label(r).to_lowercase());
+    Ok(list_secrets_on_chain(sender_address, None, false, false, true)?
+        .into_iter()
+        .filter(|(_, info)| info.sender.eq_ignore_ascii_case(sender_address))
+        .filter(|(_, info)| recipient.as_ref().is_none_or(|r| info.recipient.to_lowercase() == *r))
+        .collect())
+}
+
+/// Revoke every readable secret this wallet shared (only those shared with `recipient`, if
+/// given) in one multicall transaction, then unpin the payloads of those revoked. Returns
+/// each secret's outcome, with its unpin report when it was revoked (carrying the error if
+/// the payload could not be unpinned).
+pub fn revoke_sent(recipient: Option<&str>, password: Option<&str>, tx: &TxOptions) -> Result<Vec<(BatchItem, Option<UnpinReport>)>> {
+    let wallet_info = crate::wallet::get_wallet_info(password)?;
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet_info.private_key)?);
+    let targets = sent_secrets(&wallet_info.address, recipient)?;
+    if targets.is_empty() {
+        return Ok(Vec::new());
+    }
+    let writes = targets
+        .iter()
+        .map(|(id, _)| Ok((id.clone(), relay::Action::revoke(&encode_bytes32_hex(id)?))))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut results = Vec::new();
+    for (item, (_, info)) in send_batch(&priv_bytes, writes, tx)?.into_iter().zip(&targets) {
+        let report = if item.tx_hash.is_some() {
+            crate::blockchain::index_set_collected(&item.secret_id);
+            // The revocation is already on-chain: a payload that cannot be unpinned is
+            // reported with its secret rather than losing the other reports.
+            Some(unpin_from_ipfs(&info.ipfs_cid).unwrap_or_else(|e| UnpinReport {
+                cid: info.ipfs_cid.clone(),
+                error: Some(e.to_string()),
+                ..Default::default()
+            }))
+        } else {
+            None
+        };
+        results.push((item, report));
+    }
+    Ok(results)
+}
+
+/// Estimate the multicall of `revoke_sent`.
+pub fn estimate_revoke_sent(recipient: Option<&str>, password: Option<&str>, tx: &TxOptions) -> Result<TxEstimate> {
+    let wallet_info = crate::wallet::get_wallet_info(password)?;
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet_info.private_key)?);
+    let writes = sent_secrets(&wallet_info.address, recipient)?
+        .iter()
+        .map(|(id, _)| Ok((id.clone(), relay::Action::revoke(&encode_bytes32_hex(id)?))))
+        .collect::<Result<Vec<_>>>()?;
+    if writes.is_empty() {
+        return Err(anyhow!("no readable secrets shared by this wallet match"));
+    }
+    estimate_batch(&priv_bytes, writes, tx)
+}
+
+fn unreadable_reason(info: &OnChainSecretInfo) -> Option<&'static str> {
+    if info.revoked {
+        Some("revoked")
+    } else if info.is_expired {
+        Some("expired")
+    } else if info.limit_reached {
+        Some("read limit reached")
+    } else {
+        None
+    }
+}
+
+/// Estimate the revocation transaction of `revoke_secret` (also what `hide` sends).
+pub fn estimate_revoke_secret(secret_id: &str, password: Option<&str>, tx: &TxOptions) -> Result<TxEstimate> {
+    let wallet_info = crate::wallet::get_wallet_info(password)?;
+    let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet_info.private_key)?);
+    estimate_revoke(&priv_bytes, secret_id, tx)
+}
+
+/// One secret swept (or, on a dry run, selected) by `gc_secrets`.
+#[derive(Serialize, Debug, Clone)]
+pub struct GcEntry {
+    pub id: String,
+    pub reason: String,
+    pub report: UnpinReport,
+}
+
+/// Sweep the local index for secrets that can no longer be read (revoked, expired, or at
+/// their read limit) and release their payloads. Secrets this wallet created are unpinned
+/// from the pinning service and Kubo; secrets it only received just lose their cache entry.
+/// An unpin failure is recorded in that entry's report and the sweep continues; the secret
+/// stays in the index so the next run retries it.
+pub fn gc_secrets(dry_run: bool) -> Result<Vec<GcEntry>> {
+    let mut swept = Vec::new();
+    for (id, role) in crate::blockchain::index_uncollected() {
+        let info = match get_secret_info_on_chain(&id) {
+            Ok(i) => i,
+            Err(e) => {
+                log::warn!("Skipping secret '{}' in gc (chain read failed): {}", id, e);
+                continue;
+            }
+        };
+        let Some(reason) = unreadable_reason(&info) else {
+            continue;
+        };
+
+        let report = if dry_run {
+            UnpinReport { cid: info.ipfs_cid.clone(), ..Default::default() }
+        } else {
+            let report = if role == "sender" {
+                // One payload that cannot be unpinned must not stop the rest of the sweep.
+                unpin_from_ipfs(&info.ipfs_cid).unwrap_or_else(|e| UnpinReport {
+                    cid: info.ipfs_cid.clone(),
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                })
+            } else {
+                UnpinReport {
+                    cid: info.ipfs_cid.clone(),
+                    cache: remove_cached(&info.ipfs_cid),
+                    ..Default::default()
+                }
+            };
+            if report.error.is_none() {
+                crate::blockchain::index_set_collected(&id);
+            }
+            report
+        };
+        swept.push(GcEntry { id, reason: reason.to_string(), report });
+    }
+    Ok(swept)
+}
+
+/// Result of `prune_cache`.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PruneReport {
+    /// Entries dropped because their secret is revoked, expired or at its read limit.
+    pub unreadable_entries: usize,
+    /// Entries evicted to bring the cache under `ipfs.cache_max_bytes`.
+    pub lru_entries: usize,
+    pub lru_bytes: u64,
+}
+
+/// Drop cache entries of indexed secrets that can no longer be read, then apply the size cap.
+/// Unlike `gc_secrets` this never touches pins.
+pub fn prune_cache() -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+    for (id, _) in crate::blockchain::index_uncollected() {
+        match get_secret_info_on_chain(&id) {
+            Ok(info) if unreadable_reason(&info).is_some() => {
+                if remove_cached(&info.ipfs_cid) {
+                    report.unreadable_entries += 1;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Skipping secret '{}' in cache prune (chain read failed): {}", id, e),
+        }
+    }
+    (report.lru_entries, report.lru_bytes) = enforce_cache_limit();
+    Ok(report)
 }
 
 pub fn hide_secret(
@@ -503,6 +921,7 @@ pub fn hide_secret(
     user_filter: Option<&str>,
     user_address: &str,
     password: Option<&str>,
+    tx: &TxOptions,
 ) -> Result<usize> {
     let wallet_info = crate::wallet::get_wallet_info(password)?;
     let priv_bytes = Zeroizing::new(hex_to_bytes(&wallet_info.private_key)?);
@@ -520,7 +939,7 @@ pub fn hide_secret(
         if matches_id && matches_filter {
             // Hiding sets local visibility (`hidden = true`) and best-effort revokes on-chain
             // if this wallet is the creator; revocation failure is logged, local hide still applies.
-            if hide_secret_on_chain(&priv_bytes, &id).is_ok() {
+            if hide_secret_on_chain(&priv_bytes, &id, tx).is_ok() {
                 hidden_count += 1;
             }
         }
@@ -0,0 +1,373 @@
+//! File-backed registry for `network = "team"`, for organizations that cannot use a chain.
+//!
+//! The authoritative records live in a shared directory (`rpc_url`): an NFS share, or a clone
+//! of a git repository every member can push to. Each state change is an event file holding
+//! the registry write signed by the acting wallet (an EIP-712 intent, as for the relayer):
+//!
+//! ```text
+//! <dir>/secrets/<0x secret id>/<sequence>-<signature hash>.json
+//! <dir>/payloads/<cid>
+//! ```
+//!
+//! Nothing in the directory is trusted as state. Reads replay a secret's events in order (the
+//! intent nonce is the event's position in the secret's log, then signed time), verifying each
+//! signature and applying the contract's rules (`mock_chain::apply`) with the signer as caller
+//! and the signed time as `block.timestamp`; an event with a bad signature, or one the rules
+//! reject (a revoke by someone other than the sender, a read past the limit), is skipped.
+//! Writes check the same rules against the current state first and fail with the error the
+//! contract would give. Event files are named by content, and an event found under two names
+//! (a copy, or the same event merged from two clones) is replayed once.
+//!
+//! In a git clone, bsec pulls when it opens the registry, and before reading a payload it does
+//! not have, and commits and pushes each write. Two members writing from stale clones at once
+//! can both succeed locally; the replay after merging keeps the earlier event (e.g. only
+//! `max_reads` reads count), but a reader whose read is dropped this way has already seen the
+//! payload. Revocation deletes the payload from the directory; in git it stays in history,
+//! encrypted.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::blockchain::{keccak256, RegistryBackend};
+use crate::eth::{self, DecodedSecretInfo, TxOptions};
+use crate::ipfs::{PayloadStore, UnpinReport};
+use crate::mock_chain::{self, Secrets};
+use crate::network_config::NetworkConfig;
+use crate::relay::{Action, Intent};
+use crate::wallet::bytes_to_hex;
+
+/// EIP-712 `verifyingContract` of team registry events; there is no contract.
+const TEAM_DOMAIN: [u8; 20] = [0u8; 20];
+
+/// The shared directory of `conf`.
+pub fn team_dir(conf: &NetworkConfig) -> Result<PathBuf> {
+    match conf.rpc_url.trim() {
+        "" => Err(anyhow!(
+            "network \"team\" needs the shared registry directory: bsec config --network team --rpc <DIR>"
+        )),
+        dir => Ok(PathBuf::from(dir)),
+    }
+}
+
+/// The team registry in one shared directory.
+pub struct TeamRegistry {
+    dir: PathBuf,
+    chain_id: u32,
+}
+
+impl TeamRegistry {
+    pub fn open(conf: &NetworkConfig) -> Result<Self> {
+        let dir = team_dir(conf)?;
+        if !dir.is_dir() {
+            return Err(anyhow!("team registry directory {} does not exist", dir.display()));
+        }
+        sync(&dir);
+        Ok(Self { dir, chain_id: conf.chain_id })
+    }
+
+    fn events_dir(&self, secret_id: &[u8; 32]) -> PathBuf {
+        self.dir.join("secrets").join(format!("0x{}", bytes_to_hex(secret_id)))
+    }
+
+    /// Verified events of one secret, each once, in the order they are replayed.
+    fn events(&self, secret_id: &[u8; 32]) -> Vec<Intent> {
+        let mut files: Vec<PathBuf> = fs::read_dir(self.events_dir(secret_id))
+            .map(|rd| rd.flatten().map(|e| e.path()).filter(|p| p.extension().is_some_and(|x| x == "json")).collect())
+            .unwrap_or_default();
+        files.sort();
+        let mut events = Vec::new();
+        // Signed messages already replayed: the same event under another file name, or with
+        // its signature re-encoded, counts once.
+        let mut seen = HashSet::new();
+        for path in files {
+            let checked = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|s| Ok(serde_json::from_str::<Intent>(&s)?))
+                .and_then(|intent| self.check(&intent, secret_id).and_then(|_| Ok((intent.digest()?, intent))));
+            match checked {
+                Ok((digest, intent)) => {
+                    if seen.insert((intent.signer.to_lowercase(), digest)) {
+                        events.push(intent);
+                    } else {
+                        log::debug!("Skipping duplicate team registry event {}", path.display());
+                    }
+                }
+                Err(e) => log::warn!("Ignoring team registry event {}: {}", path.display(), e),
+            }
+        }
+        events.sort_by_key(|e| (e.nonce, e.deadline));
+        events
+    }
+
+    fn check(&self, intent: &Intent, secret_id: &[u8; 32]) -> Result<()> {
+        intent.verify()?;
+        if intent.chain_id != self.chain_id || eth::parse_address(&intent.registry)? != TEAM_DOMAIN {
+            return Err(anyhow!("signed for another registry"));
+        }
+        if intent.action.secret_id()? != *secret_id {
+            return Err(anyhow!("signed for another secret"));
+        }
+        Ok(())
+    }
+
+    /// State of one secret after replaying its events, and the number of events.
+    fn state(&self, secret_id: &[u8; 32]) -> (Secrets, u64) {
+        let mut secrets = Secrets::new();
+        let events = self.events(secret_id);
+        let count = events.len() as u64;
+        for event in events {
+            if let Err(e) = mock_chain::apply(&mut secrets, event.deadline, &event.signer, &event.action) {
+                log::warn!("Ignoring team registry {} by {}: {}", event.action.name(), event.signer, e);
+            }
+        }
+        (secrets, count)
+    }
+}
+
+impl RegistryBackend for TeamRegistry {
+    fn send(&self, priv_bytes: &[u8], action: &Action, _tx: &TxOptions) -> Result<String> {
+        let secret_id = action.secret_id()?;
+        let from = eth::addr_hex(&eth::address_bytes_from_secret(priv_bytes)?);
+        let lock = lock_dir(&self.dir)?;
+        let now = crate::wallet::current_timestamp();
+        let (mut state, sequence) = self.state(&secret_id);
+        mock_chain::apply(&mut state, now, &from, action)?;
+
+        let event = Intent::sign(action.clone(), self.chain_id, &TEAM_DOMAIN, priv_bytes, sequence, now)?;
+        let hash = keccak256(event.signature.as_bytes());
+        let path = self.events_dir(&secret_id).join(format!("{:06}-{}.json", sequence, &bytes_to_hex(&hash)[..16]));
+        fs::create_dir_all(path.parent().unwrap_or(&self.dir))?;
+        crate::wallet::write_secure_file(&path, serde_json::to_string_pretty(&event)?.as_bytes())?;
+        publish(&self.dir, &path, &format!("bsec: {} {}", action.name(), eth::display_secret_id(&secret_id)));
+        drop(lock);
+        Ok(format!("0x{}", bytes_to_hex(&hash)))
+    }
+
+    fn secret_info(&self, secret_id: &[u8; 32]) -> Result<DecodedSecretInfo> {
+        mock_chain::secret_info_at(&self.state(secret_id).0, secret_id, crate::wallet::current_timestamp())
+    }
+}
+
+/// Serializes writers on the same directory (an NFS share or one clone). In a clone the lock
+/// file lives under `.git`, out of the work tree.
+fn lock_dir(dir: &Path) -> Result<File> {
+    let path = if is_git(dir) && dir.join(".git").is_dir() { dir.join(".git") } else { dir.to_path_buf() }.join("bsec-team.lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| anyhow!("failed to open {}: {}", path.display(), e))?;
+    file.lock().map_err(|e| anyhow!("failed to lock {}: {}", path.display(), e))?;
+    Ok(file)
+}
+
+// ---------------------------------------------------------------------------
+// Payloads
+// ---------------------------------------------------------------------------
+
+/// Payloads in the team directory, by CID.
+pub struct TeamStore {
+    dir: PathBuf,
+}
+
+impl TeamStore {
+    pub fn open(conf: &NetworkConfig) -> Result<Self> {
+        Ok(Self { dir: team_dir(conf)? })
+    }
+
+    fn path(&self, cid: &str) -> PathBuf {
+        self.dir.join("payloads").join(cid)
+    }
+}
+
+impl PayloadStore for TeamStore {
+    fn put(&self, payload: &str) -> Result<String> {
+        let cid = mock_chain::raw_cid(payload.as_bytes());
+        let path = self.path(&cid);
+        fs::create_dir_all(self.dir.join("payloads"))?;
+        crate::wallet::write_secure_file(&path, payload.as_bytes())?;
+        publish(&self.dir, &path, &format!("bsec: add payload {}", cid));
+        Ok(cid)
+    }
+
+    /// A payload this clone does not have yet is pulled for.
+    fn get(&self, cid: &str) -> Result<String> {
+        let path = self.path(cid);
+        if !path.exists() {
+            sync(&self.dir);
+        }
+        fs::read_to_string(path).map_err(|_| anyhow!("CID {} is not in the team registry", cid))
+    }
+
+    fn remove(&self, cid: &str, report: &mut UnpinReport) {
+        let path = self.path(cid);
+        if fs::remove_file(&path).is_ok() {
+            publish(&self.dir, &path, &format!("bsec: remove payload {}", cid));
+            report.providers.push("team".to_string());
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Git
+// ---------------------------------------------------------------------------
+
+fn is_git(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("failed to run git: {}", e))?;
+    if !out.status.success() {
+        return Err(anyhow!("git {} failed: {}", args[0], String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+fn has_remote(dir: &Path) -> bool {
+    git(dir, &["remote"]).is_ok_and(|r| !r.trim().is_empty())
+}
+
+/// Pull a git clone; offline, the local copy is used.
+fn sync(dir: &Path) {
+    if !is_git(dir) || !has_remote(dir) {
+        return;
+    }
+    if let Err(e) = git(dir, &["pull", "--rebase", "--quiet"]) {
+        log::warn!("Could not update the team registry, using the local copy: {}", e);
+    }
+}
+
+/// Commit a changed file in a git clone and push it. A failed push leaves the commit to go out
+/// with the next write.
+fn publish(dir: &Path, path: &Path, message: &str) {
+    if !is_git(dir) {
+        return;
+    }
+    let Some(rel) = path.strip_prefix(dir).ok().and_then(|p| p.to_str()) else { return };
+    // Members without a git identity still commit; the event itself carries the signature.
+    let identity = git(dir, &["config", "user.email"]).is_ok_and(|e| !e.trim().is_empty());
+    let mut commit = vec!["-c", "user.name=bsec", "-c", "user.email=bsec@localhost", "commit", "--quiet", "-m", message, "--", rel];
+    if identity {
+        commit.drain(..4);
+    }
+    let committed = git(dir, &["add", "--all", "--", rel]).and_then(|_| git(dir, &commit));
+    if let Err(e) = committed {
+        log::warn!("Could not commit {} to the team registry: {}", rel, e);
+        return;
+    }
+    if has_remote(dir) {
+        let pushed = git(dir, &["push", "--quiet"])
+            .or_else(|_| git(dir, &["pull", "--rebase", "--quiet"]).and_then(|_| git(dir, &["push", "--quiet"])));
+        if let Err(e) = pushed {
+            log::warn!("Could not push the team registry (the change is committed locally): {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::BsecError;
+
+    const SENDER: [u8; 32] = [0x11; 32];
+    const RECIPIENT: [u8; 32] = [0x22; 32];
+    const STRANGER: [u8; 32] = [0x33; 32];
+
+    fn team(dir: &Path) -> TeamRegistry {
+        TeamRegistry { dir: dir.to_path_buf(), chain_id: 0 }
+    }
+
+    fn addr(key: &[u8; 32]) -> [u8; 20] {
+        eth::address_bytes_from_secret(key).unwrap()
+    }
+
+    #[test]
+    fn writes_follow_the_contract_rules_and_replay_from_signed_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let reg = team(dir.path());
+        let tx = TxOptions::default();
+        let id = [0x42; 32];
+        let later = crate::wallet::current_timestamp() + 3600;
+
+        reg.send(&SENDER, &Action::share(&id, &addr(&RECIPIENT), "bafyCid", later, 2, false), &tx).unwrap();
+        reg.send(&RECIPIENT, &Action::record_read(&id), &tx).unwrap();
+        let err = reg.send(&STRANGER, &Action::record_read(&id), &tx).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(BsecError::UnauthorizedViewer { .. })), "{}", err);
+        let err = reg.send(&RECIPIENT, &Action::revoke(&id), &tx).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(BsecError::UnauthorizedRevoker { .. })), "{}", err);
+
+        // A fresh handle sees the same state, rebuilt from the event files alone.
+        let info = team(dir.path()).secret_info(&id).unwrap();
+        assert_eq!((info.sender, info.recipient, info.read_count, info.max_reads), (addr(&SENDER), addr(&RECIPIENT), 1, 2));
+        assert_eq!(fs::read_dir(reg.events_dir(&id)).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn copied_events_replay_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let reg = team(dir.path());
+        let tx = TxOptions::default();
+        let id = [0x45; 32];
+        let later = crate::wallet::current_timestamp() + 3600;
+        reg.send(&SENDER, &Action::share(&id, &addr(&RECIPIENT), "bafyCid", later, 3, false), &tx).unwrap();
+        reg.send(&RECIPIENT, &Action::record_read(&id), &tx).unwrap();
+
+        // The read copied under other names, as a file manager or a merge would.
+        let events = reg.events_dir(&id);
+        let read = fs::read_dir(&events)
+            .unwrap()
+            .flatten()
+            .map(|e| e.path())
+            .find(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("000001-")))
+            .unwrap();
+        for name in ["000001-copy.json", "000009-copy.json", "zz.json"] {
+            fs::copy(&read, events.join(name)).unwrap();
+        }
+
+        assert_eq!(reg.secret_info(&id).unwrap().read_count, 1);
+        assert_eq!(reg.events(&id).len(), 2);
+    }
+
+    #[test]
+    fn forged_and_rule_breaking_events_are_ignored_on_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let reg = team(dir.path());
+        let id = [0x43; 32];
+        let later = crate::wallet::current_timestamp() + 3600;
+        reg.send(&SENDER, &Action::share(&id, &addr(&RECIPIENT), "bafyCid", later, 1, false), &TxOptions::default()).unwrap();
+        let now = crate::wallet::current_timestamp();
+        let drop_event = |name: &str, event: &Intent| {
+            fs::write(reg.events_dir(&id).join(name), serde_json::to_string(event).unwrap()).unwrap();
+        };
+
+        // A revoke signed by the stranger, written straight into the directory.
+        let foreign = Intent::sign(Action::revoke(&id), 0, &TEAM_DOMAIN, &STRANGER, 1, now).unwrap();
+        drop_event("1-foreign.json", &foreign);
+        // The sender's revoke with the signer swapped to the stranger's address.
+        let mut forged = Intent::sign(Action::revoke(&id), 0, &TEAM_DOMAIN, &SENDER, 2, now).unwrap();
+        forged.signer = eth::addr_hex(&addr(&STRANGER));
+        drop_event("2-forged.json", &forged);
+        // A genuine share for another secret, copied under this one.
+        let other = Intent::sign(Action::share(&[0x44; 32], &addr(&RECIPIENT), "x", later, 1, false), 0, &TEAM_DOMAIN, &SENDER, 3, now).unwrap();
+        drop_event("3-other.json", &other);
+        // Two genuine reads: only the first fits max_reads = 1.
+        for n in 4..6 {
+            drop_event(&format!("{}-read.json", n), &Intent::sign(Action::record_read(&id), 0, &TEAM_DOMAIN, &RECIPIENT, n, now + 1).unwrap());
+        }
+
+        let info = reg.secret_info(&id).unwrap();
+        assert!(!info.revoked);
+        assert_eq!((info.read_count, info.limit_reached), (1, true));
+        assert!(matches!(reg.secret_info(&[0x44; 32]).unwrap_err().downcast_ref(), Some(BsecError::SecretNotFound(_))));
+    }
+}
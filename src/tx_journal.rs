@@ -0,0 +1,236 @@
+//! Local journal of broadcast transactions (`~/.bsec/tx_journal.json`).
+//!
+//! Every registry write is recorded with its nonce, fees and signed raw bytes before bsec
+//! waits for the receipt, so a send that times out is not lost: `bsec tx status` picks it up
+//! later, and `bsec tx speedup` / `bsec tx cancel` replace it at the same nonce.
+//!
+//! Nonce selection, broadcast and journal writes happen under an exclusive lock on
+//! `tx_journal.lock`, so two bsec processes sharing a wallet never pick the same nonce.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Finished entries older than this are dropped from the journal on the next write.
+const RETAIN_FINISHED_SECS: u64 = 30 * 24 * 3600;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    Pending,
+    Confirmed,
+    Reverted,
+    /// Its nonce was used by a bsec replacement (`speedup` / `cancel`).
+    Replaced,
+    /// Its nonce was used by a transaction bsec did not send.
+    Dropped,
+}
+
+impl TxStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TxStatus::Pending => "pending",
+            TxStatus::Confirmed => "confirmed",
+            TxStatus::Reverted => "reverted",
+            TxStatus::Replaced => "replaced",
+            TxStatus::Dropped => "dropped",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TxRecord {
+    pub hash: String,
+    pub chain_id: u32,
+    /// Lowercase 0x-prefixed sender address.
+    pub from: String,
+    pub to: String,
+    pub nonce: u64,
+    /// 0x-prefixed calldata (`0x` for a cancellation).
+    pub data: String,
+    /// `eip1559` or `legacy`.
+    pub kind: String,
+    pub gas_limit: u128,
+    pub max_fee_per_gas: u128,
+    pub priority_fee_per_gas: Option<u128>,
+    /// Signed transaction, re-broadcast if the node has forgotten it.
+    pub raw: String,
+    pub submitted_at: u64,
+    pub status: TxStatus,
+    /// Hash of the transaction this one replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaces: Option<String>,
+    /// Hash of the replacement sent for this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<String>,
+}
+
+fn journal_file(dir: &Path) -> PathBuf {
+    dir.join("tx_journal.json")
+}
+
+fn load_from(dir: &Path) -> Vec<TxRecord> {
+    std::fs::read_to_string(journal_file(dir))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Exclusive lock on the journal. Held from nonce selection until the transaction is
+/// broadcast and recorded; released on drop.
+pub struct JournalLock {
+    dir: PathBuf,
+    _file: File,
+}
+
+impl JournalLock {
+    pub fn acquire() -> Result<Self> {
+        Self::acquire_in(&crate::wallet::get_app_dir())
+    }
+
+    fn acquire_in(dir: &Path) -> Result<Self> {
+        let path = dir.join("tx_journal.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| anyhow!("failed to open {}: {}", path.display(), e))?;
+        if file.try_lock().is_err() {
+            log::warn!("Waiting for another bsec process to finish sending a transaction...");
+            file.lock().map_err(|e| anyhow!("failed to lock {}: {}", path.display(), e))?;
+        }
+        Ok(Self { dir: dir.to_path_buf(), _file: file })
+    }
+
+    pub fn entries(&self) -> Vec<TxRecord> {
+        load_from(&self.dir)
+    }
+
+    pub fn save(&self, entries: &[TxRecord]) -> Result<()> {
+        let now = crate::wallet::current_timestamp();
+        let kept: Vec<&TxRecord> = entries
+            .iter()
+            .filter(|r| r.status == TxStatus::Pending || now.saturating_sub(r.submitted_at) < RETAIN_FINISHED_SECS)
+            .collect();
+        let json = serde_json::to_string_pretty(&kept)?;
+        crate::wallet::write_secure_file(&journal_file(&self.dir), json.as_bytes())
+    }
+
+    /// Load, modify and save in one step.
+    pub fn update<T>(&self, f: impl FnOnce(&mut Vec<TxRecord>) -> T) -> Result<T> {
+        let mut entries = self.entries();
+        let out = f(&mut entries);
+        self.save(&entries)?;
+        Ok(out)
+    }
+}
+
+/// Nonce for the next transaction from `from`: the node's pending count, or one past the
+/// highest nonce this journal still has pending if that is higher (the node may have been
+/// swapped by failover, or not yet seen a transaction another process just sent).
+pub fn next_nonce(entries: &[TxRecord], chain_id: u32, from: &str, node_pending: u64) -> u64 {
+    entries
+        .iter()
+        .filter(|r| r.status == TxStatus::Pending && r.chain_id == chain_id && r.from.eq_ignore_ascii_case(from))
+        .map(|r| r.nonce + 1)
+        .fold(node_pending, u64::max)
+}
+
+/// Find an entry by full hash or unique prefix (at least 6 hex digits after `0x`).
+pub fn find<'a>(entries: &'a [TxRecord], hash: &str) -> Result<&'a TxRecord> {
+    let needle = hash.to_lowercase();
+    if needle.trim_start_matches("0x").len() < 6 {
+        return Err(anyhow!("transaction hash {:?} is too short", hash));
+    }
+    let needle = if needle.starts_with("0x") { needle } else { format!("0x{}", needle) };
+    let mut matches = entries.iter().filter(|r| r.hash.to_lowercase().starts_with(&needle));
+    match (matches.next(), matches.next()) {
+        (Some(r), None) => Ok(r),
+        (Some(_), Some(_)) => Err(anyhow!("transaction hash {:?} is ambiguous", hash)),
+        (None, _) => Err(anyhow!("transaction {} is not in the local journal", hash)),
+    }
+}
+
+/// Minimum fee a node accepts for a same-nonce replacement: +12.5% (geth requires +10%;
+/// the extra margin covers nodes with stricter rules), rounded up.
+pub fn bump_fee(fee: u128) -> u128 {
+    fee.saturating_add(fee.div_ceil(8)).max(fee.saturating_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(hash: &str, from: &str, nonce: u64, status: TxStatus) -> TxRecord {
+        TxRecord {
+            hash: hash.to_string(),
+            chain_id: 31337,
+            from: from.to_string(),
+            to: "0x00".to_string(),
+            nonce,
+            data: "0x".to_string(),
+            kind: "eip1559".to_string(),
+            gas_limit: 21000,
+            max_fee_per_gas: 100,
+            priority_fee_per_gas: Some(1),
+            raw: "0x02".to_string(),
+            submitted_at: crate::wallet::current_timestamp(),
+            status,
+            replaces: None,
+            replaced_by: None,
+        }
+    }
+
+    #[test]
+    fn next_nonce_skips_past_pending_journal_entries() {
+        let entries = vec![
+            rec("0xaa", "0xAbC", 7, TxStatus::Pending),
+            rec("0xbb", "0xabc", 9, TxStatus::Confirmed),
+            rec("0xcc", "0xdef", 20, TxStatus::Pending),
+        ];
+        assert_eq!(next_nonce(&entries, 31337, "0xabc", 5), 8);
+        assert_eq!(next_nonce(&entries, 31337, "0xabc", 12), 12);
+        assert_eq!(next_nonce(&entries, 1, "0xabc", 5), 5);
+    }
+
+    #[test]
+    fn replacement_fee_bump_is_at_least_ten_percent() {
+        assert_eq!(bump_fee(1_000_000_000), 1_125_000_000);
+        assert_eq!(bump_fee(1), 2);
+        assert_eq!(bump_fee(0), 1);
+        assert!(bump_fee(999) * 10 >= 999 * 11);
+    }
+
+    #[test]
+    fn find_accepts_unique_prefixes() {
+        let entries = vec![rec("0xabcdef01", "0x1", 0, TxStatus::Pending), rec("0xabcdef02", "0x1", 1, TxStatus::Pending)];
+        assert_eq!(find(&entries, "0xABCDEF02").unwrap().nonce, 1);
+        assert_eq!(find(&entries, "abcdef01").unwrap().nonce, 0);
+        assert!(find(&entries, "0xabcdef").unwrap_err().to_string().contains("ambiguous"));
+        assert!(find(&entries, "0xab").unwrap_err().to_string().contains("too short"));
+        assert!(find(&entries, "0x999999").unwrap_err().to_string().contains("not in the local journal"));
+    }
+
+    #[test]
+    fn lock_serializes_writers_and_save_prunes_old_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = JournalLock::acquire_in(dir.path()).unwrap();
+
+        // A second handle on the same lock file cannot be taken while the first is held.
+        let other = OpenOptions::new().write(true).open(dir.path().join("tx_journal.lock")).unwrap();
+        assert!(other.try_lock().is_err());
+
+        let mut old = rec("0x01", "0x1", 0, TxStatus::Confirmed);
+        old.submitted_at = 1;
+        let mut old_pending = rec("0x02", "0x1", 1, TxStatus::Pending);
+        old_pending.submitted_at = 1;
+        lock.update(|e| e.extend([old, old_pending, rec("0x03", "0x1", 2, TxStatus::Confirmed)])).unwrap();
+        let hashes: Vec<String> = lock.entries().into_iter().map(|r| r.hash).collect();
+        assert_eq!(hashes, vec!["0x02", "0x03"]);
+
+        drop(lock);
+        assert!(other.try_lock().is_ok());
+    }
+}
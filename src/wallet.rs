@@ -150,6 +150,22 @@ pub fn write_secure_file_new(path: &Path, content: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Replace `path` with `content` atomically: written to a 0600 temp file in the same
+/// directory, synced, then renamed over `path`. A crash or error mid-write leaves the old
+/// file intact, and the rename replaces a symlink at `path` rather than following it.
+pub fn replace_secure_file(path: &Path, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(content)?;
+    file.as_file().sync_all()?;
+    set_private_file_permissions(file.path());
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
 
 pub fn hash_digest(input: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
@@ -173,8 +189,13 @@ pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>> {
 }
 
 pub fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    derive_key_with(password, salt, argon2::Params::default())
+}
+
+/// `derive_key` with explicit Argon2id cost parameters, as recorded in v2 env files.
+pub fn derive_key_with(password: &str, salt: &[u8], params: argon2::Params) -> Result<[u8; 32]> {
     let mut key = [0u8; 32];
-    let argon2 = Argon2::default();
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
     let effective_salt = if salt.len() < 8 {
         let mut hasher = Sha256::new();
         hasher.update(salt);
@@ -7,6 +7,14 @@
 //!   - Shell completion generation (`bsec completion <shell>`) for bash, zsh, fish, powershell
 //!   - Help banner (`bsec --help`) & JSON format flags (`--json`)
 //!   - Process exit code status 1 handling (`handle_cli_error`) on command failures
+//!   - Payload garbage collection over the local secret index (`bsec gc`)
+//!   - Local IPFS cache size limit and housekeeping (`bsec config --cache-max-size`, `bsec cache`)
+//!   - RPC fallback list and endpoint health report (`bsec config --rpc-fallbacks`, `bsec config check`)
+//!   - Fee flag validation on registry writes (`--max-fee`, `--fee-cap`)
+//!   - Local transaction journal listing and lookup (`bsec tx list`, `bsec tx status`)
+//!   - Offline signing of portable transactions (`bsec tx sign`, `bsec tx broadcast`, `bsec tx build`)
+//!   - Registry verification policy and trusted code hashes (`bsec config --registry-check`, `bsec config verify-registry`)
+//!   - Batch revocation selection and flag rules (`bsec revoke --all-from-me --recipient`)
 
 use assert_cmd::prelude::*;
 use assert_fs::prelude::*;
@@ -208,3 +216,322 @@ fn test_command_failure_exit_code_1() -> Result<(), Box<dyn std::error::Error>>
 
     Ok(())
 }
+
+/// Tests that `gc` over an empty local index is a successful no-op in both output modes.
+/// Target File: `src/secrets.rs` -> `gc_secrets()`
+/// Flow: `bsec gc`, `bsec gc --dry-run --json`
+#[test]
+fn test_gc_empty_index() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+
+    let mut cmd = Command::cargo_bin("bsec")?;
+    cmd.current_dir(temp_dir.path());
+    cmd.env("BSEC_HOME", temp_dir.path());
+    cmd.arg("gc");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing to collect."));
+
+    let mut cmd_json = Command::cargo_bin("bsec")?;
+    cmd_json.current_dir(temp_dir.path());
+    cmd_json.env("BSEC_HOME", temp_dir.path());
+    cmd_json.arg("gc").arg("--dry-run").arg("--json");
+    cmd_json.assert().success().stdout(predicate::str::starts_with("["));
+
+    Ok(())
+}
+
+/// Tests the cache size setting and `cache stats` / `cache clear` on a seeded cache dir.
+/// Target File: `src/ipfs.rs` -> `cache_stats()`, `clear_cache()`
+/// Flow: `bsec config --cache-max-size 1M` -> `bsec cache stats --json` -> `bsec cache clear`
+#[test]
+fn test_cache_stats_and_clear() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    temp_dir.child("ipfs_cache/a.json").write_str("{}")?;
+    temp_dir.child("ipfs_cache/b.json").write_str("bsec-cache-v1:AAAA:BBBB")?;
+
+    let mut cmd_conf = Command::cargo_bin("bsec")?;
+    cmd_conf.env("BSEC_HOME", temp_dir.path());
+    cmd_conf.arg("config").arg("--cache-max-size").arg("1M");
+    cmd_conf.assert().success();
+
+    let mut cmd_stats = Command::cargo_bin("bsec")?;
+    cmd_stats.env("BSEC_HOME", temp_dir.path());
+    cmd_stats.arg("cache").arg("stats").arg("--json");
+    cmd_stats.assert()
+        .success()
+        .stdout(predicate::str::contains("\"entries\": 2"))
+        .stdout(predicate::str::contains("\"encrypted_entries\": 1"))
+        .stdout(predicate::str::contains("\"max_bytes\": 1048576"));
+
+    let mut cmd_clear = Command::cargo_bin("bsec")?;
+    cmd_clear.env("BSEC_HOME", temp_dir.path());
+    cmd_clear.arg("cache").arg("clear");
+    cmd_clear.assert()
+        .success()
+        .stdout(predicate::str::contains("Cleared 2 cache entries"));
+
+    temp_dir.child("ipfs_cache/a.json").assert(predicate::path::missing());
+
+    Ok(())
+}
+
+/// Tests that `config check` probes every configured RPC endpoint and fails when none is usable.
+/// Target File: `src/eth.rs` -> `check_endpoint()`, `src/network_config.rs` -> `effective_rpc_urls()`
+/// Flow: `bsec config --network local --rpc-fallbacks ...` -> `bsec config check`
+#[test]
+fn test_config_check_reports_unreachable_endpoints() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+
+    let mut cmd_conf = Command::cargo_bin("bsec")?;
+    cmd_conf.env("BSEC_HOME", temp_dir.path());
+    cmd_conf
+        .arg("config")
+        .arg("--network")
+        .arg("local")
+        .arg("--rpc")
+        .arg("http://127.0.0.1:1")
+        .arg("--rpc-fallbacks")
+        .arg("http://127.0.0.1:2");
+    cmd_conf.assert().success();
+
+    let mut cmd_check = Command::cargo_bin("bsec")?;
+    cmd_check.env("BSEC_HOME", temp_dir.path());
+    cmd_check.arg("config").arg("check");
+    cmd_check.assert()
+        .failure()
+        .stdout(predicate::str::contains("FAIL  http://127.0.0.1:1"))
+        .stdout(predicate::str::contains("FAIL  http://127.0.0.1:2"));
+
+    Ok(())
+}
+
+/// Tests that malformed fee flags are rejected at argument parsing, before any wallet or RPC use.
+/// Target File: `src/eth.rs` -> `parse_gwei()`, `parse_native()`
+/// Flow: `bsec revoke <id> --max-fee 1.0000000001`, `bsec share --fee-cap abc`
+#[test]
+fn test_fee_flags_are_validated() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+
+    let mut cmd_fee = Command::cargo_bin("bsec")?;
+    cmd_fee.env("BSEC_HOME", temp_dir.path());
+    cmd_fee.args(["revoke", "0x01", "--max-fee", "1.0000000001"]);
+    cmd_fee.assert()
+        .failure()
+        .stderr(predicate::str::contains("more than 9 decimal places"));
+
+    let mut cmd_cap = Command::cargo_bin("bsec")?;
+    cmd_cap.env("BSEC_HOME", temp_dir.path());
+    cmd_cap.args(["share", "--content", "x", "--fee-cap", "abc"]);
+    cmd_cap.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid amount"));
+
+    Ok(())
+}
+
+/// Tests the batch revoke flags, and that an empty local index needs no transaction.
+/// Target File: `src/main.rs` -> `Commands::Revoke`, `src/secrets.rs` -> `revoke_sent()`
+/// Flow: `bsec revoke` -> `bsec revoke <id> --all-from-me` -> `bsec revoke <id> --recipient X` -> `bsec revoke --all-from-me`
+#[test]
+fn test_batch_revoke_flags() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+
+    let mut cmd_none = Command::cargo_bin("bsec")?;
+    cmd_none.env("BSEC_HOME", temp_dir.path());
+    cmd_none.arg("revoke");
+    cmd_none.assert().failure().stderr(predicate::str::contains("<SECRET_ID>"));
+
+    let mut cmd_both = Command::cargo_bin("bsec")?;
+    cmd_both.env("BSEC_HOME", temp_dir.path());
+    cmd_both.args(["revoke", "0x01", "--all-from-me"]);
+    cmd_both.assert().failure().stderr(predicate::str::contains("cannot be used with"));
+
+    let mut cmd_recipient = Command::cargo_bin("bsec")?;
+    cmd_recipient.env("BSEC_HOME", temp_dir.path());
+    cmd_recipient.args(["revoke", "0x01", "--recipient", "public"]);
+    cmd_recipient.assert().failure();
+
+    let mut cmd_init = Command::cargo_bin("bsec")?;
+    cmd_init.env("BSEC_HOME", temp_dir.path());
+    cmd_init.args(["init", "--overwrite", "--no-encryption"]);
+    cmd_init.assert().success();
+
+    // Nothing shared yet: no RPC call is made, and there is nothing to revoke.
+    let mut cmd_all = Command::cargo_bin("bsec")?;
+    cmd_all.env("BSEC_HOME", temp_dir.path());
+    cmd_all.args(["revoke", "--all-from-me", "--recipient", "public"]);
+    cmd_all.assert()
+        .success()
+        .stdout(predicate::str::contains("No readable secrets shared by this wallet match."));
+
+    Ok(())
+}
+
+/// Tests listing and looking up journaled transactions. Only finished entries are seeded, so
+/// nothing needs an RPC node.
+/// Target File: `src/tx_journal.rs`, `src/eth.rs` -> `tx_journal_refreshed()`
+/// Flow: `bsec tx list` -> `bsec tx list --all --json` -> `bsec tx status <prefix>`
+#[test]
+fn test_tx_journal_list_and_status() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let hash = format!("0x{}", "ab".repeat(32));
+    temp_dir.child("tx_journal.json").write_str(&format!(
+        r#"[{{"hash":"{}","chain_id":80002,"from":"0x{}","to":"0x{}","nonce":4,"data":"0x","kind":"eip1559",
+            "gas_limit":21000,"max_fee_per_gas":2000000000,"priority_fee_per_gas":1000000000,"raw":"0x02",
+            "submitted_at":{},"status":"confirmed"}}]"#,
+        hash,
+        "11".repeat(20),
+        "11".repeat(20),
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs()
+    ))?;
+
+    let mut cmd_list = Command::cargo_bin("bsec")?;
+    cmd_list.env("BSEC_HOME", temp_dir.path());
+    cmd_list.arg("tx").arg("list");
+    cmd_list.assert()
+        .success()
+        .stdout(predicate::str::contains("No pending transactions on chain 80002."));
+
+    let mut cmd_all = Command::cargo_bin("bsec")?;
+    cmd_all.env("BSEC_HOME", temp_dir.path());
+    cmd_all.args(["tx", "list", "--all", "--json"]);
+    cmd_all.assert()
+        .success()
+        .stdout(predicate::str::contains(hash.as_str()))
+        .stdout(predicate::str::contains("\"status\": \"confirmed\""));
+
+    let mut cmd_status = Command::cargo_bin("bsec")?;
+    cmd_status.env("BSEC_HOME", temp_dir.path());
+    cmd_status.args(["tx", "status", "0xababab"]);
+    cmd_status.assert()
+        .success()
+        .stdout(predicate::str::contains("confirmed"))
+        .stdout(predicate::str::contains("transfer"));
+
+    let mut cmd_missing = Command::cargo_bin("bsec")?;
+    cmd_missing.env("BSEC_HOME", temp_dir.path());
+    cmd_missing.args(["tx", "status", "0xcdcdcd"]);
+    cmd_missing.assert()
+        .failure()
+        .stderr(predicate::str::contains("not in the local journal"));
+
+    Ok(())
+}
+
+/// Tests the registry verification settings and that `verify-registry` fails when the registry
+/// code cannot be fetched.
+/// Target File: `src/network_config.rs` -> `parse_registry_check()`, `parse_code_hash()`, `src/blockchain.rs` -> `verify_registry()`
+/// Flow: `bsec config --registry-check enforce --trust-registry-code-hash ...` -> `bsec config --show` -> `bsec config verify-registry`
+#[test]
+fn test_registry_check_settings() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let hash = "AB".repeat(32);
+
+    let mut cmd_conf = Command::cargo_bin("bsec")?;
+    cmd_conf.env("BSEC_HOME", temp_dir.path());
+    cmd_conf.args(["config", "--network", "local", "--rpc", "http://127.0.0.1:1"]);
+    cmd_conf.args(["--registry-check", "enforce", "--trust-registry-code-hash", &hash]);
+    cmd_conf.assert().success();
+
+    let mut cmd_show = Command::cargo_bin("bsec")?;
+    cmd_show.env("BSEC_HOME", temp_dir.path());
+    cmd_show.args(["config", "--show"]);
+    cmd_show.assert()
+        .success()
+        .stdout(predicate::str::contains("Registry Check: enforce"))
+        .stdout(predicate::str::contains(format!("0x{}", "ab".repeat(32))));
+
+    let mut cmd_bad = Command::cargo_bin("bsec")?;
+    cmd_bad.env("BSEC_HOME", temp_dir.path());
+    cmd_bad.args(["config", "--registry-check", "strict"]);
+    cmd_bad.assert()
+        .failure()
+        .stderr(predicate::str::contains("warn, enforce or off"));
+
+    let mut cmd_hash = Command::cargo_bin("bsec")?;
+    cmd_hash.env("BSEC_HOME", temp_dir.path());
+    cmd_hash.args(["config", "--trust-registry-code-hash", "0x1234"]);
+    cmd_hash.assert().failure();
+
+    let mut cmd_verify = Command::cargo_bin("bsec")?;
+    cmd_verify.env("BSEC_HOME", temp_dir.path());
+    cmd_verify.args(["config", "verify-registry"]);
+    cmd_verify.assert().failure();
+
+    Ok(())
+}
+
+/// Tests signing a built transaction with no network configured, and that only the wallet
+/// named in the file can sign it and only signed files are broadcast.
+/// Target File: `src/eth.rs` -> `PortableTx::sign()`, `broadcast_signed()`, `src/main.rs` -> `read_portable_tx()`
+/// Flow: `bsec init` -> `bsec tx sign unsigned.json -o signed.json` -> `bsec tx broadcast unsigned.json`
+#[test]
+fn test_tx_sign_offline() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+
+    let mut cmd_init = Command::cargo_bin("bsec")?;
+    cmd_init.env("BSEC_HOME", temp_dir.path());
+    cmd_init.args(["init", "--overwrite", "--no-encryption"]);
+    cmd_init.assert().success();
+
+    let mut cmd_info = Command::cargo_bin("bsec")?;
+    cmd_info.env("BSEC_HOME", temp_dir.path());
+    cmd_info.args(["wallet", "info", "--json"]);
+    let info: serde_json::Value = serde_json::from_slice(&cmd_info.output()?.stdout)?;
+    let address = info["address"].as_str().ok_or("no address")?.to_lowercase();
+
+    let unsigned = |from: &str| {
+        format!(
+            r#"{{"format":"bsec-tx/1","chain_id":80002,"from":"{}","to":"0x{}","nonce":3,
+                "data":"0xf4d2d15f{}","description":"revokeSecret","kind":"eip1559","gas_limit":60000,
+                "max_fee_per_gas":40000000000,"priority_fee_per_gas":2000000000,
+                "max_cost_wei":2400000000000000,"built_at":0}}"#,
+            from,
+            "22".repeat(20),
+            "ab".repeat(32)
+        )
+    };
+    temp_dir.child("unsigned.json").write_str(&unsigned(&address))?;
+    temp_dir.child("foreign.json").write_str(&unsigned(&format!("0x{}", "33".repeat(20))))?;
+
+    // Point the RPC at a closed port: signing must not need it.
+    let mut cmd_conf = Command::cargo_bin("bsec")?;
+    cmd_conf.env("BSEC_HOME", temp_dir.path());
+    cmd_conf.args(["config", "--network", "amoy", "--rpc", "http://127.0.0.1:1"]);
+    cmd_conf.assert().success();
+
+    let signed_path = temp_dir.path().join("signed.json");
+    let mut cmd_sign = Command::cargo_bin("bsec")?;
+    cmd_sign.env("BSEC_HOME", temp_dir.path());
+    cmd_sign.arg("tx").arg("sign").arg(temp_dir.path().join("unsigned.json")).arg("-o").arg(&signed_path);
+    cmd_sign.assert()
+        .success()
+        .stderr(predicate::str::contains("revokeSecret on chain 80002 (nonce 3)"));
+    let signed: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&signed_path)?)?;
+    assert!(signed["raw"].as_str().is_some_and(|r| r.starts_with("0x02")));
+    assert!(signed["hash"].as_str().is_some_and(|h| h.len() == 66));
+
+    let mut cmd_foreign = Command::cargo_bin("bsec")?;
+    cmd_foreign.env("BSEC_HOME", temp_dir.path());
+    cmd_foreign.arg("tx").arg("sign").arg(temp_dir.path().join("foreign.json"));
+    cmd_foreign.assert()
+        .failure()
+        .stderr(predicate::str::contains("this wallet is"));
+
+    let mut cmd_unsigned = Command::cargo_bin("bsec")?;
+    cmd_unsigned.env("BSEC_HOME", temp_dir.path());
+    cmd_unsigned.arg("tx").arg("broadcast").arg(temp_dir.path().join("unsigned.json"));
+    cmd_unsigned.assert()
+        .failure()
+        .stderr(predicate::str::contains("not signed"));
+
+    let mut cmd_build = Command::cargo_bin("bsec")?;
+    cmd_build.env("BSEC_HOME", temp_dir.path());
+    cmd_build.args(["tx", "build", "revoke", "0x01", "--from", &address, "--dry-run"]);
+    cmd_build.assert()
+        .failure()
+        .stderr(predicate::str::contains("--dry-run does not apply"));
+
+    Ok(())
+}
@@ -5,6 +5,11 @@
 //!   - End-to-end `.env` file encryption and decryption using `.env.pass` key files
 //!   - File encryption (`bsec encrypt`) & decryption (`bsec decrypt`) using explicit `--password` CLI flags
 //!   - Enforcing restricted `0o600` permissions (`rw-------`) on decrypted `.env` output files
+//!   - Editing keys of a `.env.enc` in memory (`bsec set/get/unset/rename`) without writing plaintext
+//!   - Editing a `.env.enc` in `$EDITOR` (`bsec edit`): staged plaintext removed, unparsable edits refused
+//!   - Per-value encryption (`bsec encrypt --per-value`) read by `bsec run`, with one-line diffs on `bsec set`
+//!   - Encryption to wallet public keys and contacts (`bsec encrypt --to`), decrypted with each recipient's wallet
+//!   - Password rotation (`bsec rekey --recursive`): every encrypted file under a directory re-sealed, old password refused
 
 use assert_cmd::prelude::*;
 use assert_fs::prelude::*;
@@ -140,3 +145,244 @@ fn test_decrypted_file_permissions() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Tests that `set`, `get`, `rename` and `unset` work on an encrypted file, which stays
+/// encrypted and decrypts to the edited content.
+/// Target File: `src/env_file.rs` -> `edit_env_file()`, `seal()`, `open()`
+/// Flow: `bsec encrypt .env` -> `bsec set/get/rename/unset -f .env.enc` (DOTENV_PASS) -> `bsec decrypt`
+#[test]
+fn test_key_edits_on_encrypted_file() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let env_file = temp_dir.child(".env");
+    env_file.write_str("# keep me\nDB_PASS=old\nUSER=app\n")?;
+
+    let bsec = |args: &[&str]| -> Result<assert_cmd::Command, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::Command::cargo_bin("bsec")?;
+        cmd.current_dir(temp_dir.path())
+            .env("BSEC_HOME", temp_dir.path())
+            .env("DOTENV_PASS", "enc_edit_pass")
+            .args(args);
+        Ok(cmd)
+    };
+
+    bsec(&["encrypt", ".env"])?.assert().success();
+    std::fs::remove_file(env_file.path())?;
+
+    bsec(&["set", "DB_PASS", "-f", ".env.enc"])?.write_stdin("n3w-secret\n").assert().success();
+    bsec(&["rename", "USER", "DB_USER", "-f", ".env.enc"])?.assert().success();
+    bsec(&["set", "TMP=1", "-f", ".env.enc"])?.assert().success();
+    bsec(&["unset", "TMP", "-f", ".env.enc"])?.assert().success();
+    bsec(&["get", "DB_PASS", "-f", ".env.enc"])?
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("n3w-secret\n"));
+
+    let payload = std::fs::read_to_string(temp_dir.path().join(".env.enc"))?;
+    assert!(!payload.contains("n3w-secret") && !payload.contains("DB_USER"));
+    assert!(!env_file.path().exists());
+
+    bsec(&["decrypt", ".env.enc", "--out", ".env.dec"])?.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(temp_dir.path().join(".env.dec"))?,
+        "# keep me\nDB_PASS=n3w-secret\nDB_USER=app\n",
+    );
+
+    Ok(())
+}
+
+/// Tests `bsec edit` with a scripted editor: a change is re-encrypted, no change leaves the
+/// file byte-identical, an edit that does not parse is refused, and no plaintext is left in
+/// the staging area.
+/// Target File: `src/env_file.rs` -> `edit_encrypted_env_file()`, `src/materialize.rs` -> `StagedDir`
+/// Flow: `bsec encrypt .env` -> `EDITOR=<sed|true> bsec edit .env.enc` -> `bsec get`
+#[cfg(unix)]
+#[test]
+fn test_edit_encrypted_file_in_editor() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let staging = temp_dir.child("tmp");
+    staging.create_dir_all()?;
+    temp_dir.child(".env").write_str("# prod\nDB_PASS=old\n")?;
+
+    let bsec = |editor: &str, args: &[&str]| -> Result<assert_cmd::Command, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::Command::cargo_bin("bsec")?;
+        cmd.current_dir(temp_dir.path())
+            .env("BSEC_HOME", temp_dir.path())
+            .env("DOTENV_PASS", "edit_pass")
+            .env("TMPDIR", staging.path())
+            .env_remove("VISUAL")
+            .env("EDITOR", editor)
+            .args(args);
+        Ok(cmd)
+    };
+    bsec("true", &["encrypt", ".env"])?.assert().success();
+    let enc_path = temp_dir.path().join(".env.enc");
+
+    let before = std::fs::read(&enc_path)?;
+    bsec("true", &["edit", ".env.enc"])?.assert().success().stdout(predicates::str::contains("No changes"));
+    assert_eq!(std::fs::read(&enc_path)?, before);
+
+    bsec("sed -i s/=old/=\"unclosed/", &["edit", ".env.enc"])?
+        .assert()
+        .code(4)
+        .stderr(predicates::str::contains("unterminated double-quoted value"));
+    assert_eq!(std::fs::read(&enc_path)?, before);
+
+    bsec("sed -i s/=old/=rotated/", &["edit", ".env.enc"])?.assert().success();
+    assert_ne!(std::fs::read(&enc_path)?, before);
+    bsec("true", &["get", "DB_PASS", "-f", ".env.enc"])?
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("rotated\n"));
+
+    bsec("true", &["edit", ".env"])?.assert().failure();
+    assert_eq!(std::fs::read_dir(staging.path())?.count(), 0);
+
+    Ok(())
+}
+
+/// Tests per-value encryption: keys stay readable, values with references are sealed as
+/// written, `run` decrypts and expands transparently,
+/// and changing one value rewrites only that line.
+/// Target File: `src/env_file.rs` -> `encrypt_env_file()` (per-value), `load_and_parse_env()`, `edit_env_file()`
+/// Flow: `bsec encrypt .env --per-value` -> `bsec run -e .env.enc` -> `bsec set -f .env.enc` -> `bsec decrypt`
+#[test]
+fn test_per_value_encryption() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    temp_dir.child(".env").write_str("# api\nAPI_KEY=k-123\nAPI_URL=https://api/${API_KEY}\nREGION=eu\n")?;
+
+    let bsec = |args: &[&str]| -> Result<assert_cmd::Command, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::Command::cargo_bin("bsec")?;
+        cmd.current_dir(temp_dir.path())
+            .env("BSEC_HOME", temp_dir.path())
+            .env("DOTENV_PASS", "per_value_pass")
+            .args(args);
+        Ok(cmd)
+    };
+    bsec(&["encrypt", ".env", "--per-value"])?.assert().success();
+    let enc_path = temp_dir.path().join(".env.enc");
+    let sealed = std::fs::read_to_string(&enc_path)?;
+    let lines: Vec<&str> = sealed.lines().collect();
+    assert_eq!(lines[0], "# api");
+    for (line, key) in lines[1..].iter().zip(["API_KEY", "API_URL", "REGION"]) {
+        assert!(line.starts_with(&format!("{}=enc:v2:", key)), "{}", line);
+    }
+    assert!(!sealed.contains("k-123") && !sealed.contains("${API_KEY}"));
+
+    bsec(&["run", "-e", ".env.enc", "--", "sh", "-c", "printf %s \"$API_URL\""])?
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("https://api/k-123"));
+
+    bsec(&["set", "REGION=us", "-f", ".env.enc"])?.assert().success();
+    let resealed = std::fs::read_to_string(&enc_path)?;
+    let changed: Vec<usize> = (0..4).filter(|&i| resealed.lines().nth(i) != sealed.lines().nth(i)).collect();
+    assert_eq!(changed, [3]);
+
+    bsec(&["decrypt", ".env.enc", "--out", ".env.dec"])?.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(temp_dir.path().join(".env.dec"))?,
+        "# api\nAPI_KEY=k-123\nAPI_URL=https://api/${API_KEY}\nREGION=us\n",
+    );
+
+    Ok(())
+}
+
+/// Tests encrypting to wallets: each recipient decrypts with their own wallet and no password,
+/// edits keep the recipients, and a wallet that is not a recipient is refused.
+/// Target File: `src/env_file.rs` -> `seal_to()`, `open_with_wallet()`, `src/contacts.rs`
+/// Flow: `bsec contact add ci <pubkey>` -> `bsec encrypt .env --to <own pubkey> ci` -> `bsec run` (ci) -> `bsec set` -> `bsec decrypt` (outsider, exit 3)
+#[test]
+fn test_encrypt_to_wallet_recipients() -> Result<(), Box<dyn std::error::Error>> {
+    let work = assert_fs::TempDir::new()?;
+    work.child(".env").write_str("# ci\nTOKEN=t0k3n\n")?;
+    let homes = [assert_fs::TempDir::new()?, assert_fs::TempDir::new()?, assert_fs::TempDir::new()?];
+    let bsec = |home: &assert_fs::TempDir, args: &[&str]| -> Result<assert_cmd::Command, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::Command::cargo_bin("bsec")?;
+        cmd.current_dir(work.path()).env("BSEC_HOME", home.path()).env_remove("DOTENV_PASS").args(args);
+        Ok(cmd)
+    };
+    let mut pubkeys = Vec::new();
+    for home in &homes {
+        let out = bsec(home, &["init", "--overwrite", "--no-encryption"])?.output()?;
+        let stdout = String::from_utf8(out.stdout)?;
+        pubkeys.push(stdout.lines().find_map(|l| l.strip_prefix("Public Key: ")).expect("public key").trim().to_string());
+    }
+    let [alice, ci, outsider] = &homes;
+
+    bsec(alice, &["contact", "add", "ci", &pubkeys[1]])?.assert().success();
+    bsec(alice, &["contact", "list"])?.assert().success().stdout(predicates::str::contains("ci  0x04"));
+    bsec(alice, &["encrypt", ".env", "--to", &pubkeys[0], "ci"])?.assert().success();
+    let sealed = std::fs::read_to_string(work.path().join(".env.enc"))?;
+    assert!(sealed.contains("\"format\": \"bsec-env\"") && sealed.contains("\"recipients\"") && !sealed.contains("t0k3n"));
+
+    bsec(ci, &["run", "-e", ".env.enc", "--", "sh", "-c", "printf %s \"$TOKEN\""])?
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("t0k3n"));
+
+    bsec(alice, &["set", "TOKEN=rotated", "-f", ".env.enc"])?.assert().success();
+    bsec(ci, &["get", "TOKEN", "-f", ".env.enc"])?.assert().success().stdout(predicates::str::diff("rotated\n"));
+
+    bsec(outsider, &["decrypt", ".env.enc", "--out", "stolen.env"])?
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("is not a recipient"));
+    assert!(!work.path().join("stolen.env").exists());
+    bsec(alice, &["encrypt", ".env", "--to", "nobody"])?.assert().code(5);
+
+    Ok(())
+}
+
+/// Tests password rotation over a directory: whole-file and per-value files are re-sealed under
+/// the new password with fresh salts, plain files are left alone, and the old password stops working.
+/// Target File: `src/env_file.rs` -> `rekey_env_files()`, `src/wallet.rs` -> `replace_secure_file()`
+/// Flow: `bsec encrypt` (whole + per-value) -> `bsec rekey config --recursive` -> `bsec decrypt` (old: fails, new: succeeds)
+#[test]
+fn test_rekey_directory() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    temp_dir.child("config/.env").write_str("API_KEY=k-123\n")?;
+    temp_dir.child("config/prod/.env.prod").write_str("# prod\nDB_URL=postgres://db\n")?;
+
+    let bsec = |pass: &str, args: &[&str]| -> Result<assert_cmd::Command, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::Command::cargo_bin("bsec")?;
+        cmd.current_dir(temp_dir.path())
+            .env("BSEC_HOME", temp_dir.path())
+            .env("DOTENV_PASS", pass)
+            .env_remove("DOTENV_NEW_PASS")
+            .args(args);
+        Ok(cmd)
+    };
+    bsec("old_pass", &["encrypt", "config/.env"])?.assert().success();
+    bsec("old_pass", &["encrypt", "config/prod/.env.prod", "--per-value"])?.assert().success();
+    let whole = temp_dir.path().join("config/.env.enc");
+    let per_value = temp_dir.path().join("config/prod/.env.prod.enc");
+    let before = (std::fs::read_to_string(&whole)?, std::fs::read_to_string(&per_value)?);
+
+    bsec("old_pass", &["rekey", "config"])?.assert().failure();
+    bsec("old_pass", &["rekey", "config", "--recursive"])?
+        .env("DOTENV_NEW_PASS", "new_pass")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Rotated 2 file(s), skipped 0, failed 0."));
+
+    let after = (std::fs::read_to_string(&whole)?, std::fs::read_to_string(&per_value)?);
+    let salt = |sealed: &str| -> Result<serde_json::Value, serde_json::Error> {
+        Ok(serde_json::from_str::<serde_json::Value>(sealed)?["kdf"]["salt"].clone())
+    };
+    assert_ne!(salt(&before.0)?, salt(&after.0)?, "fresh salt");
+    assert_ne!(before.1, after.1);
+    assert!(after.1.starts_with("# prod\nDB_URL=enc:v2:"));
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("config/.env"))?, "API_KEY=k-123\n");
+
+    bsec("old_pass", &["decrypt", "config/.env.enc", "--out", "old.env"])?.assert().failure();
+    bsec("new_pass", &["decrypt", "config/.env.enc", "--out", "whole.env"])?.assert().success();
+    bsec("new_pass", &["decrypt", "config/prod/.env.prod.enc", "--out", "prod.env"])?.assert().success();
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("whole.env"))?, "API_KEY=k-123\n");
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("prod.env"))?, "# prod\nDB_URL=postgres://db\n");
+
+    // A wrong current password fails that file and leaves it as it was.
+    bsec("old_pass", &["rekey", "config/.env.enc", "--new-password", "third_pass"])?.assert().failure();
+    assert_eq!(std::fs::read_to_string(&whole)?, after.0);
+
+    Ok(())
+}
@@ -7,6 +7,13 @@
 //!   - Password-protected wallet secret sharing and viewing (`bsec share --password`, `bsec view --password`)
 //!   - Rejection of external 20-byte EVM addresses for ECDH key exchange without explicit SEC1 public key
 //!   - Full secret management lifecycle: `share` -> `view` -> `list` -> `revoke` -> `hide`
+//!   - Fee controls on registry writes: `--dry-run` estimates and `--fee-cap` refusal
+//!   - Registry deployment from the wallet (`bsec registry deploy`) and use of the new registry
+//!   - Offline-signed share: `bsec tx build share` -> `bsec tx sign` -> `bsec tx broadcast` -> `view`
+//!   - Gasless share and view by unfunded wallets through `bsec relay serve` (`--relayer`)
+//!   - Batched writes in one multicall: `bsec share --to A --to B`, `bsec revoke --all-from-me --recipient A`
+//!   - Share -> view -> revoke between two wallets on the in-process mock chain (`--network mock`), run in CI
+//!   - The same lifecycle on the file-backed team registry (`--network team`) in two clones of one git remote
 
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
@@ -39,22 +46,10 @@ macro_rules! require_e2e {
 /// http://localhost:8545), BSEC_E2E_REGISTRY (required), BSEC_E2E_IPFS_GATEWAY
 /// (default http://localhost:8080/ipfs/), BSEC_E2E_FUNDER (default anvil account #0).
 fn provision_local(home: &std::path::Path, addr: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let rpc = std::env::var("BSEC_E2E_RPC").unwrap_or_else(|_| "http://localhost:8545".to_string());
-    let registry = std::env::var("BSEC_E2E_REGISTRY")
-        .expect("BSEC_E2E_REGISTRY must be set for e2e (the deployed BsecSecretRegistry address)");
-    let gateway = std::env::var("BSEC_E2E_IPFS_GATEWAY")
-        .unwrap_or_else(|_| "http://localhost:8080/ipfs/".to_string());
+    let rpc = configure_local(home)?;
     let funder = std::env::var("BSEC_E2E_FUNDER")
         .unwrap_or_else(|_| "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string());
 
-    let mut cfg = Command::cargo_bin("bsec")?;
-    cfg.current_dir(home).env("BSEC_HOME", home);
-    cfg.args([
-        "config", "--network", "local", "--rpc", &rpc, "--registry", &registry,
-        "--ipfs-gateway", &gateway,
-    ]);
-    cfg.assert().success();
-
     let body = format!(
         r#"{{"jsonrpc":"2.0","id":1,"method":"eth_sendTransaction","params":[{{"from":"{}","to":"{}","value":"0x8ac7230489e80000"}}]}}"#,
         funder, addr
@@ -67,6 +62,24 @@ fn provision_local(home: &std::path::Path, addr: &str) -> Result<(), Box<dyn std
     Ok(())
 }
 
+/// Point `home` at the local e2e stack without funding its wallet. Returns the RPC URL.
+fn configure_local(home: &std::path::Path) -> Result<String, Box<dyn std::error::Error>> {
+    let rpc = std::env::var("BSEC_E2E_RPC").unwrap_or_else(|_| "http://localhost:8545".to_string());
+    let registry = std::env::var("BSEC_E2E_REGISTRY")
+        .expect("BSEC_E2E_REGISTRY must be set for e2e (the deployed BsecSecretRegistry address)");
+    let gateway = std::env::var("BSEC_E2E_IPFS_GATEWAY")
+        .unwrap_or_else(|_| "http://localhost:8080/ipfs/".to_string());
+
+    let mut cfg = Command::cargo_bin("bsec")?;
+    cfg.current_dir(home).env("BSEC_HOME", home);
+    cfg.args([
+        "config", "--network", "local", "--rpc", &rpc, "--registry", &registry,
+        "--ipfs-gateway", &gateway,
+    ]);
+    cfg.assert().success();
+    Ok(rpc)
+}
+
 /// Init an unencrypted wallet in `home`; under e2e, also point it at the local stack and fund it.
 /// Returns the init stdout so callers can parse the address / public key.
 fn init_and_provision(home: &std::path::Path) -> Result<String, Box<dyn std::error::Error>> {
@@ -364,3 +377,386 @@ fn test_wallet_and_secret_sharing_lifecycle_flow() -> Result<(), Box<dyn std::er
 
     Ok(())
 }
+
+/// Tests that `--dry-run` prints a fee estimate without sharing, and that `--fee-cap` blocks a
+/// send whose worst-case fee is above it.
+/// Target File: `src/eth.rs` -> `estimate_contract_tx()`, `send_contract_tx()`
+/// Flow: `bsec share --dry-run` -> `bsec share --fee-cap 0.000000000000000001` -> `bsec list`
+#[test]
+fn test_share_fee_controls() -> Result<(), Box<dyn std::error::Error>> {
+    require_e2e!();
+    let temp_dir = assert_fs::TempDir::new()?;
+    init_and_provision(temp_dir.path())?;
+
+    let mut cmd_dry = Command::cargo_bin("bsec")?;
+    cmd_dry.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_dry.args(["share", "--content", "fee_probe", "--to", "public", "--dry-run", "--gas-limit", "300000"]);
+    cmd_dry
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Dry run: nothing was broadcast."))
+        .stdout(predicate::str::contains("Gas limit: 300000"))
+        .stdout(predicate::str::contains("Secret ID").not());
+
+    let mut cmd_capped = Command::cargo_bin("bsec")?;
+    cmd_capped.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_capped.args(["share", "--content", "fee_probe", "--to", "public", "--fee-cap", "0.000000000000000001"]);
+    cmd_capped
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("exceeds --fee-cap"));
+
+    let mut cmd_list = Command::cargo_bin("bsec")?;
+    cmd_list.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_list.arg("list").arg("--all");
+    cmd_list
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matching secrets found."));
+
+    Ok(())
+}
+
+/// Tests deploying a fresh registry from the wallet, then sharing and viewing through it.
+/// Uses the bytecode file in BSEC_E2E_REGISTRY_BYTECODE if set, else the embedded build.
+/// Target File: `src/blockchain.rs` -> `deploy_registry()`, `src/eth.rs` -> `deploy_contract()`
+/// Flow: `bsec registry deploy --json` -> `bsec config --show` -> `bsec share` -> `bsec view`
+#[test]
+fn test_registry_deploy_and_use() -> Result<(), Box<dyn std::error::Error>> {
+    require_e2e!();
+    let temp_dir = assert_fs::TempDir::new()?;
+    init_and_provision(temp_dir.path())?;
+
+    let mut cmd_deploy = Command::cargo_bin("bsec")?;
+    cmd_deploy.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_deploy.args(["registry", "deploy", "--json"]);
+    if let Ok(path) = std::env::var("BSEC_E2E_REGISTRY_BYTECODE") {
+        cmd_deploy.arg("--bytecode").arg(path);
+    }
+    let out = cmd_deploy.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&out)?;
+    let address = report["address"].as_str().expect("deploy report has an address").to_string();
+    assert_eq!(report["saved"], serde_json::json!(true));
+    assert_ne!(Some(address.as_str()), std::env::var("BSEC_E2E_REGISTRY").ok().as_deref());
+
+    let mut cmd_show = Command::cargo_bin("bsec")?;
+    cmd_show.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_show.args(["config", "--show"]);
+    cmd_show.assert().success().stdout(predicate::str::contains(address.as_str()));
+
+    let mut cmd_share = Command::cargo_bin("bsec")?;
+    cmd_share.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_share.args(["share", "--content", "fresh_registry_payload", "--to", "public", "--ttl", "1h"]);
+    let share_out = String::from_utf8(cmd_share.assert().success().get_output().stdout.clone())?;
+    let secret_id = share_out
+        .lines()
+        .find_map(|l| l.strip_prefix("Secret ID: "))
+        .map(|s| s.trim().to_string())
+        .expect("share output should contain the secret ID");
+
+    let mut cmd_view = Command::cargo_bin("bsec")?;
+    cmd_view.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_view.args(["view", &secret_id]);
+    cmd_view.assert().success().stdout(predicate::str::contains("fresh_registry_payload"));
+
+    Ok(())
+}
+
+/// Tests the air-gapped share flow, with one wallet playing both hosts: build the unsigned
+/// shareSecret from the address alone, sign it, broadcast it, then read the secret.
+/// Target File: `src/secrets.rs` -> `build_share()`, `src/eth.rs` -> `build_tx()`, `broadcast_signed()`
+/// Flow: `bsec tx build share --from <addr> -o` -> `bsec tx sign -o` -> `bsec tx broadcast` -> `bsec view`
+#[test]
+fn test_offline_signed_share() -> Result<(), Box<dyn std::error::Error>> {
+    require_e2e!();
+    let temp_dir = assert_fs::TempDir::new()?;
+    let init_out = init_and_provision(temp_dir.path())?;
+    let address = init_out
+        .lines()
+        .find_map(|l| l.strip_prefix("Address: "))
+        .map(|s| s.trim().to_string())
+        .expect("init output should contain the wallet address");
+    let unsigned = temp_dir.path().join("unsigned.json");
+    let signed = temp_dir.path().join("signed.json");
+
+    let mut cmd_build = Command::cargo_bin("bsec")?;
+    cmd_build.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_build.args(["tx", "build", "share", "--content", "air_gapped_payload", "--to", "public", "--from", &address]);
+    cmd_build.arg("-o").arg(&unsigned);
+    let build_err = String::from_utf8(cmd_build.assert().success().get_output().stderr.clone())?;
+    let secret_id = build_err
+        .lines()
+        .find_map(|l| l.strip_prefix("Secret ID: "))
+        .map(|s| s.trim().to_string())
+        .expect("build output should contain the secret ID");
+
+    let mut cmd_sign = Command::cargo_bin("bsec")?;
+    cmd_sign.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_sign.arg("tx").arg("sign").arg(&unsigned).arg("-o").arg(&signed);
+    cmd_sign.assert().success();
+
+    let mut cmd_broadcast = Command::cargo_bin("bsec")?;
+    cmd_broadcast.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_broadcast.arg("tx").arg("broadcast").arg(&signed);
+    cmd_broadcast.assert().success().stdout(predicate::str::contains("Transaction confirmed"));
+
+    let mut cmd_view = Command::cargo_bin("bsec")?;
+    cmd_view.current_dir(temp_dir.path()).env("BSEC_HOME", temp_dir.path());
+    cmd_view.args(["view", &secret_id]);
+    cmd_view.assert().success().stdout(predicate::str::contains("air_gapped_payload"));
+
+    Ok(())
+}
+
+/// Kills the relayer process when the test ends, pass or fail.
+struct ChildGuard(std::process::Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Tests gasless registry writes: a funded relayer submits the share and the read for two
+/// wallets that hold no native tokens, from their EIP-712 signed intents.
+/// Target File: `src/relay.rs` -> `submit()`, `serve()`, `src/eth.rs` -> `sign_digest()`
+/// Flow: `bsec relay serve` (funded) -> `bsec share --relayer` (unfunded) -> `bsec view --relayer` (unfunded)
+#[test]
+fn test_gasless_share_and_view_via_relayer() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::BufRead;
+    require_e2e!();
+    let relayer_home = assert_fs::TempDir::new()?;
+    let sender_home = assert_fs::TempDir::new()?;
+    let recipient_home = assert_fs::TempDir::new()?;
+    init_and_provision(relayer_home.path())?;
+
+    let mut unfunded = Vec::new();
+    for home in [&sender_home, &recipient_home] {
+        let mut cmd = Command::cargo_bin("bsec")?;
+        cmd.current_dir(home.path()).env("BSEC_HOME", home.path());
+        cmd.args(["init", "--overwrite", "--no-encryption"]);
+        unfunded.push(String::from_utf8(cmd.output()?.stdout)?);
+        configure_local(home.path())?;
+    }
+    let recipient_pubkey = unfunded[1]
+        .lines()
+        .find_map(|l| l.strip_prefix("Public Key: "))
+        .map(|s| s.trim().to_string())
+        .expect("init output should contain the public key");
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let listen = format!("127.0.0.1:{}", port);
+    let mut serve = Command::cargo_bin("bsec")?;
+    serve.current_dir(relayer_home.path()).env("BSEC_HOME", relayer_home.path());
+    serve.args(["relay", "serve", "--listen", &listen, "--open"]);
+    let mut child = ChildGuard(serve.stdout(std::process::Stdio::piped()).spawn()?);
+    let mut ready = String::new();
+    std::io::BufReader::new(child.0.stdout.take().expect("piped stdout")).read_line(&mut ready)?;
+    assert!(ready.starts_with("Relaying for registry"), "relayer did not start: {:?}", ready);
+    let relayer_url = format!("http://{}", listen);
+
+    let mut cmd_share = Command::cargo_bin("bsec")?;
+    cmd_share.current_dir(sender_home.path()).env("BSEC_HOME", sender_home.path());
+    cmd_share.args(["share", "--content", "gasless_payload_42", "--to", &recipient_pubkey, "--relayer", &relayer_url]);
+    let share_out = String::from_utf8(cmd_share.assert().success().get_output().stdout.clone())?;
+    let secret_id = share_out
+        .lines()
+        .find_map(|l| l.strip_prefix("Secret ID: "))
+        .map(|s| s.trim().to_string())
+        .expect("share output should contain the secret ID");
+
+    // Without the relayer the unfunded recipient cannot pay for recordRead.
+    let mut cmd_direct = Command::cargo_bin("bsec")?;
+    cmd_direct.current_dir(recipient_home.path()).env("BSEC_HOME", recipient_home.path());
+    cmd_direct.args(["view", &secret_id]);
+    cmd_direct.assert().failure();
+
+    let mut cmd_view = Command::cargo_bin("bsec")?;
+    cmd_view.current_dir(recipient_home.path()).env("BSEC_HOME", recipient_home.path());
+    cmd_view.args(["view", &secret_id, "--relayer", &relayer_url]);
+    cmd_view.assert().success().stdout(predicate::str::contains("gasless_payload_42"));
+
+    Ok(())
+}
+
+/// Tests batched registry writes: one share to two recipients in a single transaction, then a
+/// batch revoke limited to one of them; the other recipient can still read.
+/// Target File: `src/secrets.rs` -> `share_secret_many()`, `revoke_sent()`, `src/blockchain.rs` -> `send_batch()`
+/// Flow: `bsec share --to <pk1> --to <pk2>` -> `bsec revoke --all-from-me --recipient <pk1>` -> `bsec view` (both)
+#[test]
+fn test_batch_share_and_revoke() -> Result<(), Box<dyn std::error::Error>> {
+    require_e2e!();
+    let sender_home = assert_fs::TempDir::new()?;
+    let homes = [assert_fs::TempDir::new()?, assert_fs::TempDir::new()?];
+    init_and_provision(sender_home.path())?;
+    let mut pubkeys = Vec::new();
+    for home in &homes {
+        let out = init_and_provision(home.path())?;
+        pubkeys.push(
+            out.lines()
+                .find_map(|l| l.strip_prefix("Public Key: "))
+                .map(|s| s.trim().to_string())
+                .expect("init output should contain the public key"),
+        );
+    }
+
+    let mut cmd_share = Command::cargo_bin("bsec")?;
+    cmd_share.current_dir(sender_home.path()).env("BSEC_HOME", sender_home.path());
+    cmd_share.args(["share", "--content", "batched_payload_7", "--to", &pubkeys[0], "--to", &pubkeys[1]]);
+    let out = String::from_utf8(cmd_share.assert().success().get_output().stdout.clone())?;
+    assert!(out.contains("Shared with 2 of 2 recipients."), "{}", out);
+    assert_eq!(out.matches("Transaction: ").count(), 1, "{}", out);
+    let ids: Vec<String> = pubkeys
+        .iter()
+        .map(|pk| {
+            out.lines()
+                .find_map(|l| l.trim().strip_prefix(&format!("{}: ", pk)))
+                .map(|s| s.trim().to_string())
+                .expect("share output should list each recipient's secret ID")
+        })
+        .collect();
+
+    let mut cmd_revoke = Command::cargo_bin("bsec")?;
+    cmd_revoke.current_dir(sender_home.path()).env("BSEC_HOME", sender_home.path());
+    cmd_revoke.args(["revoke", "--all-from-me", "--recipient", &pubkeys[0]]);
+    cmd_revoke.assert()
+        .success()
+        .stdout(predicate::str::contains("Revoked 1 of 1 secrets.").and(predicate::str::contains(&ids[0])));
+
+    let view = |i: usize| -> Result<std::process::Output, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("bsec")?;
+        cmd.current_dir(homes[i].path()).env("BSEC_HOME", homes[i].path());
+        cmd.args(["view", &ids[i]]);
+        Ok(cmd.output()?)
+    };
+    assert!(!view(0)?.status.success(), "revoked secret must not be readable");
+    let kept = view(1)?;
+    assert!(kept.status.success());
+    assert!(String::from_utf8(kept.stdout)?.contains("batched_payload_7"));
+
+    Ok(())
+}
+
+/// Point `home` at the mock chain in `chain_dir`, shared by every home given the same directory.
+fn configure_mock(home: &std::path::Path, chain_dir: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cfg = Command::cargo_bin("bsec")?;
+    cfg.current_dir(home).env("BSEC_HOME", home);
+    cfg.args(["config", "--network", "mock", "--mock-dir", chain_dir.to_str().unwrap()]);
+    cfg.assert().success();
+    Ok(())
+}
+
+/// Tests the full lifecycle against the mock chain and IPFS store, with no node or daemon, so
+/// it runs without `BSEC_E2E`. The mock enforces the registry's read limit and revocation.
+/// Target File: `src/mock_chain.rs` -> `MockRegistry`, `src/blockchain.rs` -> `registry_backend()`
+/// Flow: `bsec config --network mock` (two homes) -> `bsec share --to <pubkey> --max-reads 1` -> `bsec view` (twice) -> `bsec revoke`
+#[test]
+fn test_mock_chain_share_view_revoke() -> Result<(), Box<dyn std::error::Error>> {
+    let chain = assert_fs::TempDir::new()?;
+    let sender = assert_fs::TempDir::new()?;
+    let recipient = assert_fs::TempDir::new()?;
+    init_and_provision(sender.path())?;
+    let pubkey = init_and_provision(recipient.path())?
+        .lines()
+        .find_map(|l| l.strip_prefix("Public Key: "))
+        .map(|s| s.trim().to_string())
+        .expect("init output should contain the public key");
+    configure_mock(sender.path(), chain.path())?;
+    configure_mock(recipient.path(), chain.path())?;
+
+    let bsec = |home: &std::path::Path, args: &[&str]| -> Result<std::process::Output, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("bsec")?;
+        cmd.current_dir(home).env("BSEC_HOME", home).args(args);
+        Ok(cmd.output()?)
+    };
+
+    let share = bsec(sender.path(), &["share", "--content", "mock_chain_payload_5", "--to", &pubkey, "--ttl", "1h", "--max-reads", "1"])?;
+    assert!(share.status.success(), "{}", String::from_utf8_lossy(&share.stderr));
+    let secret_id = String::from_utf8(share.stdout)?
+        .lines()
+        .find_map(|l| l.strip_prefix("Secret ID: "))
+        .map(|s| s.trim().to_string())
+        .expect("share output should contain the secret ID");
+
+    let first = bsec(recipient.path(), &["view", &secret_id])?;
+    assert!(first.status.success(), "{}", String::from_utf8_lossy(&first.stderr));
+    assert!(String::from_utf8(first.stdout)?.contains("mock_chain_payload_5"));
+    let second = bsec(recipient.path(), &["view", &secret_id])?;
+    assert_eq!(second.status.code(), Some(3), "the read limit must be enforced");
+
+    // Only the sender can revoke, and fee estimates need a real chain.
+    assert_eq!(bsec(recipient.path(), &["revoke", &secret_id])?.status.code(), Some(3));
+    let dry_run = bsec(sender.path(), &["revoke", &secret_id, "--dry-run"])?;
+    assert!(String::from_utf8(dry_run.stderr)?.contains("no registry contract"));
+    let revoke = bsec(sender.path(), &["revoke", &secret_id])?;
+    assert!(revoke.status.success(), "{}", String::from_utf8_lossy(&revoke.stderr));
+    assert_eq!(bsec(sender.path(), &["revoke", &secret_id])?.status.code(), Some(3));
+
+    Ok(())
+}
+
+/// Run git in `dir`, failing the test on error.
+fn git(dir: &std::path::Path, args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let out = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+    Ok(())
+}
+
+/// Tests the lifecycle on the team registry with each member in their own clone of a shared
+/// git remote: every write is a signed event committed and pushed, and every command pulls first.
+/// Target File: `src/team_registry.rs` -> `TeamRegistry`, `TeamStore`
+/// Flow: `bsec config --network team --rpc <clone>` (two clones) -> `bsec share` -> `bsec view` -> `bsec revoke` -> `bsec view`
+#[test]
+fn test_team_registry_over_git() -> Result<(), Box<dyn std::error::Error>> {
+    let root = assert_fs::TempDir::new()?;
+    git(root.path(), &["init", "--quiet", "--bare", "remote.git"])?;
+    git(root.path(), &["clone", "--quiet", "remote.git", "seed"])?;
+    let seed = root.path().join("seed");
+    git(&seed, &["-c", "user.name=t", "-c", "user.email=t@example.com", "commit", "--quiet", "--allow-empty", "-m", "init"])?;
+    git(&seed, &["push", "--quiet", "origin", "HEAD"])?;
+
+    let sender = assert_fs::TempDir::new()?;
+    let recipient = assert_fs::TempDir::new()?;
+    init_and_provision(sender.path())?;
+    let pubkey = init_and_provision(recipient.path())?
+        .lines()
+        .find_map(|l| l.strip_prefix("Public Key: "))
+        .map(|s| s.trim().to_string())
+        .expect("init output should contain the public key");
+    for (home, clone) in [(sender.path(), "a"), (recipient.path(), "b")] {
+        git(root.path(), &["clone", "--quiet", "remote.git", clone])?;
+        let mut cfg = Command::cargo_bin("bsec")?;
+        cfg.current_dir(home).env("BSEC_HOME", home);
+        cfg.args(["config", "--network", "team", "--rpc", root.path().join(clone).to_str().unwrap()]);
+        cfg.assert().success();
+    }
+
+    let bsec = |home: &std::path::Path, args: &[&str]| -> Result<std::process::Output, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("bsec")?;
+        cmd.current_dir(home).env("BSEC_HOME", home).args(args);
+        Ok(cmd.output()?)
+    };
+
+    let share = bsec(sender.path(), &["share", "--content", "team_payload_3", "--to", &pubkey, "--ttl", "1h"])?;
+    assert!(share.status.success(), "{}", String::from_utf8_lossy(&share.stderr));
+    let secret_id = String::from_utf8(share.stdout)?
+        .lines()
+        .find_map(|l| l.strip_prefix("Secret ID: "))
+        .map(|s| s.trim().to_string())
+        .expect("share output should contain the secret ID");
+
+    let view = bsec(recipient.path(), &["view", &secret_id])?;
+    assert!(view.status.success(), "{}", String::from_utf8_lossy(&view.stderr));
+    assert!(String::from_utf8(view.stdout)?.contains("team_payload_3"));
+
+    let revoke = bsec(sender.path(), &["revoke", &secret_id])?;
+    assert!(revoke.status.success(), "{}", String::from_utf8_lossy(&revoke.stderr));
+    assert_eq!(bsec(recipient.path(), &["view", &secret_id])?.status.code(), Some(3));
+
+    // The recipient's read reached the sender's clone through the remote.
+    let info = bsec(sender.path(), &["list", "--all"])?;
+    assert!(String::from_utf8(info.stdout)?.contains("Reads: 1/"));
+
+    Ok(())
+}